@@ -0,0 +1,55 @@
+#![allow(clippy::tests_outside_test_module, reason = "benchmarks")]
+
+use std::fmt::Write;
+use std::hint::black_box;
+
+use anyhow_tracing::{Error, anyhow};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+fn error_with_fields() -> Error {
+    anyhow!(
+        host = "db-3.internal",
+        port = 5432,
+        database = "accounts",
+        user = "svc-billing",
+        attempt = 3,
+        timeout_ms = 2500,
+        pool = "primary",
+        region = "eu-west-1",
+        "failed to acquire connection"
+    )
+    .context("failed to load invoice")
+}
+
+/// Formats the same error ten times, as a logging pipeline with several sinks does.
+fn format_ten_times(c: &mut Criterion) {
+    let mut group = c.benchmark_group("format_10x");
+
+    group.bench_function("display", |b| {
+        let err = error_with_fields();
+        let mut buffer = String::new();
+        b.iter(|| {
+            for _ in 0..10 {
+                buffer.clear();
+                write!(buffer, "{}", black_box(&err)).expect("formatting should succeed");
+            }
+        });
+    });
+
+    group.bench_function("fresh_error", |b| {
+        let mut buffer = String::new();
+        b.iter(|| {
+            // A new error each iteration, so only the first render is cached
+            let err = error_with_fields();
+            for _ in 0..10 {
+                buffer.clear();
+                write!(buffer, "{}", black_box(&err)).expect("formatting should succeed");
+            }
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, format_ten_times);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+#![allow(clippy::tests_outside_test_module, reason = "benchmarks")]
+
+use std::hint::black_box;
+
+use anyhow_tracing::{Error, Result, ensure};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+/// Sums values after checking each of them with `ensure!`, which never fails.
+fn sum_checked(values: &[u32]) -> Result<u64> {
+    let mut sum = 0_u64;
+    for (index, &value) in values.iter().enumerate() {
+        ensure!(
+            value < 1_000,
+            index,
+            value,
+            "value {} at {} is out of range",
+            value,
+            index
+        );
+        sum = sum.wrapping_add(u64::from(value));
+    }
+    Ok(sum)
+}
+
+/// The same loop with the error built in place, as `ensure!` expanded to before it
+/// called out to a cold function.
+fn sum_checked_inline(values: &[u32]) -> Result<u64> {
+    let mut sum = 0_u64;
+    for (index, &value) in values.iter().enumerate() {
+        if value >= 1_000 {
+            return Err(
+                Error::quiet_msg(format!("value {} at {} is out of range", value, index))
+                    .with_field("index", index)
+                    .with_field("value", value),
+            );
+        }
+        sum = sum.wrapping_add(u64::from(value));
+    }
+    Ok(sum)
+}
+
+/// Checks every value of a slice in a tight loop where the check never fails.
+fn ensure_never_fails(c: &mut Criterion) {
+    let values: Vec<u32> = (0..4096).map(|i| i % 1_000).collect();
+    let mut group = c.benchmark_group("ensure_4096_never_fails");
+
+    group.bench_function("ensure", |b| {
+        b.iter(|| sum_checked(black_box(&values)));
+    });
+
+    group.bench_function("inline_error", |b| {
+        b.iter(|| sum_checked_inline(black_box(&values)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, ensure_never_fails);
+criterion_main!(benches);
@@ -0,0 +1,114 @@
+#![allow(clippy::tests_outside_test_module, reason = "benchmarks")]
+
+use std::fmt::Write;
+use std::hint::black_box;
+
+use anyhow_tracing::{Context, Error, FieldExt, Result, anyhow};
+use criterion::{Criterion, criterion_group, criterion_main};
+
+fn failing_query() -> Result<u64> {
+    Err(anyhow!(
+        database = "accounts",
+        "failed to acquire connection"
+    ))
+}
+
+/// Adds four fields to an error while it is propagated, through each enrichment API.
+fn attach_four_fields(c: &mut Criterion) {
+    let mut group = c.benchmark_group("attach_4_fields");
+
+    group.bench_function("context_trait", |b| {
+        b.iter(|| {
+            black_box(failing_query())
+                .with_field("host", "db-3.internal")
+                .with_field("port", 5432)
+                .with_field("attempt", 3)
+                .with_field_debug("pool", "primary")
+        });
+    });
+
+    group.bench_function("field_ext", |b| {
+        b.iter(|| {
+            black_box(failing_query())
+                .field("host", "db-3.internal")
+                .field("port", 5432)
+                .field("attempt", 3)
+                .field_debug("pool", "primary")
+        });
+    });
+
+    group.finish();
+}
+
+/// Creates an error with three fields, which are kept inline.
+fn construct_three_fields(c: &mut Criterion) {
+    c.bench_function("construct_3_fields", |b| {
+        b.iter(|| {
+            anyhow!(
+                host = black_box("db-3.internal"),
+                port = black_box(5432),
+                attempt = black_box(3),
+                "failed to acquire connection"
+            )
+        });
+    });
+}
+
+/// Adds eight fields to an error and formats it, as a handler that logs the error it
+/// built does. The values of the fields share one buffer.
+fn attach_and_format_eight_fields(c: &mut Criterion) {
+    c.bench_function("attach_and_format_8_fields", |b| {
+        let mut buffer = String::new();
+        b.iter(|| {
+            let err = Error::msg("failed to acquire connection")
+                .with_field("host", black_box("db-3.internal"))
+                .with_field("port", black_box(5432))
+                .with_field("database", black_box("accounts"))
+                .with_field("user", black_box("svc-billing"))
+                .with_field("attempt", black_box(3))
+                .with_field("timeout_ms", black_box(2500))
+                .with_field_debug("pool", black_box("primary"))
+                .with_field("region", black_box("eu-west-1"));
+            buffer.clear();
+            write!(buffer, "{}", err).expect("formatting should succeed");
+        });
+    });
+}
+
+/// Looks up every field of an error with thirty fields, as an exporter does, through
+/// the index built by `get_field` and by scanning the fields.
+fn lookup_thirty_fields(c: &mut Criterion) {
+    let keys: Vec<&'static str> = (0..30).map(|i| &*format!("field_{}", i).leak()).collect();
+    let err = keys.iter().zip(0..).fold(
+        Error::msg("failed to acquire connection"),
+        |err, (key, i)| err.with_field(key, i),
+    );
+    let mut group = c.benchmark_group("lookup_1000_in_30_fields");
+
+    group.bench_function("get_field", |b| {
+        b.iter(|| {
+            for key in keys.iter().cycle().take(1000) {
+                black_box(err.get_field(black_box(key)));
+            }
+        });
+    });
+
+    group.bench_function("linear_scan", |b| {
+        b.iter(|| {
+            for key in keys.iter().cycle().take(1000) {
+                black_box(err.fields().iter().find(|(k, _)| k == black_box(key)));
+            }
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    attach_four_fields,
+    construct_three_fields,
+    attach_and_format_eight_fields,
+    lookup_thirty_fields
+);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+
+use crate::HttpError;
+
+/// Emits the error at [`HttpError::level`] when the response is built, which answers
+/// with its status and JSON body.
+impl ResponseError for HttpError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::from_u16(self.status().as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        self.error().emit_at(self.level());
+        HttpResponse::build(self.status_code()).json(self.body())
+    }
+}
@@ -0,0 +1,12 @@
+use ::axum::Json;
+use ::axum::response::{IntoResponse, Response};
+
+use crate::HttpError;
+
+/// Emits the error at [`HttpError::level`], then answers with its status and JSON body.
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        self.error().emit_at(self.level());
+        (self.status(), Json(self.body())).into_response()
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::panic::Location;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use tracing::callsite::{Callsite, Identifier};
+use tracing::field::{Field, FieldSet, Value, ValueSet, display};
+use tracing::metadata::Kind;
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, dispatcher};
+
+use crate::fields::Scalar;
+use crate::{Error, Level};
+
+/// The name of events for errors without an event name.
+const NAME: &str = "error";
+
+/// The fields every event has before the fields of the error.
+pub const MESSAGE: &str = "message";
+pub const CHAIN: &str = "error.chain";
+
+/// The callsites created so far, by name, target, call site, level and field names.
+static CALLSITES: OnceLock<Mutex<HashMap<CallsiteKey, &'static ErrorCallsite>>> = OnceLock::new();
+
+type CallsiteKey = (
+    &'static str,
+    &'static str,
+    &'static Location<'static>,
+    Level,
+    Vec<&'static str>,
+);
+
+/// Get the callsite for `key`, creating and registering it if needed.
+fn callsite(key: CallsiteKey) -> &'static ErrorCallsite {
+    let mut callsites = CALLSITES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(callsite) = callsites.get(&key) {
+        return callsite;
+    }
+
+    let (name, target, location, level, names) = key;
+    let names: &'static [&'static str] = Box::leak(names.into_boxed_slice());
+    let callsite = ErrorCallsite::leak(names, |fields| {
+        Metadata::new(
+            name,
+            target,
+            level.into(),
+            Some(location.file()),
+            Some(location.line()),
+            None,
+            fields,
+            Kind::EVENT,
+        )
+    });
+    tracing::callsite::register(callsite);
+    callsites.insert((name, target, location, level, names.to_vec()), callsite);
+    callsite
+}
+
+/// Emit `error` as an event with `target` at `level`, with `extra` fields after the
+/// fields of the error, see [`Error::emit_at`](crate::Error::emit_at).
+pub fn emit_event(
+    error: &Error,
+    target: &'static str,
+    level: Level,
+    location: &'static Location<'static>,
+    extra: &[(&'static str, &str)],
+) {
+    // Checked before doing any work, as a disabled event should cost next to nothing
+    if !tracing::level_enabled!(tracing::Level::from(level)) {
+        return;
+    }
+
+    let fields = error.fields();
+    let names = [MESSAGE, CHAIN]
+        .into_iter()
+        .chain(
+            fields
+                .iter()
+                .chain(extra.iter().copied())
+                .map(|(key, _)| key),
+        )
+        .collect();
+    let name = error.event_name().unwrap_or(NAME);
+    let metadata = callsite((name, target, location, level, names)).metadata();
+
+    dispatcher::get_default(|dispatch| {
+        if !dispatch.enabled(metadata) {
+            return;
+        }
+
+        let message = error.message();
+        let chain = error.display_without_fields().to_string();
+        let message = display(&message);
+        let chain = display(&chain);
+        let recorded: Vec<Recorded<'_>> = fields
+            .iter()
+            .enumerate()
+            .map(|(index, (_, text))| {
+                fields
+                    .scalar(index)
+                    .map_or(Recorded::Text(text), Recorded::Scalar)
+            })
+            .chain(extra.iter().map(|(_, text)| Recorded::Text(text)))
+            .collect();
+        let values: Vec<&dyn Value> = [&message as &dyn Value, &chain]
+            .into_iter()
+            .chain(recorded.iter().map(Recorded::as_value))
+            .collect();
+        with_values(metadata, &values, |values| {
+            dispatch.event(&Event::new(metadata, values));
+        });
+    });
+}
+
+/// The value of a field as it is recorded on events.
+enum Recorded<'a> {
+    /// The text of a field without a typed value
+    Text(&'a str),
+    /// The typed value of a number or a boolean, recorded unquoted
+    Scalar(Scalar),
+}
+
+impl Recorded<'_> {
+    /// Get this value as a `tracing` value.
+    fn as_value(&self) -> &dyn Value {
+        match self {
+            Self::Text(text) => text,
+            Self::Scalar(Scalar::I64(value)) => value,
+            Self::Scalar(Scalar::U64(value)) => value,
+            Self::Scalar(Scalar::F64(value)) => value,
+            Self::Scalar(Scalar::Bool(value)) => value,
+        }
+    }
+}
+
+/// Emit an event for `error` with `target` at `level`, with the message of the error
+/// followed by `fields`, in place of the chain and the fields of the error.
+#[cfg(feature = "datadog")]
+pub fn emit_fields(
+    error: &Error,
+    target: &'static str,
+    level: Level,
+    location: &'static Location<'static>,
+    fields: &[(&'static str, String)],
+) {
+    let names = std::iter::once(MESSAGE)
+        .chain(fields.iter().map(|(key, _)| *key))
+        .collect();
+    let name = error.event_name().unwrap_or(NAME);
+    let metadata = callsite((name, target, location, level, names)).metadata();
+
+    dispatcher::get_default(|dispatch| {
+        if !dispatch.enabled(metadata) {
+            return;
+        }
+
+        let message = error.message();
+        let message = display(&message);
+        let values: Vec<&dyn Value> = std::iter::once(&message as &dyn Value)
+            .chain(fields.iter().map(|(_, value)| value as &dyn Value))
+            .collect();
+        with_values(metadata, &values, |values| {
+            dispatch.event(&Event::new(metadata, values));
+        });
+    });
+}
+
+/// A callsite created at runtime, for events with fields only known at runtime.
+///
+/// `tracing` needs the names of the fields of an event up front, so a callsite is
+/// created, and leaked, the first time each set of fields is seen.
+pub struct ErrorCallsite {
+    metadata: OnceLock<Metadata<'static>>,
+}
+
+impl ErrorCallsite {
+    /// Leak a new callsite, with the metadata built by `metadata` from its fields.
+    pub fn leak<F>(names: &'static [&'static str], metadata: F) -> &'static Self
+    where
+        F: FnOnce(FieldSet) -> Metadata<'static>,
+    {
+        let callsite: &'static Self = Box::leak(Box::new(Self {
+            metadata: OnceLock::new(),
+        }));
+        callsite
+            .metadata
+            .get_or_init(|| metadata(FieldSet::new(names, Identifier(callsite))));
+        callsite
+    }
+}
+
+impl Callsite for ErrorCallsite {
+    fn set_interest(&self, _interest: Interest) {}
+
+    fn metadata(&self) -> &Metadata<'_> {
+        self.metadata
+            .get()
+            .expect("the metadata is set before the callsite is registered")
+    }
+}
+
+/// The largest number of fields an emitted event has, the rest are left out.
+const MAX_FIELDS: usize = 256;
+
+/// Call `f` with the values of the fields of `metadata`, in order, as a value set,
+/// which `tracing` only builds from arrays.
+pub fn with_values<F>(metadata: &'static Metadata<'static>, values: &[&dyn Value], f: F)
+where
+    F: FnOnce(&ValueSet<'_>),
+{
+    let fields: Vec<Field> = metadata.fields().iter().collect();
+    match fields.len() {
+        0..=8 => with_array::<8, F>(metadata, &fields, values, f),
+        9..=32 => with_array::<32, F>(metadata, &fields, values, f),
+        33..=64 => with_array::<64, F>(metadata, &fields, values, f),
+        _ => with_array::<MAX_FIELDS, F>(metadata, &fields, values, f),
+    }
+}
+
+fn with_array<const N: usize, F>(
+    metadata: &'static Metadata<'static>,
+    fields: &[Field],
+    values: &[&dyn Value],
+    f: F,
+) where
+    F: FnOnce(&ValueSet<'_>),
+{
+    let Some(first) = fields.first() else {
+        return;
+    };
+    // Unused entries repeat the first field without a value, which is not recorded
+    let entries: [(&Field, Option<&dyn Value>); N] =
+        std::array::from_fn(|i| match (fields.get(i), values.get(i)) {
+            (Some(field), Some(value)) => (field, Some(*value)),
+            _ => (first, None),
+        });
+    f(&metadata.fields().value_set(&entries));
+}
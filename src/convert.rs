@@ -0,0 +1,97 @@
+use std::any::Any;
+use std::error::Error as StdError;
+
+use crate::Error;
+
+/// Errors that the [`Context`](crate::Context) trait can convert into an [`Error`].
+///
+/// This trait is sealed. The `K` parameter is one of the types in [`marker`] and only
+/// exists to keep the implementations for different kinds of error from overlapping,
+/// it is always inferred and never needs to be written out.
+///
+/// # Coherence
+///
+/// A single `impl<E: Into<anyhow::Error>> Context<T> for Result<T, E>` covers std
+/// errors, `anyhow::Error` and [`Error`], but not `Box<dyn Error + Send + Sync>`, which
+/// does not implement `std::error::Error`. A second impl for the box is rejected as
+/// overlapping, because anyhow could add a `From` impl for it in a future release.
+/// Implementing this trait under a different marker for each kind of error keeps the
+/// impls disjoint, and since every error implements it for exactly one marker, the
+/// marker is always inferred.
+///
+/// Without specialization, an [`Error`] cannot get its own impl next to the blanket
+/// one either, so it is detected at runtime and returned unchanged instead of being
+/// wrapped in a new anyhow layer. Fields added before the conversion are kept.
+pub trait IntoError<K>: sealed::Sealed<K> + Sized {
+    /// Convert this error into an [`Error`].
+    ///
+    /// As with [`Error::new`], this may emit an event for the error if it is not an
+    /// [`Error`] already.
+    #[track_caller]
+    fn into_error(self) -> Error {
+        let (error, created) = self.into_quiet_error();
+        if created { error.__created() } else { error }
+    }
+}
+
+/// Marker types selecting an [`IntoError`] implementation.
+pub mod marker {
+    /// Errors that convert into an [`anyhow::Error`], including every
+    /// `std::error::Error + Send + Sync + 'static` type, `anyhow::Error` and
+    /// [`Error`](crate::Error) itself.
+    #[derive(Debug)]
+    pub struct Anyhow(());
+
+    /// Boxed error trait objects, `Box<dyn std::error::Error + Send + Sync>`.
+    #[derive(Debug)]
+    pub struct Boxed(());
+}
+
+mod sealed {
+    use crate::Error;
+
+    pub trait Sealed<K> {
+        /// Convert this error into an [`Error`] without emitting an event for it, and
+        /// tell whether a new [`Error`] was created.
+        fn into_quiet_error(self) -> (Error, bool);
+    }
+}
+
+/// An [`Error`] is returned as is, so that its fields are kept instead of being buried
+/// inside a new anyhow layer.
+impl<E: Into<anyhow::Error> + 'static> IntoError<marker::Anyhow> for E {}
+
+impl<E: Into<anyhow::Error> + 'static> sealed::Sealed<marker::Anyhow> for E {
+    fn into_quiet_error(self) -> (Error, bool) {
+        let mut error = Some(self);
+        if let Some(error) = (&mut error as &mut dyn Any)
+            .downcast_mut::<Option<Error>>()
+            .and_then(Option::take)
+        {
+            return (error, false);
+        }
+
+        let error: anyhow::Error = error
+            .expect("the error is only taken when it is an `Error`")
+            .into();
+        // An `Error` converted into an `anyhow::Error` is unwrapped again
+        match error.downcast::<Error>() {
+            Ok(error) => (error, false),
+            Err(error) => (Error::quiet(error).with_root_type::<E>(), true),
+        }
+    }
+}
+
+/// The box is moved into the anyhow chain as is. anyhow only supports downcasting such
+/// an error to `Box<dyn StdError + Send + Sync>`, from which the concrete error can then
+/// be downcast. A boxed [`Error`] is unboxed so that its fields are kept.
+impl IntoError<marker::Boxed> for Box<dyn StdError + Send + Sync> {}
+
+impl sealed::Sealed<marker::Boxed> for Box<dyn StdError + Send + Sync> {
+    fn into_quiet_error(self) -> (Error, bool) {
+        match self.downcast::<Error>() {
+            Ok(error) => (*error, false),
+            Err(error) => (Error::quiet(anyhow::Error::from_boxed(error)), true),
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::borrow::Cow;
+
+use crate::remote::add_remote_fields;
+use crate::{Error, Level};
+
+/// The field holding the fields not named with
+/// [`set_remote_fields`](crate::set_remote_fields).
+const FIELDS_FIELD: &str = "remote.fields";
+
+/// The data of an [`Error`] as plain owned values, to send an error to another process
+/// and rebuild it there, see [`Error::to_data`] and [`Error::from_data`].
+///
+/// With the `serde` feature, this implements `Serialize` and `Deserialize`, and with the
+/// `schemars` feature, `JsonSchema` describing that shape.
+///
+/// ```rust
+/// use anyhow_tracing::{Error, anyhow};
+///
+/// anyhow_tracing::set_remote_fields(&["host"]).ok();
+/// let err = anyhow!(host = "db-3", "connection refused").context("failed to sync");
+/// let data = err.to_data();
+/// assert_eq!(data.chain, ["failed to sync", "connection refused"]);
+///
+/// let rebuilt = Error::from_data(data);
+/// assert_eq!(rebuilt.to_string(), err.to_string());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ErrorData {
+    /// The message of the outermost context layer.
+    pub message: String,
+    /// The messages of every layer, from the outermost to the root cause.
+    pub chain: Vec<String>,
+    /// The named fields, in the order they were added.
+    pub fields: Vec<(String, String)>,
+    /// The code of the error, see [`Error::code`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub code: Option<String>,
+    /// The level of the error, see [`Error::level`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub level: Option<Level>,
+    /// The notes added to the error, see [`Error::notes`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub notes: Vec<String>,
+}
+
+/// Get the data of `error`, see [`Error::to_data`].
+pub fn to_data(error: &Error) -> ErrorData {
+    error.ack();
+    ErrorData {
+        message: error.message(),
+        chain: error.chain().map(ToString::to_string).collect(),
+        fields: error
+            .fields()
+            .iter()
+            .map(|(key, value)| ((*key).to_owned(), value.to_string()))
+            .collect(),
+        code: error.code().map(ToOwned::to_owned),
+        level: error.level(),
+        notes: error.notes().iter().map(ToString::to_string).collect(),
+    }
+}
+
+/// Rebuild an error from its data, see [`Error::from_data`].
+pub fn from_data(data: ErrorData) -> Error {
+    let ErrorData {
+        message,
+        chain,
+        fields,
+        code,
+        level,
+        notes,
+    } = data;
+
+    let mut messages = chain.into_iter().rev();
+    let root = messages.next().unwrap_or(message);
+    let mut error = messages.fold(Error::quiet_msg(root), Error::context);
+    add_remote_fields(&mut error, FIELDS_FIELD, fields);
+    if let Some(code) = code {
+        error = error.with_code(code);
+    }
+    if let Some(level) = level {
+        error = error.with_level(level);
+    }
+    notes
+        .into_iter()
+        .fold(error, |error, note| error.note(Cow::Owned(note)))
+}
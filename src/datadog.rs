@@ -0,0 +1,90 @@
+//! Mapping errors to the standard attributes of Datadog.
+//!
+//! The error tracking of Datadog reads errors from the `error.kind`, `error.message`
+//! and `error.stack` attributes of logs. [`Error::to_datadog_fields`] maps an error to
+//! them:
+//!
+//! - `error.kind` is the [`code`](Error::code) of the error, or `anyhow_tracing::Error`
+//!   without one, as the type of the root cause is not known once it is wrapped.
+//! - `error.message` is the whole chain of messages joined by `: `, without the
+//!   fields.
+//! - `error.stack` is the backtrace of the error if one was captured, followed by its
+//!   span trace with the `span-trace` feature. It is left out if there is neither.
+//!
+//! The fields of the error follow under their own keys. [`emit`] emits an event with
+//! these fields, so that a JSON formatter ships them as they are.
+//!
+//! [`Error::to_datadog_fields`]: crate::Error::to_datadog_fields
+
+use std::backtrace::BacktraceStatus;
+use std::panic::Location;
+
+use crate::{Error, Level};
+
+/// The kind of errors without a code.
+const KIND: &str = "anyhow_tracing::Error";
+
+/// Get the fields of `error` under the names of Datadog, see
+/// [`Error::to_datadog_fields`](crate::Error::to_datadog_fields).
+pub(crate) fn fields(error: &Error) -> Vec<(&'static str, String)> {
+    error.ack();
+    let mut fields = Vec::with_capacity(error.fields().len().saturating_add(3));
+    fields.push(("error.kind", error.code().unwrap_or(KIND).to_owned()));
+    fields.push(("error.message", error.display_without_fields().to_string()));
+    if let Some(stack) = stack(error) {
+        fields.push(("error.stack", stack));
+    }
+    fields.extend(
+        error
+            .fields()
+            .iter()
+            .map(|(key, value)| (key, value.to_owned())),
+    );
+    fields
+}
+
+/// Render the backtrace and the span trace of `error`, if it has either.
+fn stack(error: &Error) -> Option<String> {
+    let mut sections = Vec::new();
+    let backtrace = error.backtrace();
+    if backtrace.status() == BacktraceStatus::Captured {
+        sections.push(backtrace.to_string());
+    }
+    #[cfg(feature = "span-trace")]
+    if let Some(span_trace) = error.span_trace() {
+        sections.push(format!("Span trace:\n{}", span_trace));
+    }
+    (!sections.is_empty()).then(|| sections.join("\n\n"))
+}
+
+/// Emit `error` as a `tracing` event with the fields of
+/// [`Error::to_datadog_fields`](crate::Error::to_datadog_fields), at its
+/// [`level`](Error::level) or at `ERROR` if it has none.
+///
+/// The message of the event is the message of the outermost context layer. The event
+/// has the [`target`](Error::target) of the error and the file and line this was
+/// called from, as with [`Error::emit`].
+///
+/// ```rust
+/// use anyhow_tracing::{anyhow, datadog};
+///
+/// let err = anyhow!(host = "db-3", "connection refused");
+/// datadog::emit(&err);
+/// ```
+#[track_caller]
+pub fn emit(error: &Error) {
+    let level = error.level().unwrap_or(Level::Error);
+    error.ack();
+    #[cfg(feature = "metrics")]
+    crate::metrics::record(error, level);
+    if !tracing::level_enabled!(tracing::Level::from(level)) {
+        return;
+    }
+    crate::callsite::emit_fields(
+        error,
+        error.target(),
+        level,
+        Location::caller(),
+        &fields(error),
+    );
+}
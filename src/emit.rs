@@ -0,0 +1,145 @@
+use std::panic::Location;
+use std::sync::OnceLock;
+
+#[cfg(not(feature = "tracing"))]
+use crate::field_format;
+use crate::{Error, Level};
+
+/// The target of the events emitted for errors without a module path, unless another
+/// one is set with [`set_default_target`].
+const TARGET: &str = "anyhow_tracing";
+
+/// The target of the events emitted for errors dropped without being handled.
+#[cfg(feature = "must-log")]
+const UNHANDLED_TARGET: &str = "anyhow_tracing::unhandled";
+
+/// The level errors are emitted at when they are created, set with [`set_auto_emit`].
+static AUTO_EMIT: OnceLock<Level> = OnceLock::new();
+
+/// The target of the events emitted for errors without a module path, set with
+/// [`set_default_target`].
+static DEFAULT_TARGET: OnceLock<&'static str> = OnceLock::new();
+
+/// Emit `error` at `level`, see [`Error::emit_at`], sampled at the rate set for its
+/// code if there is one.
+///
+/// Errors are emitted as `tracing` events with the `tracing` feature, and as `log`
+/// records otherwise.
+pub fn emit(error: &Error, level: Level, location: &'static Location<'static>) {
+    match crate::sample::configured_rate(error) {
+        Some(rate) => crate::sample::emit_sampled(error, rate, level, location),
+        None => emit_with(error, error.target(), level, location, &[]),
+    }
+}
+
+/// Emit `error` with `target` at `level` with `extra` fields after the fields of the
+/// error.
+pub fn emit_with(
+    error: &Error,
+    target: &'static str,
+    level: Level,
+    location: &'static Location<'static>,
+    extra: &[(&'static str, &str)],
+) {
+    error.ack();
+    #[cfg(feature = "metrics")]
+    crate::metrics::record(error, level);
+    emit_uncounted(error, target, level, location, extra);
+}
+
+/// Emit `error` as [`emit_with`] does without counting it in the metrics, for events
+/// about other errors.
+pub fn emit_uncounted(
+    error: &Error,
+    target: &'static str,
+    level: Level,
+    location: &'static Location<'static>,
+    extra: &[(&'static str, &str)],
+) {
+    #[cfg(feature = "tracing")]
+    crate::callsite::emit_event(error, target, level, location, extra);
+    #[cfg(not(feature = "tracing"))]
+    emit_record(error, target, level, location, extra);
+}
+
+/// Emit a `WARN` event for `error`, which is being dropped without being handled.
+#[cfg(feature = "must-log")]
+pub fn emit_unhandled(error: &Error, location: &'static Location<'static>) {
+    crate::callsite::emit_event(error, UNHANDLED_TARGET, Level::Warn, location, &[]);
+}
+
+/// Emit `error` as a `log` record at `level`, with the whole chain and the fields in
+/// the message, followed by the `extra` fields.
+///
+/// With the `log-kv` feature, the fields are also attached as key-values.
+#[cfg(not(feature = "tracing"))]
+fn emit_record(
+    error: &Error,
+    target: &str,
+    level: Level,
+    location: &'static Location<'static>,
+    extra: &[(&'static str, &str)],
+) {
+    let level = log::Level::from(level);
+    if !log::log_enabled!(target: target, level) {
+        return;
+    }
+
+    let mut record = log::Record::builder();
+    record
+        .level(level)
+        .target(target)
+        .file_static(Some(location.file()))
+        .line(Some(location.line()));
+    #[cfg(feature = "log-kv")]
+    let fields: Vec<(&str, &str)> = error.fields().iter().chain(extra.iter().copied()).collect();
+    #[cfg(feature = "log-kv")]
+    record.key_values(&fields);
+    let mut rendered_extra = String::new();
+    // Writing into a `String` only fails if a `Display` impl returns an error
+    field_format()
+        .write_fields(&mut rendered_extra, extra.iter().copied())
+        .ok();
+    log::logger().log(
+        &record
+            .args(format_args!("{:#}{}", error, rendered_extra))
+            .build(),
+    );
+}
+
+/// Emit an event for every [`Error`] created for the rest of the program, at `level`.
+///
+/// Events are emitted as by [`Error::emit_at`] when an error is created with
+/// [`Error::new`], [`Error::msg`], the macros, or by converting another error, once the
+/// fields given at creation are added. Context added later does not emit another
+/// event. [`Error::quiet`] and [`Error::quiet_msg`] create an error without an event.
+///
+/// This can only be done once, and should be done at startup. Returns the level back
+/// if automatic emission was already turned on.
+pub fn set_auto_emit(level: Level) -> Result<(), Level> {
+    AUTO_EMIT.set(level)
+}
+
+/// Set the target of the events emitted for errors that were not created by the macros,
+/// such as errors converted from other errors with `?`, instead of `anyhow_tracing`.
+///
+/// Errors created by the macros use the module they were created in, see
+/// [`Error::target`].
+///
+/// This can only be done once, and should be done at startup. Returns the target back
+/// if a default target was already set.
+pub fn set_default_target(target: &'static str) -> Result<(), &'static str> {
+    DEFAULT_TARGET.set(target)
+}
+
+/// Get the target of the events emitted for errors without a module path.
+pub fn default_target() -> &'static str {
+    DEFAULT_TARGET.get().copied().unwrap_or(TARGET)
+}
+
+/// Emit an event for a newly created error if automatic emission is turned on.
+pub fn auto_emit(error: &Error, location: &'static Location<'static>) {
+    if let Some(level) = AUTO_EMIT.get() {
+        emit(error, *level, location);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::{Error, ErrorData, Result};
+
+/// The version of the binary encodings, written as their first byte.
+///
+/// Members added to [`ErrorData`] have defaults, so payloads without them still decode
+/// and decoders ignore members they don't know. The version only changes when the
+/// layout changes in a way that old decoders can't read.
+const FORMAT_VERSION: u8 = 1;
+
+/// Write the format version, followed by the payload written by `encode`.
+fn versioned(encode: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+    let mut bytes = vec![FORMAT_VERSION];
+    encode(&mut bytes);
+    bytes
+}
+
+/// Split the format version off `bytes`, checking that it can be decoded.
+fn payload(bytes: &[u8]) -> Result<&[u8]> {
+    let Some((&version, payload)) = bytes.split_first() else {
+        return Err(Error::quiet_msg("the encoded error data is empty"));
+    };
+    if version != FORMAT_VERSION {
+        return Err(Error::quiet_msg("unsupported error data format version")
+            .with_field("version", version)
+            .with_field("supported", FORMAT_VERSION));
+    }
+    Ok(payload)
+}
+
+/// Wrap a decoding error of `format`.
+fn decode_error<E>(format: &'static str, error: E) -> Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    Error::quiet(anyhow::Error::new(error))
+        .context("failed to decode error data")
+        .with_field("format", format)
+}
+
+impl ErrorData {
+    /// Encode this data as CBOR, prefixed with a format version byte.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::{ErrorData, anyhow};
+    ///
+    /// let data = anyhow!(job_id = 42, "job timed out").to_data();
+    /// let bytes = data.to_cbor();
+    /// assert_eq!(ErrorData::from_cbor(&bytes).unwrap(), data);
+    /// ```
+    #[cfg(feature = "cbor")]
+    pub fn to_cbor(&self) -> Vec<u8> {
+        versioned(|bytes| {
+            ciborium::into_writer(self, bytes).expect("writing to a `Vec` doesn't fail");
+        })
+    }
+
+    /// Decode data encoded with [`ErrorData::to_cbor`].
+    ///
+    /// Fails if the bytes are not valid CBOR for error data, or were written with an
+    /// unsupported format version.
+    #[cfg(feature = "cbor")]
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self> {
+        ciborium::from_reader(payload(bytes)?).map_err(|error| decode_error("cbor", error))
+    }
+
+    /// Encode this data as MessagePack, prefixed with a format version byte.
+    ///
+    /// Members are encoded as a map by name, so that members added later can be told
+    /// apart from the ones before them.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::{ErrorData, anyhow};
+    ///
+    /// let data = anyhow!(job_id = 42, "job timed out").to_data();
+    /// let bytes = data.to_msgpack();
+    /// assert_eq!(ErrorData::from_msgpack(&bytes).unwrap(), data);
+    /// ```
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        versioned(|bytes| {
+            let mut serializer = rmp_serde::Serializer::new(bytes).with_struct_map();
+            serde::Serialize::serialize(self, &mut serializer)
+                .expect("error data serializes to MessagePack");
+        })
+    }
+
+    /// Decode data encoded with [`ErrorData::to_msgpack`].
+    ///
+    /// Fails if the bytes are not valid MessagePack for error data, or were written
+    /// with an unsupported format version.
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Self> {
+        rmp_serde::from_slice(payload(bytes)?).map_err(|error| decode_error("msgpack", error))
+    }
+}
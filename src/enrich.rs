@@ -0,0 +1,230 @@
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{self, Poll, ready};
+
+use http::Request;
+use http::request::Parts;
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{Error, IntoError, marker};
+
+/// A closure extracting fields from the head of a request.
+type Hook = Arc<dyn Fn(&Parts) -> Vec<(&'static str, String)> + Send + Sync>;
+
+/// A tower layer adding fields describing the request to the errors of a service.
+///
+/// The fields are read from the request before it is passed to the inner service, and
+/// only added if the service fails. The error of the inner service is converted into
+/// an [`Error`] as [`Context`](crate::Context) does, so it can be any error that
+/// converts into an `anyhow::Error` or a `Box<dyn Error + Send + Sync>` such as the
+/// errors of tower's own middleware, and an [`Error`] keeps its own fields. As for
+/// [`IntoError`], the `K` parameter is inferred from the error of the inner service
+/// and never needs to be written out.
+///
+/// ```rust
+/// use anyhow_tracing::ErrorEnrichLayer;
+///
+/// let layer = ErrorEnrichLayer::new()
+///     .field_from_header("request_id", "x-request-id")
+///     .record_method()
+///     .record_uri()
+///     .fields_from(|parts| {
+///         let tenant = parts.uri.host().unwrap_or("default").to_owned();
+///         vec![("tenant", tenant)]
+///     });
+/// # let _: ErrorEnrichLayer = layer;
+/// ```
+pub struct ErrorEnrichLayer<K = marker::Anyhow> {
+    /// The fields read from headers, with the name of their header.
+    headers: Vec<(&'static str, &'static str)>,
+    /// Whether the method is added as the `method` field.
+    method: bool,
+    /// Whether the URI is added as the `uri` field.
+    uri: bool,
+    /// The closures extracting other fields.
+    hooks: Vec<Hook>,
+    /// The kind of error of the inner service.
+    marker: PhantomData<K>,
+}
+
+impl<K> ErrorEnrichLayer<K> {
+    /// Create a layer adding no fields yet.
+    pub const fn new() -> Self {
+        Self {
+            headers: Vec::new(),
+            method: false,
+            uri: false,
+            hooks: Vec::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Add the value of the `header` header as the field `key`, when the request has
+    /// it. Values that are not UTF-8 are converted lossily.
+    pub fn field_from_header(mut self, key: &'static str, header: &'static str) -> Self {
+        self.headers.push((key, header));
+        self
+    }
+
+    /// Add the method of the request as the `method` field.
+    pub const fn record_method(mut self) -> Self {
+        self.method = true;
+        self
+    }
+
+    /// Add the URI of the request as the `uri` field.
+    pub const fn record_uri(mut self) -> Self {
+        self.uri = true;
+        self
+    }
+
+    /// Add the fields returned by `hook` for the head of the request, for example the
+    /// matched route stored in its extensions by a router.
+    pub fn fields_from<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Parts) -> Vec<(&'static str, String)> + Send + Sync + 'static,
+    {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+
+    /// Read the fields of the request with the head `parts`.
+    fn fields(&self, parts: &Parts) -> Vec<(&'static str, String)> {
+        let mut fields = Vec::new();
+        if self.method {
+            fields.push(("method", parts.method.to_string()));
+        }
+        if self.uri {
+            fields.push(("uri", parts.uri.to_string()));
+        }
+        for (key, header) in &self.headers {
+            if let Some(value) = parts.headers.get(*header) {
+                fields.push((key, String::from_utf8_lossy(value.as_bytes()).into_owned()));
+            }
+        }
+        for hook in &self.hooks {
+            fields.extend(hook(parts));
+        }
+        fields
+    }
+}
+
+impl<K> Default for ErrorEnrichLayer<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> Clone for ErrorEnrichLayer<K> {
+    fn clone(&self) -> Self {
+        Self {
+            headers: self.headers.clone(),
+            method: self.method,
+            uri: self.uri,
+            hooks: self.hooks.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K> fmt::Debug for ErrorEnrichLayer<K> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorEnrichLayer")
+            .field("headers", &self.headers)
+            .field("method", &self.method)
+            .field("uri", &self.uri)
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+impl<S, K> Layer<S> for ErrorEnrichLayer<K> {
+    type Service = ErrorEnrich<S, K>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ErrorEnrich {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// A service adding fields describing the request to the errors of the inner service,
+/// see [`ErrorEnrichLayer`].
+#[derive(Debug)]
+pub struct ErrorEnrich<S, K = marker::Anyhow> {
+    inner: S,
+    layer: ErrorEnrichLayer<K>,
+}
+
+impl<S: Clone, K> Clone for ErrorEnrich<S, K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S, K, B> Service<Request<B>> for ErrorEnrich<S, K>
+where
+    S: Service<Request<B>>,
+    S::Error: IntoError<K>,
+{
+    type Response = S::Response;
+    type Error = Error;
+    type Future = ErrorEnrichFuture<S::Future, K>;
+
+    fn poll_ready(&mut self, cx: &mut task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(IntoError::into_error)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let (parts, body) = request.into_parts();
+        let fields = self.layer.fields(&parts);
+        ErrorEnrichFuture {
+            future: self.inner.call(Request::from_parts(parts, body)),
+            fields,
+            marker: PhantomData,
+        }
+    }
+}
+
+pin_project! {
+    /// Future returned by [`ErrorEnrich`], which adds the fields of the request to the
+    /// error of the inner service when it fails.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct ErrorEnrichFuture<F, K = marker::Anyhow> {
+        #[pin]
+        future: F,
+        fields: Vec<(&'static str, String)>,
+        marker: PhantomData<K>,
+    }
+}
+
+impl<F, T, E, K> Future for ErrorEnrichFuture<F, K>
+where
+    F: Future<Output = Result<T, E>>,
+    E: IntoError<K>,
+{
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.future.poll(cx));
+        let fields = mem::take(this.fields);
+        Poll::Ready(result.map_err(|error| {
+            let mut error = error.into_error();
+            for (key, value) in fields {
+                error.add_field(key, value);
+            }
+            error
+        }))
+    }
+}
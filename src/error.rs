@@ -1,277 +1,3404 @@
+use std::borrow::Cow;
+use std::convert;
 use std::error::Error as StdError;
-use std::fmt;
+use std::fmt::{self, Write};
+use std::io;
+use std::iter;
+#[cfg(feature = "must-log")]
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::panic::Location;
+#[cfg(feature = "must-log")]
+use std::ptr;
+use std::sync::OnceLock;
+#[cfg(feature = "must-log")]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::Poll;
+#[cfg(any(feature = "tracing", feature = "log"))]
+use std::time::Duration;
+
+use crate::fields::{Fields, FieldsIter, Scalar};
+use crate::logfmt::write_logfmt;
+use crate::render::{FieldFormat, field_format, report_hook, write_truncated_len};
+use crate::report::Report;
+use crate::{FieldType, IntoError, Key, Level};
 
 /// A type alias for `Result<T, Error>`.
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// A literal given as the value of a field to the macros. String literals are stored
+/// without copying them, numbers and booleans keep their type, and other literals are
+/// formatted with `Display`.
+///
+/// This trait is sealed, it can't be named outside of this crate.
+pub trait LiteralValue {
+    /// Get the literal as the value of the field `key`.
+    fn to_field(&self, key: &'static str) -> MacroField<'_>;
+
+    /// Get the literal as the value of the field `key` given with `%`, which is only
+    /// formatted with `Display`.
+    fn to_display_field(&self, key: &'static str) -> MacroField<'_>;
+}
+
+impl LiteralValue for &'static str {
+    fn to_field(&self, key: &'static str) -> MacroField<'_> {
+        MacroField::Static(key, self)
+    }
+
+    fn to_display_field(&self, key: &'static str) -> MacroField<'_> {
+        MacroField::Static(key, self)
+    }
+}
+
+impl LiteralValue for char {
+    fn to_field(&self, key: &'static str) -> MacroField<'_> {
+        MacroField::Display(key, self)
+    }
+
+    fn to_display_field(&self, key: &'static str) -> MacroField<'_> {
+        MacroField::Display(key, self)
+    }
+}
+
+/// A value given to the macros without `%` or `?`.
+///
+/// Numbers and booleans keep their type through the `__field` methods of this type.
+/// Other values fall back to the method of the [`DisplayValue`] it dereferences to,
+/// which formats them with `Display`.
+///
+/// This type can't be named outside of this crate, the macros create it through
+/// [`Error::__value_field`].
+pub struct ValueField<'a, T>(DisplayValue<'a, T>);
+
+/// A value given to the macros, formatted with `Display`, see [`ValueField`].
+pub struct DisplayValue<'a, T>(&'a T);
+
+impl<'a, T> Deref for ValueField<'a, T> {
+    type Target = DisplayValue<'a, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<'a, T: fmt::Display> DisplayValue<'a, T> {
+    /// Get the value as the value of the field `key`, formatted with `Display`.
+    #[doc(hidden)]
+    pub const fn __field(&self, key: &'static str) -> MacroField<'a> {
+        MacroField::Display(key, self.0)
+    }
+}
+
+/// A number or a boolean given to the macros, which keeps its type, see [`Scalar`].
+///
+/// This trait is sealed, it can't be named outside of this crate.
+pub trait ScalarValue: fmt::Display + Copy {
+    /// Get the typed value of this value, or `None` if it doesn't fit in one.
+    fn to_scalar(self) -> Option<Scalar>;
+}
+
+macro_rules! impl_scalar_value {
+    ($($ty:ty => $variant:ident($target:ty)),* $(,)?) => {
+        $(
+            impl ScalarValue for $ty {
+                fn to_scalar(self) -> Option<Scalar> {
+                    <$target>::try_from(self).ok().map(Scalar::$variant)
+                }
+            }
+
+            impl LiteralValue for $ty {
+                fn to_field(&self, key: &'static str) -> MacroField<'_> {
+                    scalar_field(key, self)
+                }
+
+                fn to_display_field(&self, key: &'static str) -> MacroField<'_> {
+                    MacroField::Display(key, self)
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_value!(
+    u8 => U64(u64), u16 => U64(u64), u32 => U64(u64), u64 => U64(u64),
+    u128 => U64(u64), usize => U64(u64),
+    i8 => I64(i64), i16 => I64(i64), i32 => I64(i64), i64 => I64(i64),
+    i128 => I64(i64), isize => I64(i64),
+    f32 => F64(f64), f64 => F64(f64), bool => Bool(bool),
+);
+
+/// Get `value` as the value of the field `key`, with its type if it has one.
+fn scalar_field<'a, T: ScalarValue>(key: &'static str, value: &'a T) -> MacroField<'a> {
+    value
+        .to_scalar()
+        .map_or(MacroField::Display(key, value), |scalar| {
+            MacroField::Scalar(key, value, scalar)
+        })
+}
+
+impl<'a, T: ScalarValue> ValueField<'a, T> {
+    /// Get the value as the value of the field `key`, with its type.
+    #[doc(hidden)]
+    pub fn __field(&self, key: &'static str) -> MacroField<'a> {
+        scalar_field(key, self.0.0)
+    }
+}
+
+/// A field given to the macros, added to the error they create by
+/// [`Error::__msg_with_fields`].
+///
+/// This type can't be named outside of this crate, the macros create it through
+/// [`Error::__display_field`] and the functions next to it.
+pub enum MacroField<'a> {
+    /// A value formatted with `Display`
+    Display(&'static str, &'a dyn fmt::Display),
+    /// A value formatted with `Debug`
+    Debug(&'static str, &'a dyn fmt::Debug),
+    /// A static string, stored without copying it
+    Static(&'static str, &'static str),
+    /// A number or a boolean formatted with `Display`, which keeps its type
+    Scalar(&'static str, &'a dyn fmt::Display, Scalar),
+}
+
 /// An error type that extends `anyhow::Error` with named fields.
-pub struct Error {
+///
+/// Like `anyhow::Error`, it is a single pointer, so that results stay small.
+pub struct Error(Box<ErrorImpl>);
+
+/// The data of an [`Error`], behind a single allocation.
+struct ErrorImpl {
     /// The underlying anyhow error
     inner: anyhow::Error,
     /// Named fields stored as key-value pairs
-    fields: Vec<(&'static str, Box<str>)>,
+    fields: Fields,
+    /// For each context layer, the index of the first field added after it
+    frames: Vec<usize>,
+    /// Less commonly used data, allocated the first time it is set
+    metadata: Option<Box<Metadata>>,
+    /// The rendered `Display` output, cleared whenever the rendered parts change
+    display_cache: OnceLock<Box<str>>,
+    /// The module the error was created in by the macros, the target of its events
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    module_path: Option<&'static str>,
+    /// Whether the error was handled, checked when it is dropped
+    #[cfg(feature = "must-log")]
+    drop_check: DropCheck,
+}
+
+const _: () = assert!(
+    size_of::<Error>() == size_of::<usize>(),
+    "an `Error` is a single pointer"
+);
+
+/// Whether an [`Error`] was handled, see [`Error::ack`].
+#[cfg(feature = "must-log")]
+#[derive(Debug)]
+struct DropCheck {
+    /// Set once the error is emitted, rendered, inspected or acknowledged
+    handled: AtomicBool,
+    /// Where the error was created, unless it is quiet and never checked
+    location: Option<&'static Location<'static>>,
+}
+
+/// Data attached to an [`Error`] that most errors never use.
+#[derive(Default)]
+struct Metadata {
+    /// A message that is safe to show to end users
+    public_message: Option<Cow<'static, str>>,
+    /// Per-field validation problems, stored separately from the named fields
+    violations: Vec<(Cow<'static, str>, Box<str>)>,
+    /// Free-form annotations, only shown in the `Debug` output
+    notes: Vec<Cow<'static, str>>,
+    /// A stable, machine readable identifier for this kind of error
+    code: Option<Cow<'static, str>>,
+    /// A link to the documentation of this kind of error
+    docs_url: Option<Cow<'static, str>>,
+    /// The code a command-line tool exits with for this error
+    exit_code: Option<u8>,
+    /// The severity of this error
+    level: Option<Level>,
+    /// The HTTP status code of this error
+    #[cfg(feature = "http")]
+    status: Option<http::StatusCode>,
+    /// The name of the events emitted for this error
+    event_name: Option<&'static str>,
+    /// Where a [`Context`] method was first called for a layer, with the layer index
+    locations: Vec<(usize, &'static Location<'static>)>,
+    /// The spans entered when the error was created
+    #[cfg(feature = "span-trace")]
+    span_trace: Option<tracing_error::SpanTrace>,
+    /// The JSON values of fields added with `with_field_json`, by field index
+    #[cfg(feature = "json")]
+    json_fields: Vec<(usize, serde_json::Value)>,
+    /// The name of the type of the error this one was converted from
+    #[cfg(feature = "lambda")]
+    root_type: Option<&'static str>,
 }
 
-impl Error {
-    /// Create a new error from an anyhow error.
-    pub const fn new(error: anyhow::Error) -> Self {
-        Self {
-            inner: error,
-            fields: Vec::new(),
-        }
+impl Metadata {
+    /// The metadata of a newly created error, with the current span trace if one can
+    /// be captured.
+    #[cfg(feature = "span-trace")]
+    fn captured() -> Option<Box<Self>> {
+        use tracing_error::{SpanTrace, SpanTraceStatus};
+
+        let span_trace = SpanTrace::capture();
+        (span_trace.status() == SpanTraceStatus::CAPTURED).then(|| {
+            Box::new(Self {
+                span_trace: Some(span_trace),
+                ..Self::default()
+            })
+        })
+    }
+
+    #[cfg(not(feature = "span-trace"))]
+    const fn captured() -> Option<Box<Self>> {
+        None
+    }
+}
+
+impl Error {
+    /// Create a new error from an anyhow error.
+    ///
+    /// With the `tracing` feature, this emits an event for the error if automatic
+    /// emission was turned on with [`set_auto_emit`](crate::set_auto_emit).
+    #[track_caller]
+    pub fn new(error: anyhow::Error) -> Self {
+        Self::quiet(error).__created()
+    }
+
+    /// Create a new error with a message and optional fields.
+    ///
+    /// As with [`Error::new`], this may emit an event for the error.
+    #[track_caller]
+    pub fn msg<T: fmt::Display + fmt::Debug + Send + Sync + 'static>(msg: T) -> Self {
+        Self::new(anyhow::Error::msg(msg))
+    }
+
+    /// Create a new error from an anyhow error, never emitting an event for it even if
+    /// automatic emission was turned on with [`set_auto_emit`](crate::set_auto_emit).
+    pub fn quiet(error: anyhow::Error) -> Self {
+        Self(Box::new(ErrorImpl {
+            inner: error,
+            fields: Fields::default(),
+            frames: Vec::new(),
+            metadata: Metadata::captured(),
+            display_cache: OnceLock::new(),
+            #[cfg(any(feature = "tracing", feature = "log"))]
+            module_path: None,
+            #[cfg(feature = "must-log")]
+            drop_check: DropCheck {
+                handled: AtomicBool::new(false),
+                location: None,
+            },
+        }))
+    }
+
+    /// Create a new error with a message, never emitting an event for it, see
+    /// [`Error::quiet`].
+    pub fn quiet_msg<T: fmt::Display + fmt::Debug + Send + Sync + 'static>(msg: T) -> Self {
+        Self::quiet(anyhow::Error::msg(msg))
+    }
+
+    /// Create an error with a message formatted from `args` in the module at
+    /// `module_path`, as the macros do when they are given no fields.
+    ///
+    /// Errors are rare, so this is kept out of line: the macros compile to a call to
+    /// this function, leaving the code around them small.
+    #[doc(hidden)]
+    #[cold]
+    #[inline(never)]
+    #[track_caller]
+    pub fn __msg(args: fmt::Arguments<'_>, module_path: &'static str) -> Self {
+        Self::quiet_msg(fmt::format(args)).__created_in(module_path)
+    }
+
+    /// Create an error with a message formatted from `args` and `fields` in the module
+    /// at `module_path`, as the macros do when they are given fields, see
+    /// [`Error::__msg`].
+    #[doc(hidden)]
+    #[cold]
+    #[inline(never)]
+    #[track_caller]
+    pub fn __msg_with_fields(
+        args: fmt::Arguments<'_>,
+        module_path: &'static str,
+        fields: &[MacroField<'_>],
+    ) -> Self {
+        let mut error = Self::quiet_msg(fmt::format(args));
+        for field in fields {
+            error.add_macro_field(field);
+        }
+        error.__created_in(module_path)
+    }
+
+    /// Get a field given to the macros to format with `Display`.
+    #[doc(hidden)]
+    pub const fn __display_field<'a>(
+        key: &'static str,
+        value: &'a dyn fmt::Display,
+    ) -> MacroField<'a> {
+        MacroField::Display(key, value)
+    }
+
+    /// Get a field given to the macros to format with `Debug`.
+    #[doc(hidden)]
+    pub const fn __debug_field<'a>(key: &'static str, value: &'a dyn fmt::Debug) -> MacroField<'a> {
+        MacroField::Debug(key, value)
+    }
+
+    /// Get a field given to the macros without `%` or `?`, see [`ValueField`].
+    #[doc(hidden)]
+    pub const fn __value_field<T>(value: &T) -> ValueField<'_, T> {
+        ValueField(DisplayValue(value))
+    }
+
+    /// Get a field whose value is a literal in the macros, see [`LiteralValue`].
+    #[doc(hidden)]
+    pub fn __literal_field<'a, V: LiteralValue>(key: &'static str, value: &'a V) -> MacroField<'a> {
+        value.to_field(key)
+    }
+
+    /// Get a field whose value is a literal given with `%` in the macros, see
+    /// [`LiteralValue`].
+    #[doc(hidden)]
+    pub fn __display_literal_field<'a, V: LiteralValue>(
+        key: &'static str,
+        value: &'a V,
+    ) -> MacroField<'a> {
+        value.to_display_field(key)
+    }
+
+    /// Finish creating an error: attach the fields of the current span with the
+    /// `auto-span-fields` feature, and emit the event if automatic emission is on.
+    ///
+    /// Used by the macros once all the fields are added.
+    #[doc(hidden)]
+    #[track_caller]
+    #[cfg_attr(
+        not(any(feature = "tracing", feature = "log")),
+        expect(
+            clippy::missing_const_for_fn,
+            reason = "nothing is done without the features"
+        )
+    )]
+    pub fn __created(self) -> Self {
+        self.created_at(Location::caller())
+    }
+
+    /// Finish creating an error in the module at `module_path`, which becomes the
+    /// target of its events, see [`Error::__created`].
+    #[doc(hidden)]
+    #[track_caller]
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    pub fn __created_in(mut self, module_path: &'static str) -> Self {
+        self.0.module_path = Some(module_path);
+        self.created_at(Location::caller())
+    }
+
+    #[doc(hidden)]
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    pub const fn __created_in(self, _module_path: &'static str) -> Self {
+        self
+    }
+
+    /// Finish creating an error at `location`, see [`Error::__created`].
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    fn created_at(self, location: &'static Location<'static>) -> Self {
+        #[cfg(feature = "auto-span-fields")]
+        let error = self.in_current_span();
+        #[cfg(not(feature = "auto-span-fields"))]
+        let error = self;
+        #[cfg(feature = "auto-trace-context")]
+        let error = error.with_trace_context();
+        #[cfg(feature = "must-log")]
+        let mut error = error;
+        #[cfg(feature = "must-log")]
+        {
+            error.0.drop_check.location = Some(location);
+        }
+        crate::emit::auto_emit(&error, location);
+        error
+    }
+
+    /// Mark this error as handled, so that no event is emitted when it is dropped.
+    ///
+    /// With the `must-log` feature, a `WARN` event with the target
+    /// `anyhow_tracing::unhandled` is emitted for an error dropped without being
+    /// handled, such as one discarded with `let _ = do_thing();`. Emitting an error,
+    /// rendering it with `Display`, `Debug` or [`Error::report`], and downcasting it also
+    /// mark it as handled. Adding context or propagating an error with `?` moves it
+    /// without dropping it, so only the place it ends up in has to handle it. Nothing is
+    /// emitted while a thread is panicking, or for errors created with [`Error::quiet`].
+    ///
+    /// The event has the fields of the error and the file and line the error was
+    /// created at. This does nothing without the feature.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::{Result, anyhow};
+    ///
+    /// fn cleanup() -> Result<()> {
+    ///     Err(anyhow!(path = "/tmp/cache", "failed to remove the cache"))
+    /// }
+    ///
+    /// // Failing to clean up is fine here
+    /// if let Err(err) = cleanup() {
+    ///     err.ack();
+    /// }
+    /// ```
+    #[cfg_attr(
+        not(feature = "must-log"),
+        expect(
+            clippy::missing_const_for_fn,
+            reason = "the error is only marked with the feature"
+        )
+    )]
+    pub fn ack(&self) {
+        #[cfg(feature = "must-log")]
+        self.0.drop_check.handled.store(true, Ordering::Relaxed);
+    }
+
+    /// Move the data out of this error, which can't be done by destructuring it when
+    /// it implements `Drop`.
+    #[cfg(not(feature = "must-log"))]
+    fn into_parts(self) -> Box<ErrorImpl> {
+        self.0
+    }
+
+    #[cfg(feature = "must-log")]
+    fn into_parts(self) -> Box<ErrorImpl> {
+        let error = ManuallyDrop::new(self);
+        // SAFETY: `error` is never dropped, so the box is moved out of it once.
+        unsafe { ptr::read(&raw const error.0) }
+    }
+
+    /// Put an error back together from its data, see [`Error::into_parts`]. The cached
+    /// `Display` output is cleared.
+    fn from_parts(mut parts: Box<ErrorImpl>) -> Self {
+        parts.display_cache = OnceLock::new();
+        Self(parts)
+    }
+
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    const fn created_at(self, _location: &'static Location<'static>) -> Self {
+        self
+    }
+
+    /// Add a named field to this error.
+    pub fn with_field<V: fmt::Display>(mut self, key: &'static str, value: V) -> Self {
+        self.add_field(key, value);
+        self
+    }
+
+    /// Add a named field with debug formatting to this error.
+    pub fn with_field_debug<V: fmt::Debug>(mut self, key: &'static str, value: V) -> Self {
+        self.add_field_debug(key, value);
+        self
+    }
+
+    /// Add a named field with a static string value to this error, which is stored
+    /// without copying it.
+    ///
+    /// The macros store string literals the same way, `anyhow!(operation = "login", ...)`
+    /// doesn't allocate for the value of the field.
+    pub fn with_field_static(mut self, key: &'static str, value: &'static str) -> Self {
+        self.add_field_static(key, value);
+        self
+    }
+
+    /// Add a named field to this error in place, see [`Error::with_field`].
+    pub fn add_field<V: fmt::Display>(&mut self, key: &'static str, value: V) {
+        self.add_field_dyn(key, &value);
+    }
+
+    /// The body of [`Error::add_field`], kept out of line so that it isn't copied for
+    /// every type of value.
+    #[inline(never)]
+    fn add_field_dyn(&mut self, key: &'static str, value: &dyn fmt::Display) {
+        self.fields_mut().push_display(key, value);
+    }
+
+    /// Add a named field with a static string value to this error in place, see
+    /// [`Error::with_field_static`].
+    pub fn add_field_static(&mut self, key: &'static str, value: &'static str) {
+        self.fields_mut().push_static(key, value);
+    }
+
+    /// Add a named field with debug formatting to this error in place, see
+    /// [`Error::with_field_debug`].
+    pub fn add_field_debug<V: fmt::Debug>(&mut self, key: &'static str, value: V) {
+        self.add_field_debug_dyn(key, &value);
+    }
+
+    /// The body of [`Error::add_field_debug`], see [`Error::add_field_dyn`].
+    #[inline(never)]
+    fn add_field_debug_dyn(&mut self, key: &'static str, value: &dyn fmt::Debug) {
+        self.fields_mut().push_debug(key, value);
+    }
+
+    /// Add a field whose value is a literal in the macros, see [`LiteralValue`].
+    #[doc(hidden)]
+    pub fn __add_literal_field<V: LiteralValue>(&mut self, key: &'static str, value: V) {
+        self.add_macro_field(&value.to_field(key));
+    }
+
+    /// Add a field whose value is a literal given with `%` in the macros, see
+    /// [`LiteralValue`].
+    #[doc(hidden)]
+    pub fn __add_display_literal_field<V: LiteralValue>(&mut self, key: &'static str, value: V) {
+        self.add_macro_field(&value.to_display_field(key));
+    }
+
+    /// Add a field given to the macros to this error in place.
+    fn add_macro_field(&mut self, field: &MacroField<'_>) {
+        let fields = self.fields_mut();
+        match *field {
+            MacroField::Display(key, value) => fields.push_display(key, value),
+            MacroField::Debug(key, value) => fields.push_debug(key, value),
+            MacroField::Static(key, value) => fields.push_static(key, value),
+            MacroField::Scalar(key, value, scalar) => fields.push_scalar(key, value, scalar),
+        }
+    }
+
+    /// Add a named field with a structured JSON value to this error.
+    ///
+    /// The field is shown as compact single-line JSON by `Display` and returned as that
+    /// text by [`Error::get_field`], but keeps its structure in the output of
+    /// [`Error::to_json`] and of the `Serialize` implementation, see
+    /// [`Error::get_field_json`].
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    /// use serde_json::json;
+    ///
+    /// let err = anyhow!("invalid config")
+    ///     .with_field_json("limits", json!({ "max_connections": 0 }));
+    /// assert_eq!(err.to_string(), r#"invalid config [limits={"max_connections":0}]"#);
+    /// assert_eq!(err.to_json(false)["fields"]["limits"]["max_connections"], 0);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn with_field_json(mut self, key: &'static str, value: serde_json::Value) -> Self {
+        self.add_field_json(key, value);
+        self
+    }
+
+    /// Add a named field with a structured JSON value to this error in place, see
+    /// [`Error::with_field_json`].
+    #[cfg(feature = "json")]
+    pub fn add_field_json(&mut self, key: &'static str, value: serde_json::Value) {
+        let index = self.0.fields.len();
+        self.add_field(key, &value);
+        self.metadata_mut().json_fields.push((index, value));
+    }
+
+    /// Add a named field with any serializable value to this error, serialized to JSON
+    /// now and stored as with [`Error::with_field_json`].
+    ///
+    /// If the value fails to serialize, the field is added with the text
+    /// `<serialization error: ...>` instead.
+    ///
+    /// ```rust
+    /// use std::collections::BTreeMap;
+    ///
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// let headers = BTreeMap::from([("accept", "text/html")]);
+    /// let err = anyhow!("request rejected").with_field_serde("headers", &headers);
+    /// assert_eq!(err.get_field("headers"), Some(r#"{"accept":"text/html"}"#));
+    /// assert_eq!(err.to_json(false)["fields"]["headers"]["accept"], "text/html");
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn with_field_serde<V: serde::Serialize>(mut self, key: &'static str, value: V) -> Self {
+        self.add_field_serde(key, value);
+        self
+    }
+
+    /// Add a named field with any serializable value to this error in place, see
+    /// [`Error::with_field_serde`].
+    #[cfg(feature = "json")]
+    pub fn add_field_serde<V: serde::Serialize>(&mut self, key: &'static str, value: V) {
+        match serde_json::to_value(value) {
+            Ok(value) => self.add_field_json(key, value),
+            Err(error) => self.add_field(key, format_args!("<serialization error: {error}>")),
+        }
+    }
+
+    /// Add a named field to this error if the value is `Some`.
+    pub fn with_field_opt<V: fmt::Display>(mut self, key: &'static str, value: Option<V>) -> Self {
+        if let Some(value) = value {
+            self.add_field(key, value);
+        }
+        self
+    }
+
+    /// Add a named field with debug formatting to this error if the value is `Some`.
+    pub fn with_field_debug_opt<V: fmt::Debug>(
+        mut self,
+        key: &'static str,
+        value: Option<V>,
+    ) -> Self {
+        if let Some(value) = value {
+            self.add_field_debug(key, value);
+        }
+        self
+    }
+
+    /// Add several named fields to this error, in iteration order.
+    pub fn with_fields<I, V>(mut self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+    {
+        self.extend_fields(fields);
+        self
+    }
+
+    /// Add several named fields to this error in place, in iteration order.
+    ///
+    /// This is the generic counterpart of the [`Extend`] implementations, which can
+    /// only accept concrete value types. As with [`Error::with_field`], keys that are
+    /// already present are not replaced, the new value is appended after them.
+    pub fn extend_fields<I, V>(&mut self, fields: I)
+    where
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+    {
+        for (key, value) in fields {
+            self.add_field(key, value);
+        }
+    }
+
+    /// Set a message that is safe to show to end users.
+    ///
+    /// The public message is kept when context is added, but does not change the
+    /// `Display` or `Debug` output, which always describe the internal error.
+    pub fn with_public_message<M: Into<Cow<'static, str>>>(mut self, message: M) -> Self {
+        self.metadata_mut().public_message = Some(message.into());
+        self
+    }
+
+    /// Get the message that is safe to show to end users, if one was set.
+    pub fn public_message(&self) -> Option<&str> {
+        self.0.metadata.as_ref()?.public_message.as_deref()
+    }
+
+    /// Set a stable, machine readable code identifying this kind of error.
+    pub fn with_code<C: Into<Cow<'static, str>>>(mut self, code: C) -> Self {
+        self.metadata_mut().code = Some(code.into());
+        self
+    }
+
+    /// Get the code of this error, if one was set.
+    #[cfg_attr(
+        feature = "miette",
+        expect(
+            clippy::same_name_method,
+            reason = "`Diagnostic::code` returns the same code"
+        )
+    )]
+    pub fn code(&self) -> Option<&str> {
+        self.0.metadata.as_ref()?.code.as_deref()
+    }
+
+    /// Set a link to the documentation of this kind of error, such as a page
+    /// explaining its [code](Error::with_code) and how to fix it.
+    pub fn with_docs_url<U: Into<Cow<'static, str>>>(mut self, url: U) -> Self {
+        self.metadata_mut().docs_url = Some(url.into());
+        self
+    }
+
+    /// Get the link to the documentation of this error, if one was set.
+    pub fn docs_url(&self) -> Option<&str> {
+        self.0.metadata.as_ref()?.docs_url.as_deref()
+    }
+
+    /// Set the code a command-line tool exits with for this error, see
+    /// [`Error::exit_with_report`].
+    pub fn with_exit_code(mut self, code: u8) -> Self {
+        self.metadata_mut().exit_code = Some(code);
+        self
+    }
+
+    /// Get the exit code of this error, if one was set.
+    pub fn exit_code(&self) -> Option<u8> {
+        self.0.metadata.as_ref()?.exit_code
+    }
+
+    /// Set the HTTP status code that describes this error, for example to answer a
+    /// request that failed with it.
+    #[cfg(feature = "http")]
+    pub fn with_status(mut self, status: http::StatusCode) -> Self {
+        self.metadata_mut().status = Some(status);
+        self
+    }
+
+    /// Get the HTTP status code of this error, if one was set.
+    #[cfg(feature = "http")]
+    pub fn status(&self) -> Option<http::StatusCode> {
+        self.0.metadata.as_ref()?.status
+    }
+
+    /// Set the severity of this error.
+    pub fn with_level(mut self, level: Level) -> Self {
+        self.metadata_mut().level = Some(level);
+        self
+    }
+
+    /// Get the severity of this error, if one was set.
+    pub fn level(&self) -> Option<Level> {
+        self.0.metadata.as_ref()?.level
+    }
+
+    /// Get the location of the first [`Context`] method called on this error.
+    ///
+    /// This is the call site closest to where the error was created, see
+    /// [`ContextFrame::location`] for the location of each context layer. Locations
+    /// are only recorded with the `capture-location` feature, without it this always
+    /// returns `None`.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        let (_, location) = self.0.metadata.as_ref()?.locations.first()?;
+        Some(location)
+    }
+
+    /// Record the location of a [`Context`] call, unless the current layer already
+    /// has one.
+    #[cfg(feature = "capture-location")]
+    fn record_location(&mut self, location: &'static Location<'static>) {
+        let layer = self.0.frames.len();
+        let locations = &mut self.metadata_mut().locations;
+        if locations.last().is_none_or(|(last, _)| *last < layer) {
+            locations.push((layer, location));
+        }
+    }
+
+    /// Record a validation problem with the given input field.
+    ///
+    /// Violations are kept separately from the named fields so that the association
+    /// between an input field and what was wrong with it is preserved.
+    pub fn with_violation<M: fmt::Display>(mut self, field: &'static str, message: M) -> Self {
+        self.add_violation(field, message);
+        self
+    }
+
+    /// Record a validation problem in place, see [`Error::with_violation`].
+    pub fn add_violation<M: fmt::Display>(&mut self, field: &'static str, message: M) {
+        self.metadata_mut()
+            .violations
+            .push((Cow::Borrowed(field), message.to_string().into_boxed_str()));
+    }
+
+    /// Get the recorded validation problems, in the order they were added.
+    pub fn violations(&self) -> &[(Cow<'static, str>, Box<str>)] {
+        self.0
+            .metadata
+            .as_ref()
+            .map_or(&[], |metadata| metadata.violations.as_slice())
+    }
+
+    /// Add a free-form note to this error, such as a hint about the likely cause.
+    ///
+    /// Notes are shown in the `Debug` output but not in the single line `Display`
+    /// output, and do not add a context layer.
+    pub fn note<N: Into<Cow<'static, str>>>(mut self, note: N) -> Self {
+        self.metadata_mut().notes.push(note.into());
+        self
+    }
+
+    /// Get the notes added to this error, in the order they were added.
+    pub fn notes(&self) -> &[Cow<'static, str>] {
+        self.0
+            .metadata
+            .as_ref()
+            .map_or(&[], |metadata| metadata.notes.as_slice())
+    }
+
+    /// Get the named fields, in the order they were added.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// let err = anyhow!(host = "db-3", attempt = 2, "connection refused");
+    /// let fields: Vec<_> = err.fields().iter().collect();
+    /// assert_eq!(fields, [("host", "db-3"), ("attempt", "2")]);
+    /// ```
+    pub fn fields(&self) -> &Fields {
+        &self.0.fields
+    }
+
+    /// Add a named field through a typed [`Key`].
+    pub fn with_key<T: FieldType>(mut self, key: Key<T>, value: T::Value<'_>) -> Self {
+        self.add_field(key.name(), value);
+        self
+    }
+
+    /// Get a field value through a typed [`Key`], see [`Error::get_field`].
+    ///
+    /// Returns `None` if the field is missing or its value is not valid for the type
+    /// of the key.
+    pub fn get_key<T: FieldType>(&self, key: Key<T>) -> Option<T::Value<'_>> {
+        self.get_field(key.name()).and_then(T::parse)
+    }
+
+    /// Get a specific field value by key.
+    ///
+    /// This is an O(n) operation for errors with a few fields. Errors with more than
+    /// eight fields index their keys the first time this is called, so that further
+    /// lookups are O(1) until another field is added.
+    pub fn get_field(&self, key: &str) -> Option<&str> {
+        self.0.fields.find(key)
+    }
+
+    /// Get the JSON value of a field added with [`Error::with_field_json`], with the
+    /// same cost as [`Error::get_field`].
+    ///
+    /// Like [`Error::get_field`], this looks at the first field with the key, and
+    /// returns `None` if it is missing or was added with another method.
+    #[cfg(feature = "json")]
+    pub fn get_field_json(&self, key: &str) -> Option<&serde_json::Value> {
+        let index = self.0.fields.position(key)?;
+        self.json_field(index)
+    }
+
+    /// Get the JSON value of the field at `index`, if it was added with
+    /// [`Error::with_field_json`].
+    #[cfg(feature = "json")]
+    pub(crate) fn json_field(&self, index: usize) -> Option<&serde_json::Value> {
+        self.0
+            .metadata
+            .as_ref()?
+            .json_fields
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, value)| value)
+    }
+
+    /// Record that this error was converted from an error of type `E`, see
+    /// [`Error::root_type`]. An `anyhow::Error` has no type of its own, so converting
+    /// from one records nothing.
+    #[cfg(feature = "lambda")]
+    pub(crate) fn with_root_type<E: 'static>(mut self) -> Self {
+        if std::any::TypeId::of::<E>() != std::any::TypeId::of::<anyhow::Error>() {
+            self.metadata_mut().root_type = Some(std::any::type_name::<E>());
+        }
+        self
+    }
+
+    #[cfg(not(feature = "lambda"))]
+    #[expect(
+        clippy::extra_unused_type_parameters,
+        reason = "the type is only recorded with the feature"
+    )]
+    pub(crate) const fn with_root_type<E: 'static>(self) -> Self {
+        self
+    }
+
+    /// Get the name of the type of the error this one was converted from, if it was
+    /// converted from a typed error, as with `?` or a [`Context`] method.
+    #[cfg(feature = "lambda")]
+    pub(crate) fn root_type(&self) -> Option<&'static str> {
+        self.0.metadata.as_ref()?.root_type
+    }
+
+    /// Add context to this error, see [`anyhow::Context`] for more details.
+    ///
+    /// Fields added after this call belong to the new context layer, see
+    /// [`Error::contexts`].
+    pub fn context<C: fmt::Display + Send + Sync + 'static>(mut self, context: C) -> Self {
+        self.0.frames.push(self.0.fields.len());
+        let mut parts = self.into_parts();
+        parts.inner = parts.inner.context(context);
+        Self::from_parts(parts)
+    }
+
+    /// Add context to this error with a closure, see [`anyhow::Context`] for more details.
+    pub fn with_context<C, F>(self, f: F) -> Self
+    where
+        C: fmt::Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        self.context(f())
+    }
+
+    /// Iterate over the context layers of this error, from the most recently added
+    /// context down to the original error.
+    ///
+    /// Each frame holds the fields that were added while it was the outermost layer,
+    /// fields added when the error was created belong to the last (root) frame. The
+    /// flattened view of all fields is still available through [`Error::fields`].
+    pub fn contexts(&self) -> impl Iterator<Item = ContextFrame<'_>> {
+        let starts = self.0.frames.iter().rev().copied().chain(iter::once(0));
+        let ends = iter::once(self.0.fields.len()).chain(self.0.frames.iter().rev().copied());
+        let layers = (0..=self.0.frames.len()).rev();
+        starts.zip(ends).zip(self.0.inner.chain()).zip(layers).map(
+            |(((start, end), error), layer)| ContextFrame {
+                error,
+                fields: &self.0.fields,
+                start,
+                end,
+                location: self.layer_location(layer),
+            },
+        )
+    }
+
+    /// Get the recorded location of a context layer, where 0 is the root error.
+    fn layer_location(&self, layer: usize) -> Option<&'static Location<'static>> {
+        let metadata = self.0.metadata.as_ref()?;
+        metadata
+            .locations
+            .iter()
+            .find(|(l, _)| *l == layer)
+            .map(|(_, location)| *location)
+    }
+
+    /// Get the `Display` output of this error.
+    ///
+    /// The output is rendered once and cached, so repeated calls (and repeated
+    /// formatting with `{}`) do not render the message and fields again. The cache
+    /// is cleared whenever the error is changed.
+    pub fn to_display_string(&self) -> &str {
+        self.0.display_cache.get_or_init(|| {
+            let mut rendered = String::new();
+            // Writing into a `String` only fails if a `Display` impl returns an error
+            self.render_display(&mut rendered).ok();
+            rendered.into_boxed_str()
+        })
+    }
+
+    /// Mutable access to the metadata, allocating it if needed.
+    fn metadata_mut(&mut self) -> &mut Metadata {
+        self.0.metadata.get_or_insert_with(Box::default)
+    }
+
+    /// Mutable access to the fields, clearing the cached `Display` output.
+    fn fields_mut(&mut self) -> &mut Fields {
+        self.0.display_cache.take();
+        &mut self.0.fields
+    }
+
+    /// Render the message and fields as shown by `Display`.
+    fn render_display(&self, f: &mut impl Write) -> fmt::Result {
+        // Display the main error
+        write!(f, "{}", self.0.inner)?;
+        self.render_fields(f)
+    }
+
+    /// Render the fields suffix of the `Display` output, see [`FieldFormat`].
+    fn render_fields(&self, f: &mut impl Write) -> fmt::Result {
+        field_format().write_fields(f, &self.0.fields)
+    }
+
+    /// Render this error as a single logfmt line.
+    ///
+    /// The message is written under `msg`, followed by `code` and `level` if set, then
+    /// each field, and finally the rest of the chain joined by `: ` under `cause`.
+    /// Values are quoted and escaped where needed, and characters that are not allowed
+    /// in logfmt keys are replaced with `_`.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// let err = anyhow!(path = "/etc/app.toml", attempt = 3, "permission denied")
+    ///     .context("failed to open");
+    /// assert_eq!(
+    ///     err.to_logfmt(),
+    ///     r#"msg="failed to open" path=/etc/app.toml attempt=3 cause="permission denied""#
+    /// );
+    /// ```
+    pub fn to_logfmt(&self) -> String {
+        self.ack();
+        let mut rendered = String::new();
+        // Writing into a `String` only fails if a `Display` impl returns an error
+        write_logfmt(&mut rendered, self).ok();
+        rendered
+    }
+
+    /// Get a view of the fields of this error as a `valuable` map, for consumers that
+    /// inspect structured values.
+    #[cfg(feature = "valuable")]
+    pub fn field_map(&self) -> crate::FieldMap<'_> {
+        crate::FieldMap::new(&self.0.fields)
+    }
+
+    /// Get a view of this error as a `valuable` structure with its message, its causes
+    /// and its fields, see [`ErrorValue`](crate::ErrorValue).
+    ///
+    /// `tracing` can only record `valuable` values when built with
+    /// `--cfg tracing_unstable` and its `valuable` feature. Record the error with
+    /// `tracing::field::valuable` so that subscribers with `valuable` support, such as
+    /// the JSON formatter of `tracing-subscriber`, receive a nested object instead of
+    /// the `Display` string:
+    ///
+    /// ```ignore
+    /// tracing::error!(error = tracing::field::valuable(&err.as_value()), "request failed");
+    /// ```
+    #[cfg(feature = "valuable")]
+    pub const fn as_value(&self) -> crate::ErrorValue<'_> {
+        crate::ErrorValue::new(self)
+    }
+
+    /// Attach the name and the fields of the current span and its parents, as fields
+    /// prefixed with `span.`, such as `span.name` and `span.request_id`.
+    ///
+    /// The fields of spans are only known to a subscriber that has a
+    /// [`FieldCaptureLayer`](crate::FieldCaptureLayer). Without one, or outside of any
+    /// span, nothing is attached. The outermost span comes first, and a field recorded
+    /// on several spans is attached for each of them.
+    ///
+    /// With the `auto-span-fields` feature, this is done for every error when it is
+    /// created.
+    #[cfg(feature = "span-fields")]
+    pub fn in_current_span(mut self) -> Self {
+        for (key, value) in crate::span::current_span_fields() {
+            self.fields_mut().push_cow(key, value);
+        }
+        self
+    }
+
+    /// Get the backtrace of the underlying anyhow error, see
+    /// [`anyhow::Error::backtrace`]. It is only captured when `RUST_BACKTRACE` or
+    /// `RUST_LIB_BACKTRACE` is set.
+    pub fn backtrace(&self) -> &std::backtrace::Backtrace {
+        self.0.inner.backtrace()
+    }
+
+    /// Get the spans that were entered when this error was created.
+    ///
+    /// The span trace is only captured when the subscriber has a
+    /// `tracing_error::ErrorLayer`, and inside of a span. It is rendered by `Debug` and
+    /// by [`Error::report`] after the causes.
+    #[cfg(feature = "span-trace")]
+    pub fn span_trace(&self) -> Option<&tracing_error::SpanTrace> {
+        self.0.metadata.as_ref()?.span_trace.as_ref()
+    }
+
+    /// Emit this error as a `tracing` event, at its [`level`](Error::level) or at
+    /// `ERROR` if it has none. See [`Error::emit_at`].
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    #[track_caller]
+    pub fn emit(&self) {
+        crate::emit::emit(
+            self,
+            self.level().unwrap_or(Level::Error),
+            Location::caller(),
+        );
+    }
+
+    /// Emit this error as a `tracing` event at `level`.
+    ///
+    /// The message of the event is the message of the outermost context layer, the
+    /// whole chain is recorded as `error.chain`, and each field of the error is recorded
+    /// as a field of the event. Numbers and booleans given to the macros without `%` or
+    /// `?` are recorded as numbers and booleans, and the other fields as strings. The
+    /// event has the [`target`](Error::target) of the error, the name set with
+    /// [`Error::with_event_name`] or `error`, and the file and line this was called
+    /// from. Nothing is done if the level is disabled.
+    ///
+    /// Events are limited to 256 fields, further fields of the error are left out.
+    ///
+    /// With the `log` feature and without the `tracing` feature, the error is logged
+    /// as a `log` record instead, with the whole chain and the fields in the message as
+    /// rendered by `{:#}`. The `log-kv` feature also attaches the fields as key-values.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::{Level, anyhow};
+    ///
+    /// let err = anyhow!(host = "db-3", port = 5432, "connection refused");
+    /// err.emit_at(Level::Warn);
+    /// ```
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    #[track_caller]
+    pub fn emit_at(&self, level: Level) {
+        crate::emit::emit(self, level, Location::caller());
+    }
+
+    /// Emit this error as [`Error::emit`] does, with `target` as the target of the
+    /// event instead of the [`target`](Error::target) of the error.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// anyhow!(order_id = 42, "card declined").emit_with_target("my_app::payments");
+    /// ```
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    #[track_caller]
+    pub fn emit_with_target(&self, target: &'static str) {
+        crate::emit::emit_with(
+            self,
+            target,
+            self.level().unwrap_or(Level::Error),
+            Location::caller(),
+            &[],
+        );
+    }
+
+    /// Emit this error as [`Error::emit`] does with probability `rate`, from `0.0` for
+    /// never to `1.0` for always, so that a representative sample of frequent errors
+    /// is emitted.
+    ///
+    /// Emitted errors have a `sample_rate` field with `rate`, so that counts can be
+    /// weighted back up. The others are counted by code instead, and the counts are
+    /// emitted by [`flush_sampling_stats`](crate::flush_sampling_stats). Use
+    /// [`set_sampling`](crate::set_sampling) to sample all the errors with a code.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// // Emitted for about one request in a hundred
+    /// anyhow!(client = "10.0.0.7", "too many requests").emit_sampled(0.01);
+    /// ```
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    #[track_caller]
+    pub fn emit_sampled(&self, rate: f64) {
+        crate::sample::emit_sampled(
+            self,
+            rate,
+            self.level().unwrap_or(Level::Error),
+            Location::caller(),
+        );
+    }
+
+    /// Get the target of the events emitted for this error.
+    ///
+    /// This is the module the error was created in by the macros, such as
+    /// `my_app::payments`, so that error events can be filtered by module along with
+    /// the other events of the module. Errors created otherwise, such as by converting
+    /// another error with `?` or [`Context`], use the target set with
+    /// [`set_default_target`](crate::set_default_target), or `anyhow_tracing`.
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    pub fn target(&self) -> &'static str {
+        self.0
+            .module_path
+            .unwrap_or_else(crate::emit::default_target)
+    }
+
+    /// Set the name of the events emitted for this error, for subscribers that route
+    /// events by name. Events are named `error` otherwise.
+    ///
+    /// `log` records have no name, so this only applies to `tracing` events.
+    pub fn with_event_name(mut self, name: &'static str) -> Self {
+        self.metadata_mut().event_name = Some(name);
+        self
+    }
+
+    /// Get the name of the events emitted for this error, if one was set.
+    pub fn event_name(&self) -> Option<&'static str> {
+        self.0.metadata.as_ref()?.event_name
+    }
+
+    /// Emit this error as [`Error::emit`] does, at most once per `period` for errors
+    /// with the same fingerprint.
+    ///
+    /// The fingerprint is the [`code`](Error::code) of the error if it has one, and its
+    /// messages without the fields otherwise, so that the same failure with different
+    /// field values is only emitted once. The first error is emitted right away, and
+    /// the following ones are counted instead until `period` has passed since. The
+    /// next one emitted then has a `suppressed_count` field with the number of errors
+    /// left out, if there were any.
+    ///
+    /// The last emission of at most 4096 fingerprints is kept, beyond that the oldest
+    /// are forgotten.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    ///
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// for attempt in 0..1000 {
+    ///     // Emitted once, for the first attempt
+    ///     anyhow!(attempt = attempt, "connection refused")
+    ///         .emit_throttled(Duration::from_secs(10));
+    /// }
+    /// ```
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    #[track_caller]
+    pub fn emit_throttled(&self, period: Duration) {
+        let fingerprint = match self.code() {
+            Some(code) => crate::throttle::fingerprint(code),
+            None => crate::throttle::fingerprint(&self.display_without_fields()),
+        };
+        crate::throttle::emit_throttled(self, fingerprint, period, Location::caller());
+    }
+
+    /// Emit this error as [`Error::emit_throttled`] does, with the fingerprint given
+    /// by the text of `key`.
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    #[track_caller]
+    pub fn emit_throttled_by<K: fmt::Display + ?Sized>(&self, key: &K, period: Duration) {
+        let fingerprint = crate::throttle::fingerprint(key);
+        crate::throttle::emit_throttled(self, fingerprint, period, Location::caller());
+    }
+
+    /// Record this error as an exception on the OpenTelemetry span of the current
+    /// span, and set the status of that span to error.
+    ///
+    /// The span gets an `exception` event with the attributes `exception.message`,
+    /// the whole chain of messages without the fields, `exception.type`, the
+    /// [`code`](Error::code) of the error or `anyhow_tracing::Error` without one, and
+    /// each field of the error under its own key. Fields are recorded as the text they
+    /// were rendered to when they were added, so every value is a string.
+    ///
+    /// Nothing is recorded outside of any span, or if the subscriber has no
+    /// `tracing_opentelemetry` layer.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// let span = tracing::info_span!("sync");
+    /// let _guard = span.enter();
+    /// anyhow!(host = "db-3", "connection refused").record_exception();
+    /// ```
+    #[cfg(feature = "opentelemetry")]
+    pub fn record_exception(&self) {
+        crate::otel::record_exception(self);
+    }
+
+    /// Attach the OpenTelemetry trace and span IDs of the current span, as `trace_id`
+    /// and `span_id` fields in lowercase hex, so that the trace of an error can be
+    /// found from wherever the error ends up.
+    ///
+    /// Nothing is attached outside of any span, without a `tracing_opentelemetry`
+    /// layer, or if the trace is not sampled.
+    ///
+    /// With the `auto-trace-context` feature, this is done for every error when it is
+    /// created.
+    #[cfg(feature = "opentelemetry")]
+    pub fn with_trace_context(mut self) -> Self {
+        if let Some((trace_id, span_id)) = crate::otel::trace_context() {
+            self.add_field("trace_id", trace_id);
+            self.add_field("span_id", span_id);
+        }
+        self
+    }
+
+    /// Record this error on `span`, which need not be the current span.
+    ///
+    /// The whole chain of messages without the fields is recorded under `error`, each
+    /// field of the error under its own name, and all the fields together as logfmt
+    /// pairs under `error.fields`, as in `host=db-3 port=5432`.
+    ///
+    /// `tracing` only records the fields a span declared when it was created, and
+    /// leaves out the others. Declare the fields to record with
+    /// `tracing::field::Empty`; `error.fields` holds the fields whose names are not
+    /// known in advance:
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    /// use tracing::field::Empty;
+    ///
+    /// let span = tracing::info_span!("request", error = Empty, error.fields = Empty, host = Empty);
+    /// let err = anyhow!(host = "db-3", port = 5432, "connection refused");
+    /// // Records `error`, `error.fields` and `host`, but not `port`
+    /// err.record_to_span(&span);
+    /// ```
+    #[cfg(feature = "tracing")]
+    pub fn record_to_span(&self, span: &tracing::Span) {
+        self.ack();
+        span.record(
+            "error",
+            tracing::field::display(self.display_without_fields()),
+        );
+        for (key, value) in self.fields() {
+            span.record(key, value);
+        }
+        if !self.fields().is_empty() {
+            let mut rendered = String::new();
+            // Writing into a `String` only fails if a `Display` impl returns an error
+            crate::logfmt::write_logfmt_fields(&mut rendered, self.fields()).ok();
+            span.record("error.fields", rendered);
+        }
+    }
+
+    /// Get the fields of this error under the standard attribute names of Datadog, see
+    /// the [`datadog`](crate::datadog) module.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// let err = anyhow!(host = "db-3", "connection refused")
+    ///     .context("failed to sync")
+    ///     .with_code("DB_UNAVAILABLE");
+    /// let fields = err.to_datadog_fields();
+    /// assert!(fields.contains(&("error.kind", "DB_UNAVAILABLE".to_owned())));
+    /// assert!(fields.contains(&(
+    ///     "error.message",
+    ///     "failed to sync: connection refused".to_owned()
+    /// )));
+    /// assert!(fields.contains(&("host", "db-3".to_owned())));
+    /// ```
+    #[cfg(feature = "datadog")]
+    pub fn to_datadog_fields(&self) -> Vec<(&'static str, String)> {
+        crate::datadog::fields(self)
+    }
+
+    /// Render this error as a JSON value, with the entries of its
+    /// [`Serialize`](serde::Serialize) implementation.
+    ///
+    /// With `nest_fields`, dotted field keys become nested objects, so `http.status`
+    /// is rendered as `{"http": {"status": ...}}`. When a key is both a field and the
+    /// prefix of other fields, such as `http` and `http.status`, the one added last
+    /// wins. Fields added with [`Error::with_field_json`] keep their JSON value, other
+    /// fields are stored as the text they were rendered to when they were added, so
+    /// their value is a JSON string.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    /// use serde_json::json;
+    ///
+    /// let err = anyhow!("upstream unavailable")
+    ///     .with_field("http.status", 503)
+    ///     .with_field("http.method", "GET");
+    /// let value = err.to_json(true);
+    /// assert_eq!(value["message"], "upstream unavailable");
+    /// assert_eq!(value["fields"], json!({ "http": { "status": "503", "method": "GET" } }));
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_json(&self, nest_fields: bool) -> serde_json::Value {
+        crate::serialize::to_json(self, nest_fields)
+    }
+
+    /// Get the fields of this error as native systemd journal fields, see the
+    /// [`journald`](crate::journald) module.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// let err = anyhow!("upstream unavailable").with_field("http.status", 503);
+    /// let fields = err.to_journal_fields();
+    /// assert!(fields.contains(&("MESSAGE".to_owned(), "upstream unavailable".to_owned())));
+    /// assert!(fields.contains(&("PRIORITY".to_owned(), "3".to_owned())));
+    /// assert!(fields.contains(&("HTTP_STATUS".to_owned(), "503".to_owned())));
+    /// ```
+    #[cfg(feature = "journald")]
+    pub fn to_journal_fields(&self) -> Vec<(String, String)> {
+        crate::journald::fields(self)
+    }
+
+    /// Send this error to the systemd journal, with its fields as native journal
+    /// fields, see the [`journald`](crate::journald) module.
+    ///
+    /// Fails if journald is not running, or if the message is too large for a single
+    /// datagram.
+    #[cfg(all(feature = "journald", unix))]
+    pub fn emit_to_journal(&self) -> std::io::Result<()> {
+        crate::journald::emit_to(self, crate::journald::SOCKET_PATH)
+    }
+
+    /// Convert this error into a JavaScript `Error` object, for WebAssembly frontends.
+    ///
+    /// The `message` property is the [public message](Error::with_public_message) of
+    /// the error, or the message of its outermost layer without one. The `fields`
+    /// property is an object with a property for each field, holding its JSON value
+    /// when it has one, and the `code` property is the [code](Error::code) of the
+    /// error, left out if it has none. `JsValue` also converts from `Error` this way,
+    /// so functions exported with `#[wasm_bindgen]` can use `?`.
+    ///
+    /// ```ignore
+    /// use anyhow_tracing::anyhow;
+    /// use wasm_bindgen::JsValue;
+    ///
+    /// let js = anyhow!(field = "email", "invalid address").with_code("INVALID").to_js();
+    /// let fields = js_sys::Reflect::get(&js, &JsValue::from_str("fields")).unwrap();
+    /// ```
+    #[cfg(feature = "wasm")]
+    pub fn to_js(&self) -> wasm_bindgen::JsValue {
+        crate::wasm::to_js(self)
+    }
+
+    /// Convert this error into an `eyre::Report`, for code that works with eyre.
+    ///
+    /// The report holds the error itself, so its `Display` output still has the fields
+    /// and its sources are the rest of the chain. Converting the report back into an
+    /// `Error` returns this error unchanged. `?` converts with this in functions
+    /// returning an eyre result, and the other way with `From<eyre::Report>`.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::{Error, anyhow};
+    ///
+    /// let report = anyhow!(host = "db-3", "connection refused").into_eyre();
+    /// assert_eq!(report.to_string(), "connection refused [host=db-3]");
+    ///
+    /// let err = Error::from(report.wrap_err("failed to sync"));
+    /// assert_eq!(err.to_string(), "failed to sync [host=db-3]");
+    /// ```
+    #[cfg(feature = "eyre")]
+    pub fn into_eyre(self) -> eyre::Report {
+        eyre::Report::new(self)
+    }
+
+    /// Convert this error into a miette report, for its graphical rendering.
+    ///
+    /// The report shows the message of the outermost layer, then each underlying
+    /// layer as a cause, chained through `diagnostic_source`. The [code](Error::code)
+    /// and [documentation link](Error::with_docs_url) are those of the error, and its
+    /// [notes](Error::note) and fields are shown in the help at the bottom, rather
+    /// than after the message as in the `Display` output. See the `Diagnostic`
+    /// implementation of `Error` for errors rendered without conversion.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// let report = anyhow!(path = "/etc/app.toml", "missing key `port`")
+    ///     .with_code("config::missing_key")
+    ///     .note("add `port = 8080` under `[server]`")
+    ///     .into_miette();
+    /// assert_eq!(report.to_string(), "missing key `port`");
+    /// assert_eq!(report.code().unwrap().to_string(), "config::missing_key");
+    /// ```
+    #[cfg(feature = "miette")]
+    pub fn into_miette(self) -> miette::Report {
+        crate::miette::report(self)
+    }
+
+    /// Capture this error as a Sentry event with the current hub, returning the id of
+    /// the event.
+    ///
+    /// Each layer of the chain is an exception, the outermost one named by the
+    /// [code](Error::code) of the error when it has one. The fields named with
+    /// [`set_sentry_tags`](crate::set_sentry_tags) are tags, and the other fields are
+    /// in the `fields` context, with their JSON values when they have one. The level
+    /// is the [level](Error::level) of the error, `error` without one, and the
+    /// fingerprint is its code, or its messages without fields, as for
+    /// [`Error::emit_throttled`].
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// let events = sentry::test::with_captured_events(|| {
+    ///     anyhow!(user_id = 7, "no row returned")
+    ///         .with_code("USER_NOT_FOUND")
+    ///         .capture_sentry();
+    /// });
+    /// assert_eq!(events[0].exception[0].ty, "USER_NOT_FOUND");
+    /// assert_eq!(*events[0].fingerprint, ["USER_NOT_FOUND"]);
+    /// ```
+    #[cfg(feature = "sentry")]
+    pub fn capture_sentry(&self) -> sentry_core::types::Uuid {
+        crate::sentry::capture(self)
+    }
+
+    /// Reject a warp request with this error, as a [`Rejection`](crate::Rejection).
+    ///
+    /// Recover with [`handle_rejection`](crate::handle_rejection) to emit the error and
+    /// answer with its status and public details. `warp::Rejection` also converts from
+    /// `Error`, so filters can use `?` on this crate's `Result`.
+    #[cfg(feature = "warp")]
+    pub fn into_rejection(self) -> warp::Rejection {
+        crate::warp::reject(self)
+    }
+
+    /// Convert this error into a gRPC status, keeping its code and fields.
+    ///
+    /// The gRPC code is parsed from the `grpc.code` field, or else from the
+    /// [code](Error::code) of the error, as a canonical name such as `NOT_FOUND` or
+    /// `NotFound`, or as a number. It is `INTERNAL` if neither is a gRPC code. The
+    /// status message is the [public message](Error::with_public_message), or the
+    /// description of the gRPC code without one.
+    ///
+    /// The code of the error is sent as the `x-error-code` metadata entry, and each
+    /// field as an `x-error-field-<key>` entry, with the key in lower case and the
+    /// characters metadata keys don't allow replaced by `_`. Values that are not
+    /// printable ASCII are sent as binary `-bin` entries. Fields are sent to clients,
+    /// so don't add fields with internal details to errors answering requests.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    /// use tonic::Code;
+    ///
+    /// let err = anyhow!(user_id = 7, "no row returned")
+    ///     .with_code("NOT_FOUND")
+    ///     .with_public_message("user not found");
+    /// let status = err.to_status();
+    /// assert_eq!(status.code(), Code::NotFound);
+    /// assert_eq!(status.message(), "user not found");
+    /// assert_eq!(status.metadata().get("x-error-field-user_id").unwrap(), "7");
+    /// ```
+    #[cfg(feature = "tonic")]
+    pub fn to_status(&self) -> tonic::Status {
+        crate::grpc::to_status(self)
+    }
+
+    /// Rebuild an error from a gRPC status, such as one made by [`Error::to_status`]
+    /// on a server.
+    ///
+    /// The message of the status becomes the message and the public message of the
+    /// error. Its gRPC code is added as the `grpc.code` field, so converting the error
+    /// back gives the same code, then the code and fields are read back from the
+    /// metadata. Fields named with [`set_remote_fields`](crate::set_remote_fields)
+    /// keep their names, and the others are kept together as logfmt pairs in the
+    /// `grpc.fields` field, so a peer sending many different keys can't make the
+    /// program allocate names that are never freed.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::{Error, anyhow};
+    ///
+    /// anyhow_tracing::set_remote_fields(&["user_id"]).ok();
+    /// let status = anyhow!(user_id = 7, shard = 3, "no row returned")
+    ///     .with_public_message("user not found")
+    ///     .to_status();
+    /// let err = Error::from_status(&status);
+    /// assert_eq!(err.get_field("user_id"), Some("7"));
+    /// assert_eq!(err.get_field("grpc.fields"), Some("shard=3"));
+    /// ```
+    #[cfg(feature = "tonic")]
+    pub fn from_status(status: &tonic::Status) -> Self {
+        crate::grpc::from_status(status)
+    }
+
+    /// Wrap a sqlx error, keeping it available to [`Error::downcast_ref`], and add the
+    /// details it holds as fields:
+    ///
+    /// - `db.code`, `db.constraint` and `db.table`: the SQLSTATE code, constraint and
+    ///   table of an error returned by the database, when the driver reports them.
+    /// - `db.kind`: `unique_violation`, `foreign_key_violation`, `not_null_violation`
+    ///   or `check_violation` for constraint violations, `database` for other errors
+    ///   returned by the database, `not_found` when a query returned no row, and
+    ///   `connection` when the database couldn't be reached.
+    /// - `retryable=true` for connection errors, as running the query again may succeed.
+    ///
+    /// [`DbResultExt::db_context`](crate::DbResultExt::db_context) does the same while
+    /// adding context.
+    ///
+    /// As with [`Error::new`], this may emit an event for the error.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::Error;
+    ///
+    /// let err = Error::from_sqlx(sqlx::Error::PoolTimedOut);
+    /// assert_eq!(err.get_field("db.kind"), Some("connection"));
+    /// assert_eq!(err.get_field("retryable"), Some("true"));
+    /// ```
+    #[cfg(feature = "sqlx")]
+    #[track_caller]
+    pub fn from_sqlx(error: sqlx::Error) -> Self {
+        Caller::new().create(crate::sqlx::enrich(error))
+    }
+
+    /// Wrap a reqwest error, keeping it available to [`Error::downcast_ref`], and add
+    /// the details it holds as fields:
+    ///
+    /// - `http.url`: the URL of the request, without its credentials.
+    /// - `http.status`: the status of the response, for errors made by
+    ///   `Response::error_for_status`.
+    /// - `http.timeout=true` when the request timed out, and `http.connect=true` when
+    ///   the connection to the server failed.
+    /// - `retryable=true` for timeouts and connection failures, as sending the request
+    ///   again may succeed.
+    ///
+    /// [`HttpResultExt::http_context`](crate::HttpResultExt::http_context) does the
+    /// same while adding context.
+    ///
+    /// As with [`Error::new`], this may emit an event for the error.
+    #[cfg(feature = "reqwest")]
+    #[track_caller]
+    pub fn from_reqwest(error: reqwest::Error) -> Self {
+        Caller::new().create(crate::reqwest::enrich(error))
+    }
+
+    /// Convert this error into a GELF message for Graylog, sent from `host`.
+    ///
+    /// The `short_message` is the message of the outermost context layer, and the
+    /// `full_message` is the whole chain followed by the fields, as with
+    /// [`Error::fmt_compact`]. The `level` is the syslog severity of the
+    /// [level](Error::level) of the error, 3 for errors without one. The code and each
+    /// field become additional fields, prefixed with `_`, with every character that
+    /// GELF doesn't allow in names replaced by `_`. A field named `id` is left out, as
+    /// GELF reserves `_id`.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::{Level, anyhow};
+    /// use serde_json::json;
+    ///
+    /// let err = anyhow!(job_id = 42, "job timed out").with_level(Level::Warn);
+    /// assert_eq!(
+    ///     err.to_gelf("worker-1"),
+    ///     json!({
+    ///         "version": "1.1",
+    ///         "host": "worker-1",
+    ///         "short_message": "job timed out",
+    ///         "full_message": "job timed out [job_id=42]",
+    ///         "level": 4,
+    ///         "_job_id": "42",
+    ///     })
+    /// );
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn to_gelf(&self, host: &str) -> serde_json::Value {
+        crate::gelf::to_gelf(self, host)
+    }
+
+    /// Convert this error into an RFC 7807 problem details object, the body of an
+    /// `application/problem+json` response, see [`ProblemDetails`](crate::ProblemDetails).
+    ///
+    /// The title is the [public message](Error::with_public_message), or else the
+    /// message of the outermost context layer. The status is the
+    /// [status](Error::with_status) of the error, 500 without one. The detail is the
+    /// whole chain of messages, and each field becomes an extension member, so use
+    /// [`ProblemDetails::allow_extensions`](crate::ProblemDetails::allow_extensions)
+    /// and replace the detail before sending it to clients that must not see internal
+    /// details.
+    #[cfg(all(feature = "http", feature = "json"))]
+    pub fn to_problem_details(&self) -> crate::ProblemDetails {
+        crate::ProblemDetails::new(self)
+    }
+
+    /// Get the messages, fields, code, level and notes of this error as plain owned
+    /// values, which can be sent to another process, see [`ErrorData`](crate::ErrorData).
+    pub fn to_data(&self) -> crate::ErrorData {
+        crate::data::to_data(self)
+    }
+
+    /// Rebuild an error from the data of another one, see [`Error::to_data`].
+    ///
+    /// The rebuilt error has a context layer for each message of the chain, so its
+    /// `Display` output and [`Error::chain`] match the original, and all the fields
+    /// belong to the outermost layer. The underlying errors are lost, so it can't be
+    /// downcast to them. No event is emitted for it, as with [`Error::quiet`].
+    ///
+    /// Fields named with [`set_remote_fields`](crate::set_remote_fields) keep their
+    /// names, and the others are kept together as logfmt pairs in the `remote.fields`
+    /// field, so data from untrusted sources can't make the program allocate names
+    /// that are never freed.
+    pub fn from_data(data: crate::ErrorData) -> Self {
+        crate::data::from_data(data)
+    }
+
+    /// Get a multi-line rendering of this error for humans, with the causes, fields
+    /// and notes each in their own section. See [`Report`] for the layout.
+    ///
+    /// The `Debug` output is unaffected.
+    pub const fn report(&self) -> Report<'_> {
+        Report::new(self)
+    }
+
+    /// Write the report of this error for the user of a command-line tool to `out`, as
+    /// [`Error::exit_with_report`] does to standard error, without colors.
+    ///
+    /// Errors with a [public message](Error::with_public_message) are reported with it
+    /// and their notes only, unless reports were made verbose with
+    /// [`set_verbose_reports`](crate::set_verbose_reports). Verbose reports also have
+    /// the backtrace, when one was captured.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::anyhow;
+    ///
+    /// let err = anyhow!(path = "/etc/app.toml", "permission denied")
+    ///     .context("failed to open")
+    ///     .with_public_message("cannot read the configuration")
+    ///     .note("run `app init` to create it");
+    /// let mut out = Vec::new();
+    /// err.print_report(&mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "cannot read the configuration\n\nNotes:\n    run `app init` to create it\n"
+    /// );
+    /// ```
+    pub fn print_report<W: std::io::Write>(&self, out: &mut W) -> std::io::Result<()> {
+        write!(out, "{}", crate::report::cli_report(self))
+    }
+
+    /// Write the report of this error to standard error, then exit the process with its
+    /// [exit code](Error::with_exit_code), 1 without one.
+    ///
+    /// This is meant for the top of the `main` function of a command-line tool. The
+    /// report is the one of [`Error::print_report`], styled with ANSI escapes with the
+    /// `color` feature when standard error is a terminal and `NO_COLOR` is not set, see
+    /// [`Report::colored`].
+    ///
+    /// ```rust,no_run
+    /// fn run() -> anyhow_tracing::Result<()> {
+    ///     // ...
+    ///     # Ok(())
+    /// }
+    ///
+    /// fn main() {
+    ///     if let Err(err) = run() {
+    ///         err.exit_with_report();
+    ///     }
+    /// }
+    /// ```
+    pub fn exit_with_report(self) -> ! {
+        let report = crate::report::cli_report(&self);
+        #[cfg(feature = "color")]
+        let report = report.colored();
+        eprint!("{}", report);
+        std::process::exit(i32::from(self.exit_code().unwrap_or(1)))
+    }
+
+    /// Get the message of the outermost context layer, without any fields.
+    ///
+    /// For an error without context this is the message it was created with.
+    pub fn message(&self) -> String {
+        self.0.inner.to_string()
+    }
+
+    /// Get a `Display` adapter rendering the whole chain of messages joined by `: `,
+    /// as `{:#}` does, but without the fields.
+    pub fn display_without_fields(&self) -> impl fmt::Display + '_ {
+        WithoutFields(self)
+    }
+
+    /// Get a `Display` adapter rendering the whole chain on one line, followed by the
+    /// fields, as in `failed to sync: connection reset by peer [host=db-3, attempt=2]`.
+    ///
+    /// The messages of the layers are joined by `: `, as with `{:#}`, except that a
+    /// message identical to the one before it is only written once.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::Error;
+    ///
+    /// let err = Error::msg("connection reset by peer")
+    ///     .context("connection reset by peer")
+    ///     .context("failed to sync")
+    ///     .with_field("host", "db-3");
+    /// assert_eq!(
+    ///     err.fmt_compact().to_string(),
+    ///     "failed to sync: connection reset by peer [host=db-3]"
+    /// );
+    /// ```
+    pub fn fmt_compact(&self) -> impl fmt::Display + '_ {
+        Compact(self)
+    }
+
+    /// Get a `Display` adapter rendering the default `Debug` output of this error
+    /// without the backtrace, so the output is the same on every machine and whatever
+    /// `RUST_BACKTRACE` is set to.
+    ///
+    /// The report hook set with [`set_report_hook`](crate::set_report_hook) is not used.
+    /// This is the recommended form for snapshot tests of errors:
+    ///
+    /// ```rust
+    /// use anyhow_tracing::Error;
+    ///
+    /// let err = Error::msg("connection refused")
+    ///     .with_field("host", "db-3")
+    ///     .context("failed to sync");
+    /// assert_eq!(
+    ///     err.debug_stable().to_string(),
+    ///     "failed to sync\n\nCaused by:\n    connection refused\n\nFields:\n\tconnection refused:\n\t\thost: \"db-3\"\n"
+    /// );
+    /// ```
+    pub fn debug_stable(&self) -> impl fmt::Display + '_ {
+        StableDebug(self)
+    }
+
+    /// Get a `Display` adapter rendering exactly what the `Debug` output of an
+    /// `anyhow::Error` with the same chain would be, with the fields folded into the
+    /// first line as `Display` renders them.
+    ///
+    /// This helps migrating from anyhow, where tests or log parsing rely on its layout:
+    /// the `Caused by:` section and the backtrace are unchanged, and there are no
+    /// `Fields:`, `Violations:` or `Notes:` sections.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::Error;
+    ///
+    /// let err = Error::msg("connection refused")
+    ///     .context("failed to sync")
+    ///     .with_field("host", "db-3");
+    /// let anyhow_err = anyhow::anyhow!("connection refused").context("failed to sync [host=db-3]");
+    ///
+    /// assert_eq!(err.debug_anyhow_compatible().to_string(), format!("{:?}", anyhow_err));
+    /// ```
+    pub fn debug_anyhow_compatible(&self) -> impl fmt::Display + '_ {
+        AnyhowDebug(self)
+    }
+
+    /// Get a `Display` adapter rendering this error with the given field format,
+    /// instead of the one set with [`set_field_format`](crate::set_field_format).
+    ///
+    /// As with the error itself, the alternate flag (`{:#}`) renders the whole chain.
+    pub fn display_with(&self, format: FieldFormat) -> impl fmt::Display + '_ {
+        DisplayWith {
+            error: self,
+            format,
+        }
+    }
+
+    /// Get the root cause of this error.
+    pub fn root_cause(&self) -> &dyn StdError {
+        self.0.inner.root_cause()
+    }
+
+    /// Get the chain of errors.
+    pub fn chain(&self) -> anyhow::Chain<'_> {
+        self.0.inner.chain()
+    }
+
+    /// Downcast the error to a concrete type.
+    pub fn downcast<E>(self) -> std::result::Result<E, Self>
+    where
+        E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        self.ack();
+        let mut parts = self.into_parts();
+        match parts.inner.downcast::<E>() {
+            Ok(e) => Ok(e),
+            Err(inner) => {
+                parts.inner = inner;
+                Err(Self::from_parts(parts))
+            }
+        }
+    }
+
+    /// Downcast the error to a reference to a concrete type.
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+    where
+        E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        self.ack();
+        self.0.inner.downcast_ref::<E>()
+    }
+
+    /// Downcast the error to a mutable reference to a concrete type.
+    pub fn downcast_mut<E>(&mut self) -> Option<&mut E>
+    where
+        E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        self.ack();
+        self.0.display_cache.take();
+        self.0.inner.downcast_mut::<E>()
+    }
+
+    /// Check if the error is of a particular type.
+    pub fn is<E>(&self) -> bool
+    where
+        E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        self.ack();
+        self.0.inner.is::<E>()
+    }
+}
+
+/// The `Display` adapter returned by [`Error::display_without_fields`].
+struct WithoutFields<'a>(&'a Error);
+
+impl fmt::Display for WithoutFields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#}", self.0.0.inner)
+    }
+}
+
+/// The `Display` adapter returned by [`Error::fmt_compact`].
+struct Compact<'a>(&'a Error);
+
+impl fmt::Display for Compact<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut previous: Option<String> = None;
+        for layer in self.0.chain() {
+            let message = layer.to_string();
+            match &previous {
+                Some(previous) if *previous == message => continue,
+                Some(_) => f.write_str(": ")?,
+                None => {}
+            }
+            f.write_str(&message)?;
+            previous = Some(message);
+        }
+        self.0.render_fields(f)
+    }
+}
+
+/// The `Display` adapter returned by [`Error::debug_stable`].
+struct StableDebug<'a>(&'a Error);
+
+impl fmt::Display for StableDebug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_stable_chain(self.0, f)?;
+        write_debug_sections(self.0, f)
+    }
+}
+
+/// The `Display` adapter returned by [`Error::debug_anyhow_compatible`].
+struct AnyhowDebug<'a>(&'a Error);
+
+impl fmt::Display for AnyhowDebug<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // anyhow starts with the message, which is replaced by the message and the fields
+        let debug = format!("{:?}", self.0.0.inner);
+        let message = self.0.0.inner.to_string();
+        let rest = debug.strip_prefix(&message).unwrap_or_default();
+        write!(f, "{}{}", self.0, rest)
+    }
+}
+
+/// The `Display` adapter returned by [`Error::display_with`].
+struct DisplayWith<'a> {
+    error: &'a Error,
+    format: FieldFormat,
+}
+
+impl fmt::Display for DisplayWith<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "{:#}", self.error.0.inner)?;
+        } else {
+            write!(f, "{}", self.error.0.inner)?;
+        }
+        self.format.write_fields(f, &self.error.0.fields)
+    }
+}
+
+/// With the alternate flag (`{:#}`) the whole chain is rendered, joined by `: ` as
+/// anyhow does, followed by the fields.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.ack();
+        if f.alternate() {
+            write!(f, "{:#}", self.0.inner)?;
+            return self.render_fields(f);
+        }
+        f.write_str(self.to_display_string())
+    }
+}
+
+/// Uses the hook set with [`set_report_hook`](crate::set_report_hook) if there is one,
+/// and [`default_report_hook`] otherwise.
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.ack();
+        match report_hook() {
+            Some(hook) => hook(self, f),
+            None => default_report_hook(self, f),
+        }
+    }
+}
+
+/// Render the default `Debug` output of an error: the anyhow `Debug` output of the
+/// chain, followed by the fields grouped by context layer, the violations and the notes.
+///
+/// This is what the `Debug` output is when no hook was set with
+/// [`set_report_hook`](crate::set_report_hook), for hooks that extend it.
+pub fn default_report_hook(error: &Error, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{:?}", error.0.inner)?;
+    #[cfg(feature = "span-trace")]
+    if let Some(span_trace) = error.span_trace() {
+        write!(f, "\n\nSpan trace:\n{}", span_trace)?;
+    }
+    write_debug_sections(error, f)
+}
+
+/// Write the chain of messages as anyhow's `Debug` output does, without the backtrace.
+fn write_stable_chain(error: &Error, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", error.0.inner)?;
+
+    let causes: Vec<String> = error.chain().skip(1).map(ToString::to_string).collect();
+    match causes.as_slice() {
+        [] => Ok(()),
+        [cause] => write!(f, "\n\nCaused by:\n    {}", cause.replace('\n', "\n    ")),
+        causes => {
+            write!(f, "\n\nCaused by:")?;
+            for (i, cause) in causes.iter().enumerate() {
+                write!(f, "\n{:>5}: {}", i, cause.replace('\n', "\n       "))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Write the fields, violations and notes sections of the `Debug` output.
+fn write_debug_sections(error: &Error, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    // Add fields if any, grouped under the message of the layer they were added at
+    if !error.0.fields.is_empty() {
+        write!(f, "\n\nFields:\n")?;
+        if error.0.frames.is_empty() {
+            write_debug_fields(f, error.0.fields.iter(), "\t")?;
+        } else {
+            for frame in error.contexts().filter(|frame| frame.start < frame.end) {
+                writeln!(f, "\t{}:", frame.error)?;
+                write_debug_fields(f, frame.fields(), "\t\t")?;
+            }
+        }
+    }
+
+    // Add violations if any
+    let violations = error.violations();
+    if !violations.is_empty() {
+        let separator = if error.0.fields.is_empty() {
+            "\n\n"
+        } else {
+            "\n"
+        };
+        writeln!(f, "{}Violations:", separator)?;
+        for (i, (field, message)) in violations.iter().enumerate() {
+            write!(f, "\t{}: {:?}", field, message)?;
+            if i < violations.len().saturating_sub(1) {
+                write!(f, ",")?;
+            }
+            writeln!(f)?;
+        }
+    }
+
+    // Add notes if any
+    let notes = error.notes();
+    if !notes.is_empty() {
+        let separator = if error.0.fields.is_empty() && violations.is_empty() {
+            "\n\n"
+        } else {
+            "\n"
+        };
+        writeln!(f, "{}Notes:", separator)?;
+        for note in notes {
+            writeln!(f, "\t{}", note)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Write fields as a comma separated list, one per line, for the `Debug` output.
+///
+/// Keys are padded so that the values line up, and values longer than the width of
+/// the field format continue on the following lines, under the start of the value.
+fn write_debug_fields(
+    f: &mut fmt::Formatter<'_>,
+    fields: FieldsIter<'_>,
+    indent: &str,
+) -> fmt::Result {
+    let format = field_format();
+    let count = fields.len();
+    let key_width = fields
+        .clone()
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or_default();
+    let value_width = format.value_width().max(1);
+
+    for (i, (key, value)) in format.ordered(fields).into_iter().enumerate() {
+        let (value, len) = format.truncate(value);
+        let mut rendered = format!("{:?}", value);
+        write_truncated_len(&mut rendered, len)?;
+
+        let padding = key_width.saturating_sub(key.chars().count());
+        write!(f, "{}{}:{:padding$} ", indent, key, "", padding = padding)?;
+        let chars: Vec<char> = rendered.chars().collect();
+        for (line, chunk) in chars.chunks(value_width).enumerate() {
+            if line > 0 {
+                write!(
+                    f,
+                    "\n{}{:width$}",
+                    indent,
+                    "",
+                    width = key_width.saturating_add(2)
+                )?;
+            }
+            chunk.iter().try_for_each(|c| f.write_char(*c))?;
+        }
+
+        if i < count.saturating_sub(1) {
+            write!(f, ",")?;
+        }
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+/// A single context layer of an [`Error`], as returned by [`Error::contexts`].
+#[derive(Debug, Clone, Copy)]
+pub struct ContextFrame<'a> {
+    error: &'a (dyn StdError + 'static),
+    fields: &'a Fields,
+    start: usize,
+    end: usize,
+    location: Option<&'static Location<'static>>,
+}
+
+impl<'a> ContextFrame<'a> {
+    /// Get the error of this layer, whose `Display` output is the context message.
+    pub fn error(&self) -> &'a (dyn StdError + 'static) {
+        self.error
+    }
+
+    /// Get the message of this layer.
+    pub fn message(&self) -> String {
+        self.error.to_string()
+    }
+
+    /// Get the fields that were added to the error at this layer.
+    pub fn fields(&self) -> FieldsIter<'a> {
+        self.fields.range(self.start, self.end)
+    }
+
+    /// Get a field value added at this layer by key, this is an O(n) operation.
+    pub fn get_field(&self, key: &str) -> Option<&'a str> {
+        self.fields().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Get the location of the first [`Context`] method called for this layer.
+    ///
+    /// Locations are only recorded with the `capture-location` feature, without it
+    /// this always returns `None`.
+    pub const fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+}
+
+/// Emits a `WARN` event for an error that was not handled, see [`Error::ack`].
+#[cfg(feature = "must-log")]
+impl Drop for Error {
+    fn drop(&mut self) {
+        if self.0.drop_check.handled.load(Ordering::Relaxed) || std::thread::panicking() {
+            return;
+        }
+        if let Some(location) = self.0.drop_check.location {
+            crate::emit::emit_unhandled(self, location);
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.inner.source()
+    }
+
+    #[cfg(anyhow_tracing_nightly)]
+    fn provide<'a>(&'a self, request: &mut std::error::Request<'a>) {
+        let backtrace = self.0.inner.backtrace();
+        if backtrace.status() == std::backtrace::BacktraceStatus::Captured {
+            request.provide_ref::<std::backtrace::Backtrace>(backtrace);
+        }
+        request.provide_ref::<Fields>(&self.0.fields);
+    }
+}
+
+/// Appends fields in iteration order, duplicate keys are kept alongside existing ones.
+impl Extend<(&'static str, String)> for Error {
+    fn extend<I: IntoIterator<Item = (&'static str, String)>>(&mut self, iter: I) {
+        let fields = self.fields_mut();
+        iter.into_iter().for_each(|(k, v)| fields.push_str(k, &v));
+    }
+}
+
+/// Appends fields in iteration order, duplicate keys are kept alongside existing ones.
+impl Extend<(&'static str, Box<str>)> for Error {
+    fn extend<I: IntoIterator<Item = (&'static str, Box<str>)>>(&mut self, iter: I) {
+        let fields = self.fields_mut();
+        iter.into_iter().for_each(|(k, v)| fields.push_str(k, &v));
+    }
+}
+
+/// Appends fields in iteration order, duplicate keys are kept alongside existing ones.
+impl<'a> Extend<(&'static str, &'a str)> for Error {
+    fn extend<I: IntoIterator<Item = (&'static str, &'a str)>>(&mut self, iter: I) {
+        let fields = self.fields_mut();
+        iter.into_iter().for_each(|(k, v)| fields.push_str(k, v));
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    #[track_caller]
+    fn from(error: anyhow::Error) -> Self {
+        // An `Error` that was converted into an `anyhow::Error` is unwrapped again
+        match error.downcast::<Self>() {
+            Ok(error) => error,
+            Err(error) => Self::new(error),
+        }
+    }
+}
+
+/// The kind of the I/O error is added as the `io.kind` field, and its OS error code as
+/// the `io.raw_os_error` field if it has one. The `io::Error` itself is kept, so
+/// [`Error::downcast_ref`] still gets it. [`PathContext`](crate::PathContext) does the
+/// same while adding context and a path.
+impl From<io::Error> for Error {
+    #[track_caller]
+    fn from(error: io::Error) -> Self {
+        crate::io::enrich(error).__created()
+    }
+}
+
+/// A report holding an [`Error`] directly, as made by [`Error::into_eyre`], is unwrapped
+/// again. Any other report keeps its chain of messages and sources, and the fields of
+/// an [`Error`] found in that chain are added to the outermost layer.
+#[cfg(feature = "eyre")]
+impl From<eyre::Report> for Error {
+    #[track_caller]
+    fn from(report: eyre::Report) -> Self {
+        if (*report).is::<Self>() {
+            return report
+                .downcast::<Self>()
+                .expect("the report holds an `Error`");
+        }
+        let boxed: Box<dyn StdError + Send + Sync> = report.into();
+        let mut error = Self::new(anyhow::Error::from_boxed(boxed));
+        let inner = error
+            .0
+            .inner
+            .chain()
+            .find_map(|layer| layer.downcast_ref::<Self>())
+            .map(|inner| inner.0.fields.clone())
+            .unwrap_or_default();
+        for (key, value) in &inner {
+            error.add_field(key, value);
+        }
+        error
+    }
+}
+
+impl From<String> for Error {
+    #[track_caller]
+    fn from(msg: String) -> Self {
+        Self::msg(msg)
+    }
+}
+
+impl From<&str> for Error {
+    #[track_caller]
+    fn from(msg: &str) -> Self {
+        Self::msg(msg.to_string())
+    }
+}
+
+/// The call site of a [`Context`] method, only kept with the `capture-location`,
+/// `tracing` or `log` feature.
+#[derive(Debug, Clone, Copy)]
+pub struct Caller {
+    #[cfg(any(feature = "capture-location", feature = "tracing", feature = "log"))]
+    location: &'static Location<'static>,
+}
+
+impl Caller {
+    /// Get the call site of the `#[track_caller]` function this is called in.
+    #[track_caller]
+    pub const fn new() -> Self {
+        Self {
+            #[cfg(any(feature = "capture-location", feature = "tracing", feature = "log"))]
+            location: Location::caller(),
+        }
+    }
+
+    /// Record the call site on the current context layer of the error.
+    #[cfg(feature = "capture-location")]
+    pub fn record(self, mut error: Error) -> Error {
+        error.record_location(self.location);
+        error
+    }
+
+    #[cfg(not(feature = "capture-location"))]
+    #[expect(
+        clippy::unused_self,
+        reason = "the call site is only kept with the feature"
+    )]
+    pub const fn record(self, error: Error) -> Error {
+        error
+    }
+
+    /// Convert an error with [`IntoError`], map it and record the call site, finishing
+    /// the creation of the error if a new [`Error`] was created.
+    ///
+    /// The closures of the [`Context`] methods only run on the error path and end in
+    /// this function or in [`Caller::create`], which are marked cold so that the
+    /// compiler moves that path away from the code that succeeds.
+    #[cold]
+    pub fn convert<E, K, F>(self, error: E, map: F) -> Error
+    where
+        E: IntoError<K>,
+        F: FnOnce(Error) -> Error,
+    {
+        let (error, created) = error.into_quiet_error();
+        let error = self.record(map(error));
+        if created { self.created(error) } else { error }
+    }
+
+    /// Convert an error with [`IntoError`] and add a named field to it, see
+    /// [`Caller::convert`]. Only the type of the error is generic, so that the
+    /// conversion isn't copied for every type of value.
+    #[cold]
+    pub fn convert_field<E: IntoError<K>, K>(
+        self,
+        error: E,
+        key: &'static str,
+        value: &dyn fmt::Display,
+    ) -> Error {
+        self.convert(error, |mut error| {
+            error.add_field_dyn(key, value);
+            error
+        })
+    }
+
+    /// Convert an error with [`IntoError`] and add a named field with debug formatting
+    /// to it, see [`Caller::convert_field`].
+    #[cold]
+    pub fn convert_field_debug<E: IntoError<K>, K>(
+        self,
+        error: E,
+        key: &'static str,
+        value: &dyn fmt::Debug,
+    ) -> Error {
+        self.convert(error, |mut error| {
+            error.add_field_debug_dyn(key, value);
+            error
+        })
+    }
+
+    /// Record the call site on an error created by a [`Context`] method, and finish
+    /// creating it, see [`Error::__created`].
+    #[cold]
+    #[cfg_attr(
+        not(any(feature = "capture-location", feature = "tracing", feature = "log")),
+        expect(
+            clippy::missing_const_for_fn,
+            reason = "the call site is only kept with the features"
+        )
+    )]
+    pub fn create(self, error: Error) -> Error {
+        self.created(self.record(error))
+    }
+
+    /// Finish creating an error, see [`Error::__created`].
+    #[cfg(any(feature = "tracing", feature = "log"))]
+    fn created(self, error: Error) -> Error {
+        error.created_at(self.location)
+    }
+
+    #[cfg(not(any(feature = "tracing", feature = "log")))]
+    #[expect(
+        clippy::unused_self,
+        reason = "the call site is only kept with the features"
+    )]
+    const fn created(self, error: Error) -> Error {
+        error
+    }
+}
+
+/// Message used when a field is attached to a `None` without any context.
+const NONE_MESSAGE: &str = "expected Some value";
+
+/// Extension trait for adding context and fields to errors.
+///
+/// The `K` parameter selects how the error is converted into an [`Error`] (see
+/// [`IntoError`]) and is always inferred.
+///
+/// On an [`Option`], the methods that only add fields or a public message produce an
+/// error with the message `expected Some value`. Prefer [`Context::context`] or
+/// [`Context::context_field`] so that the message says which lookup failed.
+///
+/// With the `capture-location` feature, the methods record the file and line they were
+/// called from on each context layer, see [`ContextFrame::location`].
+pub trait Context<T, K = ()> {
+    /// The type returned by the methods, `Self` with the error replaced by [`Error`].
+    type Output;
+
+    /// Wrap the error value with additional context.
+    fn context<C>(self, context: C) -> Self::Output
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static;
+
+    /// Wrap the error value with additional context that is evaluated lazily.
+    fn with_context<C, F>(self, f: F) -> Self::Output
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        F: FnOnce() -> C;
+
+    /// Wrap the error with context and add a named field to it in one call.
+    ///
+    /// The field belongs to the new context layer, as if [`Context::context`] was
+    /// followed by [`Context::with_field`].
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    ///
+    /// use anyhow_tracing::Context;
+    ///
+    /// let config: HashMap<&str, u16> = HashMap::new();
+    /// let err = config
+    ///     .get("port")
+    ///     .context_field("missing configuration value", "key", "port")
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(err.to_string(), "missing configuration value [key=port]");
+    /// ```
+    fn context_field<C, V>(self, context: C, key: &'static str, value: V) -> Self::Output
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        V: fmt::Display;
+
+    /// Wrap the error with context and add named fields to it, all computed lazily by
+    /// a single closure that is only called on the error path.
+    ///
+    /// The fields belong to the new context layer, see [`Context::context_field`].
+    ///
+    /// ```rust
+    /// use anyhow_tracing::Context;
+    ///
+    /// let (segment, offset) = (7, 4096);
+    /// let result: Result<(), std::io::Error> = Err(std::io::Error::other("disk full"));
+    ///
+    /// let err = result
+    ///     .with_context_fields(|| {
+    ///         let fields = [("segment", segment.to_string()), ("offset", offset.to_string())];
+    ///         ("failed to flush segment", fields)
+    ///     })
+    ///     .unwrap_err();
+    ///
+    /// assert_eq!(err.to_string(), "failed to flush segment [segment=7, offset=4096]");
+    /// ```
+    fn with_context_fields<C, I, V, F>(self, f: F) -> Self::Output
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+        F: FnOnce() -> (C, I);
+
+    /// Add a named field to the error.
+    fn with_field<V>(self, key: &'static str, value: V) -> Self::Output
+    where
+        V: fmt::Display;
+
+    /// Add a named field with debug formatting to the error.
+    fn with_field_debug<V>(self, key: &'static str, value: V) -> Self::Output
+    where
+        V: fmt::Debug;
+
+    /// Add a named field with any serializable value to the error, see
+    /// [`Error::with_field_serde`].
+    #[cfg(feature = "json")]
+    fn with_field_serde<V>(self, key: &'static str, value: V) -> Self::Output
+    where
+        V: serde::Serialize;
+
+    /// Add a named field to the error if the value is `Some`, see
+    /// [`Error::with_field_opt`].
+    ///
+    /// ```rust
+    /// use anyhow_tracing::Context;
+    ///
+    /// let trace_id: Option<&str> = None;
+    /// let result: Result<(), std::io::Error> = Err(std::io::Error::other("timed out"));
+    ///
+    /// let err = result
+    ///     .with_field("method", "GET")
+    ///     .with_field_opt("trace_id", trace_id)
+    ///     .unwrap_err();
+    /// assert_eq!(err.to_string(), "timed out [method=GET]");
+    /// ```
+    fn with_field_opt<V>(self, key: &'static str, value: Option<V>) -> Self::Output
+    where
+        V: fmt::Display;
+
+    /// Add a named field with debug formatting to the error if the value is `Some`.
+    fn with_field_debug_opt<V>(self, key: &'static str, value: Option<V>) -> Self::Output
+    where
+        V: fmt::Debug;
+
+    /// Add several named fields to the error at once, in iteration order.
+    ///
+    /// The fields are applied in a single pass over the error, see [`Error::with_fields`].
+    ///
+    /// ```rust
+    /// use std::collections::BTreeMap;
+    ///
+    /// use anyhow_tracing::Context;
+    ///
+    /// let request = BTreeMap::from([("method", "GET"), ("path", "/users")]);
+    /// let result: Result<(), std::io::Error> = Err(std::io::Error::other("timed out"));
+    ///
+    /// let err = result.with_fields(request).unwrap_err();
+    /// assert_eq!(err.get_field("method"), Some("GET"));
+    /// assert_eq!(err.get_field("path"), Some("/users"));
+    /// ```
+    fn with_fields<I, V>(self, fields: I) -> Self::Output
+    where
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display;
+
+    /// Add a named field to the error, whose value is computed lazily.
+    ///
+    /// Arguments to [`Context::with_field`] are evaluated even when there is no error,
+    /// the closure passed here is only called on the error path, which makes it the
+    /// right choice for values that are expensive to compute.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::Context;
+    ///
+    /// fn expensive_summary(items: &[u32]) -> String {
+    ///     format!("{} items, {} total", items.len(), items.iter().sum::<u32>())
+    /// }
+    ///
+    /// let items = vec![1, 2, 3];
+    /// let result: Result<u32, std::num::ParseIntError> = "42".parse();
+    ///
+    /// // Eager: the summary is computed even though the result is `Ok`
+    /// let value = result.clone().with_field("summary", expensive_summary(&items));
+    ///
+    /// // Lazy: the summary is only computed if the result is `Err`
+    /// let value = result.with_field_with("summary", || expensive_summary(&items));
+    /// assert_eq!(value.unwrap(), 42);
+    /// ```
+    fn with_field_with<V, F>(self, key: &'static str, f: F) -> Self::Output
+    where
+        V: fmt::Display,
+        F: FnOnce() -> V;
+
+    /// Add a named field with debug formatting to the error, whose value is computed
+    /// lazily, see [`Context::with_field_with`].
+    fn with_field_debug_with<V, F>(self, key: &'static str, f: F) -> Self::Output
+    where
+        V: fmt::Debug,
+        F: FnOnce() -> V;
+
+    /// Set a message on the error that is safe to show to end users, see
+    /// [`Error::with_public_message`].
+    fn with_public_message<M>(self, message: M) -> Self::Output
+    where
+        M: Into<Cow<'static, str>>;
+
+    /// Add a free-form note to the error, see [`Error::note`].
+    fn note<N>(self, note: N) -> Self::Output
+    where
+        N: Into<Cow<'static, str>>;
+
+    /// Look at the error without changing it, for example to log it or bump a metric.
+    ///
+    /// The error is converted into an [`Error`] first, so its fields and chain are
+    /// available to the closure. The closure is only called on the error path.
+    ///
+    /// ```rust
+    /// use anyhow_tracing::{Context, anyhow};
+    ///
+    /// let result: anyhow_tracing::Result<()> = Err(anyhow!(table = "users", "query failed"));
+    ///
+    /// let mut logged = Vec::new();
+    /// let result = result.inspect_err_fields(|err| logged.push(err.to_string()));
+    ///
+    /// assert!(result.is_err());
+    /// assert_eq!(logged, ["query failed [table=users]"]);
+    /// ```
+    fn inspect_err_fields<F>(self, f: F) -> Self::Output
+    where
+        F: FnOnce(&Error);
+
+    /// Look up a single field of the error without changing it, see
+    /// [`Context::inspect_err_fields`].
+    ///
+    /// The closure receives `None` if the error has no field with the given key.
+    fn inspect_err_field<F>(self, key: &str, f: F) -> Self::Output
+    where
+        F: FnOnce(Option<&str>);
+}
+
+/// Implemented for any error that converts into an [`anyhow::Error`], which includes
+/// every `std::error::Error + Send + Sync + 'static` type as well as `anyhow::Error`
+/// itself, so `anyhow::Result` can be enriched while migrating. It is also implemented
+/// for `Box<dyn std::error::Error + Send + Sync>`.
+///
+/// When the error already is an [`Error`] the context and fields are applied to it
+/// directly, so fields added earlier in the chain are preserved.
+impl<T, E, K> Context<T, K> for std::result::Result<T, E>
+where
+    E: IntoError<K>,
+{
+    type Output = Result<T>;
+
+    #[track_caller]
+    fn context<C>(self, context: C) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert(e, |error| error.context(context)))
+    }
+
+    #[track_caller]
+    fn with_context<C, F>(self, f: F) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert(e, |error| error.with_context(f)))
+    }
+
+    #[track_caller]
+    fn context_field<C, V>(self, context: C, key: &'static str, value: V) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert(e, |error| error.context(context).with_field(key, value)))
+    }
+
+    #[track_caller]
+    fn with_context_fields<C, I, V, F>(self, f: F) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+        F: FnOnce() -> (C, I),
+    {
+        let caller = Caller::new();
+        self.map_err(|e| {
+            let (context, fields) = f();
+            caller.convert(e, |error| error.context(context).with_fields(fields))
+        })
+    }
+
+    #[track_caller]
+    fn with_field<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert_field(e, key, &value))
+    }
+
+    #[track_caller]
+    fn with_field_debug<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: fmt::Debug,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert_field_debug(e, key, &value))
+    }
+
+    #[cfg(feature = "json")]
+    #[track_caller]
+    fn with_field_serde<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: serde::Serialize,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert(e, |error| error.with_field_serde(key, value)))
+    }
+
+    #[track_caller]
+    fn with_field_opt<V>(self, key: &'static str, value: Option<V>) -> Result<T>
+    where
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| match &value {
+            Some(value) => caller.convert_field(e, key, value),
+            None => caller.convert(e, convert::identity),
+        })
+    }
+
+    #[track_caller]
+    fn with_field_debug_opt<V>(self, key: &'static str, value: Option<V>) -> Result<T>
+    where
+        V: fmt::Debug,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| match &value {
+            Some(value) => caller.convert_field_debug(e, key, value),
+            None => caller.convert(e, convert::identity),
+        })
+    }
+
+    #[track_caller]
+    fn with_fields<I, V>(self, fields: I) -> Result<T>
+    where
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert(e, |error| error.with_fields(fields)))
+    }
+
+    #[track_caller]
+    fn with_field_with<V, F>(self, key: &'static str, f: F) -> Result<T>
+    where
+        V: fmt::Display,
+        F: FnOnce() -> V,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert_field(e, key, &f()))
+    }
+
+    #[track_caller]
+    fn with_field_debug_with<V, F>(self, key: &'static str, f: F) -> Result<T>
+    where
+        V: fmt::Debug,
+        F: FnOnce() -> V,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert_field_debug(e, key, &f()))
+    }
+
+    #[track_caller]
+    fn with_public_message<M>(self, message: M) -> Result<T>
+    where
+        M: Into<Cow<'static, str>>,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert(e, |error| error.with_public_message(message)))
+    }
+
+    #[track_caller]
+    fn note<N>(self, note: N) -> Result<T>
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.convert(e, |error| error.note(note)))
+    }
+
+    #[track_caller]
+    fn inspect_err_fields<F>(self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Error),
+    {
+        let caller = Caller::new();
+        self.map_err(|e| {
+            let error = caller.convert(e, |error| error);
+            f(&error);
+            error
+        })
+    }
+
+    #[track_caller]
+    fn inspect_err_field<F>(self, key: &str, f: F) -> Result<T>
+    where
+        F: FnOnce(Option<&str>),
+    {
+        self.inspect_err_fields(|error| f(error.get_field(key)))
+    }
+}
+
+impl<T> Context<T> for Option<T> {
+    type Output = Result<T>;
+
+    #[track_caller]
+    fn context<C>(self, context: C) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(context)))
+    }
+
+    #[track_caller]
+    fn with_context<C, F>(self, f: F) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(f())))
+    }
+
+    #[track_caller]
+    fn context_field<C, V>(self, context: C, key: &'static str, value: V) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(context).with_field(key, value)))
+    }
+
+    #[track_caller]
+    fn with_context_fields<C, I, V, F>(self, f: F) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+        F: FnOnce() -> (C, I),
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| {
+            let (context, fields) = f();
+            caller.create(Error::quiet_msg(context).with_fields(fields))
+        })
+    }
+
+    #[track_caller]
+    fn with_field<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(NONE_MESSAGE).with_field(key, value)))
+    }
+
+    #[track_caller]
+    fn with_field_debug<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: fmt::Debug,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| {
+            caller.create(Error::quiet_msg(NONE_MESSAGE).with_field_debug(key, value))
+        })
+    }
+
+    #[cfg(feature = "json")]
+    #[track_caller]
+    fn with_field_serde<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: serde::Serialize,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| {
+            caller.create(Error::quiet_msg(NONE_MESSAGE).with_field_serde(key, value))
+        })
+    }
+
+    #[track_caller]
+    fn with_field_opt<V>(self, key: &'static str, value: Option<V>) -> Result<T>
+    where
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(NONE_MESSAGE).with_field_opt(key, value)))
+    }
+
+    #[track_caller]
+    fn with_field_debug_opt<V>(self, key: &'static str, value: Option<V>) -> Result<T>
+    where
+        V: fmt::Debug,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| {
+            caller.create(Error::quiet_msg(NONE_MESSAGE).with_field_debug_opt(key, value))
+        })
     }
 
-    /// Create a new error with a message and optional fields.
-    pub fn msg<T: fmt::Display + fmt::Debug + Send + Sync + 'static>(msg: T) -> Self {
-        Self::new(anyhow::Error::msg(msg))
+    #[track_caller]
+    fn with_fields<I, V>(self, fields: I) -> Result<T>
+    where
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(NONE_MESSAGE).with_fields(fields)))
     }
 
-    /// Add a named field to this error.
-    pub fn with_field<V: fmt::Display>(mut self, key: &'static str, value: V) -> Self {
-        self.fields.push((key, value.to_string().into_boxed_str()));
-        self
+    #[track_caller]
+    fn with_field_with<V, F>(self, key: &'static str, f: F) -> Result<T>
+    where
+        V: fmt::Display,
+        F: FnOnce() -> V,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(NONE_MESSAGE).with_field(key, f())))
     }
 
-    /// Add a named field with debug formatting to this error.
-    pub fn with_field_debug<V: fmt::Debug>(mut self, key: &'static str, value: V) -> Self {
-        self.fields
-            .push((key, format!("{:?}", value).into_boxed_str()));
-        self
+    #[track_caller]
+    fn with_field_debug_with<V, F>(self, key: &'static str, f: F) -> Result<T>
+    where
+        V: fmt::Debug,
+        F: FnOnce() -> V,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(NONE_MESSAGE).with_field_debug(key, f())))
     }
 
-    /// Get the named fields.
-    pub fn fields(&self) -> &[(&'static str, Box<str>)] {
-        &self.fields
+    #[track_caller]
+    fn with_public_message<M>(self, message: M) -> Result<T>
+    where
+        M: Into<Cow<'static, str>>,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| {
+            caller.create(Error::quiet_msg(NONE_MESSAGE).with_public_message(message))
+        })
     }
 
-    /// Get a specific field value by key, this is an O(n) operation.
-    pub fn get_field(&self, key: &str) -> Option<&str> {
-        self.fields
-            .iter()
-            .find(|(k, _)| *k == key)
-            .map(|(_, v)| v.as_ref())
+    #[track_caller]
+    fn note<N>(self, note: N) -> Result<T>
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(NONE_MESSAGE).note(note)))
     }
 
-    /// Add context to this error, see [`anyhow::Context`] for more details.
-    pub fn context<C: fmt::Display + Send + Sync + 'static>(self, context: C) -> Self {
-        Self {
-            inner: self.inner.context(context),
-            fields: self.fields,
-        }
+    #[track_caller]
+    fn inspect_err_fields<F>(self, f: F) -> Result<T>
+    where
+        F: FnOnce(&Error),
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| {
+            let error = caller.create(Error::quiet_msg(NONE_MESSAGE));
+            f(&error);
+            error
+        })
     }
 
-    /// Add context to this error with a closure, see [`anyhow::Context`] for more details.
-    pub fn with_context<C, F>(self, f: F) -> Self
+    #[track_caller]
+    fn inspect_err_field<F>(self, key: &str, f: F) -> Result<T>
     where
-        C: fmt::Display + Send + Sync + 'static,
-        F: FnOnce() -> C,
+        F: FnOnce(Option<&str>),
     {
-        Self {
-            inner: self.inner.context(f()),
-            fields: self.fields,
-        }
+        self.inspect_err_fields(|error| f(error.get_field(key)))
     }
+}
 
-    /// Get the root cause of this error.
-    pub fn root_cause(&self) -> &dyn StdError {
-        self.inner.root_cause()
+/// Convert an [`Option`] into a [`Result`], building the error for `None` in one step.
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use anyhow_tracing::OptionExt;
+///
+/// let cache: HashMap<&str, Vec<u8>> = HashMap::new();
+/// let err = cache.get("user:42").ok_or_field("cache miss", "key", "user:42").unwrap_err();
+///
+/// assert_eq!(err.to_string(), "cache miss [key=user:42]");
+/// ```
+pub trait OptionExt<T> {
+    /// Convert `None` into an error with the given message and a named field, see
+    /// [`Option::ok_or`].
+    fn ok_or_field<C, V>(self, message: C, key: &'static str, value: V) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        V: fmt::Display;
+
+    /// Convert `None` into an error with the given message and several named fields.
+    fn ok_or_fields<C, I, V>(self, message: C, fields: I) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display;
+}
+
+impl<T> OptionExt<T> for Option<T> {
+    #[track_caller]
+    fn ok_or_field<C, V>(self, message: C, key: &'static str, value: V) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(message).with_field(key, value)))
     }
 
-    /// Get the chain of errors.
-    pub fn chain(&self) -> anyhow::Chain {
-        self.inner.chain()
+    #[track_caller]
+    fn ok_or_fields<C, I, V>(self, message: C, fields: I) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.ok_or_else(|| caller.create(Error::quiet_msg(message).with_fields(fields)))
     }
+}
 
-    /// Downcast the error to a concrete type.
-    pub fn downcast<E>(self) -> std::result::Result<E, Self>
+/// Applies the context and fields to the error of a `Poll::Ready(Err(..))`, as
+/// returned by a hand written `Future`, other variants are returned unchanged.
+///
+/// Each method moves the error out with [`into_ready`] and calls the same method on
+/// the `Result` implementation.
+impl<T, E, K> Context<T, K> for Poll<std::result::Result<T, E>>
+where
+    E: IntoError<K>,
+{
+    type Output = Poll<Result<T>>;
+
+    #[track_caller]
+    fn context<C>(self, context: C) -> Poll<Result<T>>
     where
-        E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
     {
-        match self.inner.downcast::<E>() {
-            Ok(e) => Ok(e),
-            Err(inner) => Err(Self {
-                inner,
-                fields: self.fields,
-            }),
-        }
+        from_ready(into_ready(self).context(context))
     }
 
-    /// Downcast the error to a reference to a concrete type.
-    pub fn downcast_ref<E>(&self) -> Option<&E>
+    #[track_caller]
+    fn with_context<C, F>(self, f: F) -> Poll<Result<T>>
     where
-        E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        F: FnOnce() -> C,
     {
-        self.inner.downcast_ref::<E>()
+        from_ready(into_ready(self).with_context(f))
     }
 
-    /// Downcast the error to a mutable reference to a concrete type.
-    pub fn downcast_mut<E>(&mut self) -> Option<&mut E>
+    #[track_caller]
+    fn context_field<C, V>(self, context: C, key: &'static str, value: V) -> Poll<Result<T>>
     where
-        E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        V: fmt::Display,
     {
-        self.inner.downcast_mut::<E>()
+        from_ready(into_ready(self).context_field(context, key, value))
     }
 
-    /// Check if the error is of a particular type.
-    pub fn is<E>(&self) -> bool
+    #[track_caller]
+    fn with_context_fields<C, I, V, F>(self, f: F) -> Poll<Result<T>>
     where
-        E: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+        F: FnOnce() -> (C, I),
     {
-        self.inner.is::<E>()
+        from_ready(into_ready(self).with_context_fields(f))
     }
-}
 
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Display the main error
-        write!(f, "{}", self.inner)?;
-
-        // Add fields if any
-        if !self.fields.is_empty() {
-            write!(f, " [")?;
-            for (i, (key, value)) in self.fields.iter().enumerate() {
-                if i > 0 {
-                    write!(f, ", ")?;
-                }
-                write!(f, "{}={}", key, value)?;
-            }
-            write!(f, "]")?;
-        }
+    #[track_caller]
+    fn with_field<V>(self, key: &'static str, value: V) -> Poll<Result<T>>
+    where
+        V: fmt::Display,
+    {
+        from_ready(into_ready(self).with_field(key, value))
+    }
 
-        Ok(())
+    #[track_caller]
+    fn with_field_debug<V>(self, key: &'static str, value: V) -> Poll<Result<T>>
+    where
+        V: fmt::Debug,
+    {
+        from_ready(into_ready(self).with_field_debug(key, value))
     }
-}
 
-impl fmt::Debug for Error {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Display the main error
-        write!(f, "{:?}", self.inner)?;
-
-        // Add fields if any
-        if !self.fields.is_empty() {
-            write!(f, "\n\nFields:\n")?;
-            for (i, (key, value)) in self.fields.iter().enumerate() {
-                write!(f, "\t{}: {:?}", key, value)?;
-                if i < self.fields.len().saturating_sub(1) {
-                    write!(f, ",")?;
-                }
-                writeln!(f)?;
-            }
-        }
+    #[cfg(feature = "json")]
+    #[track_caller]
+    fn with_field_serde<V>(self, key: &'static str, value: V) -> Poll<Result<T>>
+    where
+        V: serde::Serialize,
+    {
+        from_ready(into_ready(self).with_field_serde(key, value))
+    }
 
-        Ok(())
+    #[track_caller]
+    fn with_field_opt<V>(self, key: &'static str, value: Option<V>) -> Poll<Result<T>>
+    where
+        V: fmt::Display,
+    {
+        from_ready(into_ready(self).with_field_opt(key, value))
     }
-}
 
-impl StdError for Error {
-    fn source(&self) -> Option<&(dyn StdError + 'static)> {
-        self.inner.source()
+    #[track_caller]
+    fn with_field_debug_opt<V>(self, key: &'static str, value: Option<V>) -> Poll<Result<T>>
+    where
+        V: fmt::Debug,
+    {
+        from_ready(into_ready(self).with_field_debug_opt(key, value))
     }
-}
 
-impl From<anyhow::Error> for Error {
-    fn from(error: anyhow::Error) -> Self {
-        Self::new(error)
+    #[track_caller]
+    fn with_fields<I, V>(self, fields: I) -> Poll<Result<T>>
+    where
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+    {
+        from_ready(into_ready(self).with_fields(fields))
     }
-}
 
-impl From<String> for Error {
-    fn from(msg: String) -> Self {
-        Self::msg(msg)
+    #[track_caller]
+    fn with_field_with<V, F>(self, key: &'static str, f: F) -> Poll<Result<T>>
+    where
+        V: fmt::Display,
+        F: FnOnce() -> V,
+    {
+        from_ready(into_ready(self).with_field_with(key, f))
     }
-}
 
-impl From<&str> for Error {
-    fn from(msg: &str) -> Self {
-        Self::msg(msg.to_string())
+    #[track_caller]
+    fn with_field_debug_with<V, F>(self, key: &'static str, f: F) -> Poll<Result<T>>
+    where
+        V: fmt::Debug,
+        F: FnOnce() -> V,
+    {
+        from_ready(into_ready(self).with_field_debug_with(key, f))
     }
-}
 
-/// Extension trait for adding context and fields to errors.
-pub trait Context<T> {
-    /// Wrap the error value with additional context.
-    fn context<C>(self, context: C) -> Result<T>
+    #[track_caller]
+    fn with_public_message<M>(self, message: M) -> Poll<Result<T>>
     where
-        C: fmt::Display + fmt::Debug + Send + Sync + 'static;
+        M: Into<Cow<'static, str>>,
+    {
+        from_ready(into_ready(self).with_public_message(message))
+    }
 
-    /// Wrap the error value with additional context that is evaluated lazily.
-    fn with_context<C, F>(self, f: F) -> Result<T>
+    #[track_caller]
+    fn note<N>(self, note: N) -> Poll<Result<T>>
     where
-        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
-        F: FnOnce() -> C;
+        N: Into<Cow<'static, str>>,
+    {
+        from_ready(into_ready(self).note(note))
+    }
 
-    /// Add a named field to the error.
-    fn with_field<V>(self, key: &'static str, value: V) -> Result<T>
+    #[track_caller]
+    fn inspect_err_fields<F>(self, f: F) -> Poll<Result<T>>
     where
-        V: fmt::Display;
+        F: FnOnce(&Error),
+    {
+        from_ready(into_ready(self).inspect_err_fields(f))
+    }
 
-    /// Add a named field with debug formatting to the error.
-    fn with_field_debug<V>(self, key: &'static str, value: V) -> Result<T>
+    #[track_caller]
+    fn inspect_err_field<F>(self, key: &str, f: F) -> Poll<Result<T>>
     where
-        V: fmt::Debug;
+        F: FnOnce(Option<&str>),
+    {
+        from_ready(into_ready(self).inspect_err_field(key, f))
+    }
 }
 
-impl<T, E> Context<T> for std::result::Result<T, E>
+/// Applies the context and fields to the error of a `Poll::Ready(Some(Err(..)))`, as
+/// returned by a hand written `Stream`, other variants are returned unchanged.
+///
+/// Each method transposes the item and calls the same method on the
+/// `Poll<Result<..>>` implementation.
+impl<T, E, K> Context<T, K> for Poll<Option<std::result::Result<T, E>>>
 where
-    E: std::error::Error + Send + Sync + 'static,
+    E: IntoError<K>,
 {
-    fn context<C>(self, context: C) -> Result<T>
+    type Output = Poll<Option<Result<T>>>;
+
+    #[track_caller]
+    fn context<C>(self, context: C) -> Poll<Option<Result<T>>>
     where
         C: fmt::Display + fmt::Debug + Send + Sync + 'static,
     {
-        self.map_err(|e| Error::from(anyhow::Error::from(e)).context(context))
+        self.map(Option::transpose)
+            .context(context)
+            .map(Result::transpose)
     }
 
-    fn with_context<C, F>(self, f: F) -> Result<T>
+    #[track_caller]
+    fn with_context<C, F>(self, f: F) -> Poll<Option<Result<T>>>
     where
         C: fmt::Display + fmt::Debug + Send + Sync + 'static,
         F: FnOnce() -> C,
     {
-        self.map_err(|e| Error::from(anyhow::Error::from(e)).with_context(f))
+        self.map(Option::transpose)
+            .with_context(f)
+            .map(Result::transpose)
     }
 
-    fn with_field<V>(self, key: &'static str, value: V) -> Result<T>
+    #[track_caller]
+    fn context_field<C, V>(self, context: C, key: &'static str, value: V) -> Poll<Option<Result<T>>>
     where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
         V: fmt::Display,
     {
-        self.map_err(|e| Error::from(anyhow::Error::from(e)).with_field(key, value))
+        self.map(Option::transpose)
+            .context_field(context, key, value)
+            .map(Result::transpose)
     }
 
-    fn with_field_debug<V>(self, key: &'static str, value: V) -> Result<T>
+    #[track_caller]
+    fn with_context_fields<C, I, V, F>(self, f: F) -> Poll<Option<Result<T>>>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+        F: FnOnce() -> (C, I),
+    {
+        self.map(Option::transpose)
+            .with_context_fields(f)
+            .map(Result::transpose)
+    }
+
+    #[track_caller]
+    fn with_field<V>(self, key: &'static str, value: V) -> Poll<Option<Result<T>>>
+    where
+        V: fmt::Display,
+    {
+        self.map(Option::transpose)
+            .with_field(key, value)
+            .map(Result::transpose)
+    }
+
+    #[track_caller]
+    fn with_field_debug<V>(self, key: &'static str, value: V) -> Poll<Option<Result<T>>>
     where
         V: fmt::Debug,
     {
-        self.map_err(|e| Error::from(anyhow::Error::from(e)).with_field_debug(key, value))
+        self.map(Option::transpose)
+            .with_field_debug(key, value)
+            .map(Result::transpose)
     }
-}
 
-impl<T> Context<T> for Option<T> {
-    fn context<C>(self, context: C) -> Result<T>
+    #[cfg(feature = "json")]
+    #[track_caller]
+    fn with_field_serde<V>(self, key: &'static str, value: V) -> Poll<Option<Result<T>>>
     where
-        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        V: serde::Serialize,
     {
-        self.ok_or_else(|| Error::msg(context))
+        self.map(Option::transpose)
+            .with_field_serde(key, value)
+            .map(Result::transpose)
     }
 
-    fn with_context<C, F>(self, f: F) -> Result<T>
+    #[track_caller]
+    fn with_field_opt<V>(self, key: &'static str, value: Option<V>) -> Poll<Option<Result<T>>>
     where
-        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
-        F: FnOnce() -> C,
+        V: fmt::Display,
     {
-        self.ok_or_else(|| Error::msg(f()))
+        self.map(Option::transpose)
+            .with_field_opt(key, value)
+            .map(Result::transpose)
     }
 
-    fn with_field<V>(self, key: &'static str, value: V) -> Result<T>
+    #[track_caller]
+    fn with_field_debug_opt<V>(self, key: &'static str, value: Option<V>) -> Poll<Option<Result<T>>>
+    where
+        V: fmt::Debug,
+    {
+        self.map(Option::transpose)
+            .with_field_debug_opt(key, value)
+            .map(Result::transpose)
+    }
+
+    #[track_caller]
+    fn with_fields<I, V>(self, fields: I) -> Poll<Option<Result<T>>>
     where
+        I: IntoIterator<Item = (&'static str, V)>,
         V: fmt::Display,
     {
-        self.ok_or_else(|| Error::msg("None value").with_field(key, value))
+        self.map(Option::transpose)
+            .with_fields(fields)
+            .map(Result::transpose)
     }
 
-    fn with_field_debug<V>(self, key: &'static str, value: V) -> Result<T>
+    #[track_caller]
+    fn with_field_with<V, F>(self, key: &'static str, f: F) -> Poll<Option<Result<T>>>
+    where
+        V: fmt::Display,
+        F: FnOnce() -> V,
+    {
+        self.map(Option::transpose)
+            .with_field_with(key, f)
+            .map(Result::transpose)
+    }
+
+    #[track_caller]
+    fn with_field_debug_with<V, F>(self, key: &'static str, f: F) -> Poll<Option<Result<T>>>
+    where
+        V: fmt::Debug,
+        F: FnOnce() -> V,
+    {
+        self.map(Option::transpose)
+            .with_field_debug_with(key, f)
+            .map(Result::transpose)
+    }
+
+    #[track_caller]
+    fn with_public_message<M>(self, message: M) -> Poll<Option<Result<T>>>
+    where
+        M: Into<Cow<'static, str>>,
+    {
+        self.map(Option::transpose)
+            .with_public_message(message)
+            .map(Result::transpose)
+    }
+
+    #[track_caller]
+    fn note<N>(self, note: N) -> Poll<Option<Result<T>>>
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        self.map(Option::transpose)
+            .note(note)
+            .map(Result::transpose)
+    }
+
+    #[track_caller]
+    fn inspect_err_fields<F>(self, f: F) -> Poll<Option<Result<T>>>
+    where
+        F: FnOnce(&Error),
+    {
+        self.map(Option::transpose)
+            .inspect_err_fields(f)
+            .map(Result::transpose)
+    }
+
+    #[track_caller]
+    fn inspect_err_field<F>(self, key: &str, f: F) -> Poll<Option<Result<T>>>
+    where
+        F: FnOnce(Option<&str>),
+    {
+        self.map(Option::transpose)
+            .inspect_err_field(key, f)
+            .map(Result::transpose)
+    }
+}
+
+/// Move the error of a ready `Poll` out, so that the [`Context`] methods of `Result`
+/// apply to it. A pending poll is kept as `Ok`.
+fn into_ready<T, E>(poll: Poll<std::result::Result<T, E>>) -> std::result::Result<Poll<T>, E> {
+    match poll {
+        Poll::Ready(result) => result.map(Poll::Ready),
+        Poll::Pending => Ok(Poll::Pending),
+    }
+}
+
+/// Put the error moved out by [`into_ready`] back into a ready `Poll`.
+fn from_ready<T>(result: Result<Poll<T>>) -> Poll<Result<T>> {
+    match result {
+        Ok(poll) => poll.map(Ok),
+        Err(error) => Poll::Ready(Err(error)),
+    }
+}
+
+/// Add fields to a [`Result`] that already holds an [`Error`].
+///
+/// Unlike [`Context`], which accepts any error type and converts it first, these
+/// methods push onto the existing error directly. No context layer is added and the
+/// message is unchanged, which makes this the cheapest way to enrich an error while
+/// propagating it.
+///
+/// ```rust
+/// use anyhow_tracing::{FieldExt, Result, anyhow};
+///
+/// fn load_user(id: u64) -> Result<String> {
+///     Err(anyhow!("user not found"))
+/// }
+///
+/// let err = load_user(42)
+///     .field("user_id", 42)
+///     .field_debug("roles", ["admin"])
+///     .code("USER_NOT_FOUND")
+///     .unwrap_err();
+///
+/// assert_eq!(err.to_string(), r#"user not found [user_id=42, roles=["admin"\]]"#);
+/// assert_eq!(err.code(), Some("USER_NOT_FOUND"));
+/// assert_eq!(err.chain().count(), 1);
+/// ```
+pub trait FieldExt<T> {
+    /// Add a named field to the error, see [`Error::with_field`].
+    fn field<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: fmt::Display;
+
+    /// Add a named field with debug formatting to the error, see
+    /// [`Error::with_field_debug`].
+    fn field_debug<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: fmt::Debug;
+
+    /// Add several named fields to the error, see [`Error::with_fields`].
+    fn fields<I, V>(self, fields: I) -> Result<T>
+    where
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display;
+
+    /// Set the code of the error, see [`Error::with_code`].
+    fn code<C>(self, code: C) -> Result<T>
+    where
+        C: Into<Cow<'static, str>>;
+}
+
+impl<T> FieldExt<T> for Result<T> {
+    fn field<V>(self, key: &'static str, value: V) -> Self
+    where
+        V: fmt::Display,
+    {
+        self.map_err(|e| e.with_field(key, value))
+    }
+
+    fn field_debug<V>(self, key: &'static str, value: V) -> Self
     where
         V: fmt::Debug,
     {
-        self.ok_or_else(|| Error::msg("None value").with_field_debug(key, value))
+        self.map_err(|e| e.with_field_debug(key, value))
+    }
+
+    fn fields<I, V>(self, fields: I) -> Self
+    where
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+    {
+        self.map_err(|e| e.with_fields(fields))
+    }
+
+    fn code<C>(self, code: C) -> Self
+    where
+        C: Into<Cow<'static, str>>,
+    {
+        self.map_err(|e| e.with_code(code))
     }
 }
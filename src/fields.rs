@@ -0,0 +1,257 @@
+#[cfg(feature = "span-fields")]
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::iter::FusedIterator;
+use std::slice;
+use std::sync::OnceLock;
+
+use smallvec::SmallVec;
+
+/// The named fields of an [`Error`](crate::Error), as returned by
+/// [`Error::fields`](crate::Error::fields).
+///
+/// The values of all fields are rendered into a single buffer, which grows as fields
+/// are added, so an error makes one allocation for its values however many fields it
+/// has. Static string values, such as string literals given to the macros, are kept
+/// where they are instead. The first four fields are kept inline, so that most errors
+/// don't allocate a vector for them either.
+///
+/// Errors with more than eight fields build an index of their keys the first time a
+/// field is looked up by key, so that looking up the fields of errors enriched with
+/// many fields doesn't scan all of them each time.
+///
+/// Numbers and booleans given to the macros without `%` or `?` also keep their value,
+/// so that they are recorded with their type when the error is emitted. The first two
+/// are kept inline as well.
+///
+/// When built on nightly with `--cfg anyhow_tracing_nightly` this type can also be
+/// requested from a type erased error through `std::error::request_ref::<Fields>(&err)`,
+/// and read with [`Fields::get`] or [`Fields::iter`] as any other `Fields`.
+///
+/// This replaces the `&[(&'static str, Box<str>)]` slice of earlier versions: a field
+/// is read with `fields.get(i)` instead of `fields[i]`, and iterating yields
+/// `(&'static str, &str)` pairs instead of references to tuples.
+#[derive(Clone, Default)]
+pub struct Fields {
+    /// The key and the value of each field, in the order they were added
+    entries: SmallVec<[(&'static str, Value); 4]>,
+    /// The rendered values of the fields that aren't static
+    buffer: String,
+    /// The position of the first field with each key, built by [`Fields::position`]
+    /// once there are more than [`INDEX_THRESHOLD`] fields and cleared when a field is
+    /// added
+    index: OnceLock<HashMap<&'static str, usize>>,
+    /// The typed values of the fields that have one, by the index of the field
+    scalars: SmallVec<[(usize, Scalar); 2]>,
+}
+
+/// The number of fields above which looking up a field by key builds an index of the
+/// keys, below it scanning the fields is faster than hashing the key.
+const INDEX_THRESHOLD: usize = 8;
+
+/// Where the value of a field is stored.
+#[derive(Debug, Clone, Copy)]
+enum Value {
+    /// A static string, stored without copying it
+    Static(&'static str),
+    /// The byte range of the value in the buffer of the fields
+    Buffer { start: usize, end: usize },
+}
+
+/// The value of a field that is a number or a boolean, kept next to its text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scalar {
+    /// A signed integer
+    I64(i64),
+    /// An unsigned integer
+    U64(u64),
+    /// A floating point number
+    F64(f64),
+    /// A boolean
+    Bool(bool),
+}
+
+impl Fields {
+    /// Get the number of fields.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether there are no fields.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get the key and the value of the field at `index`, in the order they were added.
+    pub fn get(&self, index: usize) -> Option<(&'static str, &str)> {
+        let (key, value) = self.entries.get(index)?;
+        Some((key, value.get(&self.buffer)))
+    }
+
+    /// Get the typed value of the field at `index`, if it has one.
+    #[cfg_attr(
+        not(any(feature = "tracing", feature = "valuable")),
+        expect(dead_code, reason = "only read with `tracing` or `valuable`")
+    )]
+    pub(crate) fn scalar(&self, index: usize) -> Option<Scalar> {
+        let position = self
+            .scalars
+            .binary_search_by_key(&index, |(i, _)| *i)
+            .ok()?;
+        self.scalars.get(position).map(|(_, scalar)| *scalar)
+    }
+
+    /// Get the position of the first field with `key`.
+    pub(crate) fn position(&self, key: &str) -> Option<usize> {
+        if self.len() <= INDEX_THRESHOLD {
+            return self.entries.iter().position(|(k, _)| *k == key);
+        }
+        let index = self.index.get_or_init(|| {
+            let mut index = HashMap::with_capacity(self.len());
+            for (position, (key, _)) in self.entries.iter().enumerate() {
+                index.entry(*key).or_insert(position);
+            }
+            index
+        });
+        index.get(key).copied()
+    }
+
+    /// Get the value of the first field with `key`.
+    pub(crate) fn find(&self, key: &str) -> Option<&str> {
+        let (_, value) = self.get(self.position(key)?)?;
+        Some(value)
+    }
+
+    /// Iterate over the keys and the values of the fields, in the order they were added.
+    pub fn iter(&self) -> FieldsIter<'_> {
+        self.range(0, self.len())
+    }
+
+    /// Iterate over the fields from index `start` up to `end`.
+    pub(crate) fn range(&self, start: usize, end: usize) -> FieldsIter<'_> {
+        FieldsIter {
+            entries: self.entries.get(start..end).unwrap_or_default().iter(),
+            buffer: &self.buffer,
+        }
+    }
+
+    /// Add a field with a static string value, without copying it.
+    pub(crate) fn push_static(&mut self, key: &'static str, value: &'static str) {
+        self.index.take();
+        self.entries.push((key, Value::Static(value)));
+    }
+
+    /// Add a field with `value` rendered with `Display`.
+    pub(crate) fn push_display(&mut self, key: &'static str, value: &dyn fmt::Display) {
+        let start = self.buffer.len();
+        // Writing into a `String` only fails if a `Display` impl returns an error
+        write!(self.buffer, "{}", value).ok();
+        self.push_rendered(key, start);
+    }
+
+    /// Add a field with `value` rendered with `Display`, and its typed value `scalar`.
+    pub(crate) fn push_scalar(
+        &mut self,
+        key: &'static str,
+        value: &dyn fmt::Display,
+        scalar: Scalar,
+    ) {
+        self.scalars.push((self.len(), scalar));
+        self.push_display(key, value);
+    }
+
+    /// Add a field with `value` rendered with `Debug`.
+    pub(crate) fn push_debug(&mut self, key: &'static str, value: &dyn fmt::Debug) {
+        let start = self.buffer.len();
+        // Writing into a `String` only fails if a `Debug` impl returns an error
+        write!(self.buffer, "{:?}", value).ok();
+        self.push_rendered(key, start);
+    }
+
+    /// Add a field with a copy of `value`.
+    pub(crate) fn push_str(&mut self, key: &'static str, value: &str) {
+        let start = self.buffer.len();
+        self.buffer.push_str(value);
+        self.push_rendered(key, start);
+    }
+
+    /// Add a field, keeping a borrowed value without copying it.
+    #[cfg(feature = "span-fields")]
+    pub(crate) fn push_cow(&mut self, key: &'static str, value: Cow<'static, str>) {
+        match value {
+            Cow::Borrowed(value) => self.push_static(key, value),
+            Cow::Owned(value) => self.push_str(key, &value),
+        }
+    }
+
+    /// Add the field whose value was just written to the buffer from `start`.
+    fn push_rendered(&mut self, key: &'static str, start: usize) {
+        let end = self.buffer.len();
+        self.index.take();
+        self.entries.push((key, Value::Buffer { start, end }));
+    }
+}
+
+impl Value {
+    /// Get the text of this value, stored in `buffer` unless it is static.
+    fn get(self, buffer: &str) -> &str {
+        match self {
+            Self::Static(value) => value,
+            Self::Buffer { start, end } => buffer.get(start..end).unwrap_or_default(),
+        }
+    }
+}
+
+impl fmt::Debug for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self).finish()
+    }
+}
+
+impl<'a> IntoIterator for &'a Fields {
+    type Item = (&'static str, &'a str);
+    type IntoIter = FieldsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the keys and the values of fields, see [`Fields::iter`] and
+/// [`ContextFrame::fields`](crate::ContextFrame::fields).
+#[derive(Clone)]
+pub struct FieldsIter<'a> {
+    entries: slice::Iter<'a, (&'static str, Value)>,
+    buffer: &'a str,
+}
+
+impl<'a> Iterator for FieldsIter<'a> {
+    type Item = (&'static str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.entries.next()?;
+        Some((key, value.get(self.buffer)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for FieldsIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.entries.next_back()?;
+        Some((key, value.get(self.buffer)))
+    }
+}
+
+impl ExactSizeIterator for FieldsIter<'_> {}
+
+impl FusedIterator for FieldsIter<'_> {}
+
+impl fmt::Debug for FieldsIter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
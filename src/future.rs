@@ -0,0 +1,168 @@
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{self, Poll, ready};
+
+use pin_project_lite::pin_project;
+
+use crate::IntoError;
+use crate::error::{Caller, Error, Result};
+
+pin_project! {
+    /// Future returned by the [`FutureContextExt`] methods, which applies context and
+    /// fields to the error of the inner future when it completes.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct ContextFuture<Fut, F, K> {
+        #[pin]
+        future: Fut,
+        map: Option<F>,
+        caller: Caller,
+        _marker: PhantomData<fn() -> K>,
+    }
+}
+
+impl<Fut, F, K> ContextFuture<Fut, F, K> {
+    #[track_caller]
+    const fn new(future: Fut, map: F) -> Self {
+        Self {
+            future,
+            map: Some(map),
+            caller: Caller::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Fut, F, T, E, K> Future for ContextFuture<Fut, F, K>
+where
+    Fut: Future<Output = std::result::Result<T, E>>,
+    E: IntoError<K>,
+    F: FnOnce(Error) -> Error,
+{
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let result = ready!(this.future.poll(cx));
+        let map = this
+            .map
+            .take()
+            .expect("ContextFuture polled after completion");
+        let caller = *this.caller;
+        Poll::Ready(result.map_err(|e| caller.convert(e, map)))
+    }
+}
+
+/// Extension trait for adding context and fields to the error of a future before it
+/// is awaited.
+///
+/// This is the future counterpart of [`Context`](crate::Context), useful when the
+/// future itself is passed on, for example to `select!`, a retry wrapper or a spawned
+/// task. The context and field values are only formatted if the future fails, and the
+/// closure passed to [`FutureContextExt::with_context`] is only called then.
+///
+/// ```rust
+/// use anyhow_tracing::FutureContextExt;
+///
+/// async fn fetch(url: &str) -> Result<String, std::io::Error> {
+///     Err(std::io::Error::other("connection refused"))
+/// }
+///
+/// # block_on(async {
+/// let url = "https://example.com";
+/// let err = fetch(url)
+///     .context("fetch failed")
+///     .with_field("url", url)
+///     .await
+///     .unwrap_err();
+///
+/// assert_eq!(err.to_string(), "fetch failed [url=https://example.com]");
+/// # });
+/// # fn block_on<F: std::future::Future>(future: F) -> F::Output {
+/// #     let mut future = std::pin::pin!(future);
+/// #     let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+/// #     loop {
+/// #         if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+/// #             return output;
+/// #         }
+/// #     }
+/// # }
+/// ```
+pub trait FutureContextExt<T, K = ()>: Sized {
+    /// Wrap the error of the future with additional context.
+    fn context<C>(self, context: C) -> ContextFuture<Self, impl FnOnce(Error) -> Error, K>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static;
+
+    /// Wrap the error of the future with lazily evaluated context.
+    fn with_context<C, F>(self, f: F) -> ContextFuture<Self, impl FnOnce(Error) -> Error, K>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        F: FnOnce() -> C;
+
+    /// Add a named field to the error of the future.
+    fn with_field<V>(
+        self,
+        key: &'static str,
+        value: V,
+    ) -> ContextFuture<Self, impl FnOnce(Error) -> Error, K>
+    where
+        V: fmt::Display;
+
+    /// Add a named field with debug formatting to the error of the future.
+    fn with_field_debug<V>(
+        self,
+        key: &'static str,
+        value: V,
+    ) -> ContextFuture<Self, impl FnOnce(Error) -> Error, K>
+    where
+        V: fmt::Debug;
+}
+
+impl<Fut, T, E, K> FutureContextExt<T, K> for Fut
+where
+    Fut: Future<Output = std::result::Result<T, E>>,
+    E: IntoError<K>,
+{
+    #[track_caller]
+    fn context<C>(self, context: C) -> ContextFuture<Self, impl FnOnce(Error) -> Error, K>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        ContextFuture::new(self, |e: Error| e.context(context))
+    }
+
+    #[track_caller]
+    fn with_context<C, F>(self, f: F) -> ContextFuture<Self, impl FnOnce(Error) -> Error, K>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        ContextFuture::new(self, |e: Error| e.with_context(f))
+    }
+
+    #[track_caller]
+    fn with_field<V>(
+        self,
+        key: &'static str,
+        value: V,
+    ) -> ContextFuture<Self, impl FnOnce(Error) -> Error, K>
+    where
+        V: fmt::Display,
+    {
+        ContextFuture::new(self, move |e: Error| e.with_field(key, value))
+    }
+
+    #[track_caller]
+    fn with_field_debug<V>(
+        self,
+        key: &'static str,
+        value: V,
+    ) -> ContextFuture<Self, impl FnOnce(Error) -> Error, K>
+    where
+        V: fmt::Debug,
+    {
+        ContextFuture::new(self, move |e: Error| e.with_field_debug(key, value))
+    }
+}
@@ -0,0 +1,55 @@
+use serde_json::{Map, Value};
+
+use crate::{Error, Level};
+
+/// The version of GELF written in the `version` field.
+const GELF_VERSION: &str = "1.1";
+
+/// Convert `error` into a GELF message, see [`Error::to_gelf`].
+pub fn to_gelf(error: &Error, host: &str) -> Value {
+    error.ack();
+    let mut message = Map::new();
+    message.insert("version".to_owned(), GELF_VERSION.into());
+    message.insert("host".to_owned(), host.into());
+    message.insert("short_message".to_owned(), error.message().into());
+    message.insert(
+        "full_message".to_owned(),
+        error.fmt_compact().to_string().into(),
+    );
+    let level = error.level().unwrap_or(Level::Error);
+    message.insert("level".to_owned(), level.syslog_severity().into());
+
+    if let Some(code) = error.code() {
+        message.insert("_code".to_owned(), code.into());
+    }
+    for (index, (key, value)) in error.fields().iter().enumerate() {
+        let Some(name) = additional_field_name(key) else {
+            continue;
+        };
+        let value = match error.json_field(index) {
+            Some(json @ (Value::Number(_) | Value::String(_))) => json.clone(),
+            Some(json) => json.to_string().into(),
+            None => Value::from(value),
+        };
+        message.insert(name, value);
+    }
+    Value::Object(message)
+}
+
+/// Get the name of the additional field for `key`, with an underscore prefix and each
+/// character other than letters, digits, `_`, `.` and `-` replaced by `_`.
+///
+/// Returns `None` for `id`, which GELF doesn't allow as an additional field.
+fn additional_field_name(key: &str) -> Option<String> {
+    if key == "id" {
+        return None;
+    }
+    let sanitized = key.chars().map(|c| {
+        if c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-') {
+            c
+        } else {
+            '_'
+        }
+    });
+    Some(std::iter::once('_').chain(sanitized).collect())
+}
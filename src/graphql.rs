@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use async_graphql::{ErrorExtensionValues, ErrorExtensions, Value};
+
+use crate::{Error, response};
+
+/// The message of errors without a public message.
+const DEFAULT_MESSAGE: &str = "internal error";
+
+/// Converts the error into a GraphQL error with only its public details.
+///
+/// The message is the [public message](Error::with_public_message) of the error, or
+/// `internal error` without one. The [code](Error::code) of the error is the `code`
+/// extension, and each field named with
+/// [`set_public_fields`](crate::response::set_public_fields) is an extension with its
+/// JSON value when it has one. Other fields and messages are never sent.
+impl ErrorExtensions for Error {
+    fn extend(&self) -> async_graphql::Error {
+        self.ack();
+        let fields = response::public_fields(self);
+        let extensions = (!fields.is_empty() || self.code().is_some()).then(|| {
+            let mut extensions = ErrorExtensionValues::default();
+            for (key, value) in fields {
+                extensions.set(key, Value::from_json(value).unwrap_or(Value::Null));
+            }
+            if let Some(code) = self.code() {
+                extensions.set("code", code);
+            }
+            extensions
+        });
+        async_graphql::Error {
+            message: self.public_message().unwrap_or(DEFAULT_MESSAGE).to_owned(),
+            source: None,
+            extensions,
+        }
+    }
+}
+
+/// Extension trait converting the errors of results into GraphQL errors, so resolvers
+/// can use `?` on this crate's `Result` without leaking internal details.
+///
+/// `async_graphql::Error` converts from any error that implements `Display`, this one
+/// included, but that conversion sends the whole message with its fields to clients.
+///
+/// ```rust
+/// use anyhow_tracing::{GraphqlResultExt, anyhow};
+///
+/// fn load_user(id: u64) -> anyhow_tracing::Result<String> {
+///     Err(anyhow!(user_id = id, "no row in users")
+///         .with_code("NOT_FOUND")
+///         .with_public_message("user not found"))
+/// }
+///
+/// fn user(id: u64) -> async_graphql::Result<String> {
+///     let name = load_user(id).extend_graphql()?;
+///     Ok(name.to_uppercase())
+/// }
+///
+/// let err = user(7).unwrap_err();
+/// assert_eq!(err.message, "user not found");
+/// ```
+pub trait GraphqlResultExt<T> {
+    /// Convert the error into a GraphQL error with its public details, keeping the
+    /// error as its source.
+    fn extend_graphql(self) -> async_graphql::Result<T>;
+}
+
+impl<T> GraphqlResultExt<T> for Result<T, Error> {
+    fn extend_graphql(self) -> async_graphql::Result<T> {
+        self.map_err(|error| {
+            let extended = error.extend();
+            async_graphql::Error {
+                source: Some(Arc::new(error)),
+                ..extended
+            }
+        })
+    }
+}
@@ -0,0 +1,137 @@
+use tonic::metadata::{
+    AsciiMetadataKey, AsciiMetadataValue, BinaryMetadataKey, BinaryMetadataValue,
+};
+use tonic::metadata::{KeyAndValueRef, MetadataMap};
+use tonic::{Code, Status};
+
+use crate::Error;
+use crate::remote::add_remote_fields;
+
+/// The field overriding the gRPC code of an error.
+const CODE_FIELD: &str = "grpc.code";
+
+/// The field holding the received fields not named with
+/// [`set_remote_fields`](crate::set_remote_fields).
+const FIELDS_FIELD: &str = "grpc.fields";
+
+/// The prefix of the metadata keys of fields.
+const FIELD_PREFIX: &str = "x-error-field-";
+
+/// The metadata key of the code of the error.
+const CODE_KEY: &str = "x-error-code";
+
+/// The gRPC codes by their canonical names.
+const CODES: &[(Code, &str)] = &[
+    (Code::Ok, "OK"),
+    (Code::Cancelled, "CANCELLED"),
+    (Code::Unknown, "UNKNOWN"),
+    (Code::InvalidArgument, "INVALID_ARGUMENT"),
+    (Code::DeadlineExceeded, "DEADLINE_EXCEEDED"),
+    (Code::NotFound, "NOT_FOUND"),
+    (Code::AlreadyExists, "ALREADY_EXISTS"),
+    (Code::PermissionDenied, "PERMISSION_DENIED"),
+    (Code::ResourceExhausted, "RESOURCE_EXHAUSTED"),
+    (Code::FailedPrecondition, "FAILED_PRECONDITION"),
+    (Code::Aborted, "ABORTED"),
+    (Code::OutOfRange, "OUT_OF_RANGE"),
+    (Code::Unimplemented, "UNIMPLEMENTED"),
+    (Code::Internal, "INTERNAL"),
+    (Code::Unavailable, "UNAVAILABLE"),
+    (Code::DataLoss, "DATA_LOSS"),
+    (Code::Unauthenticated, "UNAUTHENTICATED"),
+];
+
+/// Parse a gRPC code from its canonical name, in any case and with or without
+/// underscores, or from its number.
+fn parse_code(code: &str) -> Option<Code> {
+    if let Ok(number) = code.parse::<i32>() {
+        return CODES
+            .iter()
+            .find(|(known, _)| *known as i32 == number)
+            .map(|(known, _)| *known);
+    }
+    let normalized = code.replace('_', "");
+    CODES
+        .iter()
+        .find(|(_, name)| name.replace('_', "").eq_ignore_ascii_case(&normalized))
+        .map(|(known, _)| *known)
+}
+
+/// Get the canonical name of `code`.
+fn code_name(code: Code) -> &'static str {
+    CODES
+        .iter()
+        .find(|(known, _)| *known == code)
+        .map_or("UNKNOWN", |(_, name)| name)
+}
+
+/// Convert `error` into a gRPC status, see [`Error::to_status`].
+pub fn to_status(error: &Error) -> Status {
+    error.ack();
+    let code = error
+        .get_field(CODE_FIELD)
+        .or_else(|| error.code())
+        .and_then(parse_code)
+        .unwrap_or(Code::Internal);
+    let message = error.public_message().unwrap_or_else(|| code.description());
+
+    let mut metadata = MetadataMap::new();
+    if let Some(value) = error.code().and_then(|code| code.parse().ok()) {
+        metadata.insert(CODE_KEY, value);
+    }
+    for (key, value) in error.fields() {
+        if key != CODE_FIELD {
+            append_field(&mut metadata, key, value);
+        }
+    }
+    Status::with_metadata(code, message, metadata)
+}
+
+/// Append the field `key` to `metadata`, as an ASCII entry if the value only has
+/// visible ASCII characters and spaces, or as a binary entry otherwise.
+fn append_field(metadata: &mut MetadataMap, key: &str, value: &str) {
+    let sanitized: String = key
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '_' | '.' | '-' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '_',
+        })
+        .collect();
+    let name = format!("{FIELD_PREFIX}{sanitized}");
+    let printable = value.bytes().all(|b| matches!(b, b' '..=b'~'));
+    if printable && !name.ends_with("-bin") {
+        if let (Ok(key), Ok(value)) = (
+            name.parse::<AsciiMetadataKey>(),
+            value.parse::<AsciiMetadataValue>(),
+        ) {
+            metadata.append(key, value);
+        }
+    } else if let Ok(key) = format!("{name}-bin").parse::<BinaryMetadataKey>() {
+        metadata.append_bin(key, BinaryMetadataValue::from_bytes(value.as_bytes()));
+    }
+}
+
+/// Rebuild an error from a gRPC status, see [`Error::from_status`].
+pub fn from_status(status: &Status) -> Error {
+    let mut error = Error::quiet_msg(status.message().to_owned())
+        .with_public_message(status.message().to_owned());
+    let metadata = status.metadata();
+    if let Some(code) = metadata.get(CODE_KEY).and_then(|code| code.to_str().ok()) {
+        error = error.with_code(code.to_owned());
+    }
+    error.add_field(CODE_FIELD, code_name(status.code()));
+    let fields = metadata.iter().filter_map(|entry| {
+        let (key, value) = match entry {
+            KeyAndValueRef::Ascii(key, value) => (key.as_str(), value.to_str().ok()?.to_owned()),
+            KeyAndValueRef::Binary(key, value) => {
+                let bytes = value.to_bytes().ok()?;
+                let key = key.as_str().strip_suffix("-bin")?;
+                (key, String::from_utf8_lossy(&bytes).into_owned())
+            }
+        };
+        Some((key.strip_prefix(FIELD_PREFIX)?, value))
+    });
+    add_remote_fields(&mut error, FIELDS_FIELD, fields);
+    error
+}
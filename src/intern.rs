@@ -0,0 +1,22 @@
+use std::collections::HashSet;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// The field names created so far, see [`intern`].
+static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+/// Get `name` as a `&'static str`, as field names are.
+///
+/// Each name is only allocated once, so there are as many allocations as there are
+/// distinct names.
+pub fn intern(name: String) -> &'static str {
+    let mut names = NAMES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(existing) = names.get(name.as_str()) {
+        return existing;
+    }
+    let leaked: &'static str = Box::leak(name.into_boxed_str());
+    names.insert(leaked);
+    leaked
+}
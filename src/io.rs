@@ -0,0 +1,59 @@
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+use crate::error::{Caller, Error, Result};
+
+/// Extension trait adding context and the path of a file to the result of an I/O
+/// operation, along with the fields added when converting an [`io::Error`] into an
+/// [`Error`].
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// use anyhow_tracing::PathContext;
+///
+/// let path = Path::new("/nonexistent/config.toml");
+/// let err = std::fs::read_to_string(path)
+///     .path_context("reading config", path)
+///     .unwrap_err();
+/// assert_eq!(err.get_field("path"), Some("/nonexistent/config.toml"));
+/// assert_eq!(err.get_field("io.kind"), Some("NotFound"));
+/// ```
+pub trait PathContext<T> {
+    /// Wrap the error with the given context, after adding its I/O fields and a `path`
+    /// field with `path`, displayed lossily.
+    fn path_context<C, P>(self, context: C, path: P) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        P: AsRef<Path>;
+}
+
+impl<T> PathContext<T> for std::result::Result<T, io::Error> {
+    #[track_caller]
+    fn path_context<C, P>(self, context: C, path: P) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        let caller = Caller::new();
+        self.map_err(|error| {
+            let error = enrich(error).with_field("path", path.as_ref().display());
+            caller.create(caller.record(error).context(context))
+        })
+    }
+}
+
+/// Wrap `error` without emitting it, with its kind in `io.kind` and its OS error code
+/// in `io.raw_os_error` if it has one.
+pub fn enrich(error: io::Error) -> Error {
+    let kind = error.kind();
+    let raw_os_error = error.raw_os_error();
+    let mut error = Error::quiet(anyhow::Error::new(error))
+        .with_root_type::<io::Error>()
+        .with_field_debug("io.kind", kind);
+    if let Some(code) = raw_os_error {
+        error.add_field("io.raw_os_error", code);
+    }
+    error
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::IntoError;
+use crate::error::{Caller, Result};
+
+/// Iterator returned by [`IterContextExt::context_indexed`].
+#[derive(Debug, Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct ContextIndexed<I, C, K> {
+    iter: I,
+    context: C,
+    index: usize,
+    caller: Caller,
+    _marker: PhantomData<fn() -> K>,
+}
+
+impl<I, C, T, E, K> Iterator for ContextIndexed<I, C, K>
+where
+    I: Iterator<Item = std::result::Result<T, E>>,
+    C: fmt::Display + fmt::Debug + Send + Sync + Clone + 'static,
+    E: IntoError<K>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.iter.next()?;
+        let index = self.index;
+        self.index = self.index.saturating_add(1);
+        Some(item.map_err(|e| {
+            self.caller.convert(e, |error| {
+                error
+                    .context(self.context.clone())
+                    .with_field("index", index)
+            })
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// Extension trait for adding context to the errors yielded by an iterator.
+///
+/// ```rust
+/// use anyhow_tracing::IterContextExt;
+///
+/// fn handle(item: &str) -> Result<u32, std::num::ParseIntError> {
+///     item.parse()
+/// }
+///
+/// let items = ["1", "two", "3"];
+/// let results: Vec<_> = items.iter().copied().map(handle).context_indexed("processing item").collect();
+///
+/// let err = results[1].as_ref().unwrap_err();
+/// assert_eq!(err.to_string(), "processing item [index=1]");
+/// ```
+pub trait IterContextExt<T, K = ()>: Sized {
+    /// Wrap each error with the given context and add its position in the iterator
+    /// as an `index` field.
+    fn context_indexed<C>(self, context: C) -> ContextIndexed<Self, C, K>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + Clone + 'static;
+}
+
+impl<I, T, E, K> IterContextExt<T, K> for I
+where
+    I: Iterator<Item = std::result::Result<T, E>>,
+    E: IntoError<K>,
+{
+    #[track_caller]
+    fn context_indexed<C>(self, context: C) -> ContextIndexed<Self, C, K>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + Clone + 'static,
+    {
+        ContextIndexed {
+            iter: self,
+            context,
+            index: 0,
+            caller: Caller::new(),
+            _marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+
+use tokio::task::JoinError;
+
+use crate::IntoError;
+use crate::error::{Caller, Error, Result};
+
+/// Extension trait for the result of awaiting a tokio `JoinHandle` whose task returns
+/// a `Result`.
+///
+/// A `Result<T, JoinError>` on its own is already covered by the
+/// [`Context`](crate::Context) trait.
+///
+/// ```rust
+/// use anyhow_tracing::JoinResultExt;
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let handle = tokio::spawn(async { "42".parse::<u32>() });
+/// let value = handle.await.flatten_context("parsing task failed").unwrap();
+/// assert_eq!(value, 42);
+/// # });
+/// ```
+pub trait JoinResultExt<T, K = ()> {
+    /// Flatten the task result into a single [`Result`], wrapping any error with the
+    /// given context.
+    ///
+    /// A task that panicked becomes an error with a `task.panicked=true` field and
+    /// the panic message in `task.panic`, a cancelled task one with a
+    /// `task.cancelled=true` field. An error returned by the task itself is kept as
+    /// the cause.
+    fn flatten_context<C>(self, context: C) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static;
+}
+
+impl<T, E, K> JoinResultExt<T, K> for std::result::Result<std::result::Result<T, E>, JoinError>
+where
+    E: IntoError<K>,
+{
+    #[track_caller]
+    fn flatten_context<C>(self, context: C) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        let caller = Caller::new();
+        match self {
+            Ok(Ok(value)) => Ok(value),
+            Ok(Err(error)) => {
+                Err(caller.convert(error, |error| caller.record(error).context(context)))
+            }
+            Err(error) => Err(caller.create(caller.record(join_error(error)).context(context))),
+        }
+    }
+}
+
+/// Convert a join error into an [`Error`], extracting the panic message if any.
+fn join_error(error: JoinError) -> Error {
+    if error.is_cancelled() {
+        return Error::quiet_msg("task was cancelled").with_field("task.cancelled", true);
+    }
+
+    let payload = error.into_panic();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+    Error::quiet_msg("task panicked")
+        .with_field("task.panicked", true)
+        .with_field("task.panic", message)
+}
@@ -0,0 +1,129 @@
+//! Sending errors to the systemd journal with native structured fields.
+//!
+//! [`Error::to_journal_fields`] maps an error to journal fields:
+//!
+//! - `MESSAGE` is the whole chain of messages joined by `: `, without the fields.
+//! - `PRIORITY` is the syslog severity of the [level](Error::level) of the error, 3
+//!   for errors without one.
+//! - `CODE_FILE` and `CODE_LINE` are the [location](Error::location) of the error, only
+//!   recorded with the `capture-location` feature.
+//! - `ERROR_CODE` is the [code](Error::code) of the error, if it has one.
+//!
+//! Each field follows under its key in upper case, see [`field_name`]. [`emit_to`]
+//! sends these fields to a journal socket with the native protocol of journald, so
+//! `journalctl -o json` shows them and `journalctl HOST=db-3` filters on them.
+//!
+//! [`Error::to_journal_fields`]: crate::Error::to_journal_fields
+
+#[cfg(unix)]
+use std::io;
+#[cfg(unix)]
+use std::path::Path;
+
+use crate::{Error, Level};
+
+/// The socket journald reads native protocol messages from.
+pub const SOCKET_PATH: &str = "/run/systemd/journal/socket";
+
+/// The fields set from the error itself, which fields of the error can't replace.
+const RESERVED: &[&str] = &[
+    "MESSAGE",
+    "PRIORITY",
+    "CODE_FILE",
+    "CODE_LINE",
+    "ERROR_CODE",
+];
+
+/// The longest field name journald accepts.
+const MAX_NAME_LEN: usize = 64;
+
+/// Get the journal fields of `error`, see
+/// [`Error::to_journal_fields`](crate::Error::to_journal_fields).
+pub(crate) fn fields(error: &Error) -> Vec<(String, String)> {
+    error.ack();
+    let mut fields = Vec::with_capacity(error.fields().len().saturating_add(5));
+    fields.push((
+        "MESSAGE".to_owned(),
+        error.display_without_fields().to_string(),
+    ));
+    let level = error.level().unwrap_or(Level::Error);
+    fields.push(("PRIORITY".to_owned(), level.syslog_severity().to_string()));
+    if let Some(location) = error.location() {
+        fields.push(("CODE_FILE".to_owned(), location.file().to_owned()));
+        fields.push(("CODE_LINE".to_owned(), location.line().to_string()));
+    }
+    if let Some(code) = error.code() {
+        fields.push(("ERROR_CODE".to_owned(), code.to_owned()));
+    }
+    fields.extend(error.fields().iter().filter_map(|(key, value)| {
+        let name = field_name(key)?;
+        (!RESERVED.contains(&name.as_str())).then(|| (name, value.to_string()))
+    }));
+    fields
+}
+
+/// Get the journal field name for the field `key`, in upper case with `.`, `-` and
+/// spaces replaced by `_`, and any other character that journald doesn't allow
+/// removed.
+///
+/// Leading underscores and digits are removed, as names must start with a letter
+/// unless they are trusted fields set by journald itself, and names are cut to 64
+/// characters. Returns `None` if nothing is left.
+///
+/// ```rust
+/// use anyhow_tracing::journald::field_name;
+///
+/// assert_eq!(field_name("http.status").as_deref(), Some("HTTP_STATUS"));
+/// assert_eq!(field_name("_pid").as_deref(), Some("PID"));
+/// assert_eq!(field_name("ünïcode").as_deref(), Some("NCODE"));
+/// assert_eq!(field_name("__"), None);
+/// ```
+pub fn field_name(key: &str) -> Option<String> {
+    let name: String = key
+        .chars()
+        .filter_map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => Some(c.to_ascii_uppercase()),
+            '.' | '-' | ' ' => Some('_'),
+            _ => None,
+        })
+        .skip_while(|c| *c == '_' || c.is_ascii_digit())
+        .take(MAX_NAME_LEN)
+        .collect();
+    (!name.is_empty()).then_some(name)
+}
+
+/// Encode `fields` as a native protocol message.
+///
+/// Values without newlines are written as `NAME=value`, other values as the name, a
+/// newline, the length of the value as a little endian 64 bit integer, then the value.
+pub fn encode<K: AsRef<str>, V: AsRef<str>>(fields: &[(K, V)]) -> Vec<u8> {
+    let mut message = Vec::new();
+    for (name, value) in fields {
+        let (name, value) = (name.as_ref().as_bytes(), value.as_ref().as_bytes());
+        message.extend_from_slice(name);
+        if value.contains(&b'\n') {
+            message.push(b'\n');
+            let len = u64::try_from(value.len()).unwrap_or(u64::MAX);
+            message.extend_from_slice(&len.to_le_bytes());
+        } else {
+            message.push(b'=');
+        }
+        message.extend_from_slice(value);
+        message.push(b'\n');
+    }
+    message
+}
+
+/// Send the journal fields of `error` to the journal listening on `socket`, see
+/// [`Error::emit_to_journal`](crate::Error::emit_to_journal) for the journal of the
+/// system.
+///
+/// Fails if the socket can't be reached, or if the message is too large for a single
+/// datagram.
+#[cfg(unix)]
+pub fn emit_to<P: AsRef<Path>>(error: &Error, socket: P) -> io::Result<()> {
+    let message = encode(&fields(error));
+    let sender = std::os::unix::net::UnixDatagram::unbound()?;
+    sender.send_to(&message, socket)?;
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+/// A named field key that carries the type of the value stored under it.
+///
+/// Keys are usually declared with the [`define_fields!`](crate::define_fields) macro
+/// and used with [`Error::with_key`](crate::Error::with_key) and
+/// [`Error::get_key`](crate::Error::get_key). Values are stored like any other field,
+/// so typed and untyped access can be mixed freely.
+pub struct Key<T: FieldType> {
+    name: &'static str,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T: FieldType> Key<T> {
+    /// Create a new key with the given field name.
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            _type: PhantomData,
+        }
+    }
+
+    /// Get the field name of this key.
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+impl<T: FieldType> Clone for Key<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: FieldType> Copy for Key<T> {}
+
+impl<T: FieldType> fmt::Debug for Key<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Key").field(&self.name).finish()
+    }
+}
+
+/// A type that can be used as the value of a typed [`Key`].
+pub trait FieldType: 'static {
+    /// The value accepted when attaching a field and returned when reading it back.
+    type Value<'a>: fmt::Display;
+
+    /// Parse a stored field value, returning `None` if it is not a valid value.
+    fn parse(raw: &str) -> Option<Self::Value<'_>>;
+}
+
+macro_rules! impl_field_type_from_str {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl FieldType for $ty {
+                type Value<'a> = Self;
+
+                fn parse(raw: &str) -> Option<Self::Value<'_>> {
+                    raw.parse().ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_field_type_from_str!(
+    u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, bool, char,
+);
+
+impl FieldType for String {
+    type Value<'a> = Self;
+
+    fn parse(raw: &str) -> Option<Self::Value<'_>> {
+        Some(raw.to_owned())
+    }
+}
+
+impl FieldType for &'static str {
+    type Value<'a> = &'a str;
+
+    fn parse(raw: &str) -> Option<Self::Value<'_>> {
+        Some(raw)
+    }
+}
@@ -0,0 +1,40 @@
+use lambda_runtime::Diagnostic;
+
+use crate::Error;
+
+/// The error type reported for errors without a code or a known root type.
+const DEFAULT_ERROR_TYPE: &str = "anyhow_tracing::Error";
+
+/// Report an error returned by a Lambda handler, so handlers can return this crate's
+/// `Result`.
+///
+/// The `errorType` is the [code](Error::code) of the error, or the name of the type of
+/// the error it was converted from, such as `std::io::error::Error`, and
+/// `anyhow_tracing::Error` without either. The `errorMessage` is the whole chain
+/// followed by the fields, as with [`Error::fmt_compact`], as the runtime only reports
+/// strings.
+///
+/// ```rust
+/// use anyhow_tracing::anyhow;
+/// use lambda_runtime::Diagnostic;
+///
+/// let err = anyhow!(order_id = 7, "card declined")
+///     .context("failed to charge")
+///     .with_code("PaymentFailed");
+/// let diagnostic = Diagnostic::from(err);
+/// assert_eq!(diagnostic.error_type, "PaymentFailed");
+/// assert_eq!(diagnostic.error_message, "failed to charge: card declined [order_id=7]");
+/// ```
+impl From<Error> for Diagnostic {
+    fn from(error: Error) -> Self {
+        error.ack();
+        let error_type = error
+            .code()
+            .or_else(|| error.root_type())
+            .unwrap_or(DEFAULT_ERROR_TYPE);
+        Self {
+            error_type: error_type.to_owned(),
+            error_message: error.fmt_compact().to_string(),
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! A layer expanding the fields of recorded errors into event fields.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use tracing::callsite::{Callsite, Identifier};
+use tracing::field::{Field, Value, Visit, display};
+use tracing::level_filters::LevelFilter;
+use tracing::metadata::Kind;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Dispatch, Event, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::Error;
+use crate::callsite::{ErrorCallsite, with_values};
+use crate::intern::intern;
+
+/// The callsites of expanded events, by the callsite of the original event and field
+/// names.
+static CALLSITES: OnceLock<Mutex<HashMap<CallsiteKey, &'static ErrorCallsite>>> = OnceLock::new();
+
+type CallsiteKey = (Identifier, Vec<&'static str>);
+
+/// A layer wrapping another layer, which sees each event with an [`Error`] recorded as
+/// a field with the fields of the error added as event fields.
+///
+/// Errors are recognised when they are recorded as errors, with
+/// `error = &err as &dyn std::error::Error`. The field of the error is recorded as
+/// the error without its fields, and each field of the error is added after it as
+/// `{field}.{key}`. Events without errors are passed on as they are.
+///
+/// ```rust
+/// use anyhow_tracing::anyhow;
+/// use anyhow_tracing::layer::ErrorFieldsLayer;
+/// use tracing_subscriber::Registry;
+/// use tracing_subscriber::layer::{Layer, SubscriberExt};
+///
+/// # struct Formatter;
+/// # impl<S: tracing::Subscriber> Layer<S> for Formatter {}
+/// let subscriber = Registry::default().with(ErrorFieldsLayer::new(Formatter));
+/// tracing::subscriber::with_default(subscriber, || {
+///     let err = anyhow!(path = "/etc/app.toml", "not found");
+///     // `Formatter` sees the fields `message`, `error` and `error.path`
+///     tracing::error!(error = &err as &dyn std::error::Error, "failed to load config");
+/// });
+/// ```
+///
+/// Other errors recorded on an expanded event are recorded as their `Display` string.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ErrorFieldsLayer<L> {
+    inner: L,
+}
+
+impl<L> ErrorFieldsLayer<L> {
+    /// Wrap `inner`, which sees the expanded events.
+    pub const fn new(inner: L) -> Self {
+        Self { inner }
+    }
+
+    /// Get the wrapped layer.
+    pub const fn inner(&self) -> &L {
+        &self.inner
+    }
+
+    /// Unwrap the wrapped layer.
+    pub fn into_inner(self) -> L {
+        self.inner
+    }
+}
+
+/// The values recorded on an event, and whether any of them was an [`Error`].
+#[derive(Default)]
+struct Recorded {
+    values: Vec<(&'static str, Box<dyn Value>)>,
+    expanded: bool,
+}
+
+impl Recorded {
+    fn push<V: Value + 'static>(&mut self, field: &Field, value: V) {
+        self.values.push((field.name(), Box::new(value)));
+    }
+}
+
+impl Visit for Recorded {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.push(field, value);
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.push(field, value);
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.push(field, value);
+    }
+
+    fn record_i128(&mut self, field: &Field, value: i128) {
+        self.push(field, value);
+    }
+
+    fn record_u128(&mut self, field: &Field, value: u128) {
+        self.push(field, value);
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.push(field, value);
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, value.to_owned());
+    }
+
+    fn record_error(&mut self, field: &Field, value: &(dyn StdError + 'static)) {
+        let Some(error) = value.downcast_ref::<Error>() else {
+            self.push(field, display(value.to_string()));
+            return;
+        };
+        error.ack();
+        self.expanded = true;
+        self.push(field, display(error.display_without_fields().to_string()));
+        for (key, value) in error.fields() {
+            let name = intern(format!("{}.{}", field.name(), key));
+            self.values
+                .push((name, Box::new(display(value.to_owned()))));
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, display(format!("{:?}", value)));
+    }
+}
+
+/// Get the metadata of the expanded version of an event with `original` metadata.
+fn expanded_metadata(
+    original: &'static Metadata<'static>,
+    names: Vec<&'static str>,
+) -> &'static Metadata<'static> {
+    let mut callsites = CALLSITES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let key = (original.callsite(), names);
+    if let Some(callsite) = callsites.get(&key) {
+        return callsite.metadata();
+    }
+
+    let (identifier, names) = key;
+    let names: &'static [&'static str] = Box::leak(names.into_boxed_slice());
+    let callsite = ErrorCallsite::leak(names, |fields| {
+        Metadata::new(
+            original.name(),
+            original.target(),
+            *original.level(),
+            original.file(),
+            original.line(),
+            original.module_path(),
+            fields,
+            Kind::EVENT,
+        )
+    });
+    callsites.insert((identifier, names.to_vec()), callsite);
+    drop(callsites);
+    callsite.metadata()
+}
+
+impl<L, S> Layer<S> for ErrorFieldsLayer<L>
+where
+    L: Layer<S>,
+    S: Subscriber,
+{
+    fn on_register_dispatch(&self, subscriber: &Dispatch) {
+        self.inner.on_register_dispatch(subscriber);
+    }
+
+    fn on_layer(&mut self, subscriber: &mut S) {
+        self.inner.on_layer(subscriber);
+    }
+
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        self.inner.register_callsite(metadata)
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, S>) -> bool {
+        self.inner.enabled(metadata, ctx)
+    }
+
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        self.inner.on_new_span(attrs, id, ctx);
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        self.inner.max_level_hint()
+    }
+
+    fn on_record(&self, span: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        self.inner.on_record(span, values, ctx);
+    }
+
+    fn on_follows_from(&self, span: &Id, follows: &Id, ctx: Context<'_, S>) {
+        self.inner.on_follows_from(span, follows, ctx);
+    }
+
+    fn event_enabled(&self, event: &Event<'_>, ctx: Context<'_, S>) -> bool {
+        self.inner.event_enabled(event, ctx)
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut recorded = Recorded::default();
+        event.record(&mut recorded);
+        if !recorded.expanded {
+            self.inner.on_event(event, ctx);
+            return;
+        }
+
+        let names = recorded.values.iter().map(|(name, _)| *name).collect();
+        let metadata = expanded_metadata(event.metadata(), names);
+        let values: Vec<&dyn Value> = recorded
+            .values
+            .iter()
+            .map(|(_, value)| &**value as &dyn Value)
+            .collect();
+        with_values(metadata, &values, |values| {
+            let expanded = if event.is_contextual() {
+                Event::new(metadata, values)
+            } else {
+                Event::new_child_of(event.parent().cloned(), metadata, values)
+            };
+            self.inner.on_event(&expanded, ctx);
+        });
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        self.inner.on_enter(id, ctx);
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        self.inner.on_exit(id, ctx);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        self.inner.on_close(id, ctx);
+    }
+
+    fn on_id_change(&self, old: &Id, new: &Id, ctx: Context<'_, S>) {
+        self.inner.on_id_change(old, new, ctx);
+    }
+
+    #[doc(hidden)]
+    unsafe fn downcast_raw(&self, id: TypeId) -> Option<*const ()> {
+        if id == TypeId::of::<Self>() {
+            return Some(std::ptr::from_ref(self).cast());
+        }
+        // SAFETY: the caller upholds the contract of `downcast_raw`, which is the same
+        // for the wrapped layer.
+        unsafe { self.inner.downcast_raw(id) }
+    }
+}
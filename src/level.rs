@@ -0,0 +1,82 @@
+use std::fmt;
+
+/// The severity of an [`Error`](crate::Error).
+///
+/// Levels are ordered by increasing severity, so `Level::Trace < Level::Error`.
+///
+/// With the `serde` feature, levels are serialized by their upper case name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "UPPERCASE")
+)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Level {
+    /// Very verbose diagnostic information.
+    Trace,
+    /// Diagnostic information useful while debugging.
+    Debug,
+    /// An expected failure that is worth recording.
+    Info,
+    /// A failure that was recovered from, or may need attention.
+    Warn,
+    /// A failure that needs attention.
+    Error,
+}
+
+impl Level {
+    /// Get the name of this level in upper case, as used by `tracing`.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        }
+    }
+
+    /// Get the syslog severity of this level, as used by GELF: 3 for errors, 4 for
+    /// warnings, 6 for information and 7 for debug and trace.
+    pub const fn syslog_severity(&self) -> u8 {
+        match self {
+            Self::Trace | Self::Debug => 7,
+            Self::Info => 6,
+            Self::Warn => 4,
+            Self::Error => 3,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl From<Level> for tracing::Level {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Trace => Self::TRACE,
+            Level::Debug => Self::DEBUG,
+            Level::Info => Self::INFO,
+            Level::Warn => Self::WARN,
+            Level::Error => Self::ERROR,
+        }
+    }
+}
+
+#[cfg(feature = "log")]
+impl From<Level> for log::Level {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Trace => Self::Trace,
+            Level::Debug => Self::Debug,
+            Level::Info => Self::Info,
+            Level::Warn => Self::Warn,
+            Level::Error => Self::Error,
+        }
+    }
+}
@@ -1,11 +1,146 @@
 #![doc = include_str!("../README.md")]
+#![cfg_attr(anyhow_tracing_nightly, feature(error_generic_member_access))]
 
+#[cfg(feature = "actix")]
+mod actix;
+#[cfg(feature = "axum")]
+mod axum;
+#[cfg(feature = "tracing")]
+mod callsite;
+mod convert;
+mod data;
+#[cfg(feature = "datadog")]
+pub mod datadog;
+#[cfg(any(feature = "tracing", feature = "log"))]
+mod emit;
+#[cfg(any(feature = "cbor", feature = "msgpack"))]
+mod encoding;
+#[cfg(feature = "tower")]
+mod enrich;
 mod error;
+mod fields;
+#[cfg(feature = "futures")]
+mod future;
+#[cfg(feature = "json")]
+mod gelf;
+#[cfg(feature = "async-graphql")]
+mod graphql;
+#[cfg(feature = "tonic")]
+mod grpc;
+#[cfg(any(feature = "layer", feature = "span-fields"))]
+mod intern;
+mod io;
+mod iter;
+#[cfg(feature = "tokio")]
+mod join;
+#[cfg(feature = "journald")]
+pub mod journald;
+mod key;
+#[cfg(feature = "lambda")]
+mod lambda;
+#[cfg(feature = "layer")]
+pub mod layer;
+mod level;
+mod lock;
+mod logfmt;
 mod macros;
+#[cfg(all(feature = "metrics", any(feature = "tracing", feature = "log")))]
+pub mod metrics;
+#[cfg(feature = "miette")]
+mod miette;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+#[cfg(feature = "poem")]
+mod poem;
+#[cfg(all(feature = "http", feature = "json"))]
+mod problem;
+#[cfg(feature = "pyo3")]
+pub mod python;
+mod remote;
+mod render;
+mod report;
+#[cfg(feature = "reqwest")]
+mod reqwest;
+#[cfg(all(feature = "http", feature = "json"))]
+pub mod response;
+#[cfg(feature = "rocket")]
+mod rocket;
+#[cfg(any(feature = "tracing", feature = "log"))]
+mod sample;
+#[cfg(feature = "sentry")]
+mod sentry;
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "span-fields")]
+mod span;
+#[cfg(feature = "sqlx")]
+mod sqlx;
+#[cfg(feature = "valuable")]
+mod structured;
+mod template;
+#[cfg(feature = "test-util")]
+pub mod test;
+#[cfg(any(feature = "tracing", feature = "log"))]
+mod throttle;
+#[cfg(any(feature = "tracing", feature = "log"))]
+mod trace;
+#[cfg(feature = "warp")]
+mod warp;
+#[cfg(feature = "wasm")]
+mod wasm;
 
 // Re-export the main types and traits
 // The macros are defined in the macros module and exported automatically
 
 // Re-export commonly used anyhow types that don't conflict
 pub use anyhow::Chain;
-pub use error::{Context, Error, Result};
+pub use convert::{IntoError, marker};
+pub use data::ErrorData;
+#[cfg(any(feature = "tracing", feature = "log"))]
+pub use emit::{set_auto_emit, set_default_target};
+#[cfg(feature = "tower")]
+pub use enrich::{ErrorEnrich, ErrorEnrichFuture, ErrorEnrichLayer};
+pub use error::{Context, ContextFrame, Error, FieldExt, OptionExt, Result, default_report_hook};
+pub use fields::{Fields, FieldsIter};
+#[cfg(feature = "futures")]
+pub use future::{ContextFuture, FutureContextExt};
+#[cfg(feature = "async-graphql")]
+pub use graphql::GraphqlResultExt;
+pub use io::PathContext;
+pub use iter::{ContextIndexed, IterContextExt};
+#[cfg(feature = "tokio")]
+pub use join::JoinResultExt;
+pub use key::{FieldType, Key};
+pub use level::Level;
+pub use lock::LockResultExt;
+#[cfg(all(feature = "http", feature = "json"))]
+pub use problem::ProblemDetails;
+pub use remote::set_remote_fields;
+pub use render::{
+    Escape, FieldFormat, ReportHook, field_format, parse_fields, set_field_format, set_report_hook,
+};
+pub use report::{Report, set_verbose_reports};
+#[cfg(all(feature = "http", feature = "json"))]
+pub use response::HttpError;
+#[cfg(any(feature = "tracing", feature = "log"))]
+pub use sample::{flush_sampling_stats, set_sampling, set_sampling_seed};
+#[cfg(feature = "sentry")]
+pub use sentry::{SentryIntegration, sentry_integration, set_sentry_tags};
+#[cfg(feature = "span-fields")]
+pub use span::FieldCaptureLayer;
+#[cfg(feature = "valuable")]
+pub use structured::{ErrorValue, FieldMap};
+pub use template::ErrorTemplate;
+#[cfg(any(feature = "tracing", feature = "log"))]
+pub use trace::TraceResultExt;
+
+#[cfg(feature = "reqwest")]
+pub use self::reqwest::HttpResultExt;
+#[cfg(feature = "rocket")]
+pub use self::rocket::answered_error;
+#[cfg(feature = "sqlx")]
+pub use self::sqlx::DbResultExt;
+#[cfg(feature = "warp")]
+pub use self::warp::{Rejection, handle_rejection};
@@ -0,0 +1,88 @@
+use std::fmt;
+use std::sync::PoisonError;
+
+use crate::error::{Caller, Error, Result};
+
+/// Extension trait for adding context to the result of locking a `Mutex` or `RwLock`.
+///
+/// A [`PoisonError`] holds the lock guard, so it cannot be stored in an error that
+/// outlives the lock. These methods drop the guard and turn the poisoning into an
+/// [`Error`] with a `lock.poisoned=true` field, which the [`Context`](crate::Context)
+/// trait cannot do.
+///
+/// ```rust
+/// use std::sync::Mutex;
+///
+/// use anyhow_tracing::LockResultExt;
+///
+/// let state = Mutex::new(0);
+/// let mut guard = state.lock().context("state lock poisoned").unwrap();
+/// *guard += 1;
+/// ```
+pub trait LockResultExt<T> {
+    /// Convert a poisoned lock into an error wrapped with additional context.
+    fn context<C>(self, context: C) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static;
+
+    /// Convert a poisoned lock into an error wrapped with lazily evaluated context.
+    fn with_context<C, F>(self, f: F) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        F: FnOnce() -> C;
+
+    /// Convert a poisoned lock into an error with a named field.
+    fn with_field<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: fmt::Display;
+
+    /// Convert a poisoned lock into an error with a named field using debug formatting.
+    fn with_field_debug<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: fmt::Debug;
+}
+
+/// Build the error for a poisoned lock, dropping the guard it holds.
+fn poisoned<G>(error: PoisonError<G>, caller: Caller) -> Error {
+    let error = Error::quiet_msg(error.to_string()).with_field("lock.poisoned", true);
+    caller.record(error)
+}
+
+impl<T> LockResultExt<T> for std::result::Result<T, PoisonError<T>> {
+    #[track_caller]
+    fn context<C>(self, context: C) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.create(poisoned(e, caller).context(context)))
+    }
+
+    #[track_caller]
+    fn with_context<C, F>(self, f: F) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| caller.create(poisoned(e, caller).context(f())))
+    }
+
+    #[track_caller]
+    fn with_field<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: fmt::Display,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| poisoned(e, caller).with_field(key, value))
+    }
+
+    #[track_caller]
+    fn with_field_debug<V>(self, key: &'static str, value: V) -> Result<T>
+    where
+        V: fmt::Debug,
+    {
+        let caller = Caller::new();
+        self.map_err(|e| poisoned(e, caller).with_field_debug(key, value))
+    }
+}
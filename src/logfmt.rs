@@ -0,0 +1,87 @@
+use std::fmt::{self, Write};
+
+use crate::render::{field_format, write_escaped_control};
+use crate::{Error, Fields};
+
+/// Render an error as a logfmt line, see [`Error::to_logfmt`].
+pub fn write_logfmt<W: Write>(f: &mut W, error: &Error) -> fmt::Result {
+    write!(f, "msg=")?;
+    write_value(f, &error.message())?;
+
+    if let Some(code) = error.code() {
+        write!(f, " code=")?;
+        write_value(f, code)?;
+    }
+    if let Some(level) = error.level() {
+        write!(f, " level={}", level)?;
+    }
+
+    if !error.fields().is_empty() {
+        f.write_char(' ')?;
+        write_logfmt_fields(f, error.fields())?;
+    }
+
+    let causes: Vec<String> = error.chain().skip(1).map(ToString::to_string).collect();
+    if !causes.is_empty() {
+        write!(f, " cause=")?;
+        write_value(f, &causes.join(": "))?;
+    }
+
+    Ok(())
+}
+
+/// Render fields as logfmt pairs separated by spaces.
+pub fn write_logfmt_fields<W: Write>(f: &mut W, fields: &Fields) -> fmt::Result {
+    for (i, (key, value)) in field_format().ordered(fields).into_iter().enumerate() {
+        if i > 0 {
+            f.write_char(' ')?;
+        }
+        write_pair(f, key, value)?;
+    }
+    Ok(())
+}
+
+/// Render one field as a logfmt pair.
+pub fn write_pair<W: Write>(f: &mut W, key: &str, value: &str) -> fmt::Result {
+    write_key(f, key)?;
+    f.write_char('=')?;
+    write_value(f, value)
+}
+
+/// Write a key, replacing the characters logfmt does not allow in keys with `_`.
+fn write_key(f: &mut impl Write, key: &str) -> fmt::Result {
+    if key.is_empty() {
+        return f.write_char('_');
+    }
+    for c in key.chars() {
+        if c.is_whitespace() || c.is_control() || matches!(c, '=' | '"') {
+            f.write_char('_')?;
+        } else {
+            f.write_char(c)?;
+        }
+    }
+    Ok(())
+}
+
+/// Write a value, quoting it if it is empty or contains spaces, `=`, quotes,
+/// backslashes or control characters.
+fn write_value(f: &mut impl Write, value: &str) -> fmt::Result {
+    let needs_quotes = value.is_empty()
+        || value
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || matches!(c, '=' | '"' | '\\'));
+    if !needs_quotes {
+        return f.write_str(value);
+    }
+
+    f.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            c if c.is_control() => write_escaped_control(f, c)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
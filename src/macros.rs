@@ -17,224 +17,124 @@
 /// let err: Error = anyhow!(field1 = "value1", field2 = "value2", "Error message");
 /// let err: Error = anyhow!(field1 = "value1", field2 = "value2"; "Error message");
 /// ```
+///
+/// Fields whose value is a string literal keep the literal as it is, without
+/// allocating a copy of it, see
+/// [`Error::with_field_static`](crate::Error::with_field_static).
 #[macro_export]
 macro_rules! anyhow {
-    // Helper for processing individual field assignments
-    (@process_field $error:ident, $field_name:ident = ?$field_value:expr) => {
-        $error = $error.with_field_debug(stringify!($field_name), $field_value);
+    // Creates the error once all the fields are collected, in a cold function that
+    // adds them in order, so that only a call is left at the call site
+    (@build [$($field:tt)*] $fmt:literal $(, $args:expr)*) => {
+        $crate::Error::__msg_with_fields(
+            format_args!($fmt $(, $args)*),
+            module_path!(),
+            &[$($crate::anyhow!(@field $field)),*],
+        )
     };
-    (@process_field $error:ident, $field_name:ident = %$field_value:expr) => {
-        $error = $error.with_field(stringify!($field_name), $field_value);
+
+    // Borrows a collected field for the cold function
+    (@field (debug $name:expr, $value:expr)) => {
+        $crate::Error::__debug_field($name, &$value)
     };
-    (@process_field $error:ident, $field_name:ident = $field_value:expr) => {
-        $error = $error.with_field(stringify!($field_name), $field_value);
+    (@field (display $name:expr, $value:expr)) => {
+        $crate::Error::__display_field($name, &$value)
+    };
+    // Numbers and booleans keep their type, other values are formatted with `Display`
+    (@field (value $name:expr, $value:expr)) => {
+        $crate::Error::__value_field(&$value).__field($name)
+    };
+    // String literals are stored without copying them
+    (@field (literal $name:expr, $value:literal)) => {
+        $crate::Error::__literal_field($name, &$value)
+    };
+    (@field (display_literal $name:expr, $value:literal)) => {
+        $crate::Error::__display_literal_field($name, &$value)
     };
 
-    // Entry point for processing accumulated fields
-    (@build_from_fields [$($field_specs:tt)*], $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        $($crate::anyhow!(@process_field error, $field_specs);)*
-        error
-    }};
-
-    (@build_from_fields [$($field_specs:tt)*]; $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        $($crate::anyhow!(@process_field error, $field_specs);)*
-        error
-    }};
-
-    // Mixed debug and display fields - specific patterns for common test cases
-    (debug_data = ?$debug_val:expr, operation = %$operation_val:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field_debug("debug_data", $debug_val);
-        error = error.with_field("operation", $operation_val);
-        error
-    }};
-
-    (user_id = %$user_id:expr, session_id = %$session_id:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field("user_id", $user_id);
-        error = error.with_field("session_id", $session_id);
-        error
-    }};
-
-    (string_field = %$string_val:expr, int_field = %$int_val:expr, float_field = %$float_val:expr, bool_field = %$bool_val:expr, vec_field = ?$vec_val:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field("string_field", $string_val);
-        error = error.with_field("int_field", $int_val);
-        error = error.with_field("float_field", $float_val);
-        error = error.with_field("bool_field", $bool_val);
-        error = error.with_field_debug("vec_field", $vec_val);
-        error
-    }};
-
-    // Debug field variant - named field with ? prefix
-    ($($field_name:ident = ?$field_value:expr),+ $(,)?, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        $(
-            error = error.with_field_debug(stringify!($field_name), $field_value);
-        )+
-        error
-    }};
-
-    // Mixed debug and display fields with semicolon syntax
-    (debug_data = ?$debug_val:expr, operation = %$operation_val:expr; $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field_debug("debug_data", $debug_val);
-        error = error.with_field("operation", $operation_val);
-        error
-    }};
-
-    (user_id = %$user_id:expr, session_id = %$session_id:expr; $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field("user_id", $user_id);
-        error = error.with_field("session_id", $session_id);
-        error
-    }};
-
-    // Debug field variant with semicolon syntax
-    ($($field_name:ident = ?$field_value:expr),+ $(,)?; $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        $(
-            error = error.with_field_debug(stringify!($field_name), $field_value);
-        )+
-        error
-    }};
-
-    // Display field variant - named field with % prefix
-    ($($field_name:ident = %$field_value:expr),+ $(,)?, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        $(
-            error = error.with_field(stringify!($field_name), $field_value);
-        )+
-        error
-    }};
-
-    // Display field variant with semicolon syntax
-    ($($field_name:ident = %$field_value:expr),+ $(,)?; $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        $(
-            error = error.with_field(stringify!($field_name), $field_value);
-        )+
-        error
-    }};
-
-    // Implicit display variant - named field without prefix
-    ($($field_name:ident = $field_value:expr),+ $(,)?, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        $(
-            error = error.with_field(stringify!($field_name), $field_value);
-        )+
-        error
-    }};
-
-    // Implicit display variant with semicolon syntax
-    ($($field_name:ident = $field_value:expr),+ $(,)?; $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        $(
-            error = error.with_field(stringify!($field_name), $field_value);
-        )+
-        error
-    }};
-
-    // Positional patterns
-    (?$field_value:ident, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field_debug(stringify!($field_value), $field_value);
-        error
-    }};
-
-    (?$field_value:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field_debug("value", $field_value);
-        error
-    }};
-
-    (%$field_value:ident, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field(stringify!($field_value), $field_value);
-        error
-    }};
-
-    (%$field_value:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field("value", $field_value);
-        error
-    }};
-
-    // Positional field followed by multiple named fields (various combinations)
-    ($field_value:ident, operation = %$operation_val:expr, debug_data = ?$debug_val:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field(stringify!($field_value), $field_value);
-        error = error.with_field("operation", $operation_val);
-        error = error.with_field_debug("debug_data", $debug_val);
-        error
-    }};
-
-    // Positional debug field followed by named display field
-    (?$field_value:ident, $field_name:ident = %$named_value:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field_debug(stringify!($field_value), $field_value);
-        error = error.with_field(stringify!($field_name), $named_value);
-        error
-    }};
-
-    // Positional display field followed by mixed named fields
-    (%$field_value:ident, debug_data = ?$debug_val:expr, operation = %$operation_val:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field(stringify!($field_value), $field_value);
-        error = error.with_field_debug("debug_data", $debug_val);
-        error = error.with_field("operation", $operation_val);
-        error
-    }};
-
-    (?$field_value:ident, debug_data = ?$debug_val:expr, operation = %$operation_val:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field_debug(stringify!($field_value), $field_value);
-        error = error.with_field_debug("debug_data", $debug_val);
-        error = error.with_field("operation", $operation_val);
-        error
-    }};
-
-    ($field_value:ident, debug_data = ?$debug_val:expr, operation = %$operation_val:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field(stringify!($field_value), $field_value);
-        error = error.with_field_debug("debug_data", $debug_val);
-        error = error.with_field("operation", $operation_val);
-        error
-    }};
-
-    // Mixed positional and named fields
-    ($field_value:ident, $field_name:ident = $named_value:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field(stringify!($field_value), $field_value);
-        error = error.with_field(stringify!($field_name), $named_value);
-        error
-    }};
-
-    ($field_value:ident, $field_name:ident = %$named_value:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field(stringify!($field_value), $field_value);
-        error = error.with_field(stringify!($field_name), $named_value);
-        error
-    }};
-
-    ($field_value:ident, $field_name:ident = ?$named_value:expr, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field(stringify!($field_value), $field_value);
-        error = error.with_field_debug(stringify!($field_name), $named_value);
-        error
-    }};
-
-    ($field_value:ident, $fmt:literal $(, $args:expr)*) => {{
-        let mut error = $crate::Error::msg(format!($fmt $(, $args)*));
-        error = error.with_field(stringify!($field_value), $field_value);
-        error
-    }};
+    // Collects the fields one at a time, separated by `,`, or by `;` before the message
+    (@fields [$($acc:tt)*] $fmt:literal $(, $args:expr)*) => {
+        $crate::anyhow!(@build [$($acc)*] $fmt $(, $args)*)
+    };
+    (@fields [$($acc:tt)*] $name:ident = ?$value:expr, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (debug stringify!($name), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $name:ident = ?$value:expr; $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (debug stringify!($name), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $name:ident = %$value:literal, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (display_literal stringify!($name), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $name:ident = %$value:literal; $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (display_literal stringify!($name), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $name:ident = %$value:expr, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (display stringify!($name), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $name:ident = %$value:expr; $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (display stringify!($name), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $name:ident = $value:literal, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (literal stringify!($name), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $name:ident = $value:literal; $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (literal stringify!($name), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $name:ident = $value:expr, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (value stringify!($name), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $name:ident = $value:expr; $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (value stringify!($name), $value)] $($rest)+)
+    };
+    // Positional fields, named after the variable, or `value` for other expressions
+    (@fields [$($acc:tt)*] ?$value:ident, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (debug stringify!($value), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] ?$value:ident; $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (debug stringify!($value), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] %$value:ident, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (display stringify!($value), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] %$value:ident; $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (display stringify!($value), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $value:ident, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (value stringify!($value), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] $value:ident; $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (value stringify!($value), $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] ?$value:expr, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (debug "value", $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] ?$value:expr; $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (debug "value", $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] %$value:expr, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (display "value", $value)] $($rest)+)
+    };
+    (@fields [$($acc:tt)*] %$value:expr; $($rest:tt)+) => {
+        $crate::anyhow!(@fields [$($acc)* (display "value", $value)] $($rest)+)
+    };
 
     // Simple format string with args, no fields
     ($fmt:literal $(, $args:expr)*) => {
-        $crate::Error::msg(format!($fmt $(, $args)*))
+        $crate::Error::__msg(format_args!($fmt $(, $args)*), module_path!())
+    };
+
+    // Named and positional fields followed by the message
+    ($name:ident = $($rest:tt)+) => {
+        $crate::anyhow!(@fields [] $name = $($rest)+)
+    };
+    ($value:ident, $($rest:tt)+) => {
+        $crate::anyhow!(@fields [] $value, $($rest)+)
+    };
+    (? $($rest:tt)+) => {
+        $crate::anyhow!(@fields [] ? $($rest)+)
+    };
+    (% $($rest:tt)+) => {
+        $crate::anyhow!(@fields [] % $($rest)+)
     };
 
     // Expression conversion (e.g., error type conversion)
@@ -308,3 +208,97 @@ macro_rules! bail {
         return Err($crate::anyhow!($($args)*));
     };
 }
+
+/// Creates an `Error` from an [`ErrorTemplate`](crate::ErrorTemplate), optionally with
+/// additional named fields.
+///
+/// Fields use the same syntax as [`anyhow!`], and are added after the preset fields
+/// of the template.
+///
+/// # Examples
+///
+/// ```rust
+/// use anyhow_tracing::{ErrorTemplate, from_template};
+///
+/// let quota = ErrorTemplate::new("quota exceeded").code("QUOTA_EXCEEDED");
+///
+/// let err = from_template!(quota);
+/// let err = from_template!(quota, tenant = "acme", limit = %100, usage = ?[101, 102]);
+/// assert_eq!(err.get_field("limit"), Some("100"));
+/// ```
+#[macro_export]
+macro_rules! from_template {
+    (@fields $error:ident $(,)?) => {};
+    (@fields $error:ident, $field_name:ident = ?$field_value:expr $(, $($rest:tt)*)?) => {
+        $error.add_field_debug(stringify!($field_name), $field_value);
+        $crate::from_template!(@fields $error $(, $($rest)*)?);
+    };
+    (@fields $error:ident, $field_name:ident = %$field_value:literal $(, $($rest:tt)*)?) => {
+        $error.__add_display_literal_field(stringify!($field_name), $field_value);
+        $crate::from_template!(@fields $error $(, $($rest)*)?);
+    };
+    (@fields $error:ident, $field_name:ident = %$field_value:expr $(, $($rest:tt)*)?) => {
+        $error.add_field(stringify!($field_name), $field_value);
+        $crate::from_template!(@fields $error $(, $($rest)*)?);
+    };
+    (@fields $error:ident, $field_name:ident = $field_value:literal $(, $($rest:tt)*)?) => {
+        $error.__add_literal_field(stringify!($field_name), $field_value);
+        $crate::from_template!(@fields $error $(, $($rest)*)?);
+    };
+    (@fields $error:ident, $field_name:ident = $field_value:expr $(, $($rest:tt)*)?) => {
+        $error.add_field(stringify!($field_name), $field_value);
+        $crate::from_template!(@fields $error $(, $($rest)*)?);
+    };
+
+    ($template:expr $(,)?) => {
+        $template.new_error()
+    };
+
+    ($template:expr, $($fields:tt)+) => {{
+        let mut error = $template.__new_error_quiet();
+        $crate::from_template!(@fields error, $($fields)+);
+        error.__created_in(module_path!())
+    }};
+}
+
+/// Declares typed field keys for use with [`Error::with_key`](crate::Error::with_key)
+/// and [`Error::get_key`](crate::Error::get_key).
+///
+/// # Examples
+///
+/// ```rust
+/// use anyhow_tracing::{Error, define_fields};
+///
+/// define_fields! {
+///     /// The id of the user that made the request.
+///     pub USER_ID: u64 = "user_id";
+///     REGION: &str = "region";
+/// }
+///
+/// let err = Error::msg("request failed")
+///     .with_key(USER_ID, 42)
+///     .with_key(REGION, "eu-west-1");
+/// assert_eq!(err.get_key(USER_ID), Some(42));
+/// assert_eq!(err.get_key(REGION), Some("eu-west-1"));
+/// ```
+///
+/// Attaching a value of the wrong type is a compile error:
+///
+/// ```rust,compile_fail
+/// use anyhow_tracing::{Error, define_fields};
+///
+/// define_fields! {
+///     USER_ID: u64 = "user_id";
+/// }
+///
+/// let err = Error::msg("request failed").with_key(USER_ID, "42");
+/// ```
+#[macro_export]
+macro_rules! define_fields {
+    ($($(#[$meta:meta])* $vis:vis $name:ident : $ty:ty = $key:literal;)*) => {
+        $(
+            $(#[$meta])*
+            $vis const $name: $crate::Key<$ty> = $crate::Key::new($key);
+        )*
+    };
+}
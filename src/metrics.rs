@@ -0,0 +1,79 @@
+//! Counting emitted errors with the `metrics` crate.
+//!
+//! With the `metrics` feature, every error emitted with
+//! [`Error::emit`](crate::Error::emit), `trace_err`, automatic emission or
+//! [`Error::emit_throttled`](crate::Error::emit_throttled), including the ones left out
+//! by throttling, increments the `errors_total` counter of the installed recorder.
+//!
+//! The counter has a `code` label with the [`code`](crate::Error::code) of the error, or
+//! `none` if it has none, and a `level` label with the level it was emitted at. The
+//! fields named with [`set_label_fields`] are added as labels too, when the error has
+//! them. Only those fields are used, so that the number of label combinations stays
+//! bounded.
+//!
+//! Label names have the characters Prometheus does not allow replaced with `_`, and
+//! values have control characters replaced with `_` and are cut to 64 characters.
+
+use std::fmt::Write;
+use std::sync::OnceLock;
+
+use crate::{Error, Level};
+
+/// The name of the counter incremented for each emitted error.
+const COUNTER: &str = "errors_total";
+
+/// The longest label value, longer values are cut.
+const MAX_VALUE_LEN: usize = 64;
+
+/// The fields added as labels, set with [`set_label_fields`].
+static LABEL_FIELDS: OnceLock<&'static [&'static str]> = OnceLock::new();
+
+/// Add the fields named `fields` as labels of the `errors_total` counter, when an
+/// emitted error has them.
+///
+/// This can only be done once, and should be done at startup. Returns the names back
+/// if the label fields were already set.
+///
+/// ```rust
+/// anyhow_tracing::metrics::set_label_fields(&["route", "tenant"])
+///     .expect("the label fields are only set once");
+/// ```
+pub fn set_label_fields(fields: &'static [&'static str]) -> Result<(), &'static [&'static str]> {
+    LABEL_FIELDS.set(fields)
+}
+
+/// Increment the counter for `error`, emitted at `level`.
+pub(crate) fn record(error: &Error, level: Level) {
+    let mut labels = vec![
+        ::metrics::Label::new("code", label_value(error.code().unwrap_or("none"))),
+        ::metrics::Label::new("level", level.as_str().to_ascii_lowercase()),
+    ];
+    for name in LABEL_FIELDS.get().copied().unwrap_or_default() {
+        if let Some(value) = error.get_field(name) {
+            labels.push(::metrics::Label::new(label_name(name), label_value(value)));
+        }
+    }
+    ::metrics::counter!(COUNTER, labels).increment(1);
+}
+
+/// Get `name` with the characters not allowed in Prometheus label names replaced.
+fn label_name(name: &str) -> String {
+    let mut label = String::with_capacity(name.len());
+    if name.chars().next().is_none_or(|c| c.is_ascii_digit()) {
+        label.push('_');
+    }
+    for c in name.chars() {
+        label.push(if c.is_ascii_alphanumeric() { c } else { '_' });
+    }
+    label
+}
+
+/// Get `value` with control characters replaced, cut to [`MAX_VALUE_LEN`] characters.
+fn label_value(value: &str) -> String {
+    let mut label = String::with_capacity(value.len().min(MAX_VALUE_LEN));
+    for c in value.chars().take(MAX_VALUE_LEN) {
+        // Writing into a `String` never fails
+        label.write_char(if c.is_control() { '_' } else { c }).ok();
+    }
+    label
+}
@@ -0,0 +1,181 @@
+use std::error::Error as StdError;
+use std::fmt;
+
+use ::miette::{Diagnostic, Report, Severity};
+
+use crate::{Error, Level};
+
+/// Get the miette severity of `level`.
+const fn severity(level: Level) -> Severity {
+    match level {
+        Level::Error => Severity::Error,
+        Level::Warn => Severity::Warning,
+        Level::Info | Level::Debug | Level::Trace => Severity::Advice,
+    }
+}
+
+/// The help of an error: its notes, one per line, then its fields if `fields` is set.
+struct Help<'a> {
+    error: &'a Error,
+    fields: bool,
+}
+
+impl Help<'_> {
+    /// Whether there is anything to show.
+    fn is_empty(&self) -> bool {
+        self.error.notes().is_empty() && (!self.fields || self.error.fields().is_empty())
+    }
+}
+
+impl fmt::Display for Help<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = 0_usize;
+        for note in self.error.notes() {
+            if lines > 0 {
+                f.write_str("\n")?;
+            }
+            f.write_str(note)?;
+            lines = lines.saturating_add(1);
+        }
+        if self.fields && !self.error.fields().is_empty() {
+            if lines > 0 {
+                f.write_str("\n")?;
+            }
+            f.write_str("fields:")?;
+            for (i, (key, value)) in self.error.fields().iter().enumerate() {
+                let separator = if i == 0 { " " } else { ", " };
+                write!(f, "{separator}{key}={value}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Renders the `Display` output of the error, with its fields, as the message, and
+/// the underlying layers of the chain as causes through `source`. The help shows its
+/// [notes](Error::note). Convert the error with [`Error::into_miette`] to show the
+/// fields in the help instead, and chain causes through `diagnostic_source`.
+impl Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.code()
+            .map(|code| Box::new(code) as Box<dyn fmt::Display + 'a>)
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        self.level().map(severity)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let help = Help {
+            error: self,
+            fields: false,
+        };
+        (!help.is_empty()).then(|| Box::new(help) as Box<dyn fmt::Display + 'a>)
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.docs_url()
+            .map(|url| Box::new(url) as Box<dyn fmt::Display + 'a>)
+    }
+}
+
+/// An underlying layer of the chain of an error converted with [`Error::into_miette`].
+#[derive(Debug)]
+struct Cause {
+    /// The message of this layer.
+    message: String,
+    /// The layer under this one.
+    source: Option<Box<Self>>,
+}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl StdError for Cause {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn StdError + 'static))
+    }
+}
+
+impl Diagnostic for Cause {
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        self.source
+            .as_deref()
+            .map(|source| source as &dyn Diagnostic)
+    }
+}
+
+/// An error converted with [`Error::into_miette`], whose message has no fields.
+struct Converted {
+    /// The converted error.
+    error: Error,
+    /// The message of the outermost layer.
+    message: String,
+    /// The layers under the outermost one.
+    cause: Option<Box<Cause>>,
+}
+
+impl fmt::Debug for Converted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.error, f)
+    }
+}
+
+impl fmt::Display for Converted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl StdError for Converted {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.cause
+            .as_deref()
+            .map(|cause| cause as &(dyn StdError + 'static))
+    }
+}
+
+impl Diagnostic for Converted {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Diagnostic::code(&self.error)
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        Diagnostic::severity(&self.error)
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        let help = Help {
+            error: &self.error,
+            fields: true,
+        };
+        (!help.is_empty()).then(|| Box::new(help) as Box<dyn fmt::Display + 'a>)
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Diagnostic::url(&self.error)
+    }
+
+    fn diagnostic_source(&self) -> Option<&dyn Diagnostic> {
+        self.cause.as_deref().map(|cause| cause as &dyn Diagnostic)
+    }
+}
+
+/// Convert `error` into a miette report, see [`Error::into_miette`].
+pub fn report(error: Error) -> Report {
+    error.ack();
+    let messages: Vec<String> = error.chain().skip(1).map(ToString::to_string).collect();
+    let cause = messages.into_iter().rev().fold(None, |source, message| {
+        Some(Box::new(Cause { message, source }))
+    });
+    Report::new(Converted {
+        message: error.message(),
+        cause,
+        error,
+    })
+}
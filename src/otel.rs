@@ -0,0 +1,41 @@
+use opentelemetry::KeyValue;
+use opentelemetry::trace::{SpanId, Status, TraceContextExt, TraceId};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::Error;
+
+/// The name of the event recording an error, from the semantic conventions.
+const EXCEPTION_EVENT: &str = "exception";
+
+/// The type recorded for errors without a code.
+const EXCEPTION_TYPE: &str = "anyhow_tracing::Error";
+
+/// Get the trace and span IDs of the OpenTelemetry span of the current span, if it is
+/// sampled.
+pub fn trace_context() -> Option<(TraceId, SpanId)> {
+    let context = tracing::Span::current().context();
+    let span = context.span();
+    let span_context = span.span_context();
+    (span_context.is_valid() && span_context.is_sampled())
+        .then(|| (span_context.trace_id(), span_context.span_id()))
+}
+
+/// Record `error` as an exception event on the OpenTelemetry span of the current
+/// span, and mark that span as failed.
+pub fn record_exception(error: &Error) {
+    error.ack();
+    let message = error.display_without_fields().to_string();
+    let mut attributes = Vec::with_capacity(error.fields().len().saturating_add(2));
+    attributes.push(KeyValue::new("exception.message", message.clone()));
+    attributes.push(KeyValue::new(
+        "exception.type",
+        error.code().unwrap_or(EXCEPTION_TYPE).to_owned(),
+    ));
+    for (key, value) in error.fields() {
+        attributes.push(KeyValue::new(key, value.to_owned()));
+    }
+
+    let span = tracing::Span::current();
+    span.add_event(EXCEPTION_EVENT, attributes);
+    span.set_status(Status::error(message));
+}
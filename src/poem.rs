@@ -0,0 +1,39 @@
+use std::error::Error as StdError;
+
+use ::poem::error::ResponseError;
+use ::poem::http::StatusCode;
+use ::poem::web::Json;
+use ::poem::{IntoResponse, Response};
+
+use crate::{Error, HttpError};
+
+/// Poem only answers with standard errors, the source is the source of the wrapped error.
+impl StdError for HttpError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.error().source()
+    }
+}
+
+/// Emits the error at [`HttpError::level`] when the response is built, which answers
+/// with its status and JSON body.
+impl ResponseError for HttpError {
+    fn status(&self) -> StatusCode {
+        Self::status(self)
+    }
+
+    fn as_response(&self) -> Response {
+        self.error().emit_at(self.level());
+        let mut response = Json(self.body()).into_response();
+        response.set_status(Self::status(self));
+        response
+    }
+}
+
+/// Answers with the error as an [`HttpError`], so handlers can return this crate's
+/// `Result`. [`poem::Error::downcast_ref`](::poem::Error::downcast_ref) gets the
+/// `HttpError` back.
+impl From<Error> for ::poem::Error {
+    fn from(error: Error) -> Self {
+        HttpError::new(error).into()
+    }
+}
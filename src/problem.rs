@@ -0,0 +1,162 @@
+use http::StatusCode;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+
+use crate::Error;
+
+/// The members defined by RFC 7807, which fields can't replace.
+const STANDARD_MEMBERS: &[&str] = &["type", "title", "status", "detail", "instance"];
+
+/// An error as an RFC 7807 problem details object, the body of an
+/// `application/problem+json` response, see [`Error::to_problem_details`].
+///
+/// It serializes to the layout of the RFC: the `type`, `title`, `status` and `detail`
+/// members, the `instance` member if set, then each extension member at the top level.
+/// With the `schemars` feature, it implements `JsonSchema` describing that layout.
+///
+/// ```rust
+/// use anyhow_tracing::anyhow;
+/// use http::StatusCode;
+/// use serde_json::json;
+///
+/// let err = anyhow!(balance = 30, internal_account_id = 7, "insufficient balance")
+///     .with_public_message("You do not have enough credit.")
+///     .with_status(StatusCode::FORBIDDEN);
+/// let problem = err
+///     .to_problem_details()
+///     .with_type("https://example.com/probs/out-of-credit")
+///     .allow_extensions(&["balance"]);
+///
+/// assert_eq!(
+///     serde_json::to_value(&problem).unwrap(),
+///     json!({
+///         "type": "https://example.com/probs/out-of-credit",
+///         "title": "You do not have enough credit.",
+///         "status": 403,
+///         "detail": "insufficient balance",
+///         "balance": "30",
+///     })
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProblemDetails {
+    /// A URI identifying the type of problem, `about:blank` by default.
+    pub problem_type: String,
+    /// A short summary of the problem.
+    pub title: String,
+    /// The HTTP status code.
+    pub status: u16,
+    /// An explanation of this occurrence of the problem.
+    pub detail: String,
+    /// A URI identifying this occurrence of the problem.
+    pub instance: Option<String>,
+    /// The extension members, in order.
+    pub extensions: Vec<(String, Value)>,
+}
+
+impl ProblemDetails {
+    /// Build the problem details of `error`, see [`Error::to_problem_details`].
+    pub(crate) fn new(error: &Error) -> Self {
+        error.ack();
+        let fields = error.fields();
+        let extensions = fields
+            .iter()
+            .enumerate()
+            .filter(|&(i, (key, _))| {
+                !STANDARD_MEMBERS.contains(&key)
+                    && fields
+                        .iter()
+                        .skip(i.saturating_add(1))
+                        .all(|(other, _)| other != key)
+            })
+            .map(|(i, (key, value))| {
+                let value = error
+                    .json_field(i)
+                    .cloned()
+                    .unwrap_or_else(|| Value::from(value));
+                (key.to_owned(), value)
+            })
+            .collect();
+        let detail: Vec<String> = error.chain().map(ToString::to_string).collect();
+
+        Self {
+            problem_type: "about:blank".to_owned(),
+            title: error
+                .public_message()
+                .map_or_else(|| error.message(), ToOwned::to_owned),
+            status: error
+                .status()
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+                .as_u16(),
+            detail: detail.join(": "),
+            instance: None,
+            extensions,
+        }
+    }
+
+    /// Set the URI identifying the type of problem.
+    pub fn with_type<T: Into<String>>(mut self, problem_type: T) -> Self {
+        self.problem_type = problem_type.into();
+        self
+    }
+
+    /// Set the HTTP status code, replacing the one of the error.
+    pub const fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status.as_u16();
+        self
+    }
+
+    /// Set the URI identifying this occurrence of the problem.
+    pub fn with_instance<I: Into<String>>(mut self, instance: I) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Keep only the extension members whose key is in `allowed`, so that internal
+    /// fields are not sent to clients.
+    pub fn allow_extensions(mut self, allowed: &[&str]) -> Self {
+        self.extensions
+            .retain(|(key, _)| allowed.contains(&key.as_str()));
+        self
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for ProblemDetails {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "ProblemDetails".into()
+    }
+
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "An RFC 7807 problem details object.",
+            "type": "object",
+            "properties": {
+                "type": { "type": "string" },
+                "title": { "type": "string" },
+                "status": { "type": "integer", "minimum": 100, "maximum": 999 },
+                "detail": { "type": "string" },
+                "instance": { "type": "string" },
+            },
+            "required": ["type", "title", "status", "detail"],
+            "additionalProperties": true,
+        })
+    }
+}
+
+impl Serialize for ProblemDetails {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("type", &self.problem_type)?;
+        map.serialize_entry("title", &self.title)?;
+        map.serialize_entry("status", &self.status)?;
+        map.serialize_entry("detail", &self.detail)?;
+        if let Some(instance) = &self.instance {
+            map.serialize_entry("instance", instance)?;
+        }
+        for (key, value) in &self.extensions {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
@@ -0,0 +1,84 @@
+//! Raising errors as Python exceptions, for Rust code exposed to Python with pyo3.
+//!
+//! `PyErr` converts from [`Error`], so functions exported with `#[pyfunction]` can use
+//! `?` on this crate's `Result`. Once [`register`] added [`AnyhowTracingError`] to the
+//! extension module, errors are raised as that exception, with:
+//!
+//! - `args[0]`: the whole chain of messages joined by `: `, without the fields.
+//! - `fields`: a dict of the fields of the error, the last value of a repeated field
+//!   winning.
+//! - `code`: the [code](Error::code) of the error, or `None`.
+//! - `chain`: a list of the message of each layer, outermost first.
+//!
+//! Before that, errors are raised as a `RuntimeError` with the same message, as Python
+//! code could not catch an exception type it cannot import.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use pyo3::exceptions::{PyException, PyRuntimeError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+use crate::Error;
+
+pyo3::create_exception!(
+    anyhow_tracing,
+    AnyhowTracingError,
+    PyException,
+    "An error raised from Rust, with the `fields`, `code` and `chain` of the error."
+);
+
+/// Whether [`AnyhowTracingError`] was added to a module with [`register`].
+static REGISTERED: AtomicBool = AtomicBool::new(false);
+
+/// Add [`AnyhowTracingError`] to `module`, so that Python code can catch it, and raise
+/// errors as it from then on.
+///
+/// ```rust,ignore
+/// use pyo3::prelude::*;
+///
+/// #[pymodule]
+/// fn core(module: &Bound<'_, PyModule>) -> PyResult<()> {
+///     anyhow_tracing::python::register(module)
+/// }
+/// ```
+pub fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add(
+        "AnyhowTracingError",
+        module.py().get_type::<AnyhowTracingError>(),
+    )?;
+    REGISTERED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Set the `fields`, `code` and `chain` attributes of `exception` from `error`.
+fn set_attributes(exception: &Bound<'_, PyAny>, error: &Error) -> PyResult<()> {
+    let py = exception.py();
+    let fields = PyDict::new(py);
+    for (key, value) in error.fields() {
+        fields.set_item(key, value)?;
+    }
+    exception.setattr("fields", fields)?;
+    exception.setattr("code", error.code())?;
+    let chain = PyList::new(py, error.chain().map(|layer| layer.to_string()))?;
+    exception.setattr("chain", chain)
+}
+
+/// Raises the error as an [`AnyhowTracingError`], or as a `RuntimeError` until it is
+/// [registered](register), see the [`python`](crate::python) module.
+impl From<Error> for PyErr {
+    fn from(error: Error) -> Self {
+        error.ack();
+        let message = error.display_without_fields().to_string();
+        if !REGISTERED.load(Ordering::Relaxed) {
+            return PyRuntimeError::new_err(message);
+        }
+        Python::attach(|py| {
+            let exception = AnyhowTracingError::new_err(message);
+            match set_attributes(exception.value(py), &error) {
+                Ok(()) => exception,
+                Err(failed) => failed,
+            }
+        })
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::OnceLock;
+
+use crate::Error;
+use crate::logfmt::write_pair;
+
+/// The fields kept under their own names on rebuilt errors, set with
+/// [`set_remote_fields`].
+static REMOTE_FIELDS: OnceLock<&'static [&'static str]> = OnceLock::new();
+
+/// Keep the fields named `fields` under their own names on errors rebuilt from
+/// another process, with [`Error::from_data`] or [`Error::from_status`].
+///
+/// The names of fields come from the other process, and field names live as long as
+/// the program, so other fields are not given names of their own: they are rendered
+/// together as logfmt pairs in a single field, `remote.fields` or `grpc.fields`. gRPC
+/// metadata keys are lowercase, so the names of fields received with
+/// `Error::from_status` must be too.
+///
+/// This can only be done once, and should be done at startup. Returns the names back
+/// if the remote fields were already set.
+///
+/// ```rust
+/// anyhow_tracing::set_remote_fields(&["user_id", "request_id"])
+///     .expect("the remote fields are only set once");
+/// ```
+pub fn set_remote_fields(fields: &'static [&'static str]) -> Result<(), &'static [&'static str]> {
+    REMOTE_FIELDS.set(fields)
+}
+
+/// Add `fields` received from another process to `error`, under their own names if
+/// they were named with [`set_remote_fields`], and together in the field `rest`
+/// otherwise.
+pub fn add_remote_fields<K, I>(error: &mut Error, rest: &'static str, fields: I)
+where
+    K: AsRef<str>,
+    I: IntoIterator<Item = (K, String)>,
+{
+    let known = REMOTE_FIELDS.get().copied().unwrap_or_default();
+    let mut others = String::new();
+    for (key, value) in fields {
+        let key = key.as_ref();
+        if let Some(name) = known.iter().find(|name| **name == key) {
+            error.add_field(name, value);
+        } else if key == rest {
+            error.add_field(rest, value);
+        } else {
+            if !others.is_empty() {
+                others.push(' ');
+            }
+            // Writing into a `String` never fails
+            write_pair(&mut others, key, &value).ok();
+        }
+    }
+    if !others.is_empty() {
+        error.add_field(rest, others);
+    }
+}
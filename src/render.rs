@@ -0,0 +1,389 @@
+use std::fmt::{self, Write};
+use std::sync::OnceLock;
+
+use crate::Error;
+
+/// The format used when no other format was set with [`set_field_format`].
+static FIELD_FORMAT: OnceLock<FieldFormat> = OnceLock::new();
+
+/// The hook rendering `Debug`, set with [`set_report_hook`].
+static REPORT_HOOK: OnceLock<ReportHook> = OnceLock::new();
+
+/// A function rendering an error for its `Debug` output, see [`set_report_hook`].
+pub type ReportHook = Box<dyn Fn(&Error, &mut fmt::Formatter<'_>) -> fmt::Result + Send + Sync>;
+
+/// How field values are escaped when rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Escape {
+    /// Values are written unchanged.
+    None,
+    /// `\n`, `\r` and `\t` are written as their backslash escapes and every other
+    /// control character as `\xNN` (or `\u{..}` outside of ASCII), so a single error
+    /// can never span multiple log lines or emit terminal escape sequences.
+    ControlCharacters,
+}
+
+/// How the fields of an error are rendered after its message by `Display`.
+///
+/// The default renders ` [key=value, key=value]`, escaping control characters when
+/// the `sanitize-output` feature is enabled. Nothing is rendered for an error without
+/// fields.
+///
+/// Backslashes and the characters used by the separators and the suffix (other than
+/// whitespace) are escaped with a backslash inside values, so that the fields can be
+/// read back from a log line with [`parse_fields`]. This can be turned off with
+/// [`FieldFormat::escape_delimiters`].
+///
+/// ```rust
+/// use anyhow_tracing::{FieldFormat, anyhow};
+///
+/// let logfmt = FieldFormat::new().prefix(" ").suffix("").pair_separator(" ");
+/// let err = anyhow!(host = "db-3", port = 5432, "connection refused");
+///
+/// assert_eq!(err.display_with(logfmt).to_string(), "connection refused host=db-3 port=5432");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FieldFormat {
+    prefix: &'static str,
+    suffix: &'static str,
+    pair_separator: &'static str,
+    key_value_separator: &'static str,
+    escape: Escape,
+    escape_delimiters: bool,
+    sort_fields: bool,
+    max_value_len: Option<usize>,
+    debug_value_width: usize,
+}
+
+impl FieldFormat {
+    /// The default format, ` [key=value, key=value]`.
+    pub const DEFAULT: Self = Self {
+        prefix: " [",
+        suffix: "]",
+        pair_separator: ", ",
+        key_value_separator: "=",
+        escape: if cfg!(feature = "sanitize-output") {
+            Escape::ControlCharacters
+        } else {
+            Escape::None
+        },
+        escape_delimiters: true,
+        sort_fields: false,
+        max_value_len: None,
+        debug_value_width: 100,
+    };
+
+    /// Create the default format, to be customised with the builder methods.
+    pub const fn new() -> Self {
+        Self::DEFAULT
+    }
+
+    /// Set the text written between the message and the first field.
+    pub const fn prefix(mut self, prefix: &'static str) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// Set the text written after the last field.
+    pub const fn suffix(mut self, suffix: &'static str) -> Self {
+        self.suffix = suffix;
+        self
+    }
+
+    /// Set the text written between two fields.
+    pub const fn pair_separator(mut self, separator: &'static str) -> Self {
+        self.pair_separator = separator;
+        self
+    }
+
+    /// Set the text written between the key and the value of a field.
+    pub const fn key_value_separator(mut self, separator: &'static str) -> Self {
+        self.key_value_separator = separator;
+        self
+    }
+
+    /// Set how field values are escaped.
+    pub const fn escape(mut self, escape: Escape) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Set whether backslashes and separator characters in values are escaped.
+    pub const fn escape_delimiters(mut self, escape_delimiters: bool) -> Self {
+        self.escape_delimiters = escape_delimiters;
+        self
+    }
+
+    /// Set whether fields are rendered sorted by key, instead of in the order they
+    /// were added. Fields with the same key keep the order they were added in.
+    ///
+    /// When set as the crate default, this also sorts the fields in the `Debug`
+    /// output, in [`Report`](crate::Report)s and in [`Error::to_logfmt`](crate::Error::to_logfmt).
+    /// [`Error::fields`](crate::Error::fields) always returns them in insertion order.
+    pub const fn sort_fields(mut self, sort_fields: bool) -> Self {
+        self.sort_fields = sort_fields;
+        self
+    }
+
+    /// Set the length in bytes after which rendered values are cut off, or `None` to
+    /// render them in full, which is the default.
+    ///
+    /// A value longer than this is cut at the last character boundary within the limit
+    /// and followed by `…(len=N)` with its full length, as in `body=eyJhbGciOi…(len=18234)`.
+    /// Only the rendering is affected, the error keeps the full value. When set as the
+    /// crate default, this also applies to the `Debug` output.
+    pub const fn max_value_len(mut self, max_value_len: Option<usize>) -> Self {
+        self.max_value_len = max_value_len;
+        self
+    }
+
+    /// Set the number of characters after which a value in the `Debug` output is
+    /// continued on the next line, under the start of the value. The default is 100.
+    ///
+    /// This only applies to the `Debug` output, when set as the crate default.
+    pub const fn debug_value_width(mut self, width: usize) -> Self {
+        self.debug_value_width = width;
+        self
+    }
+
+    /// Get the number of characters after which a value in the `Debug` output wraps.
+    pub(crate) const fn value_width(&self) -> usize {
+        self.debug_value_width
+    }
+
+    /// Cut `value` off at the maximum length, returning its full length if it was cut.
+    pub(crate) fn truncate<'a>(&self, value: &'a str) -> (&'a str, Option<usize>) {
+        match self.max_value_len {
+            Some(max) if value.len() > max => {
+                let end = value
+                    .char_indices()
+                    .map(|(i, _)| i)
+                    .take_while(|&i| i <= max)
+                    .last()
+                    .unwrap_or_default();
+                (value.get(..end).unwrap_or_default(), Some(value.len()))
+            }
+            _ => (value, None),
+        }
+    }
+
+    /// Get the fields in the order this format renders them in.
+    pub(crate) fn ordered<'a>(
+        &self,
+        fields: impl IntoIterator<Item = (&'static str, &'a str)>,
+    ) -> Vec<(&'static str, &'a str)> {
+        let mut ordered: Vec<_> = fields.into_iter().collect();
+        if self.sort_fields {
+            ordered.sort_by_key(|(key, _)| *key);
+        }
+        ordered
+    }
+
+    /// Render the fields in this format.
+    pub(crate) fn write_fields<'a>(
+        &self,
+        f: &mut impl Write,
+        fields: impl IntoIterator<Item = (&'static str, &'a str)>,
+    ) -> fmt::Result {
+        let fields = self.ordered(fields);
+        if fields.is_empty() {
+            return Ok(());
+        }
+
+        f.write_str(self.prefix)?;
+        for (i, (key, value)) in fields.into_iter().enumerate() {
+            if i > 0 {
+                f.write_str(self.pair_separator)?;
+            }
+            f.write_str(key)?;
+            f.write_str(self.key_value_separator)?;
+            self.write_value(f, value)?;
+        }
+        f.write_str(self.suffix)
+    }
+
+    fn write_value(&self, f: &mut impl Write, value: &str) -> fmt::Result {
+        let (value, len) = self.truncate(value);
+        for c in value.chars() {
+            if c.is_control() && self.escape == Escape::ControlCharacters {
+                write_escaped_control(f, c)?;
+            } else if self.escape_delimiters && self.is_delimiter(c) {
+                f.write_char('\\')?;
+                f.write_char(c)?;
+            } else {
+                f.write_char(c)?;
+            }
+        }
+        write_truncated_len(f, len)
+    }
+
+    /// Whether `c` has to be escaped inside a value to keep the output parseable.
+    fn is_delimiter(&self, c: char) -> bool {
+        c == '\\'
+            || (!c.is_whitespace()
+                && [self.pair_separator, self.key_value_separator, self.suffix]
+                    .iter()
+                    .any(|delimiter| delimiter.contains(c)))
+    }
+}
+
+impl Default for FieldFormat {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Write the marker following a value that was cut off at `len` bytes, if it was.
+pub fn write_truncated_len<W: Write>(f: &mut W, len: Option<usize>) -> fmt::Result {
+    match len {
+        Some(len) => write!(f, "…(len={})", len),
+        None => Ok(()),
+    }
+}
+
+/// Set the format used to render fields for the rest of the program.
+///
+/// This can only be done once, and should be done at startup: errors that were
+/// already rendered keep their cached output. Returns the format back if one was
+/// already set.
+pub fn set_field_format(format: FieldFormat) -> Result<(), FieldFormat> {
+    FIELD_FORMAT.set(format)
+}
+
+/// Get the format used to render fields, see [`set_field_format`].
+pub fn field_format() -> FieldFormat {
+    FIELD_FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Set the hook rendering the `Debug` output of every error for the rest of the program.
+///
+/// This lets an application change how errors are reported, for example to render
+/// them as JSON in production, or to add a line pointing to the logs. The hook can
+/// call [`default_report_hook`](crate::default_report_hook) to render the usual output.
+///
+/// This can only be done once, and should be done at startup. Returns the hook back if
+/// one was already set.
+///
+/// ```rust
+/// use anyhow_tracing::{Error, default_report_hook, set_report_hook};
+///
+/// set_report_hook(Box::new(|error, f| {
+///     default_report_hook(error, f)?;
+///     write!(f, "\nSee the logs at /var/log/app.log")
+/// }))
+/// .ok();
+///
+/// let err = Error::msg("timeout");
+/// assert_eq!(format!("{:?}", err), "timeout\nSee the logs at /var/log/app.log");
+/// ```
+pub fn set_report_hook(hook: ReportHook) -> Result<(), ReportHook> {
+    REPORT_HOOK.set(hook)
+}
+
+/// Get the hook set with [`set_report_hook`], if any.
+pub fn report_hook() -> Option<&'static ReportHook> {
+    REPORT_HOOK.get()
+}
+
+/// Write a control character as its backslash escape.
+pub fn write_escaped_control<W: Write>(f: &mut W, c: char) -> fmt::Result {
+    match c {
+        '\n' => f.write_str("\\n"),
+        '\r' => f.write_str("\\r"),
+        '\t' => f.write_str("\\t"),
+        c if c.is_ascii() => write!(f, "\\x{:02x}", u32::from(c)),
+        c => {
+            for escaped in c.escape_unicode() {
+                f.write_char(escaped)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Read the fields back from a line rendered with the default [`FieldFormat`].
+///
+/// The line can be the whole `Display` output of an error or only its ` [..]` suffix,
+/// escaped characters in the values are unescaped. A message that itself contains
+/// brackets is skipped over, as long as keys contain no whitespace, `=`, `,`, `[` or
+/// `]`. Returns an empty list if the line has no fields.
+///
+/// ```rust
+/// use anyhow_tracing::{Error, parse_fields};
+///
+/// let err = Error::msg("invalid section [server]")
+///     .with_field_debug("ids", [1, 2])
+///     .with_field("path", "C:\\temp");
+/// let line = err.to_string();
+/// assert_eq!(line, r"invalid section [server] [ids=[1\, 2\], path=C:\\temp]");
+///
+/// let fields = parse_fields(&line);
+/// assert_eq!(fields[0], ("ids".to_owned(), "[1, 2]".to_owned()));
+/// assert_eq!(fields[1], ("path".to_owned(), "C:\\temp".to_owned()));
+/// ```
+pub fn parse_fields(line: &str) -> Vec<(String, String)> {
+    let starts = line
+        .match_indices(" [")
+        .map(|(i, _)| i.saturating_add(2))
+        .chain(line.starts_with('[').then_some(1));
+    starts
+        .filter_map(|start| line.get(start..).and_then(parse_field_list))
+        .next()
+        .unwrap_or_default()
+}
+
+/// Parse `key=value, key=value]` up to the end of the input.
+fn parse_field_list(input: &str) -> Option<Vec<(String, String)>> {
+    let mut fields = Vec::new();
+    let mut chars = input.chars();
+    loop {
+        let mut key = String::new();
+        loop {
+            match chars.next()? {
+                '=' if !key.is_empty() => break,
+                c if c.is_whitespace() || matches!(c, '=' | ',' | '[' | ']') => return None,
+                c => key.push(c),
+            }
+        }
+
+        let mut value = String::new();
+        loop {
+            match chars.next()? {
+                '\\' => value.push(unescape(&mut chars)?),
+                ',' => {
+                    if chars.next()? != ' ' {
+                        return None;
+                    }
+                    break;
+                }
+                ']' => {
+                    fields.push((key, value));
+                    return chars.next().is_none().then_some(fields);
+                }
+                c => value.push(c),
+            }
+        }
+        fields.push((key, value));
+    }
+}
+
+/// Read the character after a backslash, see [`write_escaped_control`].
+fn unescape(chars: &mut std::str::Chars<'_>) -> Option<char> {
+    match chars.next()? {
+        'n' => Some('\n'),
+        'r' => Some('\r'),
+        't' => Some('\t'),
+        'x' => {
+            let digits: String = chars.by_ref().take(2).collect();
+            char::from_u32(u32::from_str_radix(&digits, 16).ok()?)
+        }
+        'u' => {
+            if chars.next()? != '{' {
+                return None;
+            }
+            let digits: String = chars.by_ref().take_while(|c| *c != '}').collect();
+            char::from_u32(u32::from_str_radix(&digits, 16).ok()?)
+        }
+        c => Some(c),
+    }
+}
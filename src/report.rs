@@ -0,0 +1,226 @@
+use std::backtrace::BacktraceStatus;
+use std::fmt::{self, Write};
+use std::sync::OnceLock;
+
+use crate::Error;
+use crate::render::{field_format, write_escaped_control};
+
+const BOLD: &str = "1";
+const DIM: &str = "2";
+const CYAN: &str = "36";
+
+/// Whether the reports of [`Error::print_report`] are verbose, set with
+/// [`set_verbose_reports`].
+static VERBOSE: OnceLock<bool> = OnceLock::new();
+
+/// Make the reports written by [`Error::print_report`] and [`Error::exit_with_report`]
+/// verbose, for example when a command-line tool is run with `--verbose`.
+///
+/// Verbose reports have the internal messages and fields of errors with a public
+/// message, and the backtrace when one was captured, see [`Report::public`] and
+/// [`Report::backtrace`].
+///
+/// This can only be done once, and should be done at startup. Returns the flag back if
+/// the verbosity was already set.
+pub fn set_verbose_reports(verbose: bool) -> Result<(), bool> {
+    VERBOSE.set(verbose)
+}
+
+/// Get the report written for `error` by [`Error::print_report`].
+pub fn cli_report(error: &Error) -> Report<'_> {
+    let verbose = VERBOSE.get().copied().unwrap_or_default();
+    error.report().public(!verbose).backtrace(verbose)
+}
+
+/// A multi-line `Display` rendering of an error for humans, see [`Error::report`].
+///
+/// The message of the error is written on the first line, followed by the causes,
+/// numbered from the outermost, then the span trace with the `span-trace` feature, the
+/// fields in an aligned two-column block and finally the notes:
+///
+/// ```text
+/// failed to load config
+///
+/// Caused by:
+///     0: failed to open
+///     1: permission denied
+///
+/// Fields:
+///     path    = /etc/app.toml
+///     attempt = 3
+///
+/// Notes:
+///     check the permissions of the config directory
+/// ```
+///
+/// Sections without entries are left out. Control characters in field values are
+/// escaped when the `sanitize-output` feature is enabled, as for `Display`.
+///
+/// [`Report::public`] only shows the public message and notes of errors that have a
+/// public message, and [`Report::backtrace`] adds the backtrace after the causes.
+///
+/// With the `color` feature, [`Report::colored`] styles the report with ANSI escapes
+/// for terminals: the message bold, field keys cyan and causes dimmed.
+#[derive(Debug, Clone, Copy)]
+pub struct Report<'a> {
+    error: &'a Error,
+    compact: bool,
+    color: bool,
+    public: bool,
+    backtrace: bool,
+}
+
+impl<'a> Report<'a> {
+    pub(crate) const fn new(error: &'a Error) -> Self {
+        Self {
+            error,
+            compact: false,
+            color: false,
+            public: false,
+            backtrace: false,
+        }
+    }
+
+    /// Leave out the blank lines between sections.
+    pub const fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// Only show what is meant for end users when the error has a
+    /// [public message](Error::with_public_message): the public message then the notes,
+    /// without the internal messages and the fields.
+    ///
+    /// Errors without a public message are reported in full.
+    pub const fn public(mut self, public: bool) -> Self {
+        self.public = public;
+        self
+    }
+
+    /// Add the backtrace after the causes, when one was captured.
+    pub const fn backtrace(mut self, backtrace: bool) -> Self {
+        self.backtrace = backtrace;
+        self
+    }
+
+    /// Style the report with ANSI escapes, unless standard error is not a terminal or
+    /// the `NO_COLOR` environment variable is set to a non-empty value.
+    #[cfg(feature = "color")]
+    pub fn colored(self) -> Self {
+        use std::io::IsTerminal;
+
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        self.color(!no_color && std::io::stderr().is_terminal())
+    }
+
+    /// Style the report with ANSI escapes or not, regardless of where it is written.
+    #[cfg(feature = "color")]
+    pub const fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Write `text` in the given ANSI style if colors are enabled.
+    fn styled<T: fmt::Display>(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        style: &str,
+        text: T,
+    ) -> fmt::Result {
+        if self.color {
+            write!(f, "\x1b[{}m{}\x1b[0m", style, text)
+        } else {
+            write!(f, "{}", text)
+        }
+    }
+
+    /// Start a new section, separated from the previous one unless compact.
+    fn section(&self, f: &mut fmt::Formatter<'_>, title: &str) -> fmt::Result {
+        if !self.compact {
+            writeln!(f)?;
+        }
+        writeln!(f, "{}:", title)
+    }
+
+    /// Write the notes section, if the error has notes.
+    fn write_notes(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let notes = self.error.notes();
+        if !notes.is_empty() {
+            self.section(f, "Notes")?;
+            for note in notes {
+                writeln!(f, "    {}", note)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.ack();
+        if let Some(message) = self.error.public_message().filter(|_| self.public) {
+            self.styled(f, BOLD, message)?;
+            writeln!(f)?;
+            return self.write_notes(f);
+        }
+        self.styled(f, BOLD, self.error.message())?;
+        writeln!(f)?;
+
+        let mut causes = self.error.chain().skip(1).peekable();
+        if causes.peek().is_some() {
+            self.section(f, "Caused by")?;
+            for (i, cause) in causes.enumerate() {
+                write!(f, "    ")?;
+                self.styled(f, DIM, format_args!("{}: {}", i, cause))?;
+                writeln!(f)?;
+            }
+        }
+
+        let backtrace = self.error.backtrace();
+        if self.backtrace && backtrace.status() == BacktraceStatus::Captured {
+            self.section(f, "Backtrace")?;
+            writeln!(f, "{}", backtrace)?;
+        }
+
+        #[cfg(feature = "span-trace")]
+        if let Some(span_trace) = self.error.span_trace() {
+            self.section(f, "Span trace")?;
+            writeln!(f, "{}", span_trace)?;
+        }
+
+        let fields = field_format().ordered(self.error.fields());
+        if !fields.is_empty() {
+            self.section(f, "Fields")?;
+            let width = fields
+                .iter()
+                .map(|(key, _)| key.chars().count())
+                .max()
+                .unwrap_or_default();
+            for (key, value) in fields {
+                write!(f, "    ")?;
+                self.styled(f, CYAN, key)?;
+                let padding = width.saturating_sub(key.chars().count());
+                write!(f, "{:padding$} = ", "", padding = padding)?;
+                write_value(f, value)?;
+                writeln!(f)?;
+            }
+        }
+
+        self.write_notes(f)
+    }
+}
+
+/// Write a field value, escaping control characters if output is sanitized.
+fn write_value(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    if !cfg!(feature = "sanitize-output") {
+        return f.write_str(value);
+    }
+    for c in value.chars() {
+        if c.is_control() {
+            write_escaped_control(f, c)?;
+        } else {
+            f.write_char(c)?;
+        }
+    }
+    Ok(())
+}
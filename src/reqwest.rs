@@ -0,0 +1,66 @@
+use std::fmt;
+
+use crate::error::{Caller, Error, Result};
+
+/// Extension trait adding context to the result of a reqwest request, along with the
+/// request fields of [`Error::from_reqwest`].
+///
+/// ```rust,no_run
+/// use anyhow_tracing::{HttpResultExt, Result};
+///
+/// async fn fetch_invoice(client: &reqwest::Client) -> Result<String> {
+///     let response = client
+///         .get("https://billing.internal/invoices/7")
+///         .send()
+///         .await
+///         .and_then(reqwest::Response::error_for_status)
+///         .http_context("calling billing API")?;
+///     response.text().await.http_context("reading invoice")
+/// }
+/// ```
+pub trait HttpResultExt<T> {
+    /// Wrap the error with the given context, after adding its request fields.
+    fn http_context<C>(self, context: C) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static;
+}
+
+impl<T> HttpResultExt<T> for std::result::Result<T, ::reqwest::Error> {
+    #[track_caller]
+    fn http_context<C>(self, context: C) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        let caller = Caller::new();
+        self.map_err(|error| caller.create(caller.record(enrich(error)).context(context)))
+    }
+}
+
+/// Wrap `error` without emitting it, with its request fields, see
+/// [`Error::from_reqwest`].
+pub fn enrich(error: ::reqwest::Error) -> Error {
+    let mut fields: Vec<(&'static str, String)> = Vec::new();
+    if let Some(url) = error.url() {
+        let mut url = url.clone();
+        // Credentials are never recorded
+        url.set_password(None).ok();
+        url.set_username("").ok();
+        fields.push(("http.url", url.into()));
+    }
+    if let Some(status) = error.status() {
+        fields.push(("http.status", status.as_u16().to_string()));
+    }
+    if error.is_timeout() {
+        fields.push(("http.timeout", "true".to_owned()));
+    }
+    if error.is_connect() {
+        fields.push(("http.connect", "true".to_owned()));
+    }
+    if error.is_timeout() || error.is_connect() {
+        fields.push(("retryable", "true".to_owned()));
+    }
+
+    let mut error = Error::quiet(anyhow::Error::new(error)).with_root_type::<::reqwest::Error>();
+    error.extend(fields);
+    error
+}
@@ -0,0 +1,184 @@
+//! Answering HTTP requests with errors.
+//!
+//! [`HttpError`] wraps an [`Error`] returned by a request handler. Its response has the
+//! [status](Error::with_status) of the error, 500 without one, and a JSON body with:
+//!
+//! - `message`: the [public message](Error::with_public_message) of the error, or the
+//!   reason phrase of the status without one, as the other messages describe internal
+//!   details.
+//! - `code`: the [code](Error::code) of the error, left out if it has none.
+//! - `fields`: the fields named with [`set_public_fields`], left out if the error has
+//!   none of them. Other fields are never sent.
+//!
+//! With the `axum` feature, `HttpError` implements `IntoResponse`, and with the `actix`
+//! and `poem` features, `ResponseError`. They emit the error with all of its details when
+//! the response is built, and with the `poem` feature, `poem::Error` also converts from
+//! an `Error`, so poem handlers can return this crate's `Result`. With the `rocket`
+//! feature, it implements `Responder` the same way, and
+//! [`answered_error`](crate::answered_error) gets the error back in catchers. With the
+//! `warp` feature, [`handle_rejection`](crate::handle_rejection) answers with errors
+//! rejected as a [`Rejection`](crate::Rejection) the same way, and with the
+//! `async-graphql` feature, [`GraphqlResultExt`](crate::GraphqlResultExt) sends the same
+//! public details in the extensions of GraphQL errors.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use http::StatusCode;
+use serde_json::{Map, Value};
+
+use crate::{Error, Level};
+
+/// The fields sent in response bodies, set with [`set_public_fields`].
+static PUBLIC_FIELDS: OnceLock<&'static [&'static str]> = OnceLock::new();
+
+/// Send the fields named `fields` in response bodies, when an error has them.
+///
+/// This can only be done once, and should be done at startup. Returns the names back
+/// if the public fields were already set.
+///
+/// ```rust
+/// anyhow_tracing::response::set_public_fields(&["request_id", "retry_after"])
+///     .expect("the public fields are only set once");
+/// ```
+pub fn set_public_fields(fields: &'static [&'static str]) -> Result<(), &'static [&'static str]> {
+    PUBLIC_FIELDS.set(fields)
+}
+
+/// An [`Error`] answering an HTTP request, see the [`response`](crate::response) module.
+///
+/// It converts from anything an `Error` converts from, so handlers returning
+/// `Result<T, HttpError>` can use `?` on this crate's `Result`.
+///
+/// ```rust
+/// use anyhow_tracing::{HttpError, anyhow};
+/// use http::StatusCode;
+/// use serde_json::json;
+///
+/// fn find_user(id: u64) -> Result<String, HttpError> {
+///     Err(anyhow!(user_id = id, "no row in users")
+///         .with_status(StatusCode::NOT_FOUND)
+///         .with_public_message("user not found"))?
+/// }
+///
+/// let err = find_user(7).unwrap_err();
+/// assert_eq!(err.status(), StatusCode::NOT_FOUND);
+/// assert_eq!(err.body(), json!({ "message": "user not found" }));
+/// ```
+pub struct HttpError(Error);
+
+impl HttpError {
+    /// Wrap `error` to answer a request with it.
+    pub const fn new(error: Error) -> Self {
+        Self(error)
+    }
+
+    /// Get the wrapped error.
+    pub const fn error(&self) -> &Error {
+        &self.0
+    }
+
+    /// Unwrap the wrapped error.
+    pub fn into_inner(self) -> Error {
+        self.0
+    }
+
+    /// Get the status of the response, the status of the error or 500 without one.
+    #[cfg_attr(
+        feature = "poem",
+        expect(
+            clippy::same_name_method,
+            reason = "`ResponseError::status` returns the same status"
+        )
+    )]
+    pub fn status(&self) -> StatusCode {
+        status(&self.0)
+    }
+
+    /// Get the JSON body of the response.
+    pub fn body(&self) -> Value {
+        body(&self.0)
+    }
+
+    /// Get the level to emit the error at, its own level, or `ERROR` for server errors
+    /// and `WARN` for other statuses without one.
+    pub fn level(&self) -> Level {
+        level(&self.0)
+    }
+}
+
+/// Get the status of the response answering with `error`, see [`HttpError::status`].
+pub(crate) fn status(error: &Error) -> StatusCode {
+    error.status().unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Get the JSON body of the response answering with `error`, see [`HttpError::body`].
+pub(crate) fn body(error: &Error) -> Value {
+    let mut body = Map::new();
+    let message = error.public_message().map_or_else(
+        || {
+            status(error)
+                .canonical_reason()
+                .unwrap_or("error")
+                .to_owned()
+        },
+        ToOwned::to_owned,
+    );
+    body.insert("message".to_owned(), message.into());
+    if let Some(code) = error.code() {
+        body.insert("code".to_owned(), code.into());
+    }
+
+    let fields = public_fields(error);
+    if !fields.is_empty() {
+        body.insert("fields".to_owned(), Value::Object(fields));
+    }
+    Value::Object(body)
+}
+
+/// Get the fields of `error` named with [`set_public_fields`], with their JSON values
+/// when they have one. The last value of a repeated field wins.
+pub(crate) fn public_fields(error: &Error) -> Map<String, Value> {
+    let public = PUBLIC_FIELDS.get().copied().unwrap_or_default();
+    let mut fields = Map::new();
+    for (index, (key, value)) in error.fields().iter().enumerate() {
+        if public.contains(&key) {
+            let value = error
+                .json_field(index)
+                .cloned()
+                .unwrap_or_else(|| Value::from(value));
+            fields.insert(key.to_owned(), value);
+        }
+    }
+    fields
+}
+
+/// Get the level to emit `error` at when answering with it, see [`HttpError::level`].
+pub(crate) fn level(error: &Error) -> Level {
+    error.level().unwrap_or_else(|| {
+        if status(error).is_server_error() {
+            Level::Error
+        } else {
+            Level::Warn
+        }
+    })
+}
+
+impl<E: Into<Error>> From<E> for HttpError {
+    #[track_caller]
+    fn from(error: E) -> Self {
+        Self(error.into())
+    }
+}
+
+impl fmt::Debug for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::Cursor;
+
+use ::rocket::Request;
+use ::rocket::http::{ContentType, Status};
+use ::rocket::response::{self, Responder, Response};
+
+use crate::{Error, HttpError};
+
+/// The error a request was answered with, kept in the request local cache.
+struct Answered(Option<Error>);
+
+/// Emits the error at [`HttpError::level`], then answers with its status and JSON body.
+///
+/// If a catcher is registered for the status and matches the request, it answers
+/// instead and can get the error with [`answered_error`]. Default catchers are not
+/// used, so errors keep their JSON body unless a catcher asks for their status.
+impl<'r> Responder<'r, 'static> for HttpError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        self.error().emit_at(self.level());
+        let status = Status::new(self.status().as_u16());
+        let body = self.body().to_string();
+        let error = self.into_inner();
+        request.local_cache(move || Answered(Some(error)));
+
+        let caught = request.rocket().catchers().any(|catcher| {
+            catcher.code == Some(status.code)
+                && catcher
+                    .base
+                    .path()
+                    .segments()
+                    .prefix_of(request.uri().path().segments())
+        });
+        if caught {
+            return Err(status);
+        }
+        Response::build()
+            .status(status)
+            .header(ContentType::JSON)
+            .sized_body(body.len(), Cursor::new(body))
+            .ok()
+    }
+}
+
+/// Get the error `request` was answered with by [`HttpError`], for catchers and
+/// fairings handling the request after it.
+///
+/// Returns `None` if the request wasn't answered with an error.
+pub fn answered_error<'r>(request: &'r Request<'_>) -> Option<&'r Error> {
+    request.local_cache(|| Answered(None)).0.as_ref()
+}
@@ -0,0 +1,136 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, RandomState};
+use std::panic::Location;
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+use crate::{Error, Level, emit};
+
+/// The field with the rate an emitted error was sampled at.
+const SAMPLE_RATE: &str = "sample_rate";
+
+/// The key errors without a code are counted under.
+const NO_CODE: &str = "none";
+
+/// The sampling rates of errors by code, set with [`set_sampling`].
+static RATES: OnceLock<Mutex<HashMap<Cow<'static, str>, f64>>> = OnceLock::new();
+
+/// The number of errors left out by sampling since the last flush, by code.
+static SKIPPED: OnceLock<Mutex<HashMap<Box<str>, u64>>> = OnceLock::new();
+
+thread_local! {
+    /// The state of the generator deciding which errors are sampled on this thread.
+    static RNG: Cell<u64> = Cell::new(RandomState::new().hash_one(std::thread::current().id()));
+}
+
+/// Set the rate errors with `code` are emitted at by [`Error::emit`], `trace_err` and
+/// automatic emission, from `0.0` for none of them to `1.0` for all of them.
+///
+/// Each error is emitted with probability `rate`, with a `sample_rate` field so that
+/// counts can be weighted back up. The others are only counted, see
+/// [`flush_sampling_stats`]. Setting the rate of a code again replaces it.
+///
+/// ```rust
+/// use anyhow_tracing::{anyhow, set_sampling};
+///
+/// set_sampling("RATE_LIMITED", 0.01);
+/// // Emitted once in a hundred times
+/// anyhow!(client = "10.0.0.7", "too many requests")
+///     .with_code("RATE_LIMITED")
+///     .emit();
+/// ```
+pub fn set_sampling<C: Into<Cow<'static, str>>>(code: C, rate: f64) {
+    RATES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(code.into(), rate);
+}
+
+/// Seed the generator deciding which errors are sampled on the current thread, so that
+/// the same errors are sampled on every run.
+pub fn set_sampling_seed(seed: u64) {
+    RNG.set(seed);
+}
+
+/// Emit an `INFO` event for each code with errors left out by sampling since the last
+/// flush, with the fields `code` and `skipped_count`, and reset the counts.
+///
+/// Errors without a code are counted under `none`. Call this periodically, such as
+/// from a timer task, to keep track of the errors that were not emitted.
+#[track_caller]
+pub fn flush_sampling_stats() {
+    let Some(skipped) = SKIPPED.get() else {
+        return;
+    };
+    let skipped = std::mem::take(&mut *skipped.lock().unwrap_or_else(PoisonError::into_inner));
+    let mut skipped: Vec<_> = skipped.into_iter().collect();
+    skipped.sort_unstable();
+    for (code, count) in skipped {
+        let summary = Error::quiet_msg("errors left out by sampling")
+            .with_field("code", code)
+            .with_field("skipped_count", count);
+        emit::emit_uncounted(
+            &summary,
+            emit::default_target(),
+            Level::Info,
+            Location::caller(),
+            &[],
+        );
+    }
+}
+
+/// Get the sampling rate set for the code of `error`, if any.
+pub fn configured_rate(error: &Error) -> Option<f64> {
+    let code = error.code()?;
+    RATES
+        .get()?
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(code)
+        .copied()
+}
+
+/// Emit `error` at `level` with probability `rate`, and count it otherwise, see
+/// [`Error::emit_sampled`].
+pub fn emit_sampled(error: &Error, rate: f64, level: Level, location: &'static Location<'static>) {
+    if sampled(rate) {
+        let rate = rate.to_string();
+        let extra = [(SAMPLE_RATE, rate.as_str())];
+        emit::emit_with(error, error.target(), level, location, &extra);
+        return;
+    }
+
+    error.ack();
+    #[cfg(feature = "metrics")]
+    crate::metrics::record(error, level);
+    let mut skipped = SKIPPED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let count = skipped
+        .entry(error.code().unwrap_or(NO_CODE).into())
+        .or_default();
+    *count = count.saturating_add(1);
+    drop(skipped);
+}
+
+/// Decide whether to emit an error sampled at `rate`.
+fn sampled(rate: f64) -> bool {
+    rate >= 1.0 || (rate > 0.0 && next_unit() < rate)
+}
+
+/// Get the next number of the generator of this thread, between 0 inclusive and 1
+/// exclusive.
+fn next_unit() -> f64 {
+    // splitmix64
+    let state = RNG.get().wrapping_add(0x9E37_79B9_7F4A_7C15);
+    RNG.set(state);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    // The top 52 bits as the fraction of a number between 1 and 2
+    f64::from_bits(0x3FF0_0000_0000_0000 | (z >> 12)) - 1.0
+}
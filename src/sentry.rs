@@ -0,0 +1,181 @@
+//! Capturing errors as Sentry events, see [`Error::capture_sentry`].
+
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::sync::OnceLock;
+
+use sentry_core::protocol::{Context, Event, Exception, Map, Value};
+use sentry_core::types::Uuid;
+use sentry_core::{ClientOptions, Integration};
+
+use crate::{Error, Level, parse_fields};
+
+/// The fields sent as tags, set with [`set_sentry_tags`].
+static TAG_FIELDS: OnceLock<&'static [&'static str]> = OnceLock::new();
+
+/// The name of the context holding the fields that are not tags.
+const FIELDS_CONTEXT: &str = "fields";
+
+/// Send the fields named `fields` as tags of Sentry events, and the other fields in
+/// the `fields` context.
+///
+/// Tags are indexed and searchable in Sentry, so only name fields with few distinct
+/// values, such as a region or an endpoint, and not identifiers. This can only be
+/// done once, and should be done at startup. Returns the names back if the tags were
+/// already set.
+///
+/// ```rust
+/// anyhow_tracing::set_sentry_tags(&["region", "endpoint"])
+///     .expect("the tags are only set once");
+/// ```
+pub fn set_sentry_tags(fields: &'static [&'static str]) -> Result<(), &'static [&'static str]> {
+    TAG_FIELDS.set(fields)
+}
+
+/// Get the Sentry level of `level`.
+const fn sentry_level(level: Level) -> sentry_core::Level {
+    match level {
+        Level::Error => sentry_core::Level::Error,
+        Level::Warn => sentry_core::Level::Warning,
+        Level::Info => sentry_core::Level::Info,
+        Level::Debug | Level::Trace => sentry_core::Level::Debug,
+    }
+}
+
+/// Get the exception of one layer of an error, naming its type as `sentry` does.
+fn exception(layer: &(dyn StdError + 'static)) -> Exception {
+    let value = layer.to_string();
+    let debug = format!("{layer:?}");
+    let ty = if debug == format!("{value:?}") {
+        "Error".to_owned()
+    } else {
+        sentry_core::parse_type_from_debug(&debug).to_owned()
+    };
+    Exception {
+        ty,
+        value: Some(value),
+        ..Default::default()
+    }
+}
+
+/// Split `fields` into the tags and the `fields` context of an event.
+fn add_fields<I: IntoIterator<Item = (String, Value)>>(event: &mut Event<'static>, fields: I) {
+    let tags = TAG_FIELDS.get().copied().unwrap_or_default();
+    let mut context = Map::new();
+    for (key, value) in fields {
+        if tags.contains(&key.as_str()) {
+            let tag = match value {
+                Value::String(text) => text,
+                other @ (Value::Null
+                | Value::Bool(_)
+                | Value::Number(_)
+                | Value::Array(_)
+                | Value::Object(_)) => other.to_string(),
+            };
+            event.tags.insert(key, tag);
+        } else {
+            context.insert(key, value);
+        }
+    }
+    if !context.is_empty() {
+        event
+            .contexts
+            .insert(FIELDS_CONTEXT.to_owned(), Context::Other(context));
+    }
+}
+
+/// Build the Sentry event of `error`, see [`Error::capture_sentry`].
+pub fn event(error: &Error) -> Event<'static> {
+    error.ack();
+    let mut exceptions: Vec<Exception> = error.chain().map(exception).collect();
+    if let (Some(code), Some(outermost)) = (error.code(), exceptions.first_mut()) {
+        code.clone_into(&mut outermost.ty);
+    }
+    exceptions.reverse();
+
+    let fingerprint = error.code().map_or_else(
+        || error.display_without_fields().to_string(),
+        ToOwned::to_owned,
+    );
+    let mut event = Event {
+        exception: exceptions.into(),
+        level: sentry_level(error.level().unwrap_or(Level::Error)),
+        fingerprint: Cow::Owned(vec![Cow::Owned(fingerprint)]),
+        ..Default::default()
+    };
+    let fields = error
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(index, (key, value))| ((*key).to_owned(), field_value(error, index, value)));
+    add_fields(&mut event, fields);
+    event
+}
+
+/// Get the value of the field at `index`, its JSON value if it has one.
+#[cfg(feature = "json")]
+fn field_value(error: &Error, index: usize, text: &str) -> Value {
+    error
+        .json_field(index)
+        .cloned()
+        .unwrap_or_else(|| Value::from(text))
+}
+
+/// Get the value of the field at `index`.
+#[cfg(not(feature = "json"))]
+fn field_value(_error: &Error, _index: usize, text: &str) -> Value {
+    Value::from(text)
+}
+
+/// Capture `error` with the current hub, see [`Error::capture_sentry`].
+pub fn capture(error: &Error) -> Uuid {
+    sentry_core::capture_event(event(error))
+}
+
+/// A Sentry integration adding the fields of errors captured with
+/// `sentry::capture_error`, see [`sentry_integration`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SentryIntegration;
+
+/// Get the Sentry integration adding the fields of errors captured with
+/// `sentry::capture_error`, as [`Error::capture_sentry`] does.
+///
+/// `capture_error` only sees the `Display` output and sources of an error. For events
+/// whose outermost exception was rendered by an [`Error`], the integration reads its
+/// fields back from that output with [`parse_fields`], and adds them as tags and the
+/// `fields` context. The values are then strings, use [`Error::capture_sentry`] to keep
+/// JSON values, the level and the fingerprint.
+///
+/// ```rust
+/// let options = sentry::ClientOptions::new().add_integration(anyhow_tracing::sentry_integration());
+/// # let _ = options;
+/// ```
+pub const fn sentry_integration() -> SentryIntegration {
+    SentryIntegration
+}
+
+impl Integration for SentryIntegration {
+    fn name(&self) -> &'static str {
+        "anyhow-tracing"
+    }
+
+    fn process_event(
+        &self,
+        mut event: Event<'static>,
+        _options: &ClientOptions,
+    ) -> Option<Event<'static>> {
+        let fields = event
+            .exception
+            .last()
+            .and_then(|exception| exception.value.as_deref())
+            .map(parse_fields)
+            .unwrap_or_default();
+        add_fields(
+            &mut event,
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, Value::String(value))),
+        );
+        Some(event)
+    }
+}
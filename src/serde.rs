@@ -0,0 +1,90 @@
+//! Deserializing with the path of the failing value recorded on errors.
+//!
+//! A deserialization error such as ``missing field `price` `` doesn't tell where in a
+//! nested document the field was expected. [`deserialize_context`] tracks the path with
+//! `serde_path_to_error`, and a failure becomes an [`Error`] with the message `failed to
+//! deserialize`, the original error as its source, and the fields:
+//!
+//! - `serde.path`: the path of the failing value, such as `items[3].price`, or `.` for
+//!   the document itself.
+//! - `serde.type`: the name of the type being deserialized.
+//!
+//! With the `json` feature, `from_str`, `from_slice` and `from_value` do the same for
+//! JSON.
+//!
+//! ```rust
+//! use anyhow_tracing::serde::deserialize_context;
+//!
+//! #[derive(Debug, serde::Deserialize)]
+//! struct Order {
+//!     items: Vec<Item>,
+//! }
+//!
+//! #[derive(Debug, serde::Deserialize)]
+//! struct Item {
+//!     price: u64,
+//! }
+//!
+//! let json = r#"{ "items": [{ "price": 3 }, {}] }"#;
+//! let mut deserializer = serde_json::Deserializer::from_str(json);
+//! let err = deserialize_context::<Order, _>(&mut deserializer).unwrap_err();
+//! assert_eq!(err.get_field("serde.path"), Some("items[1]"));
+//! assert!(err.root_cause().to_string().starts_with("missing field `price`"));
+//! ```
+
+use std::any::type_name;
+use std::error::Error as StdError;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::error::{Caller, Error, Result};
+
+/// The message of deserialization errors.
+const MESSAGE: &str = "failed to deserialize";
+
+/// Deserialize a `T` from `deserializer`, recording the path of the failing value on
+/// errors, see the [`serde`](self) module.
+#[track_caller]
+pub fn deserialize_context<'de, T, D>(deserializer: D) -> Result<T>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+    D::Error: StdError + Send + Sync + 'static,
+{
+    let caller = Caller::new();
+    serde_path_to_error::deserialize(deserializer)
+        .map_err(|error| caller.create(path_error::<T, _>(error)))
+}
+
+/// Deserialize a `T` from a string of JSON.
+#[cfg(feature = "json")]
+#[track_caller]
+pub fn from_str<'a, T: Deserialize<'a>>(s: &'a str) -> Result<T> {
+    deserialize_context(&mut serde_json::Deserializer::from_str(s))
+}
+
+/// Deserialize a `T` from bytes of JSON.
+#[cfg(feature = "json")]
+#[track_caller]
+pub fn from_slice<'a, T: Deserialize<'a>>(v: &'a [u8]) -> Result<T> {
+    deserialize_context(&mut serde_json::Deserializer::from_slice(v))
+}
+
+/// Deserialize a `T` from a JSON value.
+#[cfg(feature = "json")]
+#[track_caller]
+pub fn from_value<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Result<T> {
+    deserialize_context(value)
+}
+
+/// Wrap a deserialization error without emitting it, with the path of the failing value
+/// and the name of the type being deserialized.
+fn path_error<T, E>(error: serde_path_to_error::Error<E>) -> Error
+where
+    E: StdError + Send + Sync + 'static,
+{
+    let path = error.path().to_string();
+    Error::quiet(anyhow::Error::new(error.into_inner()).context(MESSAGE))
+        .with_field("serde.path", path)
+        .with_field("serde.type", type_name::<T>())
+}
@@ -0,0 +1,144 @@
+use std::backtrace::BacktraceStatus;
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use crate::Error;
+
+/// An error is serialized as a map with these entries, in order:
+///
+/// - `message`: the message of the outermost context layer.
+/// - `chain`: the messages of every layer, from the outermost to the root cause.
+/// - `fields`: a map from the key of each field to its value, left out if the error
+///   has no fields. A key added several times appears once, with the value added last,
+///   as formats such as JSON don't allow duplicate keys. With the `json` feature,
+///   fields added with [`Error::with_field_json`] keep their structure, other values
+///   are strings.
+/// - `code`: the [`code`](Error::code) of the error, left out if it has none.
+/// - `backtrace`: the rendered backtrace, left out if none was captured.
+///
+/// ```rust
+/// use anyhow_tracing::anyhow;
+/// use serde_json::json;
+///
+/// let err = anyhow!(host = "db-3", "connection refused").context("failed to sync");
+/// let value = serde_json::to_value(&err).unwrap();
+/// assert_eq!(value["message"], "failed to sync");
+/// assert_eq!(value["chain"], json!(["failed to sync", "connection refused"]));
+/// assert_eq!(value["fields"], json!({ "host": "db-3" }));
+/// ```
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.ack();
+        let chain: Vec<String> = self.chain().map(ToString::to_string).collect();
+        let backtrace = self.backtrace();
+        let backtrace =
+            (backtrace.status() == BacktraceStatus::Captured).then(|| backtrace.to_string());
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("message", &self.message())?;
+        map.serialize_entry("chain", &chain)?;
+        if !self.fields().is_empty() {
+            map.serialize_entry("fields", &LastWins(self))?;
+        }
+        if let Some(code) = self.code() {
+            map.serialize_entry("code", code)?;
+        }
+        if let Some(backtrace) = backtrace {
+            map.serialize_entry("backtrace", &backtrace)?;
+        }
+        map.end()
+    }
+}
+
+/// Render `error` as a JSON value, see [`Error::to_json`].
+#[cfg(feature = "json")]
+pub fn to_json(error: &Error, nest_fields: bool) -> serde_json::Value {
+    let mut value = serde_json::to_value(error)
+        .expect("errors serialize to maps with string keys and string values");
+    if nest_fields {
+        if let Some(serde_json::Value::Object(fields)) = value.get_mut("fields") {
+            fields.clear();
+            for (index, (key, field)) in error.fields().iter().enumerate() {
+                let field = match error.json_field(index) {
+                    Some(json) => json.clone(),
+                    None => serde_json::Value::from(field),
+                };
+                insert_nested(fields, key, field);
+            }
+        }
+    }
+    value
+}
+
+/// Insert `value` into `object` under the dotted `key`, creating an object for each
+/// segment but the last.
+#[cfg(feature = "json")]
+fn insert_nested(
+    object: &mut serde_json::Map<String, serde_json::Value>,
+    key: &str,
+    value: serde_json::Value,
+) {
+    let Some((head, rest)) = key.split_once('.') else {
+        object.insert(key.to_owned(), value);
+        return;
+    };
+    let entry = object
+        .entry(head)
+        .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+    if !entry.is_object() {
+        *entry = serde_json::Value::Object(serde_json::Map::new());
+    }
+    if let serde_json::Value::Object(next) = entry {
+        insert_nested(next, rest, value);
+    }
+}
+
+/// The fields of an error serialized as a map, keeping the last value of each key.
+struct LastWins<'a>(&'a Error);
+
+impl Serialize for LastWins<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let fields = self.0.fields();
+        let last = |&(i, (key, _)): &(usize, (&str, &str))| {
+            fields
+                .iter()
+                .skip(i.saturating_add(1))
+                .all(|(other, _)| other != key)
+        };
+        serializer.collect_map(fields.iter().enumerate().filter(last).map(
+            |(index, (key, text))| {
+                let value = FieldValue {
+                    error: self.0,
+                    index,
+                    text,
+                };
+                (key, value)
+            },
+        ))
+    }
+}
+
+/// The value of a field, as its JSON value if it has one, or else as its text.
+struct FieldValue<'a> {
+    #[cfg_attr(
+        not(feature = "json"),
+        expect(dead_code, reason = "only read with `json`")
+    )]
+    error: &'a Error,
+    #[cfg_attr(
+        not(feature = "json"),
+        expect(dead_code, reason = "only read with `json`")
+    )]
+    index: usize,
+    text: &'a str,
+}
+
+impl Serialize for FieldValue<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[cfg(feature = "json")]
+        if let Some(json) = self.error.json_field(self.index) {
+            return json.serialize(serializer);
+        }
+        serializer.serialize_str(self.text)
+    }
+}
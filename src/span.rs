@@ -0,0 +1,106 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Span, Subscriber};
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+use crate::intern::intern;
+
+/// A layer keeping the fields recorded on each span, so that they can be attached to
+/// errors with [`Error::in_current_span`](crate::Error::in_current_span).
+///
+/// The layer must be installed on a [`Registry`]:
+///
+/// ```rust
+/// use anyhow_tracing::{Error, FieldCaptureLayer};
+/// use tracing_subscriber::Registry;
+/// use tracing_subscriber::layer::SubscriberExt;
+///
+/// let subscriber = Registry::default().with(FieldCaptureLayer::new());
+/// tracing::subscriber::with_default(subscriber, || {
+///     let span = tracing::info_span!("request", request_id = 42);
+///     let _guard = span.enter();
+///
+///     let err = Error::msg("not found").in_current_span();
+///     assert_eq!(err.get_field("span.request_id"), Some("42"));
+/// });
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FieldCaptureLayer(());
+
+impl FieldCaptureLayer {
+    /// Create the layer.
+    pub const fn new() -> Self {
+        Self(())
+    }
+}
+
+/// The fields recorded on a span, stored in its extensions.
+struct SpanFields(Vec<(&'static str, String)>);
+
+impl Visit for SpanFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((prefixed(field.name()), value.to_owned()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .push((prefixed(field.name()), format!("{:?}", value)));
+    }
+}
+
+impl<S> Layer<S> for FieldCaptureLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = SpanFields(vec![(prefixed("name"), span.name().to_owned())]);
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(fields) = span.extensions_mut().get_mut::<SpanFields>() {
+            values.record(fields);
+        }
+    }
+}
+
+/// Get the fields of the current span and its parents, outermost first.
+pub fn current_span_fields() -> Vec<(&'static str, Cow<'static, str>)> {
+    let mut fields = Vec::new();
+    Span::current().with_subscriber(|(id, dispatch)| {
+        let Some(registry) = dispatch.downcast_ref::<Registry>() else {
+            return;
+        };
+        let Some(span) = registry.span(id) else {
+            return;
+        };
+        for span in span.scope().from_root() {
+            if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+                fields.extend(
+                    span_fields
+                        .0
+                        .iter()
+                        .map(|(key, value)| (*key, Cow::Owned(value.clone()))),
+                );
+            }
+        }
+    });
+    fields
+}
+
+/// Get `span.{name}` as a `&'static str`, as field names are.
+fn prefixed(name: &str) -> &'static str {
+    intern(format!("span.{}", name))
+}
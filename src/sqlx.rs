@@ -0,0 +1,96 @@
+use std::fmt;
+
+use ::sqlx::error::ErrorKind;
+
+use crate::error::{Caller, Error, Result};
+
+/// Extension trait adding context to the result of a sqlx query, along with the
+/// database fields of [`Error::from_sqlx`].
+///
+/// ```rust
+/// use anyhow_tracing::DbResultExt;
+///
+/// let result: Result<(), sqlx::Error> = Err(sqlx::Error::RowNotFound);
+/// let err = result.db_context("loading user").unwrap_err();
+/// assert_eq!(err.get_field("db.kind"), Some("not_found"));
+/// assert!(err.downcast_ref::<sqlx::Error>().is_some());
+/// ```
+pub trait DbResultExt<T> {
+    /// Wrap the error with the given context, after adding its database fields.
+    fn db_context<C>(self, context: C) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static;
+}
+
+impl<T> DbResultExt<T> for std::result::Result<T, ::sqlx::Error> {
+    #[track_caller]
+    fn db_context<C>(self, context: C) -> Result<T>
+    where
+        C: fmt::Display + fmt::Debug + Send + Sync + 'static,
+    {
+        let caller = Caller::new();
+        self.map_err(|error| caller.create(caller.record(enrich(error)).context(context)))
+    }
+}
+
+/// Wrap `error` without emitting it, with its database fields, see
+/// [`Error::from_sqlx`].
+pub fn enrich(error: ::sqlx::Error) -> Error {
+    let mut fields: Vec<(&'static str, String)> = Vec::new();
+    if let ::sqlx::Error::Database(database) = &error {
+        if let Some(code) = database.code() {
+            fields.push(("db.code", code.into_owned()));
+        }
+        if let Some(constraint) = database.constraint() {
+            fields.push(("db.constraint", constraint.to_owned()));
+        }
+        if let Some(table) = database.table() {
+            fields.push(("db.table", table.to_owned()));
+        }
+    }
+    if let Some(kind) = kind(&error) {
+        fields.push(("db.kind", kind.to_owned()));
+    }
+    if is_retryable(&error) {
+        fields.push(("retryable", "true".to_owned()));
+    }
+
+    let mut error = Error::quiet(anyhow::Error::new(error)).with_root_type::<::sqlx::Error>();
+    error.extend(fields);
+    error
+}
+
+/// Classify `error` as a constraint violation, a missing row or a connection failure,
+/// or as another error returned by the database.
+fn kind(error: &::sqlx::Error) -> Option<&'static str> {
+    if let ::sqlx::Error::Database(database) = error {
+        return Some(match database.kind() {
+            ErrorKind::UniqueViolation => "unique_violation",
+            ErrorKind::ForeignKeyViolation => "foreign_key_violation",
+            ErrorKind::NotNullViolation => "not_null_violation",
+            ErrorKind::CheckViolation => "check_violation",
+            ErrorKind::Other | _ => "database",
+        });
+    }
+    if matches!(error, ::sqlx::Error::RowNotFound) {
+        Some("not_found")
+    } else if is_retryable(error) {
+        Some("connection")
+    } else {
+        None
+    }
+}
+
+/// Tell whether `error` comes from the connection to the database rather than from
+/// the query, so that running the query again may succeed.
+const fn is_retryable(error: &::sqlx::Error) -> bool {
+    matches!(
+        error,
+        ::sqlx::Error::Io(_)
+            | ::sqlx::Error::Tls(_)
+            | ::sqlx::Error::Protocol(_)
+            | ::sqlx::Error::PoolTimedOut
+            | ::sqlx::Error::PoolClosed
+            | ::sqlx::Error::WorkerCrashed
+    )
+}
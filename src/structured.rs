@@ -0,0 +1,105 @@
+use valuable::{
+    Fields as StructFields, Mappable, NamedField, NamedValues, StructDef, Structable, Valuable,
+    Value, Visit,
+};
+
+use crate::fields::Scalar;
+use crate::{Error, Fields};
+
+/// A view of the fields of an error as a `valuable` map, see [`Error::field_map`].
+///
+/// Each field is an entry from its key to its value, in the order they were added.
+/// Numbers and booleans given to the macros without `%` or `?` are a [`Value::I64`],
+/// [`Value::U64`], [`Value::F64`] or [`Value::Bool`], and other fields are a
+/// [`Value::String`] with the text they were rendered to when they were added.
+///
+/// [`Error::field_map`]: crate::Error::field_map
+#[derive(Debug, Clone, Copy)]
+pub struct FieldMap<'a> {
+    fields: &'a Fields,
+}
+
+impl<'a> FieldMap<'a> {
+    pub(crate) const fn new(fields: &'a Fields) -> Self {
+        Self { fields }
+    }
+}
+
+impl Valuable for FieldMap<'_> {
+    fn as_value(&self) -> Value<'_> {
+        Value::Mappable(self)
+    }
+
+    fn visit(&self, visit: &mut dyn Visit) {
+        for (index, (key, text)) in self.fields.iter().enumerate() {
+            let value = match self.fields.scalar(index) {
+                Some(Scalar::I64(value)) => Value::I64(value),
+                Some(Scalar::U64(value)) => Value::U64(value),
+                Some(Scalar::F64(value)) => Value::F64(value),
+                Some(Scalar::Bool(value)) => Value::Bool(value),
+                None => Value::String(text),
+            };
+            visit.visit_entry(Value::String(key), value);
+        }
+    }
+}
+
+impl Mappable for FieldMap<'_> {
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.fields.len(), Some(self.fields.len()))
+    }
+}
+
+/// The names of the fields of an [`ErrorValue`].
+static ERROR_FIELDS: &[NamedField<'static>] = &[
+    NamedField::new("message"),
+    NamedField::new("causes"),
+    NamedField::new("fields"),
+];
+
+/// A view of an error as a `valuable` structure, see [`Error::as_value`].
+///
+/// The structure is named `Error` and has three fields: `message`, the message of the
+/// outermost layer, `causes`, a list of the messages of the layers below it, and
+/// `fields`, the [`FieldMap`] of the error.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorValue<'a> {
+    error: &'a Error,
+}
+
+impl<'a> ErrorValue<'a> {
+    pub(crate) const fn new(error: &'a Error) -> Self {
+        Self { error }
+    }
+}
+
+impl Valuable for ErrorValue<'_> {
+    fn as_value(&self) -> Value<'_> {
+        Value::Structable(self)
+    }
+
+    fn visit(&self, visit: &mut dyn Visit) {
+        let message = self.error.message();
+        let causes: Vec<String> = self
+            .error
+            .chain()
+            .skip(1)
+            .map(ToString::to_string)
+            .collect();
+        let fields = self.error.field_map();
+        visit.visit_named_fields(&NamedValues::new(
+            ERROR_FIELDS,
+            &[
+                Value::String(&message),
+                causes.as_value(),
+                fields.as_value(),
+            ],
+        ));
+    }
+}
+
+impl Structable for ErrorValue<'_> {
+    fn definition(&self) -> StructDef<'_> {
+        StructDef::new_static("Error", StructFields::Named(ERROR_FIELDS))
+    }
+}
@@ -0,0 +1,119 @@
+use std::fmt;
+
+use crate::{Error, Level};
+
+/// A reusable description of an error that is created in many places.
+///
+/// Templates are built once, typically in a `static`, and then instantiated with
+/// [`ErrorTemplate::new_error`], [`ErrorTemplate::with_fields`] or the
+/// [`from_template!`](crate::from_template) macro. Every instantiated error owns its
+/// own copy of the preset fields, so changing one error never affects the template
+/// or other errors created from it.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::LazyLock;
+///
+/// use anyhow_tracing::{ErrorTemplate, Level, from_template};
+///
+/// static UPSTREAM_TIMEOUT: LazyLock<ErrorTemplate> = LazyLock::new(|| {
+///     ErrorTemplate::new("upstream timeout")
+///         .code("UPSTREAM_TIMEOUT")
+///         .level(Level::Warn)
+///         .field("retryable", true)
+/// });
+///
+/// let host = "billing.internal";
+/// let err = from_template!(UPSTREAM_TIMEOUT, host = %host, attempt = 3);
+/// assert_eq!(err.code(), Some("UPSTREAM_TIMEOUT"));
+/// assert_eq!(
+///     err.to_string(),
+///     "upstream timeout [retryable=true, host=billing.internal, attempt=3]"
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct ErrorTemplate {
+    message: &'static str,
+    code: Option<&'static str>,
+    level: Option<Level>,
+    fields: Vec<(&'static str, Box<str>)>,
+}
+
+impl ErrorTemplate {
+    /// Create a new template for errors with the given message.
+    pub const fn new(message: &'static str) -> Self {
+        Self {
+            message,
+            code: None,
+            level: None,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Set the code given to errors created from this template.
+    pub const fn code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    /// Set the level given to errors created from this template.
+    pub const fn level(mut self, level: Level) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Add a named field given to errors created from this template.
+    pub fn field<V: fmt::Display>(mut self, key: &'static str, value: V) -> Self {
+        self.fields.push((key, value.to_string().into_boxed_str()));
+        self
+    }
+
+    /// Add a named field with debug formatting given to errors created from this template.
+    pub fn field_debug<V: fmt::Debug>(mut self, key: &'static str, value: V) -> Self {
+        self.fields
+            .push((key, format!("{:?}", value).into_boxed_str()));
+        self
+    }
+
+    /// Get the message of this template.
+    pub const fn message(&self) -> &'static str {
+        self.message
+    }
+
+    /// Create a new error from this template.
+    ///
+    /// As with [`Error::new`], this may emit an event for the error.
+    #[track_caller]
+    pub fn new_error(&self) -> Error {
+        self.__new_error_quiet().__created()
+    }
+
+    /// Create a new error from this template without emitting an event for it.
+    ///
+    /// Used by [`from_template!`](crate::from_template) to emit the event once all the
+    /// fields are added.
+    #[doc(hidden)]
+    pub fn __new_error_quiet(&self) -> Error {
+        let mut error = Error::quiet_msg(self.message);
+        if let Some(code) = self.code {
+            error = error.with_code(code);
+        }
+        if let Some(level) = self.level {
+            error = error.with_level(level);
+        }
+        error.extend(self.fields.iter().cloned());
+        error
+    }
+
+    /// Create a new error from this template with additional named fields, which are
+    /// added after the preset fields.
+    #[track_caller]
+    pub fn with_fields<I, V>(&self, fields: I) -> Error
+    where
+        I: IntoIterator<Item = (&'static str, V)>,
+        V: fmt::Display,
+    {
+        self.__new_error_quiet().with_fields(fields).__created()
+    }
+}
@@ -0,0 +1,213 @@
+//! Capturing the events emitted for errors, for assertions in tests.
+//!
+//! [`capture`] runs a closure with a subscriber collecting the events emitted for
+//! errors, by [`Error::emit`](crate::Error::emit), `trace_err`, automatic emission and
+//! the other ways of emitting an error, and returns them. Other events are left out.
+//!
+//! The subscriber is only the default of the current thread while the closure runs, so
+//! tests running in parallel don't see each other's events. In async tests, such as
+//! with `#[tokio::test]`, use [`capture_future`] to collect the events of a future
+//! wherever it is polled.
+//!
+//! ```rust
+//! use anyhow_tracing::{Level, anyhow, test};
+//!
+//! let events = test::capture(|| {
+//!     anyhow!(host = "db-3", "connection refused").emit_at(Level::Warn);
+//! });
+//! let [event] = events.as_slice() else {
+//!     panic!("expected one event, got {events:?}");
+//! };
+//! assert_eq!(event.message(), "connection refused");
+//! assert_eq!(event.level(), Level::Warn);
+//! assert_eq!(event.field("host"), Some("db-3"));
+//! ```
+
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tracing::field::{Field, Visit};
+use tracing::instrument::WithSubscriber;
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Dispatch, Event, Metadata, Subscriber};
+
+use crate::Level;
+use crate::callsite::{CHAIN, MESSAGE};
+
+/// An event emitted for an error, as seen by [`capture`].
+#[derive(Debug, Clone)]
+pub struct CapturedErrorEvent {
+    name: &'static str,
+    target: String,
+    level: Level,
+    file: Option<&'static str>,
+    line: Option<u32>,
+    message: String,
+    chain: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl CapturedErrorEvent {
+    /// Get the message of the outermost context layer of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Get the whole chain of messages of the error, recorded as `error.chain`.
+    pub fn chain(&self) -> &str {
+        &self.chain
+    }
+
+    /// Get the level the error was emitted at.
+    pub const fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Get the target of the event, see [`Error::target`](crate::Error::target).
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Get the name of the event, see
+    /// [`Error::with_event_name`](crate::Error::with_event_name).
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Get the file the error was emitted from.
+    pub const fn file(&self) -> Option<&'static str> {
+        self.file
+    }
+
+    /// Get the line the error was emitted from.
+    pub const fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// Get the value of the field `key`, if the event has it.
+    pub fn field(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the fields of the error and any other fields of the event, such as
+    /// `suppressed_count`, in order, without the message and the chain.
+    pub fn fields(&self) -> &[(&'static str, String)] {
+        &self.fields
+    }
+}
+
+/// Run `f` and get the events emitted for errors while it runs on this thread.
+pub fn capture<F: FnOnce()>(f: F) -> Vec<CapturedErrorEvent> {
+    let collector = Collector::default();
+    tracing::subscriber::with_default(collector.clone(), f);
+    collector.take_events()
+}
+
+/// Run `future` to completion and get the events emitted for errors while it is polled.
+pub async fn capture_future<F: Future<Output = ()>>(future: F) -> Vec<CapturedErrorEvent> {
+    let collector = Collector::default();
+    future
+        .with_subscriber(Dispatch::new(collector.clone()))
+        .await;
+    collector.take_events()
+}
+
+/// A subscriber collecting the events emitted for errors at every level.
+#[derive(Clone, Default)]
+struct Collector {
+    events: Arc<Mutex<Vec<CapturedErrorEvent>>>,
+}
+
+impl Collector {
+    fn take_events(&self) -> Vec<CapturedErrorEvent> {
+        std::mem::take(&mut *self.events.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+/// The values recorded on an event.
+#[derive(Default)]
+struct Values {
+    message: Option<String>,
+    chain: Option<String>,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Visit for Values {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_owned());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.record(field, format!("{:?}", value));
+    }
+}
+
+impl Values {
+    fn record(&mut self, field: &Field, value: String) {
+        match field.name() {
+            MESSAGE => self.message = Some(value),
+            CHAIN => self.chain = Some(value),
+            name => self.fields.push((name, value)),
+        }
+    }
+}
+
+/// Get the level of an event as a [`Level`].
+const fn level(level: tracing::Level) -> Level {
+    match level {
+        tracing::Level::TRACE => Level::Trace,
+        tracing::Level::DEBUG => Level::Debug,
+        tracing::Level::INFO => Level::Info,
+        tracing::Level::WARN => Level::Warn,
+        _ => Level::Error,
+    }
+}
+
+impl Subscriber for Collector {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::TRACE)
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut values = Values::default();
+        event.record(&mut values);
+        // Only the events emitted for errors record the chain
+        let (Some(message), Some(chain)) = (values.message, values.chain) else {
+            return;
+        };
+        let metadata = event.metadata();
+        self.events
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(CapturedErrorEvent {
+                name: metadata.name(),
+                target: metadata.target().to_owned(),
+                level: level(*metadata.level()),
+                file: metadata.file(),
+                line: metadata.line(),
+                message,
+                chain,
+                fields: values.fields,
+            });
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::hash::{DefaultHasher, Hasher};
+use std::panic::Location;
+use std::sync::{Mutex, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::{Error, Level, emit};
+
+/// The field with the number of errors left out since the last one was emitted.
+const SUPPRESSED_COUNT: &str = "suppressed_count";
+
+/// The number of independently locked parts of the registry.
+const SHARDS: usize = 16;
+
+/// The number of fingerprints each shard keeps, the oldest are forgotten beyond it.
+const SHARD_CAPACITY: usize = 256;
+
+/// The last emission of each fingerprint, split into shards by fingerprint so that
+/// errors with different fingerprints rarely wait for each other.
+static REGISTRY: OnceLock<[Mutex<HashMap<u64, Window>>; SHARDS]> = OnceLock::new();
+
+/// The time since an error with a fingerprint was last emitted.
+struct Window {
+    /// When the error was last emitted
+    start: Instant,
+    /// How long emissions are suppressed for after `start`
+    period: Duration,
+    /// How many emissions were suppressed since `start`
+    suppressed: u64,
+}
+
+impl Window {
+    fn is_open(&self, now: Instant) -> bool {
+        now.duration_since(self.start) < self.period
+    }
+}
+
+/// A hasher fed with the rendered text of a value, so that nothing is allocated.
+struct HashWriter(DefaultHasher);
+
+impl Write for HashWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Get the fingerprint of the rendered text of `key`.
+pub fn fingerprint<K: fmt::Display + ?Sized>(key: &K) -> u64 {
+    let mut hasher = HashWriter(DefaultHasher::new());
+    // Writing into the hasher only fails if a `Display` impl returns an error
+    write!(hasher, "{}", key).ok();
+    hasher.0.finish()
+}
+
+/// Check whether an error with `fingerprint` should be emitted now, given that it is
+/// emitted at most once per `period`.
+///
+/// Returns the number of emissions suppressed since the last one if it should be
+/// emitted, and `None` if it should be suppressed.
+pub fn check(fingerprint: u64, period: Duration) -> Option<u64> {
+    let registry = REGISTRY.get_or_init(|| std::array::from_fn(|_| Mutex::default()));
+    let shard = fingerprint
+        .checked_rem(SHARDS as u64)
+        .and_then(|index| usize::try_from(index).ok())
+        .and_then(|index| registry.get(index))
+        .expect("the shard index is below the number of shards");
+    check_shard(
+        &mut shard.lock().unwrap_or_else(PoisonError::into_inner),
+        fingerprint,
+        period,
+        Instant::now(),
+    )
+}
+
+fn check_shard(
+    windows: &mut HashMap<u64, Window>,
+    fingerprint: u64,
+    period: Duration,
+    now: Instant,
+) -> Option<u64> {
+    if let Some(window) = windows.get_mut(&fingerprint) {
+        if window.is_open(now) {
+            window.suppressed = window.suppressed.saturating_add(1);
+            return None;
+        }
+        let suppressed = window.suppressed;
+        *window = Window {
+            start: now,
+            period,
+            suppressed: 0,
+        };
+        return Some(suppressed);
+    }
+
+    if windows.len() >= SHARD_CAPACITY {
+        windows.retain(|_, window| window.is_open(now));
+    }
+    if windows.len() >= SHARD_CAPACITY {
+        let oldest = windows
+            .iter()
+            .min_by_key(|(_, window)| window.start)
+            .map(|(fingerprint, _)| *fingerprint);
+        if let Some(oldest) = oldest {
+            windows.remove(&oldest);
+        }
+    }
+    windows.insert(
+        fingerprint,
+        Window {
+            start: now,
+            period,
+            suppressed: 0,
+        },
+    );
+    Some(0)
+}
+
+/// Emit `error` unless an error with `fingerprint` was emitted less than `period` ago,
+/// see [`Error::emit_throttled`].
+pub fn emit_throttled(
+    error: &Error,
+    fingerprint: u64,
+    period: Duration,
+    location: &'static Location<'static>,
+) {
+    error.ack();
+    let level = error.level().unwrap_or(Level::Error);
+    let Some(suppressed) = check(fingerprint, period) else {
+        #[cfg(feature = "metrics")]
+        crate::metrics::record(error, level);
+        return;
+    };
+    if suppressed == 0 {
+        emit::emit_with(error, error.target(), level, location, &[]);
+    } else {
+        let suppressed = suppressed.to_string();
+        let extra = [(SUPPRESSED_COUNT, suppressed.as_str())];
+        emit::emit_with(error, error.target(), level, location, &extra);
+    }
+}
@@ -0,0 +1,81 @@
+use std::panic::Location;
+
+use crate::IntoError;
+use crate::error::Result;
+use crate::{Level, emit};
+
+/// Extension trait for logging an error as a `tracing` event and propagating it.
+///
+/// With the `log` feature and without the `tracing` feature, errors are logged as
+/// `log` records instead, see [`Error::emit_at`](crate::Error::emit_at).
+///
+/// ```rust
+/// use anyhow_tracing::{Level, Result, TraceResultExt};
+///
+/// fn parse_port(port: &str) -> Result<u16> {
+///     let port = port.parse::<u16>().trace_err()?;
+///     Ok(port)
+/// }
+///
+/// fn read_config(path: &str) -> Result<String> {
+///     let config = std::fs::read_to_string(path).log_err(Level::Warn)?;
+///     Ok(config)
+/// }
+/// ```
+///
+/// This replaces `err` on `#[tracing::instrument]`, which records an error as a single
+/// string, for functions returning a [`Result`]. The event is emitted inside the span
+/// of the function:
+///
+/// ```rust
+/// use anyhow_tracing::{Result, TraceResultExt, anyhow};
+///
+/// #[tracing::instrument]
+/// fn connect(host: &str) -> Result<()> {
+///     (|| -> Result<()> {
+///         Err(anyhow!(host = host, "connection refused"))?;
+///         Ok(())
+///     })()
+///     .trace_err()
+/// }
+/// ```
+pub trait TraceResultExt<T, K = ()> {
+    /// Convert the error into an [`Error`](crate::Error) and emit it as a `tracing`
+    /// event at its level, or at `ERROR` if it has none, as
+    /// [`Error::emit`](crate::Error::emit) does.
+    ///
+    /// The event has the file and line this was called from.
+    fn trace_err(self) -> Result<T>;
+
+    /// Convert the error into an [`Error`](crate::Error) and emit it as a `tracing`
+    /// event at `level`, as [`Error::emit_at`](crate::Error::emit_at) does.
+    fn log_err(self, level: Level) -> Result<T>;
+}
+
+/// The event replaces the one emitted for a newly created error when automatic
+/// emission is turned on, so that the error is only logged once.
+impl<T, E, K> TraceResultExt<T, K> for std::result::Result<T, E>
+where
+    E: IntoError<K>,
+{
+    #[track_caller]
+    fn trace_err(self) -> Result<T> {
+        let location = Location::caller();
+        self.map_err(|e| {
+            let (error, _) = e.into_quiet_error();
+            let level = error.level().unwrap_or(Level::Error);
+            emit::emit(&error, level, location);
+            error
+        })
+    }
+
+    #[track_caller]
+    fn log_err(self, level: Level) -> Result<T> {
+        let location = Location::caller();
+        self.map_err(|e| {
+            let (error, _) = e.into_quiet_error();
+            emit::emit(&error, level, location);
+            error
+        })
+    }
+}
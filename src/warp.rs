@@ -0,0 +1,69 @@
+use std::fmt;
+
+use ::warp::http::StatusCode;
+use ::warp::reject::Reject;
+use ::warp::reply::{Reply, Response};
+
+use crate::{Error, response};
+
+/// An [`Error`] rejecting a warp request, see [`Error::into_rejection`].
+///
+/// [`handle_rejection`] answers with it, like [`HttpError`](crate::HttpError) does for
+/// other frameworks.
+pub struct Rejection(Error);
+
+impl Rejection {
+    /// Get the error rejecting the request.
+    pub const fn error(&self) -> &Error {
+        &self.0
+    }
+}
+
+impl Reject for Rejection {}
+
+impl fmt::Debug for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+/// Rejects with the error, so handlers can use `?` on this crate's `Result`.
+impl From<Error> for ::warp::Rejection {
+    fn from(error: Error) -> Self {
+        error.into_rejection()
+    }
+}
+
+/// Reject a request with `error`, see [`Error::into_rejection`].
+pub fn reject(error: Error) -> ::warp::Rejection {
+    ::warp::reject::custom(Rejection(error))
+}
+
+/// Answer a request rejected with an [`Error`], for use with `warp::Filter::recover`.
+///
+/// The error is emitted with all of its details at the level of
+/// [`HttpError::level`](crate::HttpError::level), then answered with its status and the
+/// JSON body described in the [`response`](crate::response) module. Other rejections,
+/// such as those of unmatched routes, are passed on to warp.
+///
+/// ```rust
+/// use anyhow_tracing::{anyhow, handle_rejection};
+/// use warp::Filter;
+///
+/// let routes = warp::path!("users" / u64)
+///     .and_then(|id: u64| async move {
+///         Err::<String, _>(anyhow!(user_id = id, "no row in users").into_rejection())
+///     })
+///     .recover(handle_rejection);
+/// # let _ = routes;
+/// ```
+pub async fn handle_rejection(rejection: ::warp::Rejection) -> Result<Response, ::warp::Rejection> {
+    let Some(Rejection(error)) = rejection.find::<Rejection>() else {
+        return Err(rejection);
+    };
+    error.emit_at(response::level(error));
+    let status = StatusCode::from_u16(response::status(error).as_u16())
+        .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    let reply = ::warp::reply::json(&response::body(error));
+    Ok(::warp::reply::with_status(reply, status).into_response())
+}
@@ -0,0 +1,53 @@
+use js_sys::{Object, Reflect};
+use wasm_bindgen::JsValue;
+
+use crate::Error;
+
+/// Set the property `key` of `target` to `value`.
+fn set(target: &JsValue, key: &str, value: &JsValue) {
+    Reflect::set(target, &JsValue::from_str(key), value)
+        .expect("properties can be set on new objects");
+}
+
+/// Get the JavaScript value of the field at `index`, its JSON value if it has one.
+#[cfg(feature = "json")]
+fn field_value(error: &Error, index: usize, text: &str) -> JsValue {
+    error
+        .json_field(index)
+        .and_then(|json| js_sys::JSON::parse(&json.to_string()).ok())
+        .unwrap_or_else(|| JsValue::from_str(text))
+}
+
+/// Get the JavaScript value of the field at `index`.
+#[cfg(not(feature = "json"))]
+fn field_value(_error: &Error, _index: usize, text: &str) -> JsValue {
+    JsValue::from_str(text)
+}
+
+/// Convert `error` into a JavaScript `Error` object, see [`Error::to_js`].
+pub fn to_js(error: &Error) -> JsValue {
+    error.ack();
+    let message = error
+        .public_message()
+        .map_or_else(|| error.message(), ToOwned::to_owned);
+    let js: JsValue = js_sys::Error::new(&message).into();
+
+    let fields = Object::new();
+    for (index, (key, value)) in error.fields().iter().enumerate() {
+        set(&fields, key, &field_value(error, index, value));
+    }
+    set(&js, "fields", &fields);
+    if let Some(code) = error.code() {
+        set(&js, "code", &JsValue::from_str(code));
+    }
+    js
+}
+
+/// Converts the error as [`Error::to_js`] does, so functions exported with
+/// `#[wasm_bindgen]` can return `Result<T, JsValue>` and use `?` on this crate's
+/// `Result`.
+impl From<Error> for JsValue {
+    fn from(error: Error) -> Self {
+        to_js(&error)
+    }
+}
@@ -0,0 +1,110 @@
+#![cfg(feature = "tracing")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Automatic emission of created errors, in its own test binary because it is global.
+
+mod common;
+
+use std::io;
+
+use anyhow_tracing::{
+    Context, Error, ErrorTemplate, Level, Result, anyhow, bail, ensure, from_template,
+    set_auto_emit,
+};
+use common::{CapturedEvent, Collector};
+
+/// Turn on automatic emission and collect the events emitted by `f`.
+fn collect<R>(f: impl FnOnce() -> R) -> (R, Vec<CapturedEvent>) {
+    set_auto_emit(Level::Warn).ok();
+    let collector = Collector::new(tracing::Level::TRACE);
+    let result = tracing::subscriber::with_default(collector.clone(), f);
+    (result, collector.take_events())
+}
+
+/// Tests that each macro invocation emits exactly one event, with all its fields.
+#[test]
+fn test_macros_emit_once() {
+    fn bails(id: u32) -> Result<()> {
+        bail!(id, "user not found");
+    }
+
+    fn ensures(value: i32) -> Result<()> {
+        ensure!(value > 0, value, "value must be positive");
+        Ok(())
+    }
+
+    let ((err, line), events) =
+        collect(|| (anyhow!(host = "db-3", port = 5432, "refused"), line!()));
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.level, tracing::Level::WARN);
+    assert_eq!(event.field("message"), Some("refused"));
+    assert_eq!(event.field("host"), Some("db-3"));
+    assert_eq!(event.field("port"), Some("5432"));
+    assert_eq!(event.target, module_path!());
+    assert_eq!(event.file.as_deref(), Some(file!()));
+    assert_eq!(event.line, Some(line));
+    assert_eq!(err.fields().len(), 2);
+
+    let (_, events) = collect(|| bails(7));
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.field("id"), Some("7"));
+
+    let (_, events) = collect(|| ensures(-1));
+    assert_eq!(events.len(), 1);
+
+    let (_, events) = collect(|| ensures(1));
+    assert!(events.is_empty());
+
+    let template = ErrorTemplate::new("quota exceeded").field("tenant", "acme");
+    let (_, events) = collect(|| from_template!(template, limit = 100));
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.field("tenant"), Some("acme"));
+    assert_eq!(event.field("limit"), Some("100"));
+}
+
+/// Tests that context added to an existing error does not emit again, while converting
+/// another error does, once its context is added.
+#[test]
+fn test_context_does_not_reemit() {
+    let (_, events) = collect(|| {
+        let err: Result<()> = Err(Error::quiet_msg("connection refused"));
+        err.context("failed to sync").with_field("attempt", 2)
+    });
+    assert!(events.is_empty());
+
+    let (_, events) = collect(|| {
+        let err: std::result::Result<(), io::Error> = Err(io::Error::other("disk full"));
+        err.context_field("failed to write", "path", "/tmp/out")
+    });
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.field("message"), Some("failed to write"));
+    assert_eq!(
+        event.field("error.chain"),
+        Some("failed to write: disk full")
+    );
+    assert_eq!(event.field("path"), Some("/tmp/out"));
+
+    let (_, events) = collect(|| None::<u32>.context_field("missing user", "id", 7));
+    assert_eq!(events.len(), 1);
+}
+
+/// Tests that quiet errors never emit.
+#[test]
+fn test_quiet() {
+    let (_, events) = collect(|| {
+        Error::quiet_msg("expected failure").with_field("id", 1);
+        Error::quiet(anyhow::anyhow!("expected failure")).context("still quiet")
+    });
+    assert!(events.is_empty());
+
+    let (_, events) = collect(|| Error::msg("loud failure"));
+    assert_eq!(events.len(), 1);
+}
@@ -0,0 +1,52 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Reports for the users of command-line tools.
+
+use anyhow_tracing::{Error, anyhow};
+use insta::assert_snapshot;
+
+/// An error with several causes, fields and help text.
+fn config_error() -> Error {
+    anyhow!(path = "/etc/app.toml", attempt = 3, "permission denied")
+        .context("failed to open")
+        .context("failed to load config")
+        .note("check the permissions of the config directory")
+        .note("run `app init` to create a default config")
+}
+
+/// Write the report of `err` into a string.
+fn print(err: &Error) -> String {
+    let mut out = Vec::new();
+    err.print_report(&mut out)
+        .expect("writing to a buffer never fails");
+    String::from_utf8(out).expect("the report is UTF-8")
+}
+
+/// Tests the report of an error without a public message, with every section.
+#[test]
+fn test_internal_error_report() {
+    assert_snapshot!("internal_error_report", print(&config_error()));
+}
+
+/// Tests that an error with a public message is reported with it and the help text
+/// only, leaving out the internal messages and fields.
+#[test]
+fn test_public_error_report() {
+    let err = config_error().with_public_message("cannot read the configuration");
+    assert_snapshot!("public_error_report", print(&err));
+}
+
+/// Tests that the report is the same as `Report` without options, and has no colors.
+#[test]
+fn test_report_matches_plain_report() {
+    let err = config_error();
+    assert_eq!(print(&err), err.report().to_string());
+    assert!(!print(&err).contains('\x1b'));
+}
+
+/// Tests the exit code of errors.
+#[test]
+fn test_exit_code() {
+    assert_eq!(config_error().exit_code(), None);
+    assert_eq!(config_error().with_exit_code(78).exit_code(), Some(78));
+}
@@ -0,0 +1,33 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Verbose reports, in their own test binary because the verbosity is process-wide.
+
+use anyhow_tracing::{anyhow, set_verbose_reports};
+
+/// Tests that verbose reports have the internal messages, the fields and the backtrace
+/// of errors with a public message.
+#[test]
+fn test_verbose_report() {
+    // SAFETY: this is the only test in this binary, so no other thread reads the environment.
+    unsafe {
+        std::env::set_var("RUST_LIB_BACKTRACE", "1");
+    }
+    set_verbose_reports(true).expect("the verbosity is only set once");
+
+    let err = anyhow!(path = "/etc/app.toml", "permission denied")
+        .context("failed to load config")
+        .with_public_message("cannot read the configuration")
+        .note("run `app init` to create a default config");
+    let mut out = Vec::new();
+    err.print_report(&mut out)
+        .expect("writing to a buffer never fails");
+    let report = String::from_utf8(out).expect("the report is UTF-8");
+
+    assert!(report.starts_with(
+        "failed to load config\n\nCaused by:\n    0: permission denied\n\nBacktrace:\n"
+    ));
+    assert!(report.ends_with(
+        "Fields:\n    path = /etc/app.toml\n\nNotes:\n    run `app init` to create a default config\n"
+    ));
+    assert!(!report.contains("cannot read the configuration"));
+}
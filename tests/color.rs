@@ -0,0 +1,30 @@
+#![cfg(feature = "color")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Colored reports, in their own test binary because `NO_COLOR` is process-wide.
+
+use anyhow_tracing::anyhow;
+
+/// Tests that forcing colors on styles the report, and that `NO_COLOR` turns auto-detection off.
+#[test]
+fn test_colored_report() {
+    let err = anyhow!(path = "/etc/app.toml", "permission denied").context("failed to open");
+
+    let colored = err.report().color(true).to_string();
+    assert!(colored.contains("\x1b[1mfailed to open\x1b[0m"));
+    assert!(colored.contains("\x1b[2m0: permission denied\x1b[0m"));
+    assert!(colored.contains("\x1b[36mpath\x1b[0m = /etc/app.toml"));
+
+    assert_eq!(
+        err.report().color(false).to_string(),
+        err.report().to_string()
+    );
+
+    // SAFETY: this is the only test in this binary, so no other thread reads the environment.
+    unsafe {
+        std::env::set_var("NO_COLOR", "1");
+    }
+    let plain = err.report().colored().to_string();
+    assert!(!plain.contains('\x1b'));
+    assert_eq!(plain, err.report().to_string());
+}
@@ -0,0 +1,100 @@
+//! A `tracing` subscriber collecting events, shared by the tests of the `tracing` feature.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+/// An event as seen by the subscriber.
+#[derive(Debug)]
+pub struct CapturedEvent {
+    pub level: tracing::Level,
+    pub target: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub fields: Vec<(String, String)>,
+}
+
+impl CapturedEvent {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A subscriber collecting the events at or above a level.
+#[derive(Clone)]
+pub struct Collector {
+    max_level: tracing::Level,
+    events: Arc<Mutex<Vec<CapturedEvent>>>,
+}
+
+impl Collector {
+    pub fn new(max_level: tracing::Level) -> Self {
+        Self {
+            max_level,
+            events: Arc::default(),
+        }
+    }
+
+    /// Take the events collected so far.
+    pub fn take_events(&self) -> Vec<CapturedEvent> {
+        std::mem::take(&mut *self.events.lock().expect("not poisoned"))
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut Vec<(String, String)>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((field.name().to_owned(), value.to_owned()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .push((field.name().to_owned(), format!("{:?}", value)));
+    }
+}
+
+impl Subscriber for Collector {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.max_level
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(LevelFilter::from_level(self.max_level))
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Vec::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        let metadata = event.metadata();
+        self.events
+            .lock()
+            .expect("not poisoned")
+            .push(CapturedEvent {
+                level: *metadata.level(),
+                target: metadata.target().to_owned(),
+                file: metadata.file().map(ToOwned::to_owned),
+                line: metadata.line(),
+                fields,
+            });
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
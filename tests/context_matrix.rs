@@ -0,0 +1,83 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Every kind of error accepted by the `Context` trait, through each of its core methods.
+
+use std::error::Error as StdError;
+use std::io;
+
+use anyhow_tracing::{Context, Error, anyhow};
+
+fn std_error() -> Result<(), io::Error> {
+    Err(io::Error::other("base error"))
+}
+
+fn anyhow_error() -> anyhow::Result<()> {
+    Err(anyhow::anyhow!("base error"))
+}
+
+fn boxed_error() -> Result<(), Box<dyn StdError + Send + Sync>> {
+    Err(Box::new(io::Error::other("base error")))
+}
+
+fn own_error() -> anyhow_tracing::Result<()> {
+    Err(anyhow!(origin = "source", "base error"))
+}
+
+fn boxed_own_error() -> Result<(), Box<dyn StdError + Send + Sync>> {
+    Err(Box::new(anyhow!(origin = "source", "base error")))
+}
+
+/// Generates a module per error kind, checking the message chain and that fields
+/// added before the conversion are kept.
+macro_rules! context_matrix {
+    ($($kind:ident => $source:expr, origin = $origin:expr;)*) => {$(
+        mod $kind {
+            use super::*;
+
+            fn assert_wrapped(err: &Error) {
+                let messages: Vec<_> = err.chain().map(ToString::to_string).collect();
+                assert_eq!(messages, ["outer", "base error"]);
+                assert_eq!(err.get_field("origin"), $origin);
+            }
+
+            fn assert_enriched(err: &Error, value: &str) {
+                assert_eq!(err.chain().count(), 1);
+                assert_eq!(err.root_cause().to_string(), "base error");
+                assert_eq!(err.get_field("origin"), $origin);
+                assert_eq!(err.get_field("key"), Some(value));
+            }
+
+            #[test]
+            fn context() {
+                let err: Error = $source.context("outer").unwrap_err();
+                assert_wrapped(&err);
+            }
+
+            #[test]
+            fn with_context() {
+                let err: Error = $source.with_context(|| "outer").unwrap_err();
+                assert_wrapped(&err);
+            }
+
+            #[test]
+            fn with_field() {
+                let err: Error = $source.with_field("key", 42).unwrap_err();
+                assert_enriched(&err, "42");
+            }
+
+            #[test]
+            fn with_field_debug() {
+                let err: Error = $source.with_field_debug("key", "value").unwrap_err();
+                assert_enriched(&err, r#""value""#);
+            }
+        }
+    )*};
+}
+
+context_matrix! {
+    std_errors => std_error(), origin = None;
+    anyhow_errors => anyhow_error(), origin = None;
+    boxed_errors => boxed_error(), origin = None;
+    own_errors => own_error(), origin = Some("source");
+    boxed_own_errors => boxed_own_error(), origin = Some("source");
+}
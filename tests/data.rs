@@ -0,0 +1,131 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Sending errors between processes as `ErrorData`.
+
+use anyhow_tracing::{Error, ErrorData, Level, anyhow};
+
+/// The fields kept under their own names by the rebuilt errors.
+const REMOTE_FIELDS: &[&str] = &["path", "attempts", "host"];
+
+/// A three-deep chain with fields, a code, a level and a note.
+fn sample_error() -> Error {
+    anyhow!(path = "/etc/app.toml", "permission denied")
+        .context("failed to read config")
+        .with_field("attempts", 3)
+        .context("failed to start")
+        .with_code("CONFIG_UNREADABLE")
+        .with_level(Level::Warn)
+        .note("check the permissions of the config directory")
+}
+
+/// Asserts that `rebuilt` has the same messages and fields as `original`.
+fn assert_same(rebuilt: &Error, original: &Error) {
+    let chain = |error: &Error| error.chain().map(ToString::to_string).collect::<Vec<_>>();
+    assert_eq!(rebuilt.to_string(), original.to_string(), "display");
+    assert_eq!(
+        format!("{:#}", rebuilt),
+        format!("{:#}", original),
+        "alternate display"
+    );
+    assert_eq!(chain(rebuilt), chain(original), "chain");
+    assert_eq!(
+        rebuilt.get_field("path"),
+        Some("/etc/app.toml"),
+        "root cause field"
+    );
+    assert_eq!(rebuilt.get_field("attempts"), Some("3"), "context field");
+    assert_eq!(rebuilt.code(), Some("CONFIG_UNREADABLE"), "code");
+    assert_eq!(rebuilt.level(), Some(Level::Warn), "level");
+    assert_eq!(rebuilt.notes(), original.notes(), "notes");
+}
+
+/// Tests the data of an error.
+#[test]
+fn test_to_data() {
+    let data = sample_error().to_data();
+    assert_eq!(
+        data,
+        ErrorData {
+            message: "failed to start".to_owned(),
+            chain: vec![
+                "failed to start".to_owned(),
+                "failed to read config".to_owned(),
+                "permission denied".to_owned(),
+            ],
+            fields: vec![
+                ("path".to_owned(), "/etc/app.toml".to_owned()),
+                ("attempts".to_owned(), "3".to_owned()),
+            ],
+            code: Some("CONFIG_UNREADABLE".to_owned()),
+            level: Some(Level::Warn),
+            notes: vec!["check the permissions of the config directory".to_owned()],
+        }
+    );
+}
+
+/// Tests rebuilding an error from its data.
+#[test]
+fn test_from_data() {
+    anyhow_tracing::set_remote_fields(REMOTE_FIELDS).ok();
+    let original = sample_error();
+    let rebuilt = Error::from_data(original.to_data());
+    assert_same(&rebuilt, &original);
+    assert_eq!(rebuilt.to_data(), original.to_data());
+}
+
+/// Tests data with an empty chain, which is rebuilt from the message.
+#[test]
+fn test_from_data_without_chain() {
+    let rebuilt = Error::from_data(ErrorData {
+        message: "connection refused".to_owned(),
+        ..ErrorData::default()
+    });
+    assert_eq!(rebuilt.to_string(), "connection refused");
+    assert_eq!(rebuilt.chain().count(), 1);
+}
+
+/// Tests that fields that were not named with `set_remote_fields` are kept together
+/// in the `remote.fields` field.
+#[test]
+fn test_from_data_groups_unknown_fields() {
+    anyhow_tracing::set_remote_fields(REMOTE_FIELDS).ok();
+    let original = anyhow!(host = "db-3", "connection refused")
+        .with_field("shard", 3)
+        .with_field("query", "SELECT 1");
+    let rebuilt = Error::from_data(original.to_data());
+    assert_eq!(rebuilt.get_field("host"), Some("db-3"));
+    assert_eq!(rebuilt.get_field("shard"), None);
+    assert_eq!(
+        rebuilt.get_field("remote.fields"),
+        Some(r#"shard=3 query="SELECT 1""#)
+    );
+
+    let again = Error::from_data(rebuilt.to_data());
+    assert_eq!(again.to_data(), rebuilt.to_data());
+}
+
+/// Tests sending an error as JSON.
+#[cfg(feature = "serde")]
+#[test]
+fn test_json_round_trip() {
+    anyhow_tracing::set_remote_fields(REMOTE_FIELDS).ok();
+    let original = sample_error();
+    let json = serde_json::to_string(&original.to_data()).expect("data serializes");
+    let data: ErrorData = serde_json::from_str(&json).expect("data deserializes");
+    assert_same(&Error::from_data(data), &original);
+}
+
+/// Tests that the optional parts of the data can be left out of the JSON.
+#[cfg(feature = "serde")]
+#[test]
+fn test_json_defaults() {
+    let data: ErrorData = serde_json::from_str(
+        r#"{"message":"timed out","chain":["timed out"],"fields":[["host","db-3"]]}"#,
+    )
+    .expect("data deserializes");
+    anyhow_tracing::set_remote_fields(REMOTE_FIELDS).ok();
+    let rebuilt = Error::from_data(data);
+    assert_eq!(rebuilt.to_string(), "timed out [host=db-3]");
+    assert_eq!(rebuilt.code(), None);
+    assert_eq!(rebuilt.level(), None);
+}
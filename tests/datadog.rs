@@ -0,0 +1,138 @@
+#![cfg(feature = "datadog")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Mapping errors to the standard attributes of Datadog.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use anyhow_tracing::{Error, Level, anyhow, datadog};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+/// A layer collecting the level, target and fields of events.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<(tracing::Level, String, Vec<(String, String)>)>>>);
+
+struct Fields<'a>(&'a mut Vec<(String, String)>);
+
+impl Visit for Fields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((field.name().to_owned(), value.to_owned()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .push((field.name().to_owned(), format!("{:?}", value)));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Capture {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Vec::new();
+        event.record(&mut Fields(&mut fields));
+        let metadata = event.metadata();
+        self.0.lock().expect("not poisoned").push((
+            *metadata.level(),
+            metadata.target().to_owned(),
+            fields,
+        ));
+    }
+}
+
+/// Get the fields of `error` for Datadog, without the backtrace, which is only
+/// captured when `RUST_BACKTRACE` is set.
+fn datadog_fields(error: &Error) -> Vec<(&'static str, String)> {
+    let mut fields = error.to_datadog_fields();
+    fields.retain(|(key, _)| *key != "error.stack");
+    fields
+}
+
+fn pairs(fields: &[(&'static str, &str)]) -> Vec<(&'static str, String)> {
+    fields
+        .iter()
+        .map(|(key, value)| (*key, (*value).to_owned()))
+        .collect()
+}
+
+/// Tests the names of the attributes, and that the chain is joined by `: `.
+#[test]
+fn test_to_datadog_fields() {
+    let err = anyhow!(host = "db-3", port = 5432, "connection refused")
+        .context("failed to sync")
+        .with_field("attempt", 2);
+    assert_eq!(
+        datadog_fields(&err),
+        pairs(&[
+            ("error.kind", "anyhow_tracing::Error"),
+            ("error.message", "failed to sync: connection refused"),
+            ("host", "db-3"),
+            ("port", "5432"),
+            ("attempt", "2"),
+        ])
+    );
+
+    let err = Error::msg("too many requests").with_code("RATE_LIMITED");
+    assert_eq!(
+        datadog_fields(&err),
+        pairs(&[
+            ("error.kind", "RATE_LIMITED"),
+            ("error.message", "too many requests"),
+        ])
+    );
+}
+
+/// Tests that the emitted event has the attributes as its fields.
+#[test]
+fn test_emit() {
+    let capture = Capture::default();
+    tracing::subscriber::with_default(Registry::default().with(capture.clone()), || {
+        let err = anyhow!(host = "db-3", "connection refused")
+            .context("failed to sync")
+            .with_level(Level::Warn);
+        datadog::emit(&err);
+    });
+
+    let events = std::mem::take(&mut *capture.0.lock().expect("not poisoned"));
+    let [(level, target, fields)] = events.as_slice() else {
+        panic!("expected one event, got {events:?}");
+    };
+    assert_eq!(*level, tracing::Level::WARN);
+    assert_eq!(target, module_path!());
+    let fields: Vec<_> = fields
+        .iter()
+        .filter(|(key, _)| key != "error.stack")
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("message", "failed to sync"),
+            ("error.kind", "anyhow_tracing::Error"),
+            ("error.message", "failed to sync: connection refused"),
+            ("host", "db-3"),
+        ]
+    );
+}
+
+/// Tests that the span trace is sent as the stack.
+#[cfg(feature = "span-trace")]
+#[test]
+fn test_span_trace_stack() {
+    let subscriber = Registry::default().with(tracing_error::ErrorLayer::default());
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("sync");
+        let _guard = span.enter();
+        let err = Error::msg("connection refused");
+        let fields = err.to_datadog_fields();
+        let stack = fields
+            .iter()
+            .find(|(key, _)| *key == "error.stack")
+            .map(|(_, stack)| stack.as_str())
+            .expect("the span trace is captured");
+        assert!(stack.contains("Span trace:"), "{stack}");
+        assert!(stack.contains("sync"), "{stack}");
+    });
+}
@@ -0,0 +1,39 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Wrapping of long values in `Debug`, in its own test binary because the crate-level
+//! format is global.
+
+use anyhow_tracing::{Error, FieldFormat, set_field_format};
+
+/// Tests that keys are aligned and long values continue under the value column.
+#[test]
+fn test_debug_value_wrapping() {
+    set_field_format(FieldFormat::new().debug_value_width(10)).expect("no format set yet");
+
+    let err = Error::msg("request failed")
+        .with_field("id", "abcdefgh")
+        .with_field("url", "https://example.com/a")
+        .with_field("method", "GET");
+    assert_eq!(
+        err.debug_stable().to_string(),
+        "request failed
+
+Fields:
+\tid:     \"abcdefgh\",
+\turl:    \"https://e
+\t        xample.com
+\t        /a\",
+\tmethod: \"GET\"
+"
+    );
+
+    // A value exactly as wide as the limit stays on one line
+    let err = Error::msg("request failed")
+        .with_field("id", "abcdefgh")
+        .context("outer");
+    assert!(
+        err.debug_stable()
+            .to_string()
+            .ends_with("\t\tid: \"abcdefgh\"\n")
+    );
+}
@@ -0,0 +1,45 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Stable `Debug` output, in its own test binary because it sets `RUST_BACKTRACE`.
+
+use anyhow_tracing::Error;
+
+fn sample_error() -> Error {
+    Error::msg("connection refused")
+        .with_field("host", "db-3")
+        .context("failed to connect")
+        .context("failed to sync")
+        .with_field("attempt", 2)
+}
+
+/// Tests that the stable output matches the default `Debug` output without a backtrace,
+/// and that capturing a backtrace does not change it.
+#[test]
+fn test_debug_stable() {
+    let expected = "failed to sync
+
+Caused by:
+    0: failed to connect
+    1: connection refused
+
+Fields:
+\tfailed to sync:
+\t\tattempt: \"2\"
+\tconnection refused:
+\t\thost: \"db-3\"
+";
+
+    // SAFETY: this is the only test in this binary, so no other thread reads the environment.
+    unsafe {
+        std::env::set_var("RUST_BACKTRACE", "1");
+    }
+    let err = sample_error();
+    assert!(format!("{:?}", err).contains("Stack backtrace:"));
+    assert_eq!(err.debug_stable().to_string(), expected);
+
+    let err = Error::msg("connection refused").context("failed to sync");
+    assert_eq!(
+        err.debug_stable().to_string(),
+        "failed to sync\n\nCaused by:\n    connection refused"
+    );
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "json")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Recording the failing path on deserialization errors.
+
+use std::collections::BTreeMap;
+
+use anyhow_tracing::serde::{deserialize_context, from_slice, from_str, from_value};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Debug, Deserialize)]
+#[expect(dead_code, reason = "only deserialized")]
+struct Order {
+    id: u64,
+    items: Vec<Item>,
+}
+
+#[derive(Debug, Deserialize)]
+#[expect(dead_code, reason = "only deserialized")]
+struct Item {
+    name: String,
+    price: Price,
+}
+
+#[derive(Debug, Deserialize)]
+#[expect(dead_code, reason = "only deserialized")]
+struct Price {
+    amount: u64,
+    currency: String,
+}
+
+/// An order whose second item has a price without a currency.
+const MISSING_CURRENCY: &str = r#"{
+    "id": 7,
+    "items": [
+        { "name": "pen", "price": { "amount": 3, "currency": "EUR" } },
+        { "name": "ink", "price": { "amount": 12 } }
+    ]
+}"#;
+
+/// Tests the path of a field missing deep in a document, the type and the source.
+#[test]
+fn test_missing_inner_field() {
+    let err = from_str::<Order>(MISSING_CURRENCY).expect_err("the currency is missing");
+    assert_eq!(err.get_field("serde.path"), Some("items[1].price"));
+    assert_eq!(err.get_field("serde.type"), Some("deserialize::Order"));
+    assert_eq!(err.message(), "failed to deserialize");
+
+    let source = err
+        .downcast_ref::<serde_json::Error>()
+        .expect("the original error is kept");
+    assert!(source.to_string().starts_with("missing field `currency`"));
+}
+
+/// Tests the path of a value of the wrong type, from bytes and from a value.
+#[test]
+fn test_invalid_type() {
+    let document = json!({ "id": 7, "items": [{ "name": "pen", "price": { "amount": "3", "currency": "EUR" } }] });
+
+    let err =
+        from_slice::<Order>(document.to_string().as_bytes()).expect_err("the amount is a string");
+    assert_eq!(err.get_field("serde.path"), Some("items[0].price.amount"));
+
+    let err = from_value::<Order>(document).expect_err("the amount is a string");
+    assert_eq!(err.get_field("serde.path"), Some("items[0].price.amount"));
+}
+
+/// Tests the path of a map entry, and of an invalid document.
+#[test]
+fn test_map_and_document_paths() {
+    let err = from_str::<BTreeMap<String, u16>>(r#"{ "http": 80, "https": "443" }"#)
+        .expect_err("the port is a string");
+    assert_eq!(err.get_field("serde.path"), Some("https"));
+
+    let err = from_str::<Order>("[]").expect_err("the document is not an object");
+    assert_eq!(err.get_field("serde.path"), Some("."));
+}
+
+/// Tests a successful deserialization through a custom deserializer.
+#[test]
+fn test_deserialize_context() {
+    let mut deserializer = serde_json::Deserializer::from_str(MISSING_CURRENCY);
+    let value: serde_json::Value =
+        deserialize_context(&mut deserializer).expect("any document is a value");
+    assert_eq!(value["items"][1]["price"]["amount"], 12);
+}
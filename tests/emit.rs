@@ -0,0 +1,189 @@
+#![cfg(feature = "test-util")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Emitting errors as `tracing` events, checked with the events captured by
+//! `anyhow_tracing::test`.
+
+mod common;
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use anyhow_tracing::{Error, Level, TraceResultExt, anyhow, test};
+use common::Collector;
+use serde_json::json;
+
+/// Tests that each field of the error is recorded as a field of the event.
+#[test]
+fn test_emit_records_fields() {
+    let err = anyhow!(host = "db-3", port = 5432, "connection refused")
+        .context("failed to sync")
+        .with_field("attempt", 2);
+
+    let mut line = 0;
+    let events = test::capture(|| {
+        line = line!().saturating_add(1);
+        err.emit();
+    });
+
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.level(), Level::Error);
+    assert_eq!(event.target(), module_path!());
+    assert_eq!(event.name(), "error");
+    assert_eq!(event.file(), Some(file!()));
+    assert_eq!(event.line(), Some(line));
+    assert_eq!(event.message(), "failed to sync");
+    assert_eq!(event.chain(), "failed to sync: connection refused");
+    assert_eq!(event.field("host"), Some("db-3"));
+    assert_eq!(event.field("port"), Some("5432"));
+    assert_eq!(event.field("attempt"), Some("2"));
+}
+
+/// Tests emitting at the stored level, at an explicit level, and at a disabled level.
+#[test]
+fn test_emit_levels() {
+    let collector = Collector::new(tracing::Level::INFO);
+    tracing::subscriber::with_default(collector.clone(), || {
+        Error::msg("slow query").with_level(Level::Warn).emit();
+        Error::msg("cache miss").emit_at(Level::Info);
+        Error::msg("retrying").emit_at(Level::Debug);
+    });
+
+    let events = collector.take_events();
+    let emitted: Vec<_> = events
+        .iter()
+        .map(|event| (event.level, event.field("message")))
+        .collect();
+    assert_eq!(
+        emitted,
+        [
+            (tracing::Level::WARN, Some("slow query")),
+            (tracing::Level::INFO, Some("cache miss")),
+        ]
+    );
+    assert!(events.iter().all(|event| event.target == "anyhow_tracing"));
+    assert!(
+        events
+            .iter()
+            .all(|event| event.file.as_deref() == Some(file!()))
+    );
+    assert!(events.iter().all(|event| event.line.is_some()));
+}
+
+/// Tests that an error with many fields records all of them.
+#[test]
+fn test_emit_many_fields() {
+    const KEYS: [&str; 40] = [
+        "k00", "k01", "k02", "k03", "k04", "k05", "k06", "k07", "k08", "k09", "k10", "k11", "k12",
+        "k13", "k14", "k15", "k16", "k17", "k18", "k19", "k20", "k21", "k22", "k23", "k24", "k25",
+        "k26", "k27", "k28", "k29", "k30", "k31", "k32", "k33", "k34", "k35", "k36", "k37", "k38",
+        "k39",
+    ];
+    let err = KEYS
+        .iter()
+        .enumerate()
+        .fold(Error::msg("many fields"), |err, (i, key)| {
+            err.with_field(key, i)
+        });
+
+    let events = test::capture(|| err.emit());
+    let event = events.first().expect("one event");
+    assert_eq!(event.fields().len(), KEYS.len());
+    assert_eq!(event.field("k39"), Some("39"));
+}
+
+/// Tests logging an error while propagating it, at the default and a given level.
+#[test]
+fn test_trace_err() {
+    fn parse(value: &str) -> anyhow_tracing::Result<u32> {
+        let value = value.parse::<u32>().trace_err()?;
+        Ok(value)
+    }
+
+    let mut line = 0;
+    let events = test::capture(|| {
+        let result = "x".parse::<u32>().trace_err();
+        line = line!().saturating_sub(1);
+        result.unwrap_err();
+    });
+
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.level(), Level::Error);
+    assert_eq!(event.message(), "invalid digit found in string");
+    assert_eq!(event.line(), Some(line));
+
+    let events = test::capture(|| {
+        let result =
+            Err::<(), _>(anyhow!(path = "/etc/app.toml", "permission denied")).log_err(Level::Warn);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "permission denied [path=/etc/app.toml]"
+        );
+    });
+
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.level(), Level::Warn);
+    assert_eq!(event.field("path"), Some("/etc/app.toml"));
+
+    // Nothing is emitted for `Ok`
+    let events = test::capture(|| assert_eq!(parse("42").ok(), Some(42)));
+    assert!(events.is_empty());
+}
+
+/// A buffer shared with the JSON formatter of `tracing-subscriber`.
+#[derive(Clone, Default)]
+struct JsonLog(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for JsonLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().expect("not poisoned").extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Tests that numbers and booleans given to the macros reach a JSON formatter with
+/// their type, while other fields are strings.
+#[test]
+fn test_emit_typed_fields_as_json() {
+    let shard: u64 = 3;
+    let err = anyhow!(
+        host = "db-3",
+        port = 5432,
+        ratio = 0.5,
+        retry = true,
+        code = %404,
+        shard,
+        "connection refused"
+    )
+    .with_field("attempt", 2);
+
+    let log = JsonLog::default();
+    let writer = log.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .json()
+        .with_writer(move || writer.clone())
+        .finish();
+    tracing::subscriber::with_default(subscriber, || err.emit());
+
+    let output = log.0.lock().expect("not poisoned").clone();
+    let event: serde_json::Value = serde_json::from_slice(&output).expect("one JSON event");
+    let fields = &event["fields"];
+    assert_eq!(fields["message"], json!("connection refused"));
+    assert_eq!(fields["host"], json!("db-3"));
+    assert_eq!(fields["port"], json!(5432));
+    assert_eq!(fields["ratio"], json!(0.5));
+    assert_eq!(fields["retry"], json!(true));
+    assert_eq!(fields["code"], json!("404"));
+    assert_eq!(fields["shard"], json!(3));
+    assert_eq!(fields["attempt"], json!("2"));
+}
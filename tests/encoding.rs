@@ -0,0 +1,162 @@
+#![cfg(any(feature = "cbor", feature = "msgpack"))]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Encoding `ErrorData` in compact binary formats.
+
+use anyhow_tracing::{Error, ErrorData, Level, anyhow};
+
+/// The data encoded in the fixtures, written with format version 1.
+fn fixture_data() -> ErrorData {
+    ErrorData {
+        message: "failed to process job".to_owned(),
+        chain: vec![
+            "failed to process job".to_owned(),
+            "connection refused".to_owned(),
+        ],
+        fields: vec![
+            ("job_id".to_owned(), "42".to_owned()),
+            ("queue".to_owned(), "billing".to_owned()),
+        ],
+        code: Some("JOB_FAILED".to_owned()),
+        level: Some(Level::Warn),
+        notes: vec!["retried 3 times".to_owned()],
+    }
+}
+
+/// The fields kept under their own names by the rebuilt errors.
+const REMOTE_FIELDS: &[&str] = &["host", "job_id"];
+
+/// An error with a chain, fields, a code, a level and a note.
+fn sample_error() -> Error {
+    anyhow!(host = "db-3", "connection refused")
+        .context("failed to process job")
+        .with_field("job_id", 42)
+        .with_code("JOB_FAILED")
+        .with_level(Level::Warn)
+        .note("retried 3 times")
+}
+
+#[cfg(feature = "cbor")]
+mod cbor {
+    use super::*;
+
+    /// Tests an error surviving a round trip through CBOR.
+    #[test]
+    fn test_round_trip() {
+        anyhow_tracing::set_remote_fields(REMOTE_FIELDS).ok();
+        let err = sample_error();
+        let bytes = err.to_data().to_cbor();
+        assert_eq!(bytes.first(), Some(&1));
+
+        let rebuilt = Error::from_data(ErrorData::from_cbor(&bytes).expect("the data decodes"));
+        assert_eq!(rebuilt.to_string(), err.to_string());
+        assert_eq!(rebuilt.to_data(), err.to_data());
+    }
+
+    /// Tests decoding a payload written by the first version of the format.
+    #[test]
+    fn test_decode_v1_fixture() {
+        let bytes = include_bytes!("fixtures/error_data_v1.cbor");
+        let data = ErrorData::from_cbor(bytes).expect("the fixture decodes");
+        assert_eq!(data, fixture_data());
+    }
+
+    /// Tests that missing optional members take their defaults and unknown members
+    /// are ignored, so that members can be added to the format.
+    #[test]
+    fn test_decode_other_members() {
+        let payload = serde_json::json!({
+            "message": "timed out",
+            "chain": ["timed out"],
+            "fields": [["host", "db-3"]],
+            "retry_after": 30,
+        });
+        let mut bytes = vec![1];
+        ciborium::into_writer(&payload, &mut bytes).expect("the payload encodes");
+
+        let data = ErrorData::from_cbor(&bytes).expect("the data decodes");
+        assert_eq!(data.fields, [("host".to_owned(), "db-3".to_owned())]);
+        assert_eq!(data.code, None);
+        assert_eq!(data.level, None);
+    }
+
+    /// Tests the errors for unsupported versions and invalid payloads.
+    #[test]
+    fn test_decode_errors() {
+        let mut bytes = fixture_data().to_cbor();
+        if let Some(version) = bytes.first_mut() {
+            *version = 2;
+        }
+        let err = ErrorData::from_cbor(&bytes).expect_err("version 2 is unsupported");
+        assert_eq!(
+            err.to_string(),
+            "unsupported error data format version [version=2, supported=1]"
+        );
+
+        let err = ErrorData::from_cbor(&[]).expect_err("empty data is invalid");
+        assert_eq!(err.to_string(), "the encoded error data is empty");
+
+        let err = ErrorData::from_cbor(&[1, 0xff, 0x00]).expect_err("the payload is invalid");
+        assert_eq!(err.message(), "failed to decode error data");
+        assert_eq!(err.get_field("format"), Some("cbor"));
+    }
+}
+
+#[cfg(feature = "msgpack")]
+mod msgpack {
+    use super::*;
+
+    /// Tests an error surviving a round trip through MessagePack.
+    #[test]
+    fn test_round_trip() {
+        anyhow_tracing::set_remote_fields(REMOTE_FIELDS).ok();
+        let err = sample_error();
+        let bytes = err.to_data().to_msgpack();
+        assert_eq!(bytes.first(), Some(&1));
+
+        let rebuilt = Error::from_data(ErrorData::from_msgpack(&bytes).expect("the data decodes"));
+        assert_eq!(rebuilt.to_string(), err.to_string());
+        assert_eq!(rebuilt.to_data(), err.to_data());
+    }
+
+    /// Tests decoding a payload written by the first version of the format.
+    #[test]
+    fn test_decode_v1_fixture() {
+        let bytes = include_bytes!("fixtures/error_data_v1.msgpack");
+        let data = ErrorData::from_msgpack(bytes).expect("the fixture decodes");
+        assert_eq!(data, fixture_data());
+    }
+
+    /// Tests that missing optional members take their defaults and unknown members
+    /// are ignored, so that members can be added to the format.
+    #[test]
+    fn test_decode_other_members() {
+        let payload = serde_json::json!({
+            "message": "timed out",
+            "chain": ["timed out"],
+            "fields": [["host", "db-3"]],
+            "retry_after": 30,
+        });
+        let mut bytes = vec![1];
+        bytes.extend(rmp_serde::to_vec(&payload).expect("the payload encodes"));
+
+        let data = ErrorData::from_msgpack(&bytes).expect("the data decodes");
+        assert_eq!(data.fields, [("host".to_owned(), "db-3".to_owned())]);
+        assert_eq!(data.code, None);
+        assert_eq!(data.notes, Vec::<String>::new());
+    }
+
+    /// Tests the errors for unsupported versions and invalid payloads.
+    #[test]
+    fn test_decode_errors() {
+        let err = ErrorData::from_msgpack(&[0]).expect_err("version 0 is unsupported");
+        assert_eq!(
+            err.to_string(),
+            "unsupported error data format version [version=0, supported=1]"
+        );
+
+        let err = ErrorData::from_msgpack(&[1, 0xc1]).expect_err("the payload is invalid");
+        assert_eq!(err.message(), "failed to decode error data");
+        assert_eq!(err.get_field("format"), Some("msgpack"));
+    }
+}
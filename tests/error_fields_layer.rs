@@ -0,0 +1,97 @@
+#![cfg(feature = "layer")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Expanding the fields of errors recorded on events.
+
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use anyhow_tracing::anyhow;
+use anyhow_tracing::layer::ErrorFieldsLayer;
+use tracing::Subscriber;
+use tracing::field::{Field, Visit};
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+/// A layer collecting the fields of events.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<Vec<(String, String)>>>>);
+
+impl Capture {
+    fn take_events(&self) -> Vec<Vec<(String, String)>> {
+        std::mem::take(&mut *self.0.lock().expect("not poisoned"))
+    }
+}
+
+struct Fields<'a>(&'a mut Vec<(String, String)>);
+
+impl Visit for Fields<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .push((field.name().to_owned(), format!("{:?}", value)));
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Capture {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let mut fields = Vec::new();
+        event.record(&mut Fields(&mut fields));
+        self.0.lock().expect("not poisoned").push(fields);
+    }
+}
+
+fn pairs(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+    fields
+        .iter()
+        .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+        .collect()
+}
+
+/// Tests that the fields of a recorded error become event fields.
+#[test]
+fn test_expands_error_fields() {
+    let capture = Capture::default();
+    let subscriber = Registry::default().with(ErrorFieldsLayer::new(capture.clone()));
+    tracing::subscriber::with_default(subscriber, || {
+        let err = anyhow!(path = "/etc/app.toml", "not found")
+            .context("failed to load config")
+            .with_field("attempt", 2);
+        tracing::error!(attempts = 3, err = &err as &dyn StdError, "giving up");
+    });
+
+    assert_eq!(
+        capture.take_events(),
+        [pairs(&[
+            ("message", "giving up"),
+            ("attempts", "3"),
+            ("err", "failed to load config: not found"),
+            ("err.path", "/etc/app.toml"),
+            ("err.attempt", "2"),
+        ])]
+    );
+}
+
+/// Tests that events without an error of this crate are passed on unchanged.
+#[test]
+fn test_passes_other_events() {
+    let capture = Capture::default();
+    let subscriber = Registry::default().with(ErrorFieldsLayer::new(capture.clone()));
+    tracing::subscriber::with_default(subscriber, || {
+        let err = anyhow!(path = "/etc/app.toml", "not found");
+        tracing::warn!(err = %err, "retrying");
+        let io = std::io::Error::other("disk full");
+        tracing::warn!(err = &io as &dyn StdError, "retrying");
+    });
+
+    assert_eq!(
+        capture.take_events(),
+        [
+            pairs(&[
+                ("message", "retrying"),
+                ("err", "not found [path=/etc/app.toml]"),
+            ]),
+            pairs(&[("message", "retrying"), ("err", "disk full")]),
+        ]
+    );
+}
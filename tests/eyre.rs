@@ -0,0 +1,82 @@
+#![cfg(feature = "eyre")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Converting errors to and from `eyre::Report`.
+
+use anyhow_tracing::{Context, Error, Result, anyhow};
+
+/// Read a file, failing with an eyre report of two layers.
+fn read_eyre() -> eyre::Result<String> {
+    eyre::WrapErr::wrap_err(
+        Err(std::io::Error::other("permission denied")),
+        "failed to read /etc/app.toml",
+    )
+}
+
+/// Connect, failing with an error of two layers with fields.
+fn connect() -> Result<()> {
+    Err(std::io::Error::other("connection refused"))
+        .context("failed to connect")
+        .with_field("host", "db-3")
+}
+
+/// Use `?` on an eyre result in a function returning this crate's `Result`.
+fn load_config() -> Result<String> {
+    Ok(read_eyre()?)
+}
+
+/// Use `?` on this crate's `Result` in a function returning an eyre result.
+fn sync() -> eyre::Result<()> {
+    connect()?;
+    Ok(())
+}
+
+/// Tests that an eyre report keeps its chain when converted with `?`.
+#[test]
+fn test_from_eyre_keeps_chain() {
+    let err = load_config().unwrap_err();
+    let chain: Vec<String> = err.chain().map(ToString::to_string).collect();
+    assert_eq!(chain, ["failed to read /etc/app.toml", "permission denied"]);
+    assert!(err.fields().is_empty());
+}
+
+/// Tests that an error converted into a report keeps its fields in the `Display`
+/// output and its chain as sources.
+#[test]
+fn test_into_eyre_keeps_fields_and_chain() {
+    let report = sync().unwrap_err();
+    assert_eq!(report.to_string(), "failed to connect [host=db-3]");
+    assert_eq!(report.chain().count(), 2);
+    assert_eq!(report.root_cause().to_string(), "connection refused");
+
+    let report = connect().unwrap_err().into_eyre();
+    assert_eq!(report.chain().count(), 2);
+}
+
+/// Tests that an error survives a round trip through a report unchanged.
+#[test]
+fn test_round_trip() {
+    let err = connect().unwrap_err().with_code("DB_DOWN");
+    let back = Error::from(err.into_eyre());
+    assert_eq!(back.chain().count(), 2);
+    assert_eq!(back.to_string(), "failed to connect [host=db-3]");
+    assert_eq!(back.get_field("host"), Some("db-3"));
+    assert_eq!(back.code(), Some("DB_DOWN"));
+}
+
+/// Tests that the fields of an error wrapped in eyre context are hoisted to the
+/// outermost layer.
+#[test]
+fn test_wrapped_error_fields_are_hoisted() {
+    let report = anyhow!(user_id = 7, "no row returned")
+        .into_eyre()
+        .wrap_err("failed to load the user");
+    let err = Error::from(report);
+    let chain: Vec<String> = err.chain().map(ToString::to_string).collect();
+    assert_eq!(
+        chain,
+        ["failed to load the user", "no row returned [user_id=7]"]
+    );
+    assert_eq!(err.get_field("user_id"), Some("7"));
+    assert_eq!(err.to_string(), "failed to load the user [user_id=7]");
+}
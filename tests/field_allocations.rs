@@ -0,0 +1,87 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Allocations made for the values of fields, in their own test binary because it
+//! replaces the global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use anyhow_tracing::{Error, anyhow};
+
+thread_local! {
+    /// The number of allocations made by the current thread.
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The system allocator, counting the allocations of each thread.
+struct CountingAllocator;
+
+// SAFETY: every call is forwarded to the system allocator.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get().saturating_add(1)));
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc`.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::dealloc`.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Count the allocations made while building an error with `build`.
+fn allocations(build: impl FnOnce() -> Error) -> usize {
+    // Anyhow reads the backtrace settings the first time an error is created, which
+    // allocates once per process
+    Error::msg("warm up").ack();
+    let before = ALLOCATIONS.with(Cell::get);
+    let error = build();
+    let after = ALLOCATIONS.with(Cell::get);
+    error.ack();
+    after.saturating_sub(before)
+}
+
+/// Tests that the values of fields given as string literals to the macros are not
+/// copied.
+#[test]
+fn test_literal_fields_do_not_allocate() {
+    let without_fields = allocations(|| anyhow!("password rejected"));
+    let with_fields = allocations(|| {
+        anyhow!(
+            operation = "login",
+            policy = %"8+ chars",
+            region = "eu-west-1",
+            "password rejected"
+        )
+    });
+    assert_eq!(with_fields, without_fields);
+
+    let error = anyhow!(operation = "login", policy = %"8+ chars", "password rejected");
+    assert_eq!(error.get_field("operation"), Some("login"));
+    assert_eq!(error.get_field("policy"), Some("8+ chars"));
+}
+
+/// Tests that `with_field_static` doesn't copy the value.
+#[test]
+fn test_with_field_static_does_not_allocate() {
+    let without_fields = allocations(|| Error::msg("password rejected"));
+    let with_fields = allocations(|| {
+        Error::msg("password rejected")
+            .with_field_static("operation", "login")
+            .with_field_static("policy", "8+ chars")
+    });
+    assert_eq!(with_fields, without_fields);
+}
+
+/// Tests that other values are formatted into a single buffer shared by all the fields.
+#[test]
+fn test_formatted_fields_allocate_once() {
+    let user = String::from("ada");
+    let without_fields = allocations(|| anyhow!("password rejected"));
+    let with_fields = allocations(|| anyhow!(attempt = 3, user = %user, "password rejected"));
+    assert_eq!(with_fields, without_fields.saturating_add(1));
+}
@@ -0,0 +1,80 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Fields added to an error between renders of it, whose values share one buffer.
+
+use anyhow_tracing::Error;
+
+/// Tests that fields added after an error was formatted show up in the next render,
+/// and that the values written before keep their text.
+#[test]
+fn test_attach_format_attach() {
+    let mut error = Error::msg("connection refused").with_field("host", "db-3");
+    assert_eq!(error.to_string(), "connection refused [host=db-3]");
+
+    error.add_field("port", 5432);
+    error.add_field_static("pool", "primary");
+    assert_eq!(
+        error.to_string(),
+        "connection refused [host=db-3, port=5432, pool=primary]"
+    );
+
+    error.add_field_debug("user", "ada");
+    error.extend([("attempt", String::from("3"))]);
+    assert_eq!(
+        error.to_string(),
+        "connection refused [host=db-3, port=5432, pool=primary, user=\"ada\", attempt=3]"
+    );
+    assert_eq!(
+        error.fields().iter().collect::<Vec<_>>(),
+        [
+            ("host", "db-3"),
+            ("port", "5432"),
+            ("pool", "primary"),
+            ("user", "\"ada\""),
+            ("attempt", "3"),
+        ]
+    );
+}
+
+/// Tests that the fields of each context layer are still split correctly when the
+/// error is formatted between the layers.
+#[test]
+fn test_attach_format_across_contexts() {
+    let error = Error::msg("connection refused")
+        .with_field("host", "db-3")
+        .context("failed to query accounts");
+    assert_eq!(error.to_string(), "failed to query accounts [host=db-3]");
+
+    let error = error
+        .with_field("table", "accounts")
+        .context("failed to load the dashboard")
+        .with_field("page", "home");
+    assert_eq!(error.get_field("table"), Some("accounts"));
+    let layers: Vec<Vec<(&str, &str)>> = error
+        .contexts()
+        .map(|frame| frame.fields().collect())
+        .collect();
+    assert_eq!(
+        layers,
+        [
+            vec![("page", "home")],
+            vec![("table", "accounts")],
+            vec![("host", "db-3")],
+        ]
+    );
+}
+
+/// Tests that a cloned set of fields keeps its values when the original has more
+/// fields added to it.
+#[test]
+fn test_clone_then_attach() {
+    let mut error = Error::msg("connection refused").with_field("host", "db-3");
+    let fields = error.fields().clone();
+    error.add_field("port", 5432);
+
+    assert_eq!(fields.iter().collect::<Vec<_>>(), [("host", "db-3")]);
+    assert_eq!(
+        error.fields().iter().collect::<Vec<_>>(),
+        [("host", "db-3"), ("port", "5432")]
+    );
+}
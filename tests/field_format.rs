@@ -0,0 +1,70 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Field formats, in their own test binary because the crate-level format is global.
+
+use anyhow_tracing::{Error, Escape, FieldFormat, anyhow, field_format, set_field_format};
+
+fn sample_error() -> Error {
+    anyhow!(
+        host = "db-3",
+        port = 5432,
+        note = "line\nbreak",
+        "connection refused"
+    )
+    .context("failed to query accounts")
+}
+
+/// Tests rendering one error under several formats, and setting the crate default.
+#[test]
+fn test_field_formats() {
+    let err = sample_error();
+
+    assert_eq!(
+        err.display_with(FieldFormat::new().escape(Escape::ControlCharacters))
+            .to_string(),
+        r"failed to query accounts [host=db-3, port=5432, note=line\nbreak]"
+    );
+
+    let logfmt = FieldFormat::new()
+        .prefix(" ")
+        .suffix("")
+        .pair_separator(" ")
+        .escape(Escape::ControlCharacters);
+    assert_eq!(
+        err.display_with(logfmt).to_string(),
+        r"failed to query accounts host=db-3 port=5432 note=line\nbreak"
+    );
+
+    let parenthesised = FieldFormat::new()
+        .prefix(" (")
+        .suffix(")")
+        .pair_separator("; ")
+        .key_value_separator(": ")
+        .escape(Escape::None);
+    assert_eq!(
+        err.display_with(parenthesised).to_string(),
+        "failed to query accounts (host: db-3; port: 5432; note: line\nbreak)"
+    );
+    assert_eq!(
+        format!(
+            "{:#}",
+            Error::msg("boom")
+                .context("outer")
+                .with_field("a", 1)
+                .display_with(parenthesised)
+        ),
+        "outer: boom (a: 1)"
+    );
+
+    // Errors without fields render the message alone in every format
+    assert_eq!(Error::msg("boom").display_with(logfmt).to_string(), "boom");
+
+    assert_eq!(field_format(), FieldFormat::DEFAULT);
+    assert_eq!(set_field_format(logfmt), Ok(()));
+    assert_eq!(set_field_format(parenthesised), Err(parenthesised));
+    assert_eq!(field_format(), logfmt);
+    assert_eq!(
+        sample_error().to_string(),
+        r"failed to query accounts host=db-3 port=5432 note=line\nbreak"
+    );
+}
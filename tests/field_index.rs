@@ -0,0 +1,77 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Looking up fields by key on errors with enough fields to index their keys.
+
+use anyhow_tracing::Error;
+
+/// The names of the fields added by [`many_fields`].
+const KEYS: [&str; 12] = [
+    "f0", "f1", "f2", "f3", "f4", "f5", "f6", "f7", "f8", "f9", "f10", "f11",
+];
+
+/// Build an error with the twelve fields of [`KEYS`], each set to its index.
+fn many_fields() -> Error {
+    KEYS.iter()
+        .zip(0..)
+        .fold(Error::msg("connection refused"), |error, (key, index)| {
+            error.with_field(key, index)
+        })
+}
+
+/// Tests that every field can be looked up once the keys are indexed.
+#[test]
+fn test_lookup_indexed_fields() {
+    let error = many_fields();
+
+    for (index, key) in KEYS.iter().enumerate() {
+        assert_eq!(error.get_field(key), Some(index.to_string().as_str()));
+    }
+    assert_eq!(error.get_field("missing"), None);
+}
+
+/// Tests that fields added after a lookup are found by the next one.
+#[test]
+fn test_lookup_after_adding_fields() {
+    let mut error = many_fields();
+    assert_eq!(error.get_field("f12"), None);
+
+    error.add_field("f12", 12);
+    error.add_field_static("region", "eu-west-1");
+    assert_eq!(error.get_field("f12"), Some("12"));
+    assert_eq!(error.get_field("region"), Some("eu-west-1"));
+
+    error.extend([("user", "ada")]);
+    let error = error.context("failed to load the dashboard");
+    assert_eq!(error.get_field("user"), Some("ada"));
+    assert_eq!(error.get_field("f0"), Some("0"));
+}
+
+/// Tests that the first field with a repeated key is found, as below the threshold.
+#[test]
+fn test_lookup_repeated_key() {
+    let mut error = many_fields().with_field("f3", "again");
+    assert_eq!(error.get_field("f3"), Some("3"));
+
+    error.add_field("dup", "first");
+    error.add_field("dup", "second");
+    assert_eq!(error.get_field("dup"), Some("first"));
+
+    let few = Error::msg("connection refused")
+        .with_field("dup", "first")
+        .with_field("dup", "second");
+    assert_eq!(few.get_field("dup"), Some("first"));
+}
+
+/// Tests that a clone keeps answering lookups for its own fields once the original
+/// has more fields added to it.
+#[test]
+fn test_lookup_on_clone() {
+    let mut error = many_fields();
+    assert_eq!(error.get_field("f11"), Some("11"));
+    let fields = error.fields().clone();
+
+    error.add_field("f12", 12);
+    assert_eq!(error.get_field("f12"), Some("12"));
+    assert_eq!(fields.len(), 12);
+    assert_eq!(fields.iter().find(|(key, _)| *key == "f12"), None);
+}
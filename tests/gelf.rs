@@ -0,0 +1,84 @@
+#![cfg(feature = "json")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Converting errors into GELF messages for Graylog.
+
+use anyhow_tracing::{Level, anyhow};
+use serde_json::json;
+
+/// Tests a message with a chain, a code and fields.
+#[test]
+fn test_to_gelf() {
+    let err = anyhow!(host = "db-3", "connection refused")
+        .context("failed to sync")
+        .with_field("attempts", 3)
+        .with_code("DB_UNAVAILABLE");
+    assert_eq!(
+        err.to_gelf("worker-1"),
+        json!({
+            "version": "1.1",
+            "host": "worker-1",
+            "short_message": "failed to sync",
+            "full_message": "failed to sync: connection refused [host=db-3, attempts=3]",
+            "level": 3,
+            "_code": "DB_UNAVAILABLE",
+            "_host": "db-3",
+            "_attempts": "3",
+        })
+    );
+}
+
+/// Tests that field names get the underscore prefix with the characters GELF doesn't
+/// allow replaced, and that `id` is left out.
+#[test]
+fn test_additional_field_names() {
+    let err = anyhow!(id = 7, "job failed")
+        .with_field("http.status", 503)
+        .with_field("retry-after", 30)
+        .with_field("user name", "ada")
+        .with_field("région/zone", "eu-west");
+    let message = err.to_gelf("worker-1");
+    let object = message.as_object().expect("GELF messages are objects");
+
+    let mut additional: Vec<&str> = object
+        .keys()
+        .map(String::as_str)
+        .filter(|key| key.starts_with('_'))
+        .collect();
+    additional.sort_unstable();
+    assert_eq!(
+        additional,
+        ["_http.status", "_r_gion_zone", "_retry-after", "_user_name"]
+    );
+    assert_eq!(message["_user_name"], "ada");
+    assert_eq!(message.get("_id"), None);
+}
+
+/// Tests the syslog severity of each level.
+#[test]
+fn test_level_mapping() {
+    let levels = [
+        (Level::Error, 3),
+        (Level::Warn, 4),
+        (Level::Info, 6),
+        (Level::Debug, 7),
+        (Level::Trace, 7),
+    ];
+    for (level, severity) in levels {
+        let err = anyhow!("job failed").with_level(level);
+        assert_eq!(err.to_gelf("worker-1")["level"], severity, "{level}");
+    }
+    assert_eq!(anyhow!("job failed").to_gelf("worker-1")["level"], 3);
+}
+
+/// Tests that numeric JSON fields stay numbers and other structured values become
+/// compact strings, as GELF only allows strings and numbers.
+#[test]
+fn test_json_field_values() {
+    let err = anyhow!("job failed")
+        .with_field_json("duration_ms", json!(1250))
+        .with_field_json("tags", json!(["billing", "nightly"]));
+    let message = err.to_gelf("worker-1");
+    assert_eq!(message["_duration_ms"], 1250);
+    assert_eq!(message["_tags"], r#"["billing","nightly"]"#);
+}
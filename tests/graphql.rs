@@ -0,0 +1,120 @@
+#![cfg(feature = "async-graphql")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Converting errors into GraphQL errors with extensions.
+
+use anyhow_tracing::{Context, GraphqlResultExt, Result, anyhow};
+use async_graphql::{EmptyMutation, EmptySubscription, ErrorExtensions, Object, Schema};
+use serde_json::{Value, json};
+
+/// Load a user, failing for unknown users.
+fn load_user(id: u64) -> Result<String> {
+    if id == 1 {
+        return Ok("ada".to_owned());
+    }
+    Err(anyhow!(user_id = id, table = "users", "no row returned")
+        .with_code("USER_NOT_FOUND")
+        .with_public_message("user not found")
+        .with_field_json("retry_after", json!(30))
+        .with_field("request_id", "req-7"))
+}
+
+/// Reach the database, failing without a public message.
+fn sync() -> Result<bool> {
+    let result: std::result::Result<bool, std::io::Error> =
+        Err(std::io::Error::other("connection refused"));
+    result.context("failed to reach the database")
+}
+
+struct Query;
+
+#[Object]
+#[allow(clippy::unused_async, reason = "resolvers must be async")]
+impl Query {
+    async fn user(&self, id: u64) -> async_graphql::Result<String> {
+        let name = load_user(id).extend_graphql()?;
+        Ok(name)
+    }
+
+    async fn sync(&self) -> async_graphql::Result<bool> {
+        sync().extend_graphql()
+    }
+}
+
+/// Execute `query` against the schema, returning the JSON response.
+async fn execute(query: &str) -> Value {
+    anyhow_tracing::response::set_public_fields(&["request_id", "retry_after"]).ok();
+    let schema = Schema::new(Query, EmptyMutation, EmptySubscription);
+    let response = schema.execute(query).await;
+    serde_json::to_value(&response).expect("the response serializes")
+}
+
+/// Tests a successful query, which is not affected.
+#[tokio::test]
+async fn test_ok() {
+    let response = execute("{ user(id: 1) }").await;
+    assert_eq!(response, json!({ "data": { "user": "ada" } }));
+}
+
+/// Tests that the public message, code and allowlisted fields are sent as extensions,
+/// with typed values, and that internal fields and messages don't leak.
+#[tokio::test]
+async fn test_extensions() {
+    let response = execute("{ user(id: 7) }").await;
+    assert_eq!(
+        response["errors"],
+        json!([{
+            "message": "user not found",
+            "locations": [{ "line": 1, "column": 3 }],
+            "path": ["user"],
+            "extensions": {
+                "code": "USER_NOT_FOUND",
+                "request_id": "req-7",
+                "retry_after": 30,
+            },
+        }])
+    );
+}
+
+/// Tests an error without a public message or any public details.
+#[tokio::test]
+async fn test_internal_error() {
+    let response = execute("{ sync }").await;
+    assert_eq!(
+        response["errors"],
+        json!([{
+            "message": "internal error",
+            "locations": [{ "line": 1, "column": 3 }],
+            "path": ["sync"],
+        }])
+    );
+}
+
+/// Tests that the error is kept as the source of the GraphQL error.
+#[test]
+fn test_source() {
+    let err = load_user(7).extend_graphql().unwrap_err();
+    let source = err
+        .source
+        .as_ref()
+        .and_then(|source| source.downcast_ref::<anyhow_tracing::Error>())
+        .expect("the error is the source");
+    assert_eq!(source.get_field("table"), Some("users"));
+}
+
+/// Tests that `ErrorExtensions` lets more extensions be added.
+#[test]
+fn test_extend_with() {
+    let err = anyhow!("rate limited")
+        .with_code("RATE_LIMITED")
+        .extend_with(|_, extensions| extensions.set("limit", 100));
+    let extensions = err.extensions.expect("the error has extensions");
+    assert_eq!(
+        extensions.get("code"),
+        Some(&async_graphql::Value::from("RATE_LIMITED"))
+    );
+    assert_eq!(
+        extensions.get("limit"),
+        Some(&async_graphql::Value::from(100))
+    );
+}
@@ -0,0 +1,83 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Errors with more fields than are kept inline.
+
+use anyhow_tracing::Error;
+
+/// The names of the fields added by [`many_fields`].
+const KEYS: [&str; 12] = [
+    "f0", "f1", "f2", "f3", "f4", "f5", "f6", "f7", "f8", "f9", "f10", "f11",
+];
+
+/// Build an error with the twelve fields of [`KEYS`], each set to its index.
+fn many_fields() -> Error {
+    KEYS.iter()
+        .zip(0..)
+        .fold(Error::msg("connection refused"), |error, (key, index)| {
+            error.with_field(key, index)
+        })
+}
+
+/// Tests that fields past the inline ones keep their order and can be looked up.
+#[test]
+fn test_fields_spill_in_order() {
+    let error = many_fields();
+
+    let keys: Vec<&str> = error.fields().iter().map(|(key, _)| key).collect();
+    assert_eq!(keys, KEYS);
+    assert_eq!(error.get_field("f0"), Some("0"));
+    assert_eq!(error.get_field("f4"), Some("4"));
+    assert_eq!(error.get_field("f11"), Some("11"));
+    assert_eq!(
+        error.to_string(),
+        "connection refused [f0=0, f1=1, f2=2, f3=3, f4=4, f5=5, f6=6, f7=7, f8=8, f9=9, \
+         f10=10, f11=11]"
+    );
+}
+
+/// Tests that the fields of context layers are split correctly when the fields spill
+/// between layers.
+#[test]
+fn test_contexts_across_spill() {
+    let error = Error::msg("connection refused")
+        .with_field("host", "db-3")
+        .with_field("port", 5432)
+        .with_field("attempt", 3)
+        .context("failed to query accounts")
+        .with_field("table", "accounts")
+        .with_field("query", "select")
+        .with_field("timeout_ms", 500)
+        .context("failed to load the dashboard")
+        .with_fields([
+            ("user", "ada"),
+            ("org", "acme"),
+            ("page", "home"),
+            ("region", "eu"),
+        ]);
+
+    assert_eq!(error.fields().len(), 10);
+    let layers: Vec<Vec<&str>> = error
+        .contexts()
+        .map(|frame| frame.fields().map(|(key, _)| key).collect())
+        .collect();
+    assert_eq!(
+        layers,
+        [
+            vec!["user", "org", "page", "region"],
+            vec!["table", "query", "timeout_ms"],
+            vec!["host", "port", "attempt"],
+        ]
+    );
+}
+
+/// Tests that fields extended in place after spilling are appended.
+#[test]
+fn test_extend_after_spill() {
+    let mut error = many_fields();
+    error.extend([("f12", "12"), ("f13", "13")]);
+    error.add_field("f14", 14);
+
+    assert_eq!(error.fields().len(), 15);
+    assert_eq!(error.fields().get(14), Some(("f14", "14")));
+    assert_eq!(error.get_field("f12"), Some("12"));
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "test-util")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Emitting the errors of instrumented functions with their fields.
+
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use anyhow_tracing::{Error, Level, Result, TraceResultExt, anyhow, test};
+use serde_json::json;
+use tracing_subscriber::layer::SubscriberExt as _;
+
+async fn read(path: &str) -> Result<String> {
+    tokio::task::yield_now().await;
+    Err(anyhow!(path = path, code = "ENOENT", "file not found"))
+}
+
+#[tracing::instrument]
+async fn load_config(path: &str) -> Result<String> {
+    async {
+        let text = read(path).await?;
+        Ok::<_, Error>(text)
+    }
+    .await
+    .trace_err()
+}
+
+#[tracing::instrument(err)]
+async fn load_config_instrumented(path: &str) -> Result<String> {
+    read(path).await
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("the runtime is built")
+        .block_on(future)
+}
+
+/// Tests that `trace_err` emits each field of an error returned with `?` separately.
+#[test]
+fn test_trace_err_in_instrumented_fn() {
+    let events = test::capture(|| {
+        block_on(load_config("/etc/app.toml")).expect_err("the file is missing");
+    });
+
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.level(), Level::Error);
+    assert_eq!(event.target(), module_path!());
+    assert_eq!(event.file(), Some(file!()));
+    assert!(event.line().is_some());
+    assert_eq!(event.message(), "file not found");
+    assert_eq!(event.field("path"), Some("/etc/app.toml"));
+    assert_eq!(event.field("code"), Some("ENOENT"));
+}
+
+/// A buffer shared with the JSON formatter of `tracing-subscriber`.
+#[derive(Clone, Default)]
+struct JsonLog(Arc<Mutex<Vec<u8>>>);
+
+impl JsonLog {
+    /// Install a JSON formatting layer writing into this buffer while `f` runs.
+    fn with_default<R>(&self, f: impl FnOnce() -> R) -> R {
+        let writer = self.clone();
+        let layer = tracing_subscriber::fmt::layer()
+            .json()
+            .with_file(true)
+            .with_line_number(true)
+            .with_writer(move || writer.clone());
+        tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), f)
+    }
+
+    /// Parse the events written so far, one JSON object per line.
+    fn events(&self) -> Vec<serde_json::Value> {
+        let output = self.0.lock().expect("not poisoned").clone();
+        serde_json::Deserializer::from_slice(&output)
+            .into_iter()
+            .collect::<serde_json::Result<_>>()
+            .expect("the events are JSON")
+    }
+}
+
+impl io::Write for JsonLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().expect("not poisoned").extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Tests that `#[instrument(err)]` records the error as a single string, which
+/// `trace_err` is the replacement for.
+#[test]
+fn test_instrument_err_records_one_field() {
+    let log = JsonLog::default();
+    log.with_default(|| {
+        block_on(load_config_instrumented("/etc/app.toml")).expect_err("the file is missing");
+    });
+
+    let events = log.events();
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event["level"], json!("ERROR"));
+    assert_eq!(event["target"], json!(module_path!()));
+    assert_eq!(event["filename"], json!(file!()));
+    assert!(event["line_number"].is_u64());
+    assert_eq!(
+        event["fields"],
+        json!({"error": "file not found [path=/etc/app.toml, code=ENOENT]"})
+    );
+}
+
+/// Tests that `trace_err` writes each field of the error as its own JSON key, where
+/// `#[instrument(err)]` writes them inside one string.
+#[test]
+fn test_trace_err_writes_one_key_per_field() {
+    let log = JsonLog::default();
+    log.with_default(|| {
+        block_on(load_config("/etc/app.toml")).expect_err("the file is missing");
+    });
+
+    let events = log.events();
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event["level"], json!("ERROR"));
+    let fields = &event["fields"];
+    assert_eq!(fields["message"], json!("file not found"));
+    assert_eq!(fields["path"], json!("/etc/app.toml"));
+    assert_eq!(fields["code"], json!("ENOENT"));
+    assert_eq!(fields.get("error"), None);
+    assert_eq!(event["span"]["name"], json!("load_config"));
+    assert_eq!(event["span"]["path"], json!("/etc/app.toml"));
+}
+
+/// Tests capturing the events of a future in an async test.
+#[tokio::test]
+async fn test_trace_err_in_async_test() {
+    let events = test::capture_future(async {
+        load_config("/etc/app.toml")
+            .await
+            .expect_err("the file is missing");
+    })
+    .await;
+
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.message(), "file not found");
+    assert_eq!(event.field("path"), Some("/etc/app.toml"));
+}
@@ -1,8 +1,9 @@
 #![allow(clippy::tests_outside_test_module, reason = "integration tests")]
 
+use std::collections::BTreeMap;
 use std::io;
 
-use anyhow_tracing::{Context, Error, Result, anyhow, bail, ensure};
+use anyhow_tracing::{Context, Error, FieldFormat, Result, anyhow, bail, ensure};
 use insta::assert_snapshot;
 
 /// Tests the various forms of the `anyhow!` macro for creating errors.
@@ -162,10 +163,10 @@ fn test_error_wrapping_and_downcasting() {
     let original_error = io::Error::new(io::ErrorKind::PermissionDenied, "access denied");
 
     // Wrap the original error and add context
-    let err: Error =
-        Error::from(anyhow::Error::from(original_error)).with_field("request_id", "req-abc");
+    let err: Error = Error::from(original_error).with_field("request_id", "req-abc");
 
-    // Verify that the field was added
+    // Verify that the kind and the field were added
+    assert_eq!(err.get_field("io.kind"), Some("PermissionDenied"));
     assert_eq!(err.get_field("request_id"), Some("req-abc"));
 
     // Use `is()` to check for the underlying error type
@@ -226,7 +227,11 @@ fn test_error_methods_for_context_and_fields() {
 
     // Check error message for all context
     let msg = err.to_string();
-    assert_eq!(msg, "lazy context [field1=value1, field2=[10, 20]]");
+    assert_eq!(msg, r"lazy context [field1=value1, field2=[10\, 20\]]");
+    assert_eq!(
+        format!("{err:#}"),
+        r"lazy context: eager context: base error [field1=value1, field2=[10\, 20\]]"
+    );
 
     // Check for all fields
     assert_eq!(err.get_field("field1"), Some("value1"));
@@ -241,5 +246,1116 @@ fn test_error_methods_for_context_and_fields() {
 
     // Snapshot the complex error with multiple contexts and fields
     assert_snapshot!("complex_error_display", format!("{}", err));
+    assert_snapshot!("complex_error_alternate_display", format!("{:#}", err));
     assert_snapshot!("complex_error_debug", format!("{:?}", err));
 }
+
+/// Tests that fields can be added in place to an error that is only reachable
+/// through a mutable reference, such as one stored on a struct.
+#[test]
+fn test_add_field_through_mutable_reference() {
+    struct Attempt {
+        error: Error,
+    }
+
+    impl Attempt {
+        fn record(&mut self, key: &'static str, value: u32) {
+            self.error.add_field(key, value);
+        }
+    }
+
+    let mut attempt = Attempt {
+        error: anyhow!("request failed"),
+    };
+    for retry in 1..=2 {
+        attempt.record("retry", retry);
+    }
+    attempt.error.add_field_debug("status", Some(503));
+
+    assert_eq!(attempt.error.fields().len(), 3);
+    assert_eq!(attempt.error.get_field("status"), Some("Some(503)"));
+    assert_eq!(
+        attempt.error.to_string(),
+        "request failed [retry=1, retry=2, status=Some(503)]"
+    );
+}
+
+/// Tests that an error can be extended from a map of fields, keeping the
+/// iteration order of the map and appending duplicate keys.
+#[test]
+fn test_extend_error_from_map() {
+    let mut err = anyhow!(region = "eu-west-1", "upload failed");
+
+    let mut headers = BTreeMap::new();
+    headers.insert("region", "us-east-1".to_string());
+    headers.insert("content_type", "text/plain".to_string());
+    headers.insert("bucket", "assets".to_string());
+    err.extend(headers);
+
+    let keys: Vec<&str> = err.fields().iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, ["region", "bucket", "content_type", "region"]);
+    assert_eq!(err.get_field("bucket"), Some("assets"));
+    assert_eq!(err.get_field("region"), Some("eu-west-1"));
+
+    err.extend([("attempt", "1")]);
+    err.extend_fields([("size", 512), ("parts", 2)]);
+    assert_eq!(err.get_field("attempt"), Some("1"));
+    assert_eq!(err.get_field("parts"), Some("2"));
+
+    let err = Error::msg("base").with_fields([("a", 1), ("b", 2)]);
+    assert_eq!(err.to_string(), "base [a=1, b=2]");
+}
+
+/// Tests that a public message set deep in the call stack survives context
+/// layers, while `Display` keeps describing the internal error.
+#[test]
+fn test_public_message_survives_context() {
+    fn query() -> Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::ConnectionRefused,
+            "connect to 10.0.0.7:5432",
+        ))
+        .with_public_message("The service is temporarily unavailable")
+    }
+
+    fn load_user() -> Result<()> {
+        query().map_err(|e| e.context("failed to query users table"))
+    }
+
+    let err = load_user().unwrap_err().context("failed to load profile");
+
+    assert_eq!(
+        err.public_message(),
+        Some("The service is temporarily unavailable")
+    );
+    assert_eq!(err.to_string(), "failed to load profile");
+    assert_eq!(err.chain().count(), 3);
+
+    let err = Error::msg("no public message");
+    assert_eq!(err.public_message(), None);
+}
+
+/// Tests that validation violations are accumulated separately from fields
+/// and rendered in the debug output.
+#[test]
+fn test_validation_violations() {
+    let err = anyhow!("invalid signup request");
+    assert!(err.violations().is_empty());
+    assert!(!format!("{:?}", err).contains("Violations:"));
+
+    let err = err.with_violation("email", "is not a valid address");
+    assert_eq!(err.violations().len(), 1);
+    assert_eq!(err.violations()[0].0, "email");
+    assert_eq!(&*err.violations()[0].1, "is not a valid address");
+    assert_snapshot!("single_violation_error_debug", format!("{:?}", err));
+
+    let mut err = err
+        .with_field("request_id", "req-123")
+        .with_violation("age", format_args!("must be at least {}", 18));
+    err.add_violation("password", "is too short");
+
+    assert_eq!(err.violations().len(), 3);
+    assert_eq!(err.fields().len(), 1);
+    assert_eq!(err.get_field("email"), None);
+    assert_eq!(
+        err.to_string(),
+        "invalid signup request [request_id=req-123]"
+    );
+    assert_snapshot!("violations_error_debug", format!("{:?}", err));
+}
+
+/// Tests that errors instantiated from the same template are independent of each
+/// other and of the template itself.
+#[test]
+fn test_error_template_instances_are_independent() {
+    use std::sync::LazyLock;
+
+    use anyhow_tracing::{ErrorTemplate, Level, from_template};
+
+    static TIMEOUT: LazyLock<ErrorTemplate> = LazyLock::new(|| {
+        ErrorTemplate::new("upstream timeout")
+            .code("UPSTREAM_TIMEOUT")
+            .level(Level::Warn)
+            .field("retryable", true)
+    });
+
+    let first = from_template!(TIMEOUT, host = %"db-1", attempt = 1);
+    let mut second = TIMEOUT.with_fields([("host", "db-2")]);
+    second.add_field("extra", "only on second");
+
+    assert_eq!(
+        first.to_string(),
+        "upstream timeout [retryable=true, host=db-1, attempt=1]"
+    );
+    assert_eq!(
+        second.to_string(),
+        "upstream timeout [retryable=true, host=db-2, extra=only on second]"
+    );
+    assert_eq!(first.code(), second.code());
+    assert_eq!(first.level(), Some(Level::Warn));
+    assert_eq!(second.level(), Some(Level::Warn));
+
+    let fresh = TIMEOUT.new_error();
+    assert_eq!(fresh.fields().len(), 1);
+    assert_eq!(fresh.to_string(), "upstream timeout [retryable=true]");
+}
+
+/// Tests typed field keys declared with `define_fields!`.
+#[test]
+fn test_typed_field_keys() {
+    use anyhow_tracing::define_fields;
+
+    define_fields! {
+        USER_ID: u64 = "user_id";
+        REGION: &str = "region";
+        RETRYABLE: bool = "retryable";
+    }
+
+    let region = String::from("eu-west-1");
+    let err = anyhow!("request failed")
+        .with_key(USER_ID, 42)
+        .with_key(REGION, &region)
+        .with_field("retryable", "maybe");
+
+    assert_eq!(err.get_key(USER_ID), Some(42));
+    assert_eq!(err.get_key(REGION), Some("eu-west-1"));
+    assert_eq!(err.get_field("user_id"), Some("42"));
+    assert_eq!(USER_ID.name(), "user_id");
+
+    // Values that do not parse as the declared type are not returned
+    assert_eq!(err.get_key(RETRYABLE), None);
+    assert_eq!(
+        err.to_string(),
+        "request failed [user_id=42, region=eu-west-1, retryable=maybe]"
+    );
+}
+
+/// Tests that control characters in field values are escaped when rendered, so a
+/// single error cannot produce multiple log lines or terminal escape sequences.
+#[test]
+#[cfg(feature = "sanitize-output")]
+fn test_field_values_are_sanitized_when_rendered() {
+    let err = anyhow!(
+        newline = %"first\nsecond",
+        carriage = %"one\rtwo",
+        ansi = %"\x1b[31mred\x1b[0m",
+        null = %"a\0b",
+        "control characters"
+    );
+
+    assert_eq!(
+        err.to_string(),
+        r"control characters [newline=first\nsecond, carriage=one\rtwo, ansi=\x1b[31mred\x1b[0m, null=a\x00b]"
+    );
+    assert!(!format!("{:?}", err).contains('\x1b'));
+
+    // The stored values are unchanged
+    assert_eq!(err.get_field("newline"), Some("first\nsecond"));
+    assert_eq!(err.get_field("ansi"), Some("\x1b[31mred\x1b[0m"));
+    assert_eq!(err.get_field("null"), Some("a\0b"));
+}
+
+/// Tests that the cached `Display` output is reused and refreshed whenever the
+/// error changes.
+#[test]
+fn test_display_cache_is_invalidated_on_change() {
+    let mut err = anyhow!(host = "db-1", "connection failed");
+    assert_eq!(err.to_display_string(), "connection failed [host=db-1]");
+    assert_eq!(err.to_string(), err.to_display_string());
+
+    err.add_field("attempt", 2);
+    assert_eq!(
+        err.to_display_string(),
+        "connection failed [host=db-1, attempt=2]"
+    );
+
+    let err = err.with_field("port", 5432);
+    assert_eq!(
+        err.to_string(),
+        "connection failed [host=db-1, attempt=2, port=5432]"
+    );
+
+    let mut err = err.context("failed to load user");
+    assert_eq!(
+        err.to_display_string(),
+        "failed to load user [host=db-1, attempt=2, port=5432]"
+    );
+
+    err.extend([("shard", "7")]);
+    assert_eq!(
+        format!("{}", err),
+        "failed to load user [host=db-1, attempt=2, port=5432, shard=7]"
+    );
+}
+
+/// Tests that fields are grouped under the context layer they were added with.
+#[test]
+fn test_context_frames_own_their_fields() {
+    let err = anyhow!(path = "/etc/app.toml", "file not found")
+        .with_field("mode", "read")
+        .context("failed to load config")
+        .with_field("attempt", 2)
+        .context("startup failed")
+        .context("service crashed")
+        .with_field("service", "billing");
+
+    let frames: Vec<_> = err.contexts().collect();
+    assert_eq!(frames.len(), 4);
+
+    assert_eq!(frames[0].message(), "service crashed");
+    assert_eq!(frames[0].get_field("service"), Some("billing"));
+    assert_eq!(frames[1].message(), "startup failed");
+    assert_eq!(frames[1].fields().len(), 0);
+    assert_eq!(frames[2].message(), "failed to load config");
+    assert_eq!(frames[2].get_field("attempt"), Some("2"));
+    assert_eq!(frames[3].message(), "file not found");
+    assert_eq!(frames[3].fields().len(), 2);
+    assert_eq!(frames[3].get_field("path"), Some("/etc/app.toml"));
+    assert_eq!(frames[3].get_field("attempt"), None);
+
+    // The flattened view is unchanged
+    let keys: Vec<&str> = err.fields().iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, ["path", "mode", "attempt", "service"]);
+    assert_eq!(
+        err.to_string(),
+        "service crashed [path=/etc/app.toml, mode=read, attempt=2, service=billing]"
+    );
+    assert_snapshot!("context_frames_error_debug", format!("{:?}", err));
+
+    // An error without any context has a single frame
+    let err = anyhow!("base");
+    assert_eq!(err.contexts().count(), 1);
+}
+
+/// Tests the `Context` trait on `anyhow::Result`, the common migration path.
+#[test]
+fn test_context_trait_on_anyhow_result() {
+    fn read_config() -> anyhow::Result<String> {
+        let err = io::Error::new(io::ErrorKind::NotFound, "no such file");
+        Err(anyhow::Error::from(err).context("failed to read config"))
+    }
+
+    let err = read_config()
+        .with_field("path", "/etc/app.toml")
+        .unwrap_err();
+    assert_eq!(err.get_field("path"), Some("/etc/app.toml"));
+    assert_eq!(err.chain().count(), 2);
+    assert!(err.is::<io::Error>());
+    assert_eq!(
+        err.to_string(),
+        "failed to read config [path=/etc/app.toml]"
+    );
+
+    let err = read_config().context("startup failed").unwrap_err();
+    assert_eq!(err.chain().count(), 3);
+
+    let err = read_config()
+        .with_context(|| "lazy startup failure")
+        .unwrap_err();
+    assert_eq!(err.to_string(), "lazy startup failure");
+
+    let err = read_config()
+        .with_field_debug("attempts", [1, 2])
+        .unwrap_err();
+    assert_eq!(err.get_field("attempts"), Some("[1, 2]"));
+}
+
+/// Tests that the `Context` trait applied to a `Result` that already holds an
+/// `Error` keeps the fields added earlier instead of re-wrapping the error.
+#[test]
+fn test_context_trait_preserves_existing_fields() {
+    fn do_thing() -> Result<()> {
+        Err(io::Error::new(io::ErrorKind::TimedOut, "timed out")).with_field("host", "db-1")
+    }
+
+    let retry = 3;
+    let err = do_thing()
+        .with_field("retry", retry)
+        .context("failed")
+        .with_field_debug("shard", Some(7))
+        .unwrap_err();
+
+    assert_eq!(err.get_field("host"), Some("db-1"));
+    assert_eq!(err.get_field("retry"), Some("3"));
+    assert_eq!(err.get_field("shard"), Some("Some(7)"));
+    assert_eq!(err.chain().count(), 2);
+    assert_eq!(err.contexts().count(), 2);
+    assert!(err.is::<io::Error>());
+    assert_eq!(
+        err.to_string(),
+        "failed [host=db-1, retry=3, shard=Some(7)]"
+    );
+
+    // Errors that round-trip through `anyhow::Error` keep their fields too
+    let wrapped: anyhow::Result<()> = do_thing().map_err(anyhow::Error::from);
+    let err = wrapped.context("outer").unwrap_err();
+    assert_eq!(err.get_field("host"), Some("db-1"));
+    assert_eq!(err.to_string(), "outer [host=db-1]");
+}
+
+/// Tests the `Context` trait on results holding boxed error trait objects.
+#[test]
+fn test_context_trait_on_boxed_error() {
+    type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+    fn call_plugin() -> std::result::Result<(), BoxError> {
+        Err(Box::new(io::Error::new(
+            io::ErrorKind::BrokenPipe,
+            "plugin exited",
+        )))
+    }
+
+    fn original_kind(err: &Error) -> Option<io::ErrorKind> {
+        err.downcast_ref::<BoxError>()
+            .and_then(|boxed| boxed.downcast_ref::<io::Error>())
+            .map(io::Error::kind)
+    }
+
+    let err = call_plugin().context("plugin call failed").unwrap_err();
+    assert_eq!(err.to_string(), "plugin call failed");
+    assert_eq!(err.chain().count(), 2);
+    assert_eq!(original_kind(&err), Some(io::ErrorKind::BrokenPipe));
+
+    let err = call_plugin()
+        .with_context(|| format!("plugin {} failed", "resize"))
+        .unwrap_err();
+    assert_eq!(err.to_string(), "plugin resize failed");
+    assert_eq!(original_kind(&err), Some(io::ErrorKind::BrokenPipe));
+
+    let err = call_plugin().with_field("plugin", "resize").unwrap_err();
+    assert_eq!(err.to_string(), "plugin exited [plugin=resize]");
+    assert!(err.is::<BoxError>());
+    assert_eq!(original_kind(&err), Some(io::ErrorKind::BrokenPipe));
+
+    let err = call_plugin().with_field_debug("args", ["-v"]).unwrap_err();
+    assert_eq!(err.get_field("args"), Some(r#"["-v"]"#));
+    assert_eq!(original_kind(&err), Some(io::ErrorKind::BrokenPipe));
+
+    // A boxed `Error` keeps its fields
+    let boxed: std::result::Result<(), BoxError> =
+        Err(Box::new(anyhow!(host = "db-1", "query failed")));
+    let err = boxed.context("load failed").unwrap_err();
+    assert_eq!(err.to_string(), "load failed [host=db-1]");
+}
+
+/// Tests that lazily computed fields are only evaluated on the error path.
+#[test]
+fn test_lazy_fields_are_only_evaluated_on_error() {
+    let ok: std::result::Result<u32, io::Error> = Ok(1);
+    let value = ok
+        .with_field_with("summary", || -> String {
+            panic!("evaluated on the Ok path")
+        })
+        .with_field_debug_with("items", || -> Vec<u32> {
+            panic!("evaluated on the Ok path")
+        })
+        .unwrap();
+    assert_eq!(value, 1);
+
+    let some = Some(2);
+    let value = some
+        .with_field_with("summary", || -> String {
+            panic!("evaluated on the Some path")
+        })
+        .unwrap();
+    assert_eq!(value, 2);
+
+    let err: std::result::Result<u32, io::Error> = Err(io::Error::other("failed"));
+    let err = err
+        .with_field_with("summary", || "3 items")
+        .with_field_debug_with("items", || vec![1, 2, 3])
+        .unwrap_err();
+    assert_eq!(err.get_field("summary"), Some("3 items"));
+    assert_eq!(err.get_field("items"), Some("[1, 2, 3]"));
+
+    let err = None::<u32>.with_field_with("key", || "port").unwrap_err();
+    assert_eq!(err.get_field("key"), Some("port"));
+}
+
+/// Tests attaching several fields at once through the Context trait.
+#[test]
+fn test_context_trait_with_fields() {
+    let fields = BTreeMap::from([("attempt", 3), ("port", 8080), ("retries", 5)]);
+
+    let result: std::result::Result<(), io::Error> = Err(io::Error::other("connection refused"));
+    let err = result
+        .with_field("host", "localhost")
+        .with_fields(fields.clone())
+        .unwrap_err();
+
+    assert_eq!(err.fields().len(), 4);
+    let keys: Vec<_> = err.fields().iter().map(|(key, _)| key).collect();
+    assert_eq!(keys, ["host", "attempt", "port", "retries"]);
+    assert_eq!(err.get_field("attempt"), Some("3"));
+    assert_eq!(err.get_field("port"), Some("8080"));
+    assert_eq!(err.get_field("retries"), Some("5"));
+
+    let err = None::<()>.with_fields(fields).unwrap_err();
+    assert_eq!(err.fields().len(), 3);
+    assert_eq!(err.get_field("port"), Some("8080"));
+}
+
+/// Tests the messages produced for `None` with and without explicit context.
+#[test]
+fn test_option_messages() {
+    let err = None::<u16>.with_field("key", "port").unwrap_err();
+    assert_snapshot!("option_default_message_display", format!("{}", err));
+
+    let err = None::<u16>
+        .context_field("missing configuration value", "key", "port")
+        .unwrap_err();
+    assert_eq!(err.get_field("key"), Some("port"));
+    assert_snapshot!("option_context_field_display", format!("{}", err));
+    assert_snapshot!("option_context_field_debug", format!("{:?}", err));
+
+    let result: std::result::Result<u16, io::Error> = Err(io::Error::other("file not found"));
+    let err = result
+        .context_field("failed to load configuration", "path", "config.toml")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "failed to load configuration [path=config.toml]"
+    );
+    let frame = err.contexts().next().unwrap();
+    assert_eq!(frame.message(), "failed to load configuration");
+    assert_eq!(frame.get_field("path"), Some("config.toml"));
+}
+
+/// Tests that the Context trait records the call site of each context layer.
+#[cfg(feature = "capture-location")]
+#[test]
+fn test_context_trait_captures_location() {
+    let result: std::result::Result<(), io::Error> = Err(io::Error::other("connection reset"));
+    let (result, inner_line) = (result.with_field("host", "localhost"), line!());
+    let result = result.with_field("port", 5432);
+    let (result, outer_line) = (result.context("failed to query database"), line!());
+    let err = result.unwrap_err();
+
+    let location = err.location().unwrap();
+    assert!(location.file().ends_with("integration_tests.rs"));
+    assert_eq!(location.line(), inner_line);
+
+    let frames: Vec<_> = err.contexts().collect();
+    let outer = frames.first().unwrap().location().unwrap();
+    assert_eq!(outer.line(), outer_line);
+    assert_eq!(frames.last().unwrap().location(), Some(location));
+
+    // Locations are not part of the rendered output
+    assert_eq!(
+        err.to_string(),
+        "failed to query database [host=localhost, port=5432]"
+    );
+}
+
+/// Tests that inspecting an error runs only on the error path and leaves it unchanged.
+#[test]
+fn test_inspect_err_fields() {
+    let ok: std::result::Result<u32, io::Error> = Ok(1);
+    let value = ok
+        .inspect_err_fields(|_| panic!("inspected on the Ok path"))
+        .inspect_err_field("host", |_| panic!("inspected on the Ok path"))
+        .unwrap();
+    assert_eq!(value, 1);
+
+    let err: std::result::Result<u32, io::Error> = Err(io::Error::other("connection refused"));
+    let mut seen = Vec::new();
+    let mut host = None;
+    let err = err
+        .with_field("host", "localhost")
+        .inspect_err_fields(|err| seen.push(err.to_string()))
+        .inspect_err_field("host", |value| host = value.map(str::to_owned))
+        .inspect_err_field("port", |value| assert_eq!(value, None))
+        .unwrap_err();
+
+    assert_eq!(seen, ["connection refused [host=localhost]"]);
+    assert_eq!(host.as_deref(), Some("localhost"));
+    assert_eq!(err.to_string(), "connection refused [host=localhost]");
+    assert_eq!(err.fields().len(), 1);
+    assert_eq!(err.contexts().count(), 1);
+}
+
+/// Tests that FieldExt adds fields without adding context layers.
+#[test]
+fn test_field_ext_does_not_grow_chain() {
+    use anyhow_tracing::FieldExt;
+
+    let result: Result<()> = Err(anyhow!(database = "accounts", "query failed"));
+    let err = result
+        .field("host", "db-3.internal")
+        .field_debug("tables", ["users", "roles"])
+        .fields([("attempt", 3), ("timeout_ms", 2500)])
+        .code("DB_QUERY_FAILED")
+        .unwrap_err();
+
+    assert_eq!(err.chain().count(), 1);
+    assert_eq!(err.contexts().count(), 1);
+    assert_eq!(err.code(), Some("DB_QUERY_FAILED"));
+    assert_eq!(
+        err.to_string(),
+        r#"query failed [database=accounts, host=db-3.internal, tables=["users"\, "roles"\], attempt=3, timeout_ms=2500]"#
+    );
+
+    let ok: Result<u32> = Ok(1);
+    assert_eq!(ok.field("host", "db-3.internal").unwrap(), 1);
+}
+
+/// Tests the Context trait on the `Poll` shapes returned by hand written futures and streams.
+#[test]
+fn test_context_trait_on_poll() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Poll, Waker};
+
+    /// A future that is pending on the first poll and fails on the second.
+    struct FailSecondPoll {
+        polled: bool,
+    }
+
+    impl Future for FailSecondPoll {
+        type Output = std::result::Result<u32, io::Error>;
+
+        fn poll(mut self: Pin<&mut Self>, _: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+            if self.polled {
+                Poll::Ready(Err(io::Error::other("connection reset")))
+            } else {
+                self.polled = true;
+                Poll::Pending
+            }
+        }
+    }
+
+    let mut cx = std::task::Context::from_waker(Waker::noop());
+    let mut future = FailSecondPoll { polled: false };
+
+    let poll = Pin::new(&mut future)
+        .poll(&mut cx)
+        .context("request failed")
+        .with_field("attempt", 1);
+    assert!(poll.is_pending());
+
+    let poll = Pin::new(&mut future)
+        .poll(&mut cx)
+        .context("request failed")
+        .with_field("attempt", 2);
+    let Poll::Ready(Err(err)) = poll else {
+        panic!("expected a ready error, got {poll:?}");
+    };
+    assert_eq!(err.to_string(), "request failed [attempt=2]");
+    assert_eq!(err.chain().count(), 2);
+
+    let ready: Poll<std::result::Result<u32, io::Error>> = Poll::Ready(Ok(3));
+    assert!(matches!(ready.with_field("attempt", 3), Poll::Ready(Ok(3))));
+
+    // Stream items
+    let item: Poll<Option<std::result::Result<u32, io::Error>>> =
+        Poll::Ready(Some(Err(io::Error::other("malformed frame"))));
+    let Poll::Ready(Some(Err(err))) = item
+        .with_field("offset", 128)
+        .with_field_debug("frame", [0xde, 0xad])
+    else {
+        panic!("expected a ready error");
+    };
+    assert_eq!(
+        err.to_string(),
+        r"malformed frame [offset=128, frame=[222\, 173\]]"
+    );
+
+    let end: Poll<Option<std::result::Result<u32, io::Error>>> = Poll::Ready(None);
+    assert!(matches!(end.context("stream failed"), Poll::Ready(None)));
+    let pending: Poll<Option<std::result::Result<u32, io::Error>>> = Poll::Pending;
+    assert!(pending.context("stream failed").is_pending());
+}
+
+/// Tests that the Context trait on the `Poll` shapes records the call site as the
+/// `Result` implementation it delegates to does.
+#[cfg(feature = "capture-location")]
+#[test]
+fn test_context_trait_on_poll_captures_location() {
+    use std::task::Poll;
+
+    let poll: Poll<std::result::Result<(), io::Error>> =
+        Poll::Ready(Err(io::Error::other("connection reset")));
+    let (poll, line) = (poll.context("request failed"), line!());
+    let Poll::Ready(Err(err)) = poll else {
+        panic!("expected a ready error, got {poll:?}");
+    };
+    let location = err.location().unwrap();
+    assert!(location.file().ends_with("integration_tests.rs"));
+    assert_eq!(location.line(), line);
+
+    let item: Poll<Option<std::result::Result<(), io::Error>>> =
+        Poll::Ready(Some(Err(io::Error::other("malformed frame"))));
+    let (item, line) = (item.with_field("offset", 128), line!());
+    let Poll::Ready(Some(Err(err))) = item else {
+        panic!("expected a ready error, got {item:?}");
+    };
+    let location = err.location().unwrap();
+    assert!(location.file().ends_with("integration_tests.rs"));
+    assert_eq!(location.line(), line);
+}
+
+/// Tests decorating a future with context and fields before awaiting it.
+#[cfg(feature = "futures")]
+#[tokio::test]
+async fn test_future_context_ext() {
+    use anyhow_tracing::FutureContextExt;
+
+    fn fetch(fail: bool) -> std::future::Ready<std::result::Result<u32, io::Error>> {
+        if fail {
+            std::future::ready(Err(io::Error::other("connection refused")))
+        } else {
+            std::future::ready(Ok(200))
+        }
+    }
+
+    let err = fetch(true)
+        .with_field("url", "https://example.com")
+        .with_field_debug("headers", ["accept"])
+        .context("fetch failed")
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"fetch failed [url=https://example.com, headers=["accept"\]]"#
+    );
+    assert_eq!(err.get_field("url"), Some("https://example.com"));
+    assert_eq!(err.get_field("headers"), Some(r#"["accept"]"#));
+    assert_eq!(err.root_cause().to_string(), "connection refused");
+
+    let status = fetch(false)
+        .with_context(|| -> String { panic!("evaluated on the success path") })
+        .with_field("url", "https://example.com")
+        .await
+        .unwrap();
+    assert_eq!(status, 200);
+}
+
+/// Tests that a lazy message and lazy fields are produced by one closure, called once.
+#[test]
+fn test_with_context_fields() {
+    use std::cell::Cell;
+
+    let calls = Cell::new(0);
+    let (segment, offset) = (7, 4096);
+    let flush = || {
+        calls.set(calls.get() + 1);
+        let fields = [
+            ("segment", segment.to_string()),
+            ("offset", offset.to_string()),
+        ];
+        ("failed to flush segment", fields)
+    };
+
+    let ok: std::result::Result<(), io::Error> = Ok(());
+    ok.with_context_fields(flush).unwrap();
+    assert_eq!(calls.get(), 0);
+
+    let err: std::result::Result<(), io::Error> = Err(io::Error::other("disk full"));
+    let err = err.with_context_fields(flush).unwrap_err();
+    assert_eq!(calls.get(), 1);
+    assert_eq!(
+        err.to_string(),
+        "failed to flush segment [segment=7, offset=4096]"
+    );
+    assert_eq!(err.root_cause().to_string(), "disk full");
+
+    let err = None::<()>.with_context_fields(flush).unwrap_err();
+    assert_eq!(calls.get(), 2);
+    assert_eq!(
+        err.to_string(),
+        "failed to flush segment [segment=7, offset=4096]"
+    );
+}
+
+/// Tests converting `None` into an error with a message and fields in one call.
+#[test]
+fn test_option_ok_or_field() {
+    use std::collections::HashMap;
+
+    use anyhow_tracing::OptionExt;
+
+    let cache: HashMap<&str, u32> = HashMap::from([("user:7", 7)]);
+
+    assert_eq!(
+        cache
+            .get("user:7")
+            .ok_or_field("cache miss", "key", "user:7")
+            .unwrap(),
+        &7
+    );
+
+    let err = cache
+        .get("user:42")
+        .ok_or_field("cache miss", "key", "user:42")
+        .unwrap_err();
+    assert_eq!(err.to_string(), "cache miss [key=user:42]");
+    assert_eq!(err.chain().count(), 1);
+
+    let err = cache
+        .get("user:42")
+        .ok_or_fields("cache miss", [("key", "user:42"), ("shard", "3")])
+        .unwrap_err();
+    assert_eq!(err.to_string(), "cache miss [key=user:42, shard=3]");
+    assert!(!format!("{err:?}").contains("expected Some value"));
+    assert!(!format!("{err:?}").contains("None value"));
+}
+
+/// Tests that each error from a batch is tagged with the index of its item.
+#[test]
+fn test_context_indexed() {
+    use anyhow_tracing::IterContextExt;
+
+    let items = ["10", "x", "30", "y", "50"];
+    let results: Vec<Result<u32>> = items
+        .iter()
+        .map(|item| item.parse::<u32>())
+        .context_indexed("processing item")
+        .collect();
+
+    assert_eq!(results.len(), 5);
+    let failures: Vec<_> = results
+        .iter()
+        .filter_map(|result| result.as_ref().err())
+        .collect();
+    assert_eq!(failures.len(), 2);
+    assert_eq!(failures[0].get_field("index"), Some("1"));
+    assert_eq!(failures[1].get_field("index"), Some("3"));
+    assert_eq!(failures[0].to_string(), "processing item [index=1]");
+    assert_eq!(
+        failures[1].root_cause().to_string(),
+        "invalid digit found in string"
+    );
+    assert_eq!(results[4].as_ref().ok(), Some(&50));
+}
+
+/// Tests converting poisoned `Mutex` and `RwLock` results into errors.
+#[test]
+fn test_lock_poisoning() {
+    use std::sync::{Arc, Mutex, RwLock};
+
+    use anyhow_tracing::LockResultExt;
+
+    let mutex = Arc::new(Mutex::new(0));
+    let rwlock = Arc::new(RwLock::new(0));
+
+    assert_eq!(*mutex.lock().context("state lock poisoned").unwrap(), 0);
+
+    let (thread_mutex, thread_rwlock) = (Arc::clone(&mutex), Arc::clone(&rwlock));
+    let poisoner = std::thread::spawn(move || {
+        let _mutex = thread_mutex.lock();
+        let _rwlock = thread_rwlock.write();
+        panic!("poisoning the locks");
+    });
+    assert!(poisoner.join().is_err());
+
+    let err = mutex.lock().context("state lock poisoned").unwrap_err();
+    assert_eq!(err.to_string(), "state lock poisoned [lock.poisoned=true]");
+    assert_eq!(
+        err.root_cause().to_string(),
+        "poisoned lock: another task failed inside"
+    );
+
+    let err = rwlock.read().with_field("lock", "config").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "poisoned lock: another task failed inside [lock.poisoned=true, lock=config]"
+    );
+
+    let err = rwlock
+        .write()
+        .with_context(|| "config lock poisoned")
+        .unwrap_err();
+    assert_eq!(err.get_field("lock.poisoned"), Some("true"));
+}
+
+/// Tests flattening the results of panicked, cancelled and failed tokio tasks.
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_join_result_ext() {
+    use anyhow_tracing::JoinResultExt;
+
+    let handle = tokio::spawn(async { Err::<(), _>(io::Error::other("upload failed")) });
+    let err = handle
+        .await
+        .flatten_context("sync task failed")
+        .unwrap_err();
+    assert_eq!(err.to_string(), "sync task failed");
+    assert_eq!(err.root_cause().to_string(), "upload failed");
+
+    let handle = tokio::spawn(async {
+        if true {
+            panic!("index out of bounds");
+        }
+        Ok::<(), io::Error>(())
+    });
+    let err = handle
+        .await
+        .flatten_context("sync task failed")
+        .unwrap_err();
+    assert_eq!(err.get_field("task.panicked"), Some("true"));
+    assert_eq!(err.get_field("task.panic"), Some("index out of bounds"));
+    assert_eq!(err.root_cause().to_string(), "task panicked");
+
+    let handle = tokio::spawn(std::future::pending::<std::result::Result<(), io::Error>>());
+    handle.abort();
+    let err = handle
+        .await
+        .flatten_context("sync task failed")
+        .unwrap_err();
+    assert_eq!(err.get_field("task.cancelled"), Some("true"));
+    assert_eq!(err.get_field("task.panicked"), None);
+
+    // The Context trait covers a plain join result
+    let handle = tokio::spawn(async { panic!("boom") });
+    let err: Error = handle.await.context("task failed").unwrap_err();
+    assert_eq!(err.to_string(), "task failed");
+}
+
+/// Tests that optional fields are only recorded when present, keeping their order.
+#[test]
+fn test_with_field_opt() {
+    let trace_id: Option<&str> = Some("trace-1");
+    let user: Option<&str> = None;
+    let roles: Option<Vec<&str>> = Some(vec!["admin"]);
+
+    let result: std::result::Result<(), io::Error> = Err(io::Error::other("forbidden"));
+    let err = result
+        .with_field("method", "DELETE")
+        .with_field_opt("trace_id", trace_id)
+        .with_field_opt("user", user)
+        .with_field("path", "/users/7")
+        .with_field_debug_opt("roles", roles)
+        .with_field_debug_opt("session", None::<u64>)
+        .unwrap_err();
+
+    let keys: Vec<_> = err.fields().iter().map(|(key, _)| key).collect();
+    assert_eq!(keys, ["method", "trace_id", "path", "roles"]);
+    assert_eq!(
+        err.to_string(),
+        r#"forbidden [method=DELETE, trace_id=trace-1, path=/users/7, roles=["admin"\]]"#
+    );
+
+    let err = None::<()>.with_field_opt("user", user).unwrap_err();
+    assert!(err.fields().is_empty());
+
+    let err = None::<()>.with_field_opt("trace_id", trace_id).unwrap_err();
+    assert_eq!(err.get_field("trace_id"), Some("trace-1"));
+}
+
+/// Tests that notes accumulate across layers and only appear in the Debug output.
+#[test]
+fn test_notes() {
+    let result: std::result::Result<(), io::Error> = Err(io::Error::other("no space left"));
+    let err = result
+        .with_field("segment", 7)
+        .note("this usually means the disk is full")
+        .context("failed to flush segment")
+        .note("retried 3 times before giving up")
+        .unwrap_err()
+        .note("the write-ahead log was not affected");
+
+    assert_eq!(
+        err.notes(),
+        [
+            "this usually means the disk is full",
+            "retried 3 times before giving up",
+            "the write-ahead log was not affected",
+        ]
+    );
+    assert_eq!(err.to_string(), "failed to flush segment [segment=7]");
+    assert_snapshot!("notes_error_debug", format!("{:?}", err));
+
+    let err = Error::msg("shutdown requested").note("in-flight requests were drained");
+    assert_snapshot!("notes_without_fields_error_debug", format!("{:?}", err));
+}
+
+/// Tests the Debug layout of fields for errors with one, three and no field layers.
+#[test]
+fn test_debug_fields_grouped_by_layer() {
+    let err = anyhow!(host = "db-3", port = 5432, "connection refused");
+    assert_snapshot!("one_layer_error_debug", format!("{:?}", err));
+
+    let err = anyhow!(host = "db-3", port = 5432, "connection refused")
+        .context("failed to query accounts")
+        .with_field("query", "select_balance")
+        .context("failed to load invoice")
+        .with_field("invoice_id", 42)
+        .with_field("attempt", 2);
+    assert_snapshot!("three_layer_error_debug", format!("{:?}", err));
+
+    let err = Error::msg("connection refused")
+        .context("failed to query accounts")
+        .context("failed to load invoice");
+    assert_snapshot!("fieldless_error_debug", format!("{:?}", err));
+}
+
+/// Tests getting the message and the chain without the fields.
+#[test]
+fn test_message_without_fields() {
+    let err = anyhow!(path = "/etc/app.toml", "invalid section [server]")
+        .context("File operation failed [config]")
+        .with_field("attempt", 2);
+
+    assert_eq!(err.message(), "File operation failed [config]");
+    assert_eq!(
+        err.display_without_fields().to_string(),
+        "File operation failed [config]: invalid section [server]"
+    );
+    assert_eq!(
+        err.to_string(),
+        "File operation failed [config] [path=/etc/app.toml, attempt=2]"
+    );
+
+    // No fields and no context
+    let err = Error::msg("plain failure");
+    assert_eq!(err.message(), "plain failure");
+    assert_eq!(err.display_without_fields().to_string(), "plain failure");
+}
+
+/// Tests rendering errors as logfmt lines, quoting and escaping where needed.
+#[test]
+fn test_to_logfmt() {
+    let err =
+        anyhow!(path = "/etc/app.toml", attempt = 3, "permission denied").context("failed to open");
+    assert_eq!(
+        err.to_logfmt(),
+        r#"msg="failed to open" path=/etc/app.toml attempt=3 cause="permission denied""#
+    );
+
+    let err = Error::msg("bad input")
+        .with_field("query", "name = \"alice\"")
+        .with_field("body", "line one\nline two")
+        .with_field("empty", "")
+        .with_field("path", r"C:\temp")
+        .with_field("user id", "7")
+        .with_code("INVALID_INPUT")
+        .with_level(anyhow_tracing::Level::Warn);
+    assert_eq!(
+        err.to_logfmt(),
+        r#"msg="bad input" code=INVALID_INPUT level=WARN query="name = \"alice\"" body="line one\nline two" empty="" path="C:\\temp" user_id=7"#
+    );
+
+    assert_eq!(Error::msg("timeout").to_logfmt(), "msg=timeout");
+}
+
+/// Tests the human readable report of a deep chain with fields and notes, with the
+/// line break of a field value escaped.
+#[test]
+#[cfg(feature = "sanitize-output")]
+fn test_report_deep_chain() {
+    let err = anyhow!(path = "/etc/app.toml", attempt = 3, "permission denied")
+        .context("failed to open")
+        .with_field("user", "deploy")
+        .with_field("reason", "line one\nline two")
+        .context("failed to load config")
+        .with_field("service", "api")
+        .note("check the permissions of the config directory");
+
+    assert_snapshot!("report_deep_chain", err.report().to_string());
+    assert_snapshot!(
+        "report_deep_chain_compact",
+        err.report().compact(true).to_string()
+    );
+}
+
+/// Tests the report of a bare message, which has no sections.
+#[test]
+fn test_report_bare_message() {
+    let err = Error::msg("timeout");
+    assert_snapshot!("report_bare_message", err.report().to_string());
+}
+
+/// Tests sorting fields by key for a single rendering.
+#[test]
+fn test_display_with_sorted_fields() {
+    let err = anyhow!(port = 5432, host = "db-3", "connection refused");
+    let sorted = FieldFormat::new().sort_fields(true);
+    assert_eq!(
+        err.display_with(sorted).to_string(),
+        "connection refused [host=db-3, port=5432]"
+    );
+    assert_eq!(err.to_string(), "connection refused [port=5432, host=db-3]");
+}
+
+/// Tests cutting off long values when rendering, on character boundaries.
+#[test]
+fn test_display_with_max_value_len() {
+    let err = Error::msg("bad request")
+        .with_field("body", "héllo wörld")
+        .with_field("short", "ok");
+
+    // "hé" is exactly 3 bytes
+    let format = FieldFormat::new().max_value_len(Some(3));
+    assert_eq!(
+        err.display_with(format).to_string(),
+        "bad request [body=hé…(len=13), short=ok]"
+    );
+
+    // The limit falls inside "é", which is not split
+    let format = FieldFormat::new().max_value_len(Some(2));
+    assert_eq!(
+        err.display_with(format).to_string(),
+        "bad request [body=h…(len=13), short=ok]"
+    );
+
+    // A value exactly at the limit is rendered in full
+    let format = FieldFormat::new().max_value_len(Some(13));
+    assert_eq!(
+        err.display_with(format).to_string(),
+        "bad request [body=héllo wörld, short=ok]"
+    );
+
+    assert_eq!(err.to_string(), "bad request [body=héllo wörld, short=ok]");
+    assert_eq!(err.get_field("body"), Some("héllo wörld"));
+}
+
+/// Tests rendering the chain on one line, writing repeated messages once.
+#[test]
+fn test_fmt_compact() {
+    let err = Error::msg("os error 104")
+        .context("connection reset by peer")
+        .context("connection reset by peer")
+        .with_field("host", "db-3")
+        .context("failed to sync")
+        .with_field("attempt", 2);
+    assert_eq!(
+        err.fmt_compact().to_string(),
+        "failed to sync: connection reset by peer: os error 104 [host=db-3, attempt=2]"
+    );
+
+    // Only adjacent messages are deduplicated
+    let err = Error::msg("timeout").context("retrying").context("timeout");
+    assert_eq!(err.fmt_compact().to_string(), "timeout: retrying: timeout");
+
+    assert_eq!(Error::msg("timeout").fmt_compact().to_string(), "timeout");
+}
+
+/// Tests that the anyhow compatible output matches an `anyhow::Error` with the same
+/// chain and the fields folded into its outermost message.
+#[test]
+fn test_debug_anyhow_compatible() {
+    let err = anyhow!(host = "db-3", "connection refused")
+        .context("failed to connect")
+        .context("failed to sync")
+        .with_field("attempt", 2);
+    let anyhow_err = anyhow::anyhow!("connection refused")
+        .context("failed to connect")
+        .context("failed to sync [host=db-3, attempt=2]");
+    assert_eq!(
+        err.debug_anyhow_compatible().to_string(),
+        format!("{:?}", anyhow_err)
+    );
+
+    let err = Error::msg("connection refused").context("failed to sync");
+    let anyhow_err = anyhow::anyhow!("connection refused").context("failed to sync");
+    assert_eq!(
+        err.debug_anyhow_compatible().to_string(),
+        format!("{:?}", anyhow_err)
+    );
+
+    let err = anyhow!(host = "db-3", "connection refused");
+    assert_eq!(
+        err.debug_anyhow_compatible().to_string(),
+        format!("{:?}", anyhow::anyhow!("connection refused [host=db-3]"))
+    );
+}
@@ -0,0 +1,58 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Converting I/O errors, with their kind, OS error code and path.
+
+use std::io;
+use std::path::Path;
+
+use anyhow_tracing::{Error, PathContext, Result};
+
+/// Read the file at `path`, converting the error with `?`.
+fn read(path: &Path) -> Result<String> {
+    Ok(std::fs::read_to_string(path)?)
+}
+
+/// Tests that `?` converts a missing file, with its kind and OS error code.
+#[test]
+fn test_not_found() {
+    let err = read(Path::new("/nonexistent/config.toml")).expect_err("the file is missing");
+    assert_eq!(err.get_field("io.kind"), Some("NotFound"));
+    #[cfg(unix)]
+    assert_eq!(err.get_field("io.raw_os_error"), Some("2"));
+    let io_err = err.downcast_ref::<io::Error>().expect("the error is kept");
+    assert_eq!(io_err.kind(), io::ErrorKind::NotFound);
+}
+
+/// Tests a permission error from the OS, and one made without an OS error code.
+#[test]
+fn test_permission_denied() {
+    let err = Error::from(io::Error::from_raw_os_error(13));
+    assert_eq!(err.get_field("io.kind"), Some("PermissionDenied"));
+    assert_eq!(err.get_field("io.raw_os_error"), Some("13"));
+
+    let err = Error::from(io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "access denied",
+    ));
+    assert_eq!(
+        err.fields().iter().collect::<Vec<_>>(),
+        [("io.kind", "PermissionDenied")]
+    );
+    assert_eq!(err.to_string(), "access denied [io.kind=PermissionDenied]");
+}
+
+/// Tests that `path_context` adds the context on top of the path and I/O fields.
+#[test]
+fn test_path_context() {
+    let path = Path::new("/nonexistent/config.toml");
+    let err = std::fs::read_to_string(path)
+        .map_err(|_| io::Error::new(io::ErrorKind::NotFound, "no such file"))
+        .path_context("reading config", path)
+        .expect_err("the file is missing");
+    assert_eq!(
+        err.to_string(),
+        "reading config [io.kind=NotFound, path=/nonexistent/config.toml]"
+    );
+    assert_eq!(err.root_cause().to_string(), "no such file");
+    assert!(err.downcast_ref::<io::Error>().is_some());
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "journald")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Sending errors to the systemd journal.
+
+use anyhow_tracing::journald::{encode, field_name};
+use anyhow_tracing::{Level, anyhow};
+
+/// Tests the field names for keys that need no changes, or only upper casing.
+#[test]
+fn test_field_name_upper_case() {
+    assert_eq!(field_name("HOST").as_deref(), Some("HOST"));
+    assert_eq!(field_name("request_id").as_deref(), Some("REQUEST_ID"));
+    assert_eq!(field_name("attempt2").as_deref(), Some("ATTEMPT2"));
+}
+
+/// Tests the field names for keys with characters that journald doesn't allow.
+#[test]
+fn test_field_name_sanitized() {
+    assert_eq!(field_name("http.status").as_deref(), Some("HTTP_STATUS"));
+    assert_eq!(field_name("retry-after").as_deref(), Some("RETRY_AFTER"));
+    assert_eq!(field_name("user name").as_deref(), Some("USER_NAME"));
+    assert_eq!(field_name("région/zone").as_deref(), Some("RGIONZONE"));
+    assert_eq!(field_name("_pid").as_deref(), Some("PID"));
+    assert_eq!(field_name("2fa_method").as_deref(), Some("FA_METHOD"));
+    assert_eq!(field_name("_").as_deref(), None);
+    assert_eq!(field_name("ß").as_deref(), None);
+    assert_eq!(
+        field_name(&"k".repeat(100)).map(|name| name.len()),
+        Some(64)
+    );
+}
+
+/// Tests the fields of an error, with the fields named after the ones set from the
+/// error itself left out.
+#[test]
+fn test_to_journal_fields() {
+    let err = anyhow!(host = "db-3", message = "ignored", "connection refused")
+        .context("failed to sync")
+        .with_field("http.status", 503)
+        .with_code("DB_UNAVAILABLE")
+        .with_level(Level::Warn);
+    let fields = err.to_journal_fields();
+    let fields: Vec<(&str, &str)> = fields
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .filter(|(name, _)| !name.starts_with("CODE_"))
+        .collect();
+    assert_eq!(
+        fields,
+        [
+            ("MESSAGE", "failed to sync: connection refused"),
+            ("PRIORITY", "4"),
+            ("ERROR_CODE", "DB_UNAVAILABLE"),
+            ("HOST", "db-3"),
+            ("HTTP_STATUS", "503"),
+        ]
+    );
+}
+
+/// Tests the native protocol encoding, with the length-prefixed form for values with
+/// newlines.
+#[test]
+fn test_encode() {
+    let message = encode(&[("MESSAGE", "disk full"), ("NOTE", "line 1\nline 2")]);
+    let mut expected = b"MESSAGE=disk full\nNOTE\n".to_vec();
+    expected.extend_from_slice(&13_u64.to_le_bytes());
+    expected.extend_from_slice(b"line 1\nline 2\n");
+    assert_eq!(message, expected);
+}
+
+/// Tests sending an error to a socket standing in for journald.
+#[cfg(unix)]
+#[test]
+fn test_emit_to_socket() {
+    use std::os::unix::net::UnixDatagram;
+
+    let path = std::env::temp_dir().join(format!("anyhow-tracing-journal-{}", std::process::id()));
+    std::fs::remove_file(&path).ok();
+    let journal = UnixDatagram::bind(&path).expect("the socket binds");
+
+    let err = anyhow!(job_id = 42, "job timed out").with_level(Level::Info);
+    anyhow_tracing::journald::emit_to(&err, &path).expect("the message is sent");
+
+    let mut buffer = [0; 1024];
+    let len = journal.recv(&mut buffer).expect("the message is received");
+    std::fs::remove_file(&path).ok();
+    assert_eq!(
+        buffer.get(..len),
+        Some(encode(&err.to_journal_fields()).as_slice())
+    );
+    assert_eq!(
+        String::from_utf8_lossy(buffer.get(..len).unwrap_or_default()),
+        "MESSAGE=job timed out\nPRIORITY=6\nJOB_ID=42\n"
+    );
+}
+
+/// Tests that sending fails when nothing listens on the socket.
+#[cfg(unix)]
+#[test]
+fn test_emit_to_missing_socket() {
+    let err = anyhow!("job timed out");
+    let path = std::env::temp_dir().join("anyhow-tracing-no-journal");
+    anyhow_tracing::journald::emit_to(&err, path).expect_err("nothing listens on the socket");
+}
+
+/// Tests the location of the error, recorded with the `capture-location` feature.
+#[cfg(feature = "capture-location")]
+#[test]
+fn test_code_location() {
+    use anyhow_tracing::Context;
+
+    let result: Result<(), std::io::Error> = Err(std::io::Error::other("disk full"));
+    let err = result.context("failed to flush").unwrap_err();
+    let line = line!() - 1;
+    let fields = err.to_journal_fields();
+    assert!(fields.contains(&("CODE_FILE".to_owned(), file!().to_owned())));
+    assert!(fields.contains(&("CODE_LINE".to_owned(), line.to_string())));
+}
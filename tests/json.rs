@@ -0,0 +1,213 @@
+#![cfg(feature = "json")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Rendering errors as `serde_json` values.
+
+use std::collections::BTreeMap;
+
+use anyhow_tracing::{Context, anyhow};
+use serde_json::{Value, json};
+
+/// Remove the backtrace, which is only captured when `RUST_BACKTRACE` is set.
+fn without_backtrace(mut value: Value) -> Value {
+    value
+        .as_object_mut()
+        .expect("errors render as objects")
+        .remove("backtrace");
+    value
+}
+
+/// Tests an error with flat fields.
+#[test]
+fn test_to_json_flat() {
+    let err = anyhow!(host = "db-3", "connection refused")
+        .context("failed to sync")
+        .with_field("http.status", 503)
+        .with_code("DB_UNAVAILABLE");
+    assert_eq!(
+        without_backtrace(err.to_json(false)),
+        json!({
+            "message": "failed to sync",
+            "chain": ["failed to sync", "connection refused"],
+            "fields": { "host": "db-3", "http.status": "503" },
+            "code": "DB_UNAVAILABLE",
+        })
+    );
+}
+
+/// Tests that dotted keys become nested objects.
+#[test]
+fn test_to_json_nested() {
+    let err = anyhow!(host = "db-3", "connection refused")
+        .with_field("http.status", 503)
+        .with_field("http.request.method", "GET")
+        .with_field("http.request.path", "/sync");
+    assert_eq!(
+        without_backtrace(err.to_json(true))["fields"],
+        json!({
+            "host": "db-3",
+            "http": {
+                "status": "503",
+                "request": { "method": "GET", "path": "/sync" },
+            },
+        })
+    );
+}
+
+/// Tests that the field added last wins when a key is also a prefix of other keys.
+#[test]
+fn test_to_json_nested_conflicts() {
+    let err = anyhow!("upstream unavailable")
+        .with_field("http", "h2")
+        .with_field("http.status", 503);
+    assert_eq!(
+        err.to_json(true)["fields"],
+        json!({ "http": { "status": "503" } })
+    );
+
+    let err = anyhow!("upstream unavailable")
+        .with_field("http.status", 503)
+        .with_field("http", "h2");
+    assert_eq!(err.to_json(true)["fields"], json!({ "http": "h2" }));
+}
+
+/// Tests that field values keep the text they were rendered to, so values that look
+/// like numbers or booleans stay strings.
+#[test]
+fn test_to_json_values_keep_their_text() {
+    let err = anyhow!(retryable = false, attempts = 3, "timed out").with_field("zip", "007");
+    assert_eq!(
+        err.to_json(false)["fields"],
+        json!({ "retryable": "false", "attempts": "3", "zip": "007" })
+    );
+}
+
+/// Tests that an error without fields has no `fields` entry.
+#[test]
+fn test_to_json_without_fields() {
+    let err = anyhow!("timed out");
+    assert_eq!(err.to_json(true).get("fields"), None);
+}
+
+/// Tests that an object field survives into `to_json` untouched, and is shown as
+/// compact JSON by `get_field` and `Display`.
+#[test]
+fn test_with_field_json() {
+    let limits = json!({ "max_connections": 0, "hosts": ["db-1", "db-2"], "tls": true });
+    let err = anyhow!(path = "/etc/app.toml", "invalid config")
+        .with_field_json("limits", limits.clone())
+        .context("failed to start");
+
+    assert_eq!(err.to_json(false)["fields"]["limits"], limits);
+    assert_eq!(err.to_json(true)["fields"]["limits"], limits);
+    assert_eq!(err.get_field_json("limits"), Some(&limits));
+    assert_eq!(err.get_field_json("path"), None);
+    assert_eq!(err.get_field_json("missing"), None);
+
+    let compact = r#"{"hosts":["db-1","db-2"],"max_connections":0,"tls":true}"#;
+    assert_eq!(err.get_field("limits"), Some(compact));
+    // `Display` escapes the delimiters of the fields list in values
+    assert_eq!(
+        err.to_string(),
+        r#"failed to start [path=/etc/app.toml, limits={"hosts":["db-1"\,"db-2"\]\,"max_connections":0\,"tls":true}]"#
+    );
+}
+
+/// Tests that the `Serialize` output keeps the structure of JSON fields, and the value
+/// added last when a key is added as JSON and as text.
+#[test]
+fn test_serialize_field_json() {
+    let err = anyhow!("invalid config")
+        .with_field_json(
+            "violations",
+            json!([{ "field": "port", "reason": "out of range" }]),
+        )
+        .with_field_json("limits", json!({ "max_connections": 0 }))
+        .with_field("limits", "none");
+    let value = serde_json::to_value(&err).expect("errors serialize");
+    assert_eq!(
+        value["fields"],
+        json!({
+            "violations": [{ "field": "port", "reason": "out of range" }],
+            "limits": "none",
+        })
+    );
+}
+
+/// A summary of a request, with nested maps.
+#[derive(serde::Serialize)]
+struct RequestSummary {
+    method: &'static str,
+    headers: BTreeMap<&'static str, &'static str>,
+    routes: BTreeMap<&'static str, BTreeMap<&'static str, u16>>,
+}
+
+/// A value whose serialization always fails.
+struct Unserializable;
+
+impl serde::Serialize for Unserializable {
+    fn serialize<S: serde::Serializer>(&self, _serializer: S) -> Result<S::Ok, S::Error> {
+        Err(serde::ser::Error::custom(
+            "connection handles can't be serialized",
+        ))
+    }
+}
+
+fn request_summary() -> RequestSummary {
+    RequestSummary {
+        method: "GET",
+        headers: BTreeMap::from([("accept", "text/html")]),
+        routes: BTreeMap::from([("/users", BTreeMap::from([("GET", 200), ("POST", 201)]))]),
+    }
+}
+
+/// Tests a struct with nested maps, kept as structured data and shown as compact JSON
+/// with sorted keys.
+#[test]
+fn test_with_field_serde() {
+    let summary = request_summary();
+    let err = anyhow!("request rejected").with_field_serde("request", &summary);
+    let expected = json!({
+        "method": "GET",
+        "headers": { "accept": "text/html" },
+        "routes": { "/users": { "GET": 200, "POST": 201 } },
+    });
+    assert_eq!(err.to_json(false)["fields"]["request"], expected);
+    assert_eq!(err.get_field_json("request"), Some(&expected));
+    assert_eq!(
+        err.get_field("request"),
+        Some(
+            r#"{"headers":{"accept":"text/html"},"method":"GET","routes":{"/users":{"GET":200,"POST":201}}}"#
+        )
+    );
+}
+
+/// Tests that a value failing to serialize is added as the serialization error.
+#[test]
+fn test_with_field_serde_failure() {
+    let err = anyhow!("request rejected").with_field_serde("connection", Unserializable);
+    assert_eq!(
+        err.get_field("connection"),
+        Some("<serialization error: connection handles can't be serialized>")
+    );
+    assert_eq!(err.get_field_json("connection"), None);
+    assert_eq!(
+        err.to_json(false)["fields"]["connection"],
+        "<serialization error: connection handles can't be serialized>"
+    );
+}
+
+/// Tests the method of the `Context` trait on results and options.
+#[test]
+fn test_context_with_field_serde() {
+    let result: Result<(), std::io::Error> = Err(std::io::Error::other("timed out"));
+    let err = result
+        .with_field_serde("request", request_summary())
+        .unwrap_err();
+    assert_eq!(err.to_json(false)["fields"]["request"]["method"], "GET");
+
+    let err = None::<()>
+        .with_field_serde("attempts", [1, 2, 3])
+        .unwrap_err();
+    assert_eq!(err.get_field_json("attempts"), Some(&json!([1, 2, 3])));
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "lambda")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Reporting errors returned by AWS Lambda handlers.
+
+use std::fmt::Debug;
+
+use anyhow_tracing::{Context, Error, Result, anyhow};
+use lambda_runtime::{Diagnostic, LambdaEvent};
+use serde_json::{Value, json};
+
+/// A handler failing to charge an order.
+async fn charge(event: LambdaEvent<Value>) -> Result<Value> {
+    let order_id = event.payload.get("order_id").and_then(Value::as_u64);
+    Err(anyhow!(order_id = ?order_id, "card declined")
+        .context("failed to charge")
+        .with_code("PaymentFailed"))
+}
+
+/// Check that `handler` can be run by the Lambda runtime.
+fn assert_handler<F, Fut, T, E>(_handler: F)
+where
+    F: Fn(LambdaEvent<Value>) -> Fut,
+    Fut: Future<Output = std::result::Result<T, E>>,
+    E: Into<Diagnostic> + Debug,
+{
+}
+
+/// Tests the diagnostic of an error with a code and fields, and its JSON payload.
+#[test]
+fn test_diagnostic_with_code() {
+    assert_handler(charge);
+
+    let err = anyhow!(order_id = 7, "card declined")
+        .context("failed to charge")
+        .with_code("PaymentFailed");
+    let diagnostic = Diagnostic::from(err);
+    assert_eq!(
+        serde_json::to_value(&diagnostic).expect("the diagnostic serializes"),
+        json!({
+            "errorType": "PaymentFailed",
+            "errorMessage": "failed to charge: card declined [order_id=7]",
+        })
+    );
+}
+
+/// Tests that errors without a code are reported with the type of the error they were
+/// converted from.
+#[test]
+fn test_diagnostic_with_root_type() {
+    let result: std::result::Result<u16, std::num::ParseIntError> = "port".parse();
+    let err = result
+        .context("invalid port")
+        .with_field("value", "port")
+        .expect_err("the port is not a number");
+    let diagnostic = Diagnostic::from(err);
+    assert_eq!(diagnostic.error_type, "core::num::error::ParseIntError");
+    assert_eq!(
+        diagnostic.error_message,
+        "invalid port: invalid digit found in string [value=port]"
+    );
+
+    let err = Error::from(std::io::Error::other("connection reset"));
+    assert_eq!(Diagnostic::from(err).error_type, "std::io::error::Error");
+}
+
+/// Tests the error type of errors created from a message.
+#[test]
+fn test_diagnostic_default_type() {
+    let diagnostic = Diagnostic::from(anyhow!("job timed out"));
+    assert_eq!(diagnostic.error_type, "anyhow_tracing::Error");
+    assert_eq!(diagnostic.error_message, "job timed out");
+}
@@ -0,0 +1,169 @@
+#![cfg(all(feature = "log", not(feature = "tracing")))]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Emitting errors as `log` records, checked with a logger capturing records.
+
+use std::sync::{Mutex, OnceLock};
+use std::thread::{self, ThreadId};
+
+use anyhow_tracing::{Level, TraceResultExt, anyhow};
+
+/// A record as seen by the logger.
+#[derive(Debug)]
+struct CapturedRecord {
+    thread: ThreadId,
+    level: log::Level,
+    target: String,
+    file: Option<String>,
+    line: Option<u32>,
+    message: String,
+    #[cfg_attr(
+        not(feature = "log-kv"),
+        expect(dead_code, reason = "key-values are only attached with the feature")
+    )]
+    key_values: Vec<(String, String)>,
+}
+
+/// A logger capturing every record, shared by the tests.
+struct Capture(Mutex<Vec<CapturedRecord>>);
+
+#[cfg(feature = "log-kv")]
+struct KeyValues<'a>(&'a mut Vec<(String, String)>);
+
+#[cfg(feature = "log-kv")]
+impl<'kvs> log::kv::VisitSource<'kvs> for KeyValues<'_> {
+    fn visit_pair(
+        &mut self,
+        key: log::kv::Key<'kvs>,
+        value: log::kv::Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+impl log::Log for Capture {
+    fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        #[cfg_attr(
+            not(feature = "log-kv"),
+            expect(unused_mut, reason = "key-values are only attached with the feature")
+        )]
+        let mut key_values = Vec::new();
+        #[cfg(feature = "log-kv")]
+        record
+            .key_values()
+            .visit(&mut KeyValues(&mut key_values))
+            .expect("visiting key-values does not fail");
+        self.0.lock().expect("not poisoned").push(CapturedRecord {
+            thread: thread::current().id(),
+            level: record.level(),
+            target: record.target().to_owned(),
+            file: record.file().map(ToOwned::to_owned),
+            line: record.line(),
+            message: record.args().to_string(),
+            key_values,
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the logger, and take the records logged on this thread so far.
+fn take_records() -> Vec<CapturedRecord> {
+    static LOGGER: OnceLock<&'static Capture> = OnceLock::new();
+    let logger = LOGGER.get_or_init(|| {
+        let logger: &'static Capture = Box::leak(Box::new(Capture(Mutex::default())));
+        log::set_logger(logger).expect("only this test sets a logger");
+        log::set_max_level(log::LevelFilter::Trace);
+        logger
+    });
+    let current = thread::current().id();
+    let mut records = logger.0.lock().expect("not poisoned");
+    let (taken, rest) = records
+        .drain(..)
+        .partition(|record| record.thread == current);
+    *records = rest;
+    taken
+}
+
+/// Tests that an error is logged at its level with its fields in the message.
+#[test]
+fn test_emit_logs_record() {
+    take_records();
+    let err = anyhow!(host = "db-3", port = 5432, "connection refused")
+        .context("failed to sync")
+        .with_level(Level::Warn);
+
+    let line = line!();
+    err.emit();
+
+    let records = take_records();
+    let [record] = records.as_slice() else {
+        panic!("expected one record, got {:?}", records);
+    };
+    assert_eq!(record.level, log::Level::Warn);
+    assert_eq!(record.target, module_path!());
+    assert_eq!(record.file.as_deref(), Some(file!()));
+    assert_eq!(record.line, Some(line.saturating_add(1)));
+    assert_eq!(
+        record.message,
+        "failed to sync: connection refused [host=db-3, port=5432]"
+    );
+}
+
+/// Tests the mapping of each level, through `log_err` and `trace_err`.
+#[test]
+fn test_levels() {
+    take_records();
+    for level in [
+        Level::Trace,
+        Level::Debug,
+        Level::Info,
+        Level::Warn,
+        Level::Error,
+    ] {
+        Err::<(), _>(anyhow!("failed"))
+            .log_err(level)
+            .expect_err("the error is propagated");
+    }
+    Err::<(), _>(anyhow!("failed"))
+        .trace_err()
+        .expect_err("the error is propagated");
+
+    let levels: Vec<log::Level> = take_records().iter().map(|record| record.level).collect();
+    assert_eq!(
+        levels,
+        [
+            log::Level::Trace,
+            log::Level::Debug,
+            log::Level::Info,
+            log::Level::Warn,
+            log::Level::Error,
+            log::Level::Error,
+        ]
+    );
+}
+
+/// Tests that the fields are attached as key-values with the `log-kv` feature.
+#[cfg(feature = "log-kv")]
+#[test]
+fn test_key_values() {
+    take_records();
+    anyhow!(host = "db-3", port = 5432, "connection refused").emit();
+
+    let records = take_records();
+    let [record] = records.as_slice() else {
+        panic!("expected one record, got {:?}", records);
+    };
+    assert_eq!(
+        record.key_values,
+        [
+            ("host".to_owned(), "db-3".to_owned()),
+            ("port".to_owned(), "5432".to_owned()),
+        ]
+    );
+}
@@ -0,0 +1,176 @@
+#![cfg(all(feature = "metrics", any(feature = "tracing", feature = "log")))]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Counting emitted errors with a `metrics` recorder.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::time::Duration;
+
+use anyhow_tracing::{Level, TraceResultExt, anyhow};
+use metrics::{
+    Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+};
+
+/// The labels of a counter, in the order they were given.
+type Labels = Vec<(String, String)>;
+
+/// A recorder keeping the value of each counter by name and labels.
+#[derive(Default)]
+struct Counters(Mutex<BTreeMap<(String, Labels), Arc<AtomicU64>>>);
+
+struct CounterValue(Arc<AtomicU64>);
+
+impl CounterFn for CounterValue {
+    fn increment(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    fn absolute(&self, value: u64) {
+        self.0.fetch_max(value, Ordering::Relaxed);
+    }
+}
+
+impl Recorder for Counters {
+    fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+        let labels = key
+            .labels()
+            .map(|label| (label.key().to_owned(), label.value().to_owned()))
+            .collect();
+        let value = Arc::clone(
+            self.0
+                .lock()
+                .expect("not poisoned")
+                .entry((key.name().to_owned(), labels))
+                .or_default(),
+        );
+        Counter::from_arc(Arc::new(CounterValue(value)))
+    }
+
+    fn register_gauge(&self, _key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+        Gauge::noop()
+    }
+
+    fn register_histogram(&self, _key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+        Histogram::noop()
+    }
+}
+
+impl Counters {
+    fn values(&self) -> Vec<(String, Labels, u64)> {
+        self.0
+            .lock()
+            .expect("not poisoned")
+            .iter()
+            .map(|((name, labels), value)| {
+                (name.clone(), labels.clone(), value.load(Ordering::Relaxed))
+            })
+            .collect()
+    }
+}
+
+fn labels(labels: &[(&str, &str)]) -> Labels {
+    labels
+        .iter()
+        .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+        .collect()
+}
+
+/// Run `f` with a recorder, returning the counters recorded.
+fn record<F: FnOnce()>(f: F) -> Vec<(String, Labels, u64)> {
+    static LABEL_FIELDS: Once = Once::new();
+    LABEL_FIELDS.call_once(|| {
+        anyhow_tracing::metrics::set_label_fields(&["route", "span.tenant"])
+            .expect("only this test sets the label fields");
+    });
+
+    let counters = Counters::default();
+    metrics::with_local_recorder(&counters, f);
+    counters.values()
+}
+
+/// Tests that emitted errors are counted by code, level and allowlisted fields.
+#[test]
+fn test_counts_emitted_errors() {
+    let counters = record(|| {
+        for user in 0..3 {
+            anyhow!(route = "/login", user = user, "invalid password")
+                .with_code("AUTH_FAILED")
+                .emit_at(Level::Warn);
+        }
+        Err::<(), _>(anyhow!(route = "/upload", "disk full"))
+            .trace_err()
+            .expect_err("the error is propagated");
+    });
+
+    assert_eq!(
+        counters,
+        [
+            (
+                "errors_total".to_owned(),
+                labels(&[
+                    ("code", "AUTH_FAILED"),
+                    ("level", "warn"),
+                    ("route", "/login")
+                ]),
+                3,
+            ),
+            (
+                "errors_total".to_owned(),
+                labels(&[("code", "none"), ("level", "error"), ("route", "/upload")]),
+                1,
+            ),
+        ]
+    );
+}
+
+/// Tests that label names and values are made safe for Prometheus.
+#[test]
+fn test_sanitizes_labels() {
+    let long = "x".repeat(100);
+    let counters = record(|| {
+        anyhow!(route = "/a\nb", "failed")
+            .with_field("span.tenant", long)
+            .with_code("BAD\tCODE")
+            .emit();
+    });
+
+    let [(_, labels, 1)] = counters.as_slice() else {
+        panic!("expected one counter, got {:?}", counters);
+    };
+    assert_eq!(
+        labels
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .collect::<Vec<_>>(),
+        ["code", "level", "route", "span_tenant"]
+    );
+    assert_eq!(
+        labels.first().map(|(_, value)| value.as_str()),
+        Some("BAD_CODE")
+    );
+    assert_eq!(labels.get(2).map(|(_, value)| value.as_str()), Some("/a_b"));
+    assert_eq!(labels.get(3).map(|(_, value)| value.len()), Some(64));
+}
+
+/// Tests that errors left out by throttling are still counted.
+#[test]
+fn test_counts_throttled_errors() {
+    let counters = record(|| {
+        for _ in 0..10 {
+            anyhow!("metrics backlog").emit_throttled(Duration::from_secs(60));
+        }
+    });
+
+    let [(_, _, count)] = counters.as_slice() else {
+        panic!("expected one counter, got {:?}", counters);
+    };
+    assert_eq!(*count, 10);
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "miette")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Rendering errors as miette diagnostics.
+
+use anyhow_tracing::{Context, Level, Result, anyhow};
+use miette::{Diagnostic, GraphicalReportHandler, GraphicalTheme};
+
+/// Load the configuration, failing on a missing key.
+fn load_config() -> Result<u16> {
+    let result: std::result::Result<u16, std::io::Error> = Err(std::io::Error::other(
+        "missing key `port` in table `server`",
+    ));
+    result
+        .context("failed to parse the configuration")
+        .with_field("path", "/etc/app.toml")
+        .context("failed to start the server")
+        .with_field("attempt", 2)
+}
+
+/// Render `diagnostic` with the unicode theme and without colors.
+fn render(diagnostic: &dyn Diagnostic) -> String {
+    let mut rendered = String::new();
+    GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+        .with_width(80)
+        .with_links(false)
+        .render_report(&mut rendered, diagnostic)
+        .expect("the report renders");
+    rendered
+}
+
+/// Tests the graphical report of a converted error.
+#[test]
+fn test_into_miette_report() {
+    let report = load_config()
+        .unwrap_err()
+        .with_code("config::missing_key")
+        .with_docs_url("https://docs.example.com/errors/config-missing-key")
+        .note("add `port = 8080` under `[server]`")
+        .into_miette();
+    insta::assert_snapshot!("into_miette_report", render(report.as_ref()));
+}
+
+/// Tests that the causes are chained through `diagnostic_source`.
+#[test]
+fn test_diagnostic_source() {
+    let report = load_config().unwrap_err().into_miette();
+    let mut messages = vec![report.to_string()];
+    let mut source = report.diagnostic_source();
+    while let Some(diagnostic) = source {
+        messages.push(diagnostic.to_string());
+        source = diagnostic.diagnostic_source();
+    }
+    assert_eq!(
+        messages,
+        [
+            "failed to start the server",
+            "failed to parse the configuration",
+            "missing key `port` in table `server`",
+        ]
+    );
+}
+
+/// Tests the `Diagnostic` implementation of the error itself.
+#[test]
+fn test_error_diagnostic() {
+    let err = anyhow!(port = 80, "permission denied")
+        .with_code("net::bind")
+        .with_docs_url("https://docs.example.com/errors/net-bind")
+        .with_level(Level::Warn)
+        .note("ports below 1024 need privileges");
+    let diagnostic: &dyn Diagnostic = &err;
+    assert_eq!(diagnostic.code().unwrap().to_string(), "net::bind");
+    assert_eq!(
+        diagnostic.url().unwrap().to_string(),
+        "https://docs.example.com/errors/net-bind"
+    );
+    assert_eq!(diagnostic.severity(), Some(miette::Severity::Warning));
+    assert_eq!(
+        diagnostic.help().unwrap().to_string(),
+        "ports below 1024 need privileges"
+    );
+    assert!(anyhow!("plain").help().is_none());
+}
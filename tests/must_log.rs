@@ -0,0 +1,82 @@
+#![cfg(feature = "must-log")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Emitting an event for errors dropped without being handled.
+
+mod common;
+
+use std::panic;
+
+use anyhow_tracing::{Error, Result, anyhow};
+use common::{CapturedEvent, Collector};
+
+fn fail() -> Result<()> {
+    Err(anyhow!(path = "/tmp/cache", "failed to remove the cache"))
+}
+
+fn propagate() -> Result<()> {
+    fail()?;
+    Ok(())
+}
+
+/// Run `f` with a collector, returning the events emitted.
+fn collect<F: FnOnce()>(f: F) -> Vec<CapturedEvent> {
+    let collector = Collector::new(tracing::Level::TRACE);
+    tracing::subscriber::with_default(collector.clone(), f);
+    collector.take_events()
+}
+
+/// Tests that an error discarded without being handled emits a warning.
+#[test]
+fn test_unhandled_error_warns() {
+    let events = collect(|| {
+        drop(propagate().map_err(|err| err.context("failed to clean up")));
+    });
+
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.level, tracing::Level::WARN);
+    assert_eq!(event.target, "anyhow_tracing::unhandled");
+    assert_eq!(event.file.as_deref(), Some(file!()));
+    assert_eq!(event.line, Some(14));
+    assert_eq!(event.field("message"), Some("failed to clean up"));
+    assert_eq!(event.field("path"), Some("/tmp/cache"));
+}
+
+/// Tests that errors which were handled in any way are dropped silently.
+#[test]
+fn test_handled_errors_are_silent() {
+    let events = collect(|| {
+        fail().expect_err("fails").ack();
+        fail().expect_err("fails").emit();
+        let _ = fail().expect_err("fails").to_string();
+        assert!(!format!("{:?}", propagate().expect_err("fails")).is_empty());
+        let _ = fail().expect_err("fails").report().to_string();
+        let _ = fail().expect_err("fails").is::<std::io::Error>();
+        fail()
+            .expect_err("fails")
+            .downcast::<std::io::Error>()
+            .expect_err("not an I/O error");
+        Error::quiet_msg("expected failure");
+    });
+
+    // Only the event of `emit` itself
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {:?}", events);
+    };
+    assert_eq!(event.target, module_path!());
+}
+
+/// Tests that nothing is emitted for errors dropped while unwinding.
+#[test]
+fn test_no_warning_while_panicking() {
+    let events = collect(|| {
+        let result = panic::catch_unwind(|| {
+            let _err = anyhow!("failed before the panic");
+            panic!("unwinding");
+        });
+        result.expect_err("the closure panics");
+    });
+    assert!(events.is_empty(), "{events:?}");
+}
@@ -0,0 +1,170 @@
+#![cfg(feature = "opentelemetry")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Recording errors as exceptions on OpenTelemetry spans.
+
+use anyhow_tracing::{Error, anyhow};
+use opentelemetry::trace::{Status, TracerProvider as _};
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::SubscriberExt;
+
+/// Run `f` with a subscriber exporting spans to memory, and get the finished spans.
+fn export_spans(f: impl FnOnce()) -> Vec<SpanData> {
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = Registry::default()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("tests")));
+    tracing::subscriber::with_default(subscriber, f);
+    exporter
+        .get_finished_spans()
+        .expect("exporter not shut down")
+}
+
+/// Get the attributes of the first event of a span as text.
+fn attributes(span: &SpanData) -> Vec<(String, String)> {
+    span.events
+        .first()
+        .expect("event exists")
+        .attributes
+        .iter()
+        .map(|kv| (kv.key.to_string(), kv.value.to_string()))
+        .collect()
+}
+
+fn pairs(fields: &[(&str, &str)]) -> Vec<(String, String)> {
+    fields
+        .iter()
+        .map(|(key, value)| ((*key).to_owned(), (*value).to_owned()))
+        .collect()
+}
+
+/// Tests that an error is recorded as an exception event on the current span.
+#[test]
+fn test_record_exception() {
+    let spans = export_spans(|| {
+        let span = tracing::info_span!("sync");
+        let _guard = span.enter();
+        // Created quietly, as `auto-trace-context` would add fields to it otherwise
+        Error::quiet_msg("connection refused")
+            .with_field("host", "db-3")
+            .with_field("port", 5432)
+            .context("failed to sync")
+            .record_exception();
+    });
+
+    let [span] = spans.as_slice() else {
+        panic!("expected one span, got {spans:?}");
+    };
+    assert_eq!(span.name, "sync");
+    assert_eq!(
+        span.status,
+        Status::error("failed to sync: connection refused")
+    );
+    let event = span.events.first().expect("event exists");
+    assert_eq!(event.name, "exception");
+    assert_eq!(
+        attributes(span),
+        pairs(&[
+            ("exception.message", "failed to sync: connection refused"),
+            ("exception.type", "anyhow_tracing::Error"),
+            ("host", "db-3"),
+            ("port", "5432"),
+        ])
+    );
+}
+
+/// Tests that the code of an error is recorded as the type of the exception.
+#[test]
+fn test_record_exception_code() {
+    let spans = export_spans(|| {
+        let span = tracing::info_span!("sync");
+        let _guard = span.enter();
+        Error::quiet_msg("connection refused")
+            .with_code("E_CONN")
+            .record_exception();
+    });
+
+    let [span] = spans.as_slice() else {
+        panic!("expected one span, got {spans:?}");
+    };
+    assert_eq!(
+        attributes(span),
+        pairs(&[
+            ("exception.message", "connection refused"),
+            ("exception.type", "E_CONN"),
+        ])
+    );
+}
+
+/// Tests that nothing is recorded outside of any span, or without an OpenTelemetry
+/// layer.
+#[test]
+fn test_record_exception_without_span() {
+    let spans = export_spans(|| anyhow!("connection refused").record_exception());
+    assert!(spans.is_empty());
+
+    let span = tracing::info_span!("sync");
+    let _guard = span.enter();
+    anyhow!("connection refused").record_exception();
+}
+
+/// Tests that the trace and span IDs of the current span are attached in hex.
+#[test]
+fn test_with_trace_context() {
+    let mut err = None;
+    let spans = export_spans(|| {
+        let span = tracing::info_span!("sync");
+        let _guard = span.enter();
+        err = Some(Error::quiet_msg("connection refused").with_trace_context());
+    });
+
+    let [span] = spans.as_slice() else {
+        panic!("expected one span, got {spans:?}");
+    };
+    let err = err.expect("the error was created");
+    let fields: Vec<_> = err.fields().iter().collect();
+    let [("trace_id", trace_id), ("span_id", span_id)] = fields.as_slice() else {
+        panic!("expected the trace context, got {:?}", err.fields());
+    };
+    assert_eq!(trace_id.len(), 32);
+    assert_eq!(span_id.len(), 16);
+    assert!(
+        trace_id
+            .chars()
+            .chain(span_id.chars())
+            .all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+    );
+    assert_eq!(**trace_id, span.span_context.trace_id().to_string());
+    assert_eq!(**span_id, span.span_context.span_id().to_string());
+}
+
+/// Tests that nothing is attached outside of any span, or without an OpenTelemetry
+/// layer.
+#[test]
+fn test_with_trace_context_without_span() {
+    export_spans(|| {
+        let err = Error::quiet_msg("connection refused").with_trace_context();
+        assert!(err.fields().is_empty());
+    });
+
+    let span = tracing::info_span!("sync");
+    let _guard = span.enter();
+    let err = Error::quiet_msg("connection refused").with_trace_context();
+    assert!(err.fields().is_empty());
+}
+
+/// Tests that the macros attach the trace context.
+#[cfg(feature = "auto-trace-context")]
+#[test]
+fn test_auto_trace_context() {
+    export_spans(|| {
+        let span = tracing::info_span!("sync");
+        let _guard = span.enter();
+        let err = anyhow!(host = "db-3", "connection refused");
+        let keys: Vec<_> = err.fields().iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["host", "trace_id", "span_id"]);
+    });
+}
@@ -0,0 +1,64 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Reading fields back out of rendered errors.
+
+use anyhow_tracing::{Error, parse_fields};
+use proptest::prelude::*;
+
+/// Values that are easily mistaken for the field syntax.
+const NASTY_VALUES: &[&str] = &[
+    "",
+    "[10, 20]",
+    "[[nested], [brackets]]",
+    r#"{"user": {"id": 7, "roles": ["admin", "ops"]}}"#,
+    r"C:\Users\svc\AppData\Local\Temp\",
+    r"\\server\share",
+    "key=value, other=thing]",
+    "trailing backslash \\",
+    "line\nbreak\ttab\rreturn",
+    "bell\u{7} and \u{9b}csi",
+    "]",
+    ", ",
+    "=",
+];
+
+fn rendered_fields(values: &[String]) -> (Error, Vec<(String, String)>) {
+    const KEYS: [&str; 4] = ["first", "second.key", "third_key", "fourth-key"];
+
+    let mut err = Error::msg("message with [brackets] and key=value, pairs");
+    let mut expected = Vec::new();
+    for (key, value) in KEYS.iter().cycle().zip(values) {
+        err.add_field(key, value);
+        expected.push(((*key).to_owned(), value.clone()));
+    }
+    (err, expected)
+}
+
+/// Tests that every nasty value survives rendering and parsing unchanged.
+#[test]
+fn test_nasty_values_round_trip() {
+    for value in NASTY_VALUES {
+        let (err, expected) = rendered_fields(&[(*value).to_owned(), "plain".to_owned()]);
+        let line = err.to_string();
+        assert_eq!(parse_fields(&line), expected, "line: {line}");
+        assert_eq!(err.get_field("first"), Some(*value));
+    }
+}
+
+/// Tests that a line without fields parses to no fields.
+#[test]
+fn test_parse_without_fields() {
+    assert!(parse_fields("plain failure").is_empty());
+    assert!(parse_fields("failure in section [server]").is_empty());
+    assert!(parse_fields("").is_empty());
+}
+
+proptest! {
+    /// Tests that arbitrary values round trip through the rendered output.
+    #[test]
+    fn test_arbitrary_values_round_trip(values in prop::collection::vec(any::<String>(), 1..6)) {
+        let (err, expected) = rendered_fields(&values);
+        let line = err.to_string();
+        prop_assert_eq!(parse_fields(&line), expected, "line: {}", line);
+    }
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "poem")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Answering poem requests with errors.
+
+use anyhow_tracing::{Context, HttpError, Result, anyhow};
+use poem::http::StatusCode;
+use poem::test::TestClient;
+use poem::web::{Json, Path};
+use poem::{Route, get, handler};
+use serde_json::{Value, json};
+
+/// Load a user, failing with a 404 for unknown users.
+fn load_user(id: u64) -> Result<String> {
+    if id == 1 {
+        return Ok("ada".to_owned());
+    }
+    Err(anyhow!(user_id = id, table = "users", "no row returned")
+        .with_status(StatusCode::NOT_FOUND)
+        .with_code("USER_NOT_FOUND")
+        .with_public_message("user not found")
+        .with_field("request_id", "req-7"))
+}
+
+#[handler]
+fn get_user(Path(id): Path<u64>) -> Result<Json<Value>> {
+    let name = load_user(id)?;
+    Ok(Json(json!({ "id": id, "name": name })))
+}
+
+#[handler]
+fn sync() -> Result<Json<Value>> {
+    let result: std::result::Result<(), std::io::Error> =
+        Err(std::io::Error::other("connection refused"));
+    result
+        .context("failed to reach the database")
+        .with_field("host", "db-3")?;
+    Ok(Json(json!({ "synced": true })))
+}
+
+/// Send a GET request to `uri`, returning the status and the JSON body.
+async fn call(uri: &str) -> (StatusCode, Value) {
+    anyhow_tracing::response::set_public_fields(&["request_id"]).ok();
+    let app = Route::new()
+        .at("/users/:id", get(get_user))
+        .at("/sync", get(sync));
+    let response = TestClient::new(app).get(uri).send().await.0;
+    let status = response.status();
+    let body = response
+        .into_body()
+        .into_json()
+        .await
+        .expect("the body is JSON");
+    (status, body)
+}
+
+/// Tests a successful request, which is not affected.
+#[tokio::test]
+async fn test_ok() {
+    let (status, body) = call("/users/1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "id": 1, "name": "ada" }));
+}
+
+/// Tests an error mapped to a 404, whose internal fields and messages don't leak.
+#[tokio::test]
+async fn test_not_found() {
+    let (status, body) = call("/users/7").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        body,
+        json!({
+            "message": "user not found",
+            "code": "USER_NOT_FOUND",
+            "fields": { "request_id": "req-7" },
+        })
+    );
+}
+
+/// Tests an error without a status or public message, answered with a 500 and the
+/// reason phrase.
+#[tokio::test]
+async fn test_default_internal_server_error() {
+    let (status, body) = call("/sync").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body, json!({ "message": "Internal Server Error" }));
+}
+
+/// Tests that the `HttpError` can be downcast from a poem error.
+#[test]
+fn test_downcast() {
+    let error: poem::Error = load_user(7).unwrap_err().into();
+    assert_eq!(error.status(), StatusCode::NOT_FOUND);
+    let http = error
+        .downcast_ref::<HttpError>()
+        .expect("the error is an HttpError");
+    assert_eq!(http.error().code(), Some("USER_NOT_FOUND"));
+}
+
+/// Tests that the error is emitted with all of its details when the response is built.
+#[cfg(feature = "test-util")]
+#[tokio::test]
+async fn test_error_is_emitted() {
+    use anyhow_tracing::Level;
+    use anyhow_tracing::test::capture_future;
+
+    let events = capture_future(async {
+        call("/users/7").await;
+        call("/sync").await;
+    })
+    .await;
+
+    let [not_found, failed] = events.as_slice() else {
+        panic!("expected two events, got {:?}", events);
+    };
+    assert_eq!(not_found.level(), Level::Warn);
+    assert_eq!(not_found.field("table"), Some("users"));
+    assert_eq!(failed.level(), Level::Error);
+    assert_eq!(
+        failed.chain(),
+        "failed to reach the database: connection refused"
+    );
+    assert_eq!(failed.field("host"), Some("db-3"));
+}
@@ -0,0 +1,110 @@
+#![cfg(all(feature = "http", feature = "json"))]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Converting errors into RFC 7807 problem details.
+
+use anyhow_tracing::anyhow;
+use http::StatusCode;
+use serde_json::json;
+
+/// Tests the first example of the RFC, with an instance and extension members.
+#[test]
+fn test_rfc_out_of_credit_example() {
+    let err = anyhow!("Your current balance is 30, but that costs 50.")
+        .with_public_message("You do not have enough credit.")
+        .with_status(StatusCode::FORBIDDEN)
+        .with_field_json("balance", json!(30))
+        .with_field_json("accounts", json!(["/account/12345", "/account/67890"]));
+    let problem = err
+        .to_problem_details()
+        .with_type("https://example.com/probs/out-of-credit")
+        .with_instance("/account/12345/msgs/abc");
+
+    let json = serde_json::to_string(&problem).expect("problem details serialize");
+    assert_eq!(
+        json,
+        concat!(
+            r#"{"type":"https://example.com/probs/out-of-credit","#,
+            r#""title":"You do not have enough credit.","#,
+            r#""status":403,"#,
+            r#""detail":"Your current balance is 30, but that costs 50.","#,
+            r#""instance":"/account/12345/msgs/abc","#,
+            r#""balance":30,"#,
+            r#""accounts":["/account/12345","/account/67890"]}"#,
+        )
+    );
+}
+
+/// Tests the validation example of the RFC, with the status given as a parameter.
+#[test]
+fn test_rfc_invalid_params_example() {
+    let invalid_params = json!([
+        { "name": "age", "reason": "must be a positive integer" },
+        { "name": "color", "reason": "must be 'green', 'red' or 'blue'" },
+    ]);
+    let err = anyhow!("request validation failed")
+        .with_public_message("Your request parameters didn't validate.")
+        .with_field_json("invalid-params", invalid_params.clone());
+    let problem = err
+        .to_problem_details()
+        .with_type("https://example.net/validation-error")
+        .with_status(StatusCode::BAD_REQUEST);
+
+    assert_eq!(
+        serde_json::to_value(&problem).expect("problem details serialize"),
+        json!({
+            "type": "https://example.net/validation-error",
+            "title": "Your request parameters didn't validate.",
+            "status": 400,
+            "detail": "request validation failed",
+            "invalid-params": invalid_params,
+        })
+    );
+}
+
+/// Tests the defaults for an error without a public message or a status.
+#[test]
+fn test_defaults() {
+    let err = anyhow!(host = "db-3", "connection refused").context("failed to sync");
+    assert_eq!(
+        serde_json::to_value(err.to_problem_details()).expect("problem details serialize"),
+        json!({
+            "type": "about:blank",
+            "title": "failed to sync",
+            "status": 500,
+            "detail": "failed to sync: connection refused",
+            "host": "db-3",
+        })
+    );
+}
+
+/// Tests that only allowed fields are kept, and that fields can't replace the members
+/// defined by the RFC.
+#[test]
+fn test_allow_extensions() {
+    let err = anyhow!(user_id = 42, sql = "SELECT 1", "query failed")
+        .with_field("status", "degraded")
+        .with_field("retry_after", 30)
+        .with_field("retry_after", 60)
+        .with_status(StatusCode::SERVICE_UNAVAILABLE);
+
+    let problem = err.to_problem_details();
+    let keys: Vec<&str> = problem
+        .extensions
+        .iter()
+        .map(|(key, _)| key.as_str())
+        .collect();
+    assert_eq!(keys, ["user_id", "sql", "retry_after"]);
+
+    let problem = problem.allow_extensions(&["retry_after", "status"]);
+    assert_eq!(
+        serde_json::to_value(&problem).expect("problem details serialize"),
+        json!({
+            "type": "about:blank",
+            "title": "query failed",
+            "status": 503,
+            "detail": "query failed",
+            "retry_after": "60",
+        })
+    );
+}
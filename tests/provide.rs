@@ -0,0 +1,36 @@
+#![cfg(anyhow_tracing_nightly)]
+#![feature(error_generic_member_access)]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+use std::error::{Error as StdError, request_ref};
+
+use anyhow_tracing::{Error, Fields, anyhow};
+
+/// Tests that the fields of an error can be recovered after it has been erased
+/// into a trait object.
+#[test]
+fn test_request_fields_from_dyn_error() {
+    let err: Error = anyhow!(user_id = "42", attempt = 3, "login failed");
+    let boxed: Box<dyn StdError + Send + Sync> = Box::new(err.context("handler failed"));
+
+    let fields = request_ref::<Fields>(&*boxed).expect("fields should be provided");
+    assert_eq!(fields.len(), 2);
+    assert_eq!(fields.get(0), Some(("user_id", "42")));
+    assert_eq!(fields.get(1), Some(("attempt", "3")));
+    let collected: Vec<_> = fields.iter().collect();
+    assert_eq!(collected, [("user_id", "42"), ("attempt", "3")]);
+}
+
+/// Tests that no backtrace is provided when none was captured.
+#[test]
+fn test_request_backtrace_when_disabled() {
+    let err = Error::msg("no backtrace");
+    let dyn_err: &dyn StdError = &err;
+
+    if std::env::var_os("RUST_BACKTRACE").is_none()
+        && std::env::var_os("RUST_LIB_BACKTRACE").is_none()
+    {
+        assert!(request_ref::<std::backtrace::Backtrace>(dyn_err).is_none());
+    }
+    assert!(request_ref::<Fields>(dyn_err).is_some());
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "pyo3")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Raising errors as Python exceptions.
+
+use anyhow_tracing::{Context, Result, anyhow};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyModule};
+
+/// Load a user, failing with two layers and fields.
+fn load_user(id: u64) -> Result<String> {
+    Err(anyhow!(user_id = id, table = "users", "no row returned"))
+        .context("failed to load the user")
+        .with_field("user_id", id.saturating_add(1))
+        .map_err(|err| err.with_code("USER_NOT_FOUND"))
+}
+
+/// Run the Python `code` with the exception raised for the error of `load_user(7)` as
+/// `e` and the registered module as `core`.
+fn run_with_exception(code: &std::ffi::CStr) {
+    Python::attach(|py| {
+        let module = PyModule::new(py, "core").expect("the module is created");
+        anyhow_tracing::python::register(&module).expect("the exception is registered");
+        let err = PyErr::from(load_user(7).expect_err("the user is unknown"));
+
+        let locals = PyDict::new(py);
+        locals.set_item("e", err.value(py)).expect("`e` is set");
+        locals.set_item("core", module).expect("`core` is set");
+        py.run(code, None, Some(&locals))
+            .unwrap_or_else(|err| panic!("the Python assertions failed: {err}"));
+    });
+}
+
+/// Tests the type and message of the exception.
+#[test]
+fn test_exception_type() {
+    run_with_exception(
+        cr#"
+assert isinstance(e, core.AnyhowTracingError)
+assert isinstance(e, Exception)
+assert type(e).__name__ == "AnyhowTracingError"
+assert e.args[0] == "failed to load the user: no row returned", e.args
+assert str(e) == e.args[0]
+"#,
+    );
+}
+
+/// Tests the `fields`, `code` and `chain` attributes.
+#[test]
+fn test_attributes() {
+    run_with_exception(
+        cr#"
+assert e.fields == {"user_id": "8", "table": "users"}, e.fields
+assert e.code == "USER_NOT_FOUND", e.code
+assert e.chain == ["failed to load the user", "no row returned"], e.chain
+"#,
+    );
+}
+
+/// Tests that the exception is raised from an exported function and can be caught.
+#[test]
+fn test_raised_from_function() {
+    Python::attach(|py| {
+        let module = PyModule::new(py, "core").expect("the module is created");
+        anyhow_tracing::python::register(&module).expect("the exception is registered");
+        let failing = pyo3::types::PyCFunction::new_closure(py, None, None, |_, _| {
+            anyhow_tracing::Result::<()>::Err(anyhow!(attempt = 3, "timed out"))
+                .map_err(PyErr::from)
+        })
+        .expect("the function is created");
+
+        let locals = PyDict::new(py);
+        locals
+            .set_item("failing", failing)
+            .expect("`failing` is set");
+        locals.set_item("core", module).expect("`core` is set");
+        py.run(
+            cr#"
+try:
+    failing()
+except core.AnyhowTracingError as e:
+    assert e.fields == {"attempt": "3"}, e.fields
+    assert e.code is None
+else:
+    raise AssertionError("nothing was raised")
+"#,
+            None,
+            Some(&locals),
+        )
+        .unwrap_or_else(|err| panic!("the Python assertions failed: {err}"));
+    });
+}
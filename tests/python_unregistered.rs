@@ -0,0 +1,20 @@
+#![cfg(feature = "pyo3")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Raising errors as Python exceptions before the exception type is registered, in a
+//! separate binary as registering is global.
+
+use anyhow_tracing::anyhow;
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::prelude::*;
+
+/// Tests that errors are raised as a `RuntimeError` with the same message.
+#[test]
+fn test_runtime_error() {
+    Python::attach(|py| {
+        let err = PyErr::from(anyhow!(user_id = 7, "no row returned").context("failed to load"));
+        assert!(err.is_instance_of::<PyRuntimeError>(py));
+        assert_eq!(err.value(py).to_string(), "failed to load: no row returned");
+        assert!(!err.value(py).hasattr("fields").unwrap());
+    });
+}
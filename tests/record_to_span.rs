@@ -0,0 +1,101 @@
+#![cfg(feature = "tracing")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Recording errors on a chosen span.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use anyhow_tracing::anyhow;
+use tracing::Subscriber;
+use tracing::field::{Empty, Field, Visit};
+use tracing::span::{Id, Record};
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+/// A layer collecting the values recorded on spans after they were created, by the
+/// name of the span.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<(String, String, String)>>>);
+
+impl Capture {
+    fn take_records(&self) -> Vec<(String, String, String)> {
+        std::mem::take(&mut *self.0.lock().expect("not poisoned"))
+    }
+}
+
+struct Fields<'a>(&'a str, &'a mut Vec<(String, String, String)>);
+
+impl Visit for Fields<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.1
+            .push((self.0.to_owned(), field.name().to_owned(), value.to_owned()));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.1.push((
+            self.0.to_owned(),
+            field.name().to_owned(),
+            format!("{:?}", value),
+        ));
+    }
+}
+
+impl<S> Layer<S> for Capture
+where
+    S: Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+{
+    fn on_record(&self, span: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let span = ctx.span(span).expect("span exists");
+        let mut records = self.0.lock().expect("not poisoned");
+        values.record(&mut Fields(span.name(), &mut records));
+    }
+}
+
+fn triples(records: &[(&str, &str, &str)]) -> Vec<(String, String, String)> {
+    records
+        .iter()
+        .map(|(span, key, value)| ((*span).to_owned(), (*key).to_owned(), (*value).to_owned()))
+        .collect()
+}
+
+/// Tests that an error is recorded on the given span rather than the current one.
+#[test]
+fn test_record_to_span() {
+    let capture = Capture::default();
+    let subscriber = Registry::default().with(capture.clone());
+    tracing::subscriber::with_default(subscriber, || {
+        let request =
+            tracing::info_span!("request", error = Empty, error.fields = Empty, host = Empty);
+        let task = tracing::info_span!("task", error = Empty);
+        let _guard = task.enter();
+        anyhow!(host = "db-3", port = 5432, "connection refused")
+            .context("failed to sync")
+            .record_to_span(&request);
+    });
+
+    assert_eq!(
+        capture.take_records(),
+        triples(&[
+            ("request", "error", "failed to sync: connection refused"),
+            ("request", "host", "db-3"),
+            ("request", "error.fields", "host=db-3 port=5432"),
+        ])
+    );
+}
+
+/// Tests that `error.fields` is not recorded for an error without fields.
+#[test]
+fn test_record_to_span_without_fields() {
+    let capture = Capture::default();
+    let subscriber = Registry::default().with(capture.clone());
+    tracing::subscriber::with_default(subscriber, || {
+        let request = tracing::info_span!("request", error = Empty, error.fields = Empty);
+        anyhow!("connection refused").record_to_span(&request);
+    });
+
+    assert_eq!(
+        capture.take_records(),
+        triples(&[("request", "error", "connection refused")])
+    );
+}
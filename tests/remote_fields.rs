@@ -0,0 +1,98 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Memory kept by errors rebuilt from other processes, in its own test binary because
+//! it replaces the global allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use anyhow_tracing::{Error, ErrorData};
+
+thread_local! {
+    /// The number of bytes allocated and not freed by the current thread.
+    static LIVE_BYTES: Cell<isize> = const { Cell::new(0) };
+}
+
+/// The system allocator, counting the live bytes of each thread.
+struct CountingAllocator;
+
+/// Add `delta` to the live bytes of the current thread.
+fn count(delta: isize) {
+    LIVE_BYTES.with(|live| live.set(live.get().saturating_add(delta)));
+}
+
+/// Get the size of `layout` as a signed number of bytes.
+fn size(layout: Layout) -> isize {
+    isize::try_from(layout.size()).unwrap_or(isize::MAX)
+}
+
+// SAFETY: every call is forwarded to the system allocator.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count(size(layout));
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::alloc`.
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        count(size(layout).saturating_neg());
+        // SAFETY: the caller upholds the contract of `GlobalAlloc::dealloc`.
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Count the bytes still allocated after calling `rebuild` with each number up to
+/// `count`, after calling it once to warm up.
+fn retained_bytes(count: usize, mut rebuild: impl FnMut(usize)) -> isize {
+    rebuild(usize::MAX);
+    let before = LIVE_BYTES.with(Cell::get);
+    for i in 0..count {
+        rebuild(i);
+    }
+    LIVE_BYTES.with(Cell::get).saturating_sub(before)
+}
+
+/// Tests that rebuilding errors from statuses with many different field keys keeps no
+/// memory once the errors are dropped.
+#[cfg(feature = "tonic")]
+#[test]
+fn test_status_keys_are_not_kept() {
+    use tonic::metadata::{AsciiMetadataKey, MetadataMap};
+    use tonic::{Code, Status};
+
+    let retained = retained_bytes(10_000, |i| {
+        let mut metadata = MetadataMap::new();
+        let key = format!("x-error-field-key_{i}")
+            .parse::<AsciiMetadataKey>()
+            .expect("the key is valid metadata");
+        metadata.insert(key, i.into());
+        let status = Status::with_metadata(Code::NotFound, "no row returned", metadata);
+        let error = Error::from_status(&status);
+        assert_eq!(
+            error.get_field("grpc.fields"),
+            Some(format!("key_{i}={i}").as_str())
+        );
+    });
+    assert!(retained < 1024, "{retained} bytes were kept");
+}
+
+/// Tests that rebuilding errors from data with many different field keys keeps no
+/// memory once the errors are dropped.
+#[test]
+fn test_data_keys_are_not_kept() {
+    let retained = retained_bytes(10_000, |i| {
+        let error = Error::from_data(ErrorData {
+            message: "no row returned".to_owned(),
+            fields: vec![(format!("key_{i}"), i.to_string())],
+            ..ErrorData::default()
+        });
+        assert_eq!(
+            error.get_field("remote.fields"),
+            Some(format!("key_{i}={i}").as_str())
+        );
+    });
+    assert!(retained < 1024, "{retained} bytes were kept");
+}
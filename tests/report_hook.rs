@@ -0,0 +1,27 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Report hooks, in their own test binary because the hook is global.
+
+use anyhow_tracing::{Error, default_report_hook, set_report_hook};
+use insta::assert_snapshot;
+
+/// Tests that an installed hook renders the `Debug` output, and that it can only be set once.
+#[test]
+fn test_report_hook() {
+    let installed = set_report_hook(Box::new(|error, f| {
+        default_report_hook(error, f)?;
+        write!(f, "\nSee the logs at /var/log/app.log")
+    }));
+    assert!(installed.is_ok());
+
+    let err = Error::msg("connection refused")
+        .with_field("host", "db-3")
+        .context("failed to sync")
+        .with_field("attempt", 2);
+    assert_snapshot!("report_hook_debug", format!("{:?}", err));
+
+    // Display is not affected
+    assert_eq!(err.to_string(), "failed to sync [host=db-3, attempt=2]");
+
+    assert!(set_report_hook(Box::new(|_, f| f.write_str("other"))).is_err());
+}
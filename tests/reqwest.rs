@@ -0,0 +1,112 @@
+#![cfg(feature = "reqwest")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Adding request fields to reqwest errors.
+
+use std::time::Duration;
+
+use anyhow_tracing::{Error, HttpResultExt};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Start a server answering every request with `response`.
+async fn server(response: ResponseTemplate) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(response)
+        .mount(&server)
+        .await;
+    server
+}
+
+/// Tests the URL and status of an error made from a 500 response, which is not
+/// retryable, and that the reqwest error can be downcast.
+#[tokio::test]
+async fn test_error_status() {
+    let server = server(ResponseTemplate::new(500)).await;
+    let url = format!("{}/invoices/7", server.uri());
+    let error = reqwest::get(&url)
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .expect_err("the server fails");
+
+    let err = Error::from_reqwest(error);
+    assert_eq!(
+        err.fields().iter().collect::<Vec<_>>(),
+        [("http.url", url.as_str()), ("http.status", "500"),]
+    );
+    let reqwest_err = err
+        .downcast_ref::<reqwest::Error>()
+        .expect("the error is kept");
+    assert!(reqwest_err.is_status());
+}
+
+/// Tests that the credentials in the URL are not recorded.
+#[tokio::test]
+async fn test_url_without_credentials() {
+    let server = server(ResponseTemplate::new(503)).await;
+    let url = server.uri().replace("http://", "http://admin:hunter2@");
+    let error = reqwest::get(&url)
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .expect_err("the server fails");
+
+    let err = Error::from_reqwest(error);
+    assert_eq!(
+        err.get_field("http.url"),
+        Some(&*format!("{}/", server.uri()))
+    );
+}
+
+/// Tests that a connection failure is classified and retryable.
+#[tokio::test]
+async fn test_connect_error() {
+    let error = reqwest::get("http://127.0.0.1:1/")
+        .await
+        .expect_err("nothing listens on the port");
+
+    let err = Error::from_reqwest(error);
+    assert_eq!(err.get_field("http.url"), Some("http://127.0.0.1:1/"));
+    assert_eq!(err.get_field("http.connect"), Some("true"));
+    assert_eq!(err.get_field("http.timeout"), None);
+    assert_eq!(err.get_field("retryable"), Some("true"));
+}
+
+/// Tests that a timeout is classified and retryable.
+#[tokio::test]
+async fn test_timeout() {
+    let server = server(ResponseTemplate::new(200).set_delay(Duration::from_secs(5))).await;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_millis(50))
+        .build()
+        .expect("the client is valid");
+    let error = client
+        .get(server.uri())
+        .send()
+        .await
+        .expect_err("the request times out");
+
+    let err = Error::from_reqwest(error);
+    assert_eq!(err.get_field("http.timeout"), Some("true"));
+    assert_eq!(err.get_field("retryable"), Some("true"));
+}
+
+/// Tests that `http_context` adds the context on top of the request fields.
+#[tokio::test]
+async fn test_http_context() {
+    let server = server(ResponseTemplate::new(404)).await;
+    let err = reqwest::get(server.uri())
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .http_context("calling billing API")
+        .expect_err("the server fails");
+
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "calling billing API [http.url={}/, http.status=404]",
+            server.uri()
+        )
+    );
+    assert!(err.downcast_ref::<reqwest::Error>().is_some());
+}
@@ -0,0 +1,144 @@
+#![cfg(feature = "rocket")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Answering Rocket requests with errors.
+
+use anyhow_tracing::{Context, HttpError, Result, answered_error, anyhow};
+use http::StatusCode;
+use rocket::http::Status;
+use rocket::local::blocking::Client;
+use rocket::{Request, catch, catchers, get, routes};
+use serde_json::{Value, json};
+
+/// Load a user, failing with a 404 for unknown users.
+fn load_user(id: u64) -> Result<String> {
+    if id == 1 {
+        return Ok("ada".to_owned());
+    }
+    Err(anyhow!(user_id = id, table = "users", "no row returned")
+        .with_status(StatusCode::NOT_FOUND)
+        .with_code("USER_NOT_FOUND")
+        .with_public_message("user not found")
+        .with_field("request_id", "req-7"))
+}
+
+#[get("/users/<id>")]
+fn get_user(id: u64) -> std::result::Result<String, HttpError> {
+    let name = load_user(id)?;
+    Ok(json!({ "id": id, "name": name }).to_string())
+}
+
+#[get("/sync")]
+fn sync() -> std::result::Result<String, HttpError> {
+    let result: std::result::Result<(), std::io::Error> =
+        Err(std::io::Error::other("connection refused"));
+    result
+        .context("failed to reach the database")
+        .with_field("host", "db-3")?;
+    Ok(json!({ "synced": true }).to_string())
+}
+
+#[catch(404)]
+fn not_found(request: &Request<'_>) -> String {
+    answered_error(request).map_or_else(
+        || "no such page".to_owned(),
+        |error| format!("caught {}", error.code().unwrap_or("nothing")),
+    )
+}
+
+fn client() -> Client {
+    anyhow_tracing::response::set_public_fields(&["request_id"]).ok();
+    let rocket = rocket::build()
+        .mount("/", routes![get_user, sync])
+        .mount("/admin", routes![get_user])
+        .register("/admin", catchers![not_found]);
+    Client::tracked(rocket).expect("the rocket is valid")
+}
+
+/// Send a GET request to `uri`, returning the status and the body.
+fn call(uri: &str) -> (Status, String) {
+    let client = client();
+    let response = client.get(uri).dispatch();
+    let status = response.status();
+    (status, response.into_string().expect("the body is read"))
+}
+
+/// Send a GET request to `uri`, returning the status and the JSON body.
+fn call_json(uri: &str) -> (Status, Value) {
+    let (status, body) = call(uri);
+    (
+        status,
+        serde_json::from_str(&body).expect("the body is JSON"),
+    )
+}
+
+/// Tests a successful request, which is not affected.
+#[test]
+fn test_ok() {
+    let (status, body) = call_json("/users/1");
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body, json!({ "id": 1, "name": "ada" }));
+}
+
+/// Tests an error with a status, a public message, a code and a public field, whose
+/// internal fields and messages don't leak.
+#[test]
+fn test_error_with_status() {
+    let (status, body) = call_json("/users/7");
+    assert_eq!(status, Status::NotFound);
+    assert_eq!(
+        body,
+        json!({
+            "message": "user not found",
+            "code": "USER_NOT_FOUND",
+            "fields": { "request_id": "req-7" },
+        })
+    );
+}
+
+/// Tests an error without a status or public message, answered with a 500 and the
+/// reason phrase.
+#[test]
+fn test_error_defaults() {
+    let (status, body) = call_json("/sync");
+    assert_eq!(status, Status::InternalServerError);
+    assert_eq!(body, json!({ "message": "Internal Server Error" }));
+}
+
+/// Tests that a catcher registered for the status answers instead, with the error.
+#[test]
+fn test_error_in_catcher() {
+    let (status, body) = call("/admin/users/7");
+    assert_eq!(status, Status::NotFound);
+    assert_eq!(body, "caught USER_NOT_FOUND");
+
+    let (status, body) = call("/admin/missing");
+    assert_eq!(status, Status::NotFound);
+    assert_eq!(body, "no such page");
+}
+
+/// Tests that the error is emitted with all of its details when the response is built.
+#[cfg(feature = "test-util")]
+#[test]
+fn test_error_is_emitted() {
+    use anyhow_tracing::Level;
+    use anyhow_tracing::test::capture;
+
+    let events = capture(|| {
+        call("/users/7");
+        call("/sync");
+    });
+
+    let [not_found, failed] = events.as_slice() else {
+        panic!("expected two events, got {:?}", events);
+    };
+    assert_eq!(not_found.level(), Level::Warn);
+    assert_eq!(not_found.chain(), "no row returned");
+    assert_eq!(not_found.field("table"), Some("users"));
+    assert_eq!(failed.level(), Level::Error);
+    assert_eq!(
+        failed.chain(),
+        "failed to reach the database: connection refused"
+    );
+    assert_eq!(failed.field("host"), Some("db-3"));
+}
@@ -0,0 +1,105 @@
+#![cfg(feature = "test-util")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Emitting a sample of errors, and counting the ones left out.
+
+use anyhow_tracing::{
+    Error, Level, anyhow, flush_sampling_stats, set_sampling, set_sampling_seed, test,
+};
+
+/// Tests that errors are always emitted at rate 1 and never at rate 0.
+#[test]
+fn test_emit_sampled_bounds() {
+    let events = test::capture(|| {
+        for _ in 0..10 {
+            anyhow!("too many requests")
+                .with_code("BOUNDS")
+                .emit_sampled(1.0);
+            anyhow!("too many requests")
+                .with_code("BOUNDS")
+                .emit_sampled(0.0);
+        }
+    });
+
+    assert_eq!(events.len(), 10);
+    assert!(
+        events
+            .iter()
+            .all(|event| event.field("sample_rate") == Some("1"))
+    );
+}
+
+/// Tests that about `rate` of the errors are emitted, and that the same errors are
+/// emitted with the same seed.
+#[test]
+fn test_emit_sampled_seeded() {
+    let sample = || {
+        set_sampling_seed(42);
+        test::capture(|| {
+            for attempt in 0..1000 {
+                anyhow!(attempt = attempt, "too many requests")
+                    .with_code("SEEDED")
+                    .emit_sampled(0.1);
+            }
+        })
+    };
+
+    let events = sample();
+    assert!((50..150).contains(&events.len()), "{} events", events.len());
+    assert!(
+        events
+            .iter()
+            .all(|event| event.field("sample_rate") == Some("0.1"))
+    );
+    let attempts = |events: &[test::CapturedErrorEvent]| -> Vec<Option<String>> {
+        events
+            .iter()
+            .map(|event| event.field("attempt").map(ToOwned::to_owned))
+            .collect()
+    };
+    assert_eq!(attempts(&events), attempts(&sample()));
+}
+
+/// Tests that the rate set for a code applies to `emit`, and that the errors left out
+/// are counted until they are flushed.
+#[test]
+fn test_set_sampling_and_flush() {
+    set_sampling("RATE_LIMITED", 0.0);
+    let events = test::capture(|| {
+        for _ in 0..5 {
+            anyhow!("too many requests")
+                .with_code("RATE_LIMITED")
+                .emit();
+        }
+        Error::msg("disk full").emit_sampled(0.0);
+        // Other codes are not sampled
+        anyhow!("not found").with_code("NOT_FOUND").emit();
+    });
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {events:?}");
+    };
+    assert_eq!(event.message(), "not found");
+
+    // Other tests leave out errors with other codes, which are not checked here
+    let flush = || -> Vec<(String, String)> {
+        test::capture(flush_sampling_stats)
+            .iter()
+            .filter(|event| matches!(event.field("code"), Some("RATE_LIMITED" | "none")))
+            .map(|event| {
+                assert_eq!(event.level(), Level::Info);
+                assert_eq!(event.target(), "anyhow_tracing");
+                assert_eq!(event.message(), "errors left out by sampling");
+                let field = |key| event.field(key).unwrap_or_default().to_owned();
+                (field("code"), field("skipped_count"))
+            })
+            .collect()
+    };
+    assert_eq!(
+        flush(),
+        [
+            ("RATE_LIMITED".to_owned(), "5".to_owned()),
+            ("none".to_owned(), "1".to_owned()),
+        ]
+    );
+    assert!(flush().is_empty());
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "schemars")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! The JSON schemas describing the serialized shape of errors.
+
+use anyhow_tracing::{ErrorData, Level, anyhow};
+use serde_json::json;
+
+/// Compile the schema of `T`.
+fn validator<T: schemars::JsonSchema>() -> jsonschema::Validator {
+    let schema = serde_json::to_value(schemars::schema_for!(T)).expect("schemas serialize");
+    jsonschema::validator_for(&schema).expect("the schema is valid")
+}
+
+/// Tests that the data of an error with every member set matches the schema.
+#[test]
+fn test_error_data_matches_schema() {
+    let err = anyhow!(path = "/etc/app.toml", "permission denied")
+        .context("failed to read config")
+        .with_field("attempts", 3)
+        .with_code("CONFIG_UNREADABLE")
+        .with_level(Level::Warn)
+        .note("check the permissions of the config directory");
+    let data = serde_json::to_value(err.to_data()).expect("data serializes");
+
+    let validator = validator::<ErrorData>();
+    if let Err(error) = validator.validate(&data) {
+        panic!("{data} doesn't match the schema: {error}");
+    }
+    assert!(
+        validator.is_valid(
+            &serde_json::to_value(anyhow!("timed out").to_data()).expect("data serializes")
+        )
+    );
+}
+
+/// Tests that the schema describes the required and optional members.
+#[test]
+fn test_error_data_schema_shape() {
+    let validator = validator::<ErrorData>();
+    let minimal = json!({ "message": "timed out", "chain": ["timed out"], "fields": [] });
+    assert!(validator.is_valid(&minimal));
+
+    let mut wrong_level = minimal.clone();
+    wrong_level["level"] = json!("CRITICAL");
+    assert!(!validator.is_valid(&wrong_level));
+
+    let mut wrong_fields = minimal.clone();
+    wrong_fields["fields"] = json!({ "host": "db-3" });
+    assert!(!validator.is_valid(&wrong_fields));
+
+    let mut without_chain = minimal;
+    without_chain
+        .as_object_mut()
+        .expect("the data is an object")
+        .remove("chain");
+    assert!(!validator.is_valid(&without_chain));
+}
+
+/// Tests that problem details with extension members match their schema.
+#[cfg(all(feature = "http", feature = "json"))]
+#[test]
+fn test_problem_details_match_schema() {
+    let err = anyhow!(balance = 30, "insufficient balance")
+        .with_field_json("accounts", json!(["/account/12345"]))
+        .with_public_message("You do not have enough credit.");
+    let problem = err
+        .to_problem_details()
+        .with_instance("/account/12345/msgs/abc");
+    let value = serde_json::to_value(&problem).expect("problem details serialize");
+
+    let validator = validator::<anyhow_tracing::ProblemDetails>();
+    assert!(validator.is_valid(&value));
+    assert!(!validator.is_valid(&json!({ "type": "about:blank", "title": "failed" })));
+}
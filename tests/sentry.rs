@@ -0,0 +1,134 @@
+#![cfg(feature = "sentry")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Capturing errors as Sentry events.
+
+use anyhow_tracing::{Context, Level, anyhow, sentry_integration, set_sentry_tags};
+use sentry::protocol::{Context as SentryContext, Event, Value};
+use sentry::test::{with_captured_envelopes, with_captured_events_options};
+
+/// Set the fields sent as tags, the same for every test.
+fn set_tags() {
+    set_sentry_tags(&["region", "endpoint"]).ok();
+}
+
+/// Get the `fields` context of `event`.
+fn fields_context<'a>(event: &'a Event<'static>) -> &'a sentry::protocol::Map<String, Value> {
+    match event.contexts.get("fields") {
+        Some(SentryContext::Other(fields)) => fields,
+        other => panic!("expected the fields context, got {other:?}"),
+    }
+}
+
+/// Tests the tags, contexts, exceptions, level and fingerprint of a captured envelope.
+#[test]
+fn test_capture_sentry() {
+    set_tags();
+    let mut id = None;
+    let envelopes = with_captured_envelopes(|| {
+        let result: std::result::Result<(), std::io::Error> =
+            Err(std::io::Error::other("connection refused"));
+        let err = result
+            .context("failed to load the user")
+            .with_field("region", "eu-west-1")
+            .with_field("user_id", 7)
+            .unwrap_err()
+            .with_code("USER_LOAD_FAILED")
+            .with_level(Level::Warn);
+        id = Some(err.capture_sentry());
+    });
+
+    let [envelope] = envelopes.as_slice() else {
+        panic!("expected one envelope, got {}", envelopes.len());
+    };
+    let event = envelope.event().expect("the envelope holds an event");
+    assert_eq!(Some(event.event_id), id);
+    assert_eq!(event.level, sentry::Level::Warning);
+    assert_eq!(*event.fingerprint, ["USER_LOAD_FAILED"]);
+
+    let exceptions: Vec<_> = event
+        .exception
+        .iter()
+        .map(|exception| (exception.ty.as_str(), exception.value.as_deref()))
+        .collect();
+    assert_eq!(
+        exceptions,
+        [
+            ("Custom", Some("connection refused")),
+            ("USER_LOAD_FAILED", Some("failed to load the user")),
+        ]
+    );
+
+    assert_eq!(
+        event.tags.get("region").map(String::as_str),
+        Some("eu-west-1")
+    );
+    assert!(!event.tags.contains_key("user_id"));
+    let fields = fields_context(event);
+    assert_eq!(fields.get("user_id"), Some(&Value::from("7")));
+    assert!(!fields.contains_key("region"));
+}
+
+/// Tests the level and fingerprint of an error without a level or code.
+#[test]
+fn test_defaults() {
+    let events = sentry::test::with_captured_events(|| {
+        anyhow!("disk full")
+            .context("failed to write the report")
+            .capture_sentry();
+    });
+    let [event] = events.as_slice() else {
+        panic!("expected one event, got {}", events.len());
+    };
+    assert_eq!(event.level, sentry::Level::Error);
+    assert_eq!(
+        *event.fingerprint,
+        ["failed to write the report: disk full"]
+    );
+    assert!(event.tags.is_empty());
+    assert!(!event.contexts.contains_key("fields"));
+}
+
+/// Tests that JSON fields keep their values in the `fields` context.
+#[cfg(feature = "json")]
+#[test]
+fn test_json_fields() {
+    let events = sentry::test::with_captured_events(|| {
+        anyhow!("rate limited")
+            .with_field_json("limits", serde_json::json!({ "minute": 60 }))
+            .capture_sentry();
+    });
+    let fields = fields_context(&events[0]);
+    assert_eq!(
+        fields.get("limits"),
+        Some(&serde_json::json!({ "minute": 60 }))
+    );
+}
+
+/// Tests that the integration adds the fields of errors captured with
+/// `sentry::capture_error`, and leaves other events alone.
+#[test]
+fn test_integration() {
+    set_tags();
+    let options = sentry::ClientOptions::new().add_integration(sentry_integration());
+    let events = with_captured_events_options(
+        || {
+            let err = anyhow!(endpoint = "/users", user_id = 7, "no row returned");
+            sentry::capture_error(&err);
+            err.ack();
+            sentry::capture_error(&std::io::Error::other("plain [error]"));
+        },
+        options,
+    );
+
+    let [ours, plain] = events.as_slice() else {
+        panic!("expected two events, got {}", events.len());
+    };
+    assert_eq!(
+        ours.tags.get("endpoint").map(String::as_str),
+        Some("/users")
+    );
+    assert_eq!(fields_context(ours).get("user_id"), Some(&Value::from("7")));
+    assert!(plain.tags.is_empty());
+    assert!(!plain.contexts.contains_key("fields"));
+}
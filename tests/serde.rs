@@ -0,0 +1,74 @@
+#![cfg(feature = "serde")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Serializing errors with `serde`.
+
+use anyhow_tracing::{Error, anyhow};
+use serde_json::{Value, json};
+
+/// Serialize `error` to JSON and parse it back, without the backtrace, which is only
+/// captured when `RUST_BACKTRACE` is set.
+fn round_trip(error: &Error) -> Value {
+    let json = serde_json::to_string(error).expect("errors serialize");
+    let mut value: Value = serde_json::from_str(&json).expect("the output is valid JSON");
+    value
+        .as_object_mut()
+        .expect("errors serialize as maps")
+        .remove("backtrace");
+    value
+}
+
+/// Tests an error with a three-deep chain and display and debug fields.
+#[test]
+fn test_serialize_chain_and_fields() {
+    let err = anyhow!(path = "/etc/app.toml", "permission denied")
+        .context("failed to read config")
+        .with_field_debug("attempts", vec![1, 2])
+        .context("failed to start")
+        .with_field("retryable", false)
+        .with_code("CONFIG_UNREADABLE");
+
+    assert_eq!(
+        round_trip(&err),
+        json!({
+            "message": "failed to start",
+            "chain": ["failed to start", "failed to read config", "permission denied"],
+            "fields": {
+                "path": "/etc/app.toml",
+                "attempts": "[1, 2]",
+                "retryable": "false",
+            },
+            "code": "CONFIG_UNREADABLE",
+        })
+    );
+}
+
+/// Tests that the fields and the code are left out when the error has none.
+#[test]
+fn test_serialize_without_optional_sections() {
+    assert_eq!(
+        round_trip(&Error::msg("disk full")),
+        json!({
+            "message": "disk full",
+            "chain": ["disk full"],
+        })
+    );
+}
+
+/// Tests that the value added last is kept for a key added several times.
+#[test]
+fn test_serialize_duplicate_keys() {
+    let err = anyhow!(attempt = 1, host = "db-3", "connection refused")
+        .context("failed to sync")
+        .with_field("attempt", 2);
+
+    let json = serde_json::to_string(&err).expect("errors serialize");
+    let fields = json
+        .split_once(r#""fields":"#)
+        .map(|(_, fields)| fields)
+        .expect("the error has fields");
+    assert!(
+        fields.starts_with(r#"{"host":"db-3","attempt":"2"}"#),
+        "{fields}"
+    );
+}
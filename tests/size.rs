@@ -0,0 +1,14 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! The size of errors and of the results holding them.
+
+use anyhow_tracing::{Error, Result};
+
+/// Tests that an error is a single pointer like `anyhow::Error`, so that a result of
+/// `()` is no larger than the error.
+#[test]
+fn test_error_is_one_pointer() {
+    assert_eq!(size_of::<Error>(), size_of::<usize>());
+    assert_eq!(size_of::<Error>(), size_of::<anyhow::Error>());
+    assert_eq!(size_of::<Result<()>>(), size_of::<usize>());
+}
@@ -0,0 +1,39 @@
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Sorted fields, in their own test binary because the crate-level format is global.
+
+use anyhow_tracing::{Error, FieldFormat, set_field_format};
+
+/// Tests that an error built with its fields in two orders renders identically when
+/// fields are sorted, while `fields` keeps the insertion order.
+#[test]
+fn test_sorted_fields() {
+    set_field_format(FieldFormat::new().sort_fields(true)).expect("no format set yet");
+
+    let first = Error::msg("connection refused")
+        .with_field("port", 5432)
+        .with_field("host", "db-3")
+        .with_field("attempt", 1)
+        .with_field("attempt", 2)
+        .context("failed to query accounts");
+    let second = Error::msg("connection refused")
+        .with_field("attempt", 1)
+        .with_field("host", "db-3")
+        .with_field("attempt", 2)
+        .with_field("port", 5432)
+        .context("failed to query accounts");
+
+    assert_eq!(
+        first.to_string(),
+        "failed to query accounts [attempt=1, attempt=2, host=db-3, port=5432]"
+    );
+    assert_eq!(first.to_string(), second.to_string());
+    assert_eq!(format!("{:#}", first), format!("{:#}", second));
+    assert_eq!(format!("{:?}", first), format!("{:?}", second));
+    assert_eq!(first.to_logfmt(), second.to_logfmt());
+    assert_eq!(first.report().to_string(), second.report().to_string());
+
+    let keys = |err: &Error| err.fields().iter().map(|(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(keys(&first), ["port", "host", "attempt", "attempt"]);
+    assert_eq!(keys(&second), ["attempt", "host", "attempt", "port"]);
+}
@@ -0,0 +1,81 @@
+#![cfg(feature = "span-fields")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Attaching the fields of the current span to errors.
+
+use anyhow_tracing::{Error, FieldCaptureLayer};
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::SubscriberExt;
+
+fn keys(err: &Error) -> Vec<(&str, &str)> {
+    err.fields().iter().collect()
+}
+
+/// Tests attaching the fields of nested spans, including ones recorded later.
+#[test]
+fn test_in_current_span() {
+    let subscriber = Registry::default().with(FieldCaptureLayer::new());
+    tracing::subscriber::with_default(subscriber, || {
+        let request = tracing::info_span!("request", request_id = 42, tenant = "acme");
+        let _request = request.enter();
+        let query = tracing::info_span!("query", table = tracing::field::Empty);
+        query.record("table", "accounts");
+        let _query = query.enter();
+
+        let err = Error::quiet_msg("not found")
+            .with_field("id", 7)
+            .in_current_span();
+        assert_eq!(
+            keys(&err),
+            [
+                ("id", "7"),
+                ("span.name", "request"),
+                ("span.request_id", "42"),
+                ("span.tenant", "acme"),
+                ("span.name", "query"),
+                ("span.table", "accounts"),
+            ]
+        );
+    });
+}
+
+/// Tests that nothing is attached outside of a span or without the layer.
+#[test]
+fn test_in_current_span_noop() {
+    let subscriber = Registry::default().with(FieldCaptureLayer::new());
+    tracing::subscriber::with_default(subscriber, || {
+        let err = Error::quiet_msg("not found").in_current_span();
+        assert!(err.fields().is_empty());
+    });
+
+    tracing::subscriber::with_default(Registry::default(), || {
+        let span = tracing::info_span!("request", request_id = 42);
+        let _span = span.enter();
+        let err = Error::quiet_msg("not found").in_current_span();
+        assert!(err.fields().is_empty());
+    });
+
+    let err = Error::quiet_msg("not found").in_current_span();
+    assert!(err.fields().is_empty());
+}
+
+/// Tests that the macros attach the fields of the current span.
+#[cfg(feature = "auto-span-fields")]
+#[test]
+fn test_auto_span_fields() {
+    let subscriber = Registry::default().with(FieldCaptureLayer::new());
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("request", request_id = 42);
+        let _span = span.enter();
+
+        let err = anyhow_tracing::anyhow!(id = 7, "not found");
+        assert_eq!(
+            keys(&err),
+            [
+                ("id", "7"),
+                ("span.name", "request"),
+                ("span.request_id", "42")
+            ]
+        );
+    });
+}
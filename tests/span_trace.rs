@@ -0,0 +1,48 @@
+#![cfg(feature = "span-trace")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Capturing the spans an error is created in.
+
+use anyhow_tracing::Error;
+use tracing_error::ErrorLayer;
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::SubscriberExt;
+
+#[tracing::instrument]
+fn load_config(path: &str) -> Error {
+    parse_config()
+}
+
+#[tracing::instrument]
+fn parse_config() -> Error {
+    Error::msg("invalid syntax")
+}
+
+/// Tests that the spans of nested instrumented functions are captured and rendered.
+#[test]
+fn test_span_trace() {
+    let subscriber = Registry::default().with(ErrorLayer::default());
+    tracing::subscriber::with_default(subscriber, || {
+        let err = load_config("app.toml");
+        assert!(err.span_trace().is_some());
+
+        let debug = format!("{err:?}");
+        assert!(debug.contains("Span trace:"), "{debug}");
+        assert!(debug.contains("load_config"), "{debug}");
+        assert!(debug.contains("parse_config"), "{debug}");
+
+        let report = err.report().to_string();
+        assert!(report.contains("Span trace:"), "{report}");
+        assert!(report.contains("parse_config"), "{report}");
+    });
+}
+
+/// Tests that nothing is captured without an `ErrorLayer`.
+#[test]
+fn test_span_trace_without_layer() {
+    tracing::subscriber::with_default(Registry::default(), || {
+        let err = load_config("app.toml");
+        assert!(err.span_trace().is_none());
+        assert!(!format!("{err:?}").contains("Span trace:"));
+    });
+}
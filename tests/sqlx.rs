@@ -0,0 +1,164 @@
+#![cfg(feature = "sqlx")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Adding database fields to sqlx errors.
+
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt;
+
+use anyhow_tracing::{DbResultExt, Error};
+use sqlx::error::{DatabaseError, ErrorKind};
+
+/// An error returned by a mocked database.
+#[derive(Debug)]
+struct MockDatabaseError {
+    code: &'static str,
+    constraint: Option<&'static str>,
+    table: Option<&'static str>,
+    kind: fn() -> ErrorKind,
+}
+
+impl fmt::Display for MockDatabaseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl StdError for MockDatabaseError {}
+
+impl DatabaseError for MockDatabaseError {
+    fn message(&self) -> &str {
+        "constraint violated"
+    }
+
+    fn code(&self) -> Option<Cow<'_, str>> {
+        Some(Cow::Borrowed(self.code))
+    }
+
+    fn as_error(&self) -> &(dyn StdError + Send + Sync + 'static) {
+        self
+    }
+
+    fn as_error_mut(&mut self) -> &mut (dyn StdError + Send + Sync + 'static) {
+        self
+    }
+
+    fn into_error(self: Box<Self>) -> Box<dyn StdError + Send + Sync + 'static> {
+        self
+    }
+
+    fn constraint(&self) -> Option<&str> {
+        self.constraint
+    }
+
+    fn table(&self) -> Option<&str> {
+        self.table
+    }
+
+    fn kind(&self) -> ErrorKind {
+        (self.kind)()
+    }
+}
+
+/// Build a unique violation on the email of users.
+fn unique_violation() -> sqlx::Error {
+    sqlx::Error::Database(Box::new(MockDatabaseError {
+        code: "23505",
+        constraint: Some("users_email_key"),
+        table: Some("users"),
+        kind: || ErrorKind::UniqueViolation,
+    }))
+}
+
+/// Tests the fields of a unique violation, and that the sqlx error can be downcast.
+#[test]
+fn test_unique_violation() {
+    let err = Error::from_sqlx(unique_violation());
+    assert_eq!(
+        err.fields().iter().collect::<Vec<_>>(),
+        [
+            ("db.code", "23505"),
+            ("db.constraint", "users_email_key"),
+            ("db.table", "users"),
+            ("db.kind", "unique_violation"),
+        ]
+    );
+    let sqlx_err = err
+        .downcast_ref::<sqlx::Error>()
+        .expect("the error is kept");
+    assert!(
+        sqlx_err
+            .as_database_error()
+            .is_some_and(|database| database.is_unique_violation())
+    );
+}
+
+/// Tests a foreign key violation, and a database error of another kind without a
+/// constraint or table.
+#[test]
+fn test_database_kinds() {
+    let err = Error::from_sqlx(sqlx::Error::Database(Box::new(MockDatabaseError {
+        code: "23503",
+        constraint: Some("orders_user_id_fkey"),
+        table: Some("orders"),
+        kind: || ErrorKind::ForeignKeyViolation,
+    })));
+    assert_eq!(err.get_field("db.kind"), Some("foreign_key_violation"));
+
+    let err = Error::from_sqlx(sqlx::Error::Database(Box::new(MockDatabaseError {
+        code: "42P01",
+        constraint: None,
+        table: None,
+        kind: || ErrorKind::Other,
+    })));
+    assert_eq!(
+        err.fields().iter().collect::<Vec<_>>(),
+        [("db.code", "42P01"), ("db.kind", "database")]
+    );
+}
+
+/// Tests that a missing row is classified, and is not retryable.
+#[test]
+fn test_not_found() {
+    let err = Error::from_sqlx(sqlx::Error::RowNotFound);
+    assert_eq!(
+        err.fields().iter().collect::<Vec<_>>(),
+        [("db.kind", "not_found")]
+    );
+}
+
+/// Tests that connection errors are classified and retryable.
+#[test]
+fn test_connection_errors() {
+    for error in [
+        sqlx::Error::PoolTimedOut,
+        sqlx::Error::PoolClosed,
+        sqlx::Error::Io(std::io::Error::other("connection reset")),
+    ] {
+        let err = Error::from_sqlx(error);
+        assert_eq!(err.get_field("db.kind"), Some("connection"));
+        assert_eq!(err.get_field("retryable"), Some("true"));
+    }
+}
+
+/// Tests that other errors have no fields.
+#[test]
+fn test_other_errors() {
+    let err = Error::from_sqlx(sqlx::Error::ColumnNotFound("email".to_owned()));
+    assert!(err.fields().is_empty());
+}
+
+/// Tests that `db_context` adds the context on top of the database fields.
+#[test]
+fn test_db_context() {
+    let result: Result<(), sqlx::Error> = Err(unique_violation());
+    let err = result
+        .db_context("inserting user")
+        .expect_err("the query failed");
+    assert_eq!(
+        err.to_string(),
+        "inserting user [db.code=23505, db.constraint=users_email_key, db.table=users, db.kind=unique_violation]"
+    );
+    assert!(err.downcast_ref::<sqlx::Error>().is_some());
+}
@@ -0,0 +1,105 @@
+#![cfg(feature = "tracing")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! The target and name of the events emitted for errors.
+
+use std::sync::{Arc, Mutex};
+
+use anyhow_tracing::{Context as _, Error, Result, anyhow, set_default_target};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::Registry;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+/// A layer collecting the name and target of events.
+#[derive(Clone, Default)]
+struct Capture(Arc<Mutex<Vec<(String, String)>>>);
+
+impl<S: Subscriber> Layer<S> for Capture {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        self.0
+            .lock()
+            .expect("not poisoned")
+            .push((metadata.name().to_owned(), metadata.target().to_owned()));
+    }
+}
+
+/// Run `f` and get the name and target of the events it emitted.
+fn collect(f: impl FnOnce()) -> Vec<(String, String)> {
+    let capture = Capture::default();
+    tracing::subscriber::with_default(Registry::default().with(capture.clone()), f);
+    std::mem::take(&mut *capture.0.lock().expect("not poisoned"))
+}
+
+fn pairs(events: &[(&str, &str)]) -> Vec<(String, String)> {
+    events
+        .iter()
+        .map(|(name, target)| ((*name).to_owned(), (*target).to_owned()))
+        .collect()
+}
+
+mod payments {
+    use anyhow_tracing::{Error, anyhow};
+
+    pub fn charge() -> Error {
+        anyhow!(order_id = 42, "card declined")
+    }
+}
+
+fn parse() -> Result<u16> {
+    "port".parse::<u16>().context("invalid port")
+}
+
+/// Tests that the target of an event is the module the error was created in.
+#[test]
+fn test_target_is_module_path() {
+    let err = payments::charge();
+    assert_eq!(err.target(), "target::payments");
+
+    let events = collect(|| {
+        err.emit();
+        anyhow!("disk full").emit();
+    });
+    assert_eq!(
+        events,
+        pairs(&[("error", "target::payments"), ("error", "target")])
+    );
+}
+
+/// Tests that the target can be given when emitting an error.
+#[test]
+fn test_emit_with_target() {
+    let events = collect(|| payments::charge().emit_with_target("my_app::billing"));
+    assert_eq!(events, pairs(&[("error", "my_app::billing")]));
+}
+
+/// Tests that events are named by the event name of the error.
+#[test]
+fn test_event_name() {
+    let err = payments::charge().with_event_name("payment_error");
+    assert_eq!(err.event_name(), Some("payment_error"));
+
+    let events = collect(|| {
+        err.emit();
+        payments::charge().emit();
+    });
+    assert_eq!(
+        events,
+        pairs(&[
+            ("payment_error", "target::payments"),
+            ("error", "target::payments"),
+        ])
+    );
+}
+
+/// Tests that errors not created by the macros use the default target.
+#[test]
+fn test_default_target() {
+    set_default_target("my_app").expect("not set yet");
+    assert_eq!(set_default_target("other"), Err("other"));
+
+    let err = parse().expect_err("not a number");
+    assert_eq!(err.target(), "my_app");
+    assert_eq!(Error::msg("disk full").target(), "my_app");
+    assert_eq!(payments::charge().target(), "target::payments");
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "tracing")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Emitting errors at most once per period.
+
+mod common;
+
+use std::thread;
+use std::time::Duration;
+
+use anyhow_tracing::anyhow;
+use common::{CapturedEvent, Collector};
+
+/// Run `f` with a collector, returning the events emitted.
+fn collect<F: FnOnce()>(f: F) -> Vec<CapturedEvent> {
+    let collector = Collector::new(tracing::Level::TRACE);
+    tracing::subscriber::with_default(collector.clone(), f);
+    collector.take_events()
+}
+
+/// Tests that errors with the same messages are only emitted once per period, whatever
+/// their fields.
+#[test]
+fn test_emit_throttled() {
+    let events = collect(|| {
+        for attempt in 0..1000 {
+            anyhow!(attempt = attempt, "connection refused")
+                .context("failed to sync")
+                .emit_throttled(Duration::from_secs(60));
+        }
+        anyhow!("disk full").emit_throttled(Duration::from_secs(60));
+    });
+
+    let [first, other] = events.as_slice() else {
+        panic!("expected two events, got {:?}", events);
+    };
+    assert_eq!(first.level, tracing::Level::ERROR);
+    assert_eq!(first.target, module_path!());
+    assert_eq!(first.file.as_deref(), Some(file!()));
+    assert!(first.line.is_some());
+    assert_eq!(first.field("attempt"), Some("0"));
+    assert_eq!(first.field("suppressed_count"), None);
+    assert_eq!(other.field("message"), Some("disk full"));
+}
+
+/// Tests that the first error after the period has the number of errors left out.
+#[test]
+fn test_suppressed_count() {
+    let period = Duration::from_millis(50);
+    let events = collect(|| {
+        for _ in 0..1000 {
+            anyhow!("queue full").emit_throttled(period);
+        }
+        thread::sleep(period);
+        anyhow!("queue full").emit_throttled(period);
+        anyhow!("queue full").emit_throttled(period);
+    });
+
+    let [first, summary] = events.as_slice() else {
+        panic!("expected two events, got {:?}", events);
+    };
+    assert_eq!(first.field("suppressed_count"), None);
+    assert_eq!(summary.field("message"), Some("queue full"));
+    assert_eq!(summary.field("suppressed_count"), Some("999"));
+}
+
+/// Tests that errors are throttled by their code, or by an explicit key.
+#[test]
+fn test_emit_throttled_by_key() {
+    let period = Duration::from_secs(60);
+    let events = collect(|| {
+        anyhow!("rate limited by api-1")
+            .with_code("RATE_LIMITED")
+            .emit_throttled(period);
+        anyhow!("rate limited by api-2")
+            .with_code("RATE_LIMITED")
+            .emit_throttled(period);
+        for shard in 0..1000 {
+            anyhow!(shard = shard, "shard {shard} unavailable")
+                .emit_throttled_by("shard unavailable", period);
+        }
+    });
+
+    let messages: Vec<Option<&str>> = events.iter().map(|event| event.field("message")).collect();
+    assert_eq!(
+        messages,
+        [Some("rate limited by api-1"), Some("shard 0 unavailable")]
+    );
+}
@@ -0,0 +1,277 @@
+#![cfg(feature = "tonic")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Converting errors to and from tonic statuses.
+
+use std::convert::Infallible;
+use std::future::{Ready, ready};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use anyhow_tracing::{Error, anyhow};
+use http::uri::PathAndQuery;
+use tokio::net::TcpListener;
+use tonic::body::Body;
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::server::{Grpc, UnaryService};
+use tonic::transport::server::TcpIncoming;
+use tonic::transport::{Endpoint, Server};
+use tonic::{Code, Request, Response, Status};
+use tower::Service;
+
+/// The fields kept under their own names by the errors rebuilt from statuses.
+const REMOTE_FIELDS: &[&str] = &["user_id", "city"];
+
+/// A codec for messages without content, as the test service needs none.
+#[derive(Clone, Copy)]
+struct EmptyCodec;
+
+impl Codec for EmptyCodec {
+    type Encode = ();
+    type Decode = ();
+    type Encoder = Self;
+    type Decoder = Self;
+
+    fn encoder(&mut self) -> Self {
+        Self
+    }
+
+    fn decoder(&mut self) -> Self {
+        Self
+    }
+}
+
+impl Encoder for EmptyCodec {
+    type Item = ();
+    type Error = Status;
+
+    fn encode(&mut self, (): (), _: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        Ok(())
+    }
+}
+
+impl Decoder for EmptyCodec {
+    type Item = ();
+    type Error = Status;
+
+    fn decode(&mut self, _: &mut DecodeBuf<'_>) -> Result<Option<()>, Status> {
+        Ok(Some(()))
+    }
+}
+
+/// A handler looking up a user that doesn't exist.
+fn find_user() -> anyhow_tracing::Result<()> {
+    Err(anyhow!(user_id = 7, city = "Zürich", "no row returned")
+        .with_code("NOT_FOUND")
+        .with_public_message("user not found"))
+}
+
+/// The gRPC method calling [`find_user`].
+struct FindUser;
+
+impl UnaryService<()> for FindUser {
+    type Response = ();
+    type Future = Ready<Result<Response<()>, Status>>;
+
+    fn call(&mut self, _: Request<()>) -> Self::Future {
+        ready(
+            find_user()
+                .map(Response::new)
+                .map_err(|err| err.to_status()),
+        )
+    }
+}
+
+/// A gRPC server answering every request with [`FindUser`].
+#[derive(Clone)]
+struct UserServer;
+
+impl Service<http::Request<Body>> for UserServer {
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<Body>) -> Self::Future {
+        Box::pin(async move { Ok(Grpc::new(EmptyCodec).unary(FindUser, request).await) })
+    }
+}
+
+/// Serve [`UserServer`] on a local port, call it and get the status the client
+/// receives.
+async fn call_user_server() -> Status {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("a local port is free");
+    let address = listener.local_addr().expect("the listener has an address");
+    tokio::spawn(Server::builder().serve_with_incoming(UserServer, TcpIncoming::from(listener)));
+
+    let channel = Endpoint::from_shared(format!("http://{address}"))
+        .expect("the address is a valid URI")
+        .connect()
+        .await
+        .expect("the server accepts connections");
+    let mut client = tonic::client::Grpc::new(channel);
+    client.ready().await.expect("the channel is ready");
+    client
+        .unary::<(), (), _>(
+            Request::new(()),
+            PathAndQuery::from_static("/users.Users/FindUser"),
+            EmptyCodec,
+        )
+        .await
+        .expect_err("the server answers with an error")
+}
+
+/// Tests the gRPC code parsed from the code of the error, by name or number.
+#[test]
+fn test_grpc_code_from_the_error_code() {
+    assert_eq!(
+        anyhow!("missing").with_code("NOT_FOUND").to_status().code(),
+        Code::NotFound
+    );
+    assert_eq!(
+        anyhow!("missing").with_code("not_found").to_status().code(),
+        Code::NotFound
+    );
+    assert_eq!(
+        anyhow!("busy")
+            .with_code("ResourceExhausted")
+            .to_status()
+            .code(),
+        Code::ResourceExhausted
+    );
+    assert_eq!(
+        anyhow!("bad").with_code("3").to_status().code(),
+        Code::InvalidArgument
+    );
+    assert_eq!(
+        anyhow!("oops").with_code("USER_GONE").to_status().code(),
+        Code::Internal
+    );
+    assert_eq!(anyhow!("oops").to_status().code(), Code::Internal);
+}
+
+/// Tests that the `grpc.code` field takes precedence over the code of the error.
+#[test]
+fn test_grpc_code_field_overrides_the_error_code() {
+    let status = anyhow!("replica down")
+        .with_field("grpc.code", "UNAVAILABLE")
+        .with_code("REPLICA_DOWN")
+        .to_status();
+    assert_eq!(status.code(), Code::Unavailable);
+    assert_eq!(
+        status.metadata().get("x-error-code").unwrap(),
+        "REPLICA_DOWN"
+    );
+    assert!(status.metadata().get("x-error-field-grpc.code").is_none());
+}
+
+/// Tests that only the public message is sent, or the description of the code.
+#[test]
+fn test_message_is_public() {
+    let status = anyhow!(user_id = 7, "no row in users")
+        .with_code("NOT_FOUND")
+        .with_public_message("user not found")
+        .to_status();
+    assert_eq!(status.message(), "user not found");
+
+    let status = anyhow!("connection refused to 10.0.0.3").to_status();
+    assert_eq!(status.message(), Code::Internal.description());
+}
+
+/// Tests that fields are sent as metadata, keeping repeated keys.
+#[test]
+fn test_fields_are_metadata() {
+    let status = anyhow!(user_id = 7, "no row returned")
+        .with_field("Request.Id", "req 9")
+        .with_field("tag", "a")
+        .with_field("tag", "b")
+        .to_status();
+    let metadata = status.metadata();
+    assert_eq!(metadata.get("x-error-field-user_id").unwrap(), "7");
+    assert_eq!(metadata.get("x-error-field-request.id").unwrap(), "req 9");
+    let tags: Vec<_> = metadata.get_all("x-error-field-tag").iter().collect();
+    assert_eq!(tags, ["a", "b"]);
+}
+
+/// Tests that values that are not printable ASCII are sent as binary metadata.
+#[test]
+fn test_non_ascii_values_are_binary() {
+    let status = anyhow!(city = "Zürich", note = "two\nlines", "lookup failed").to_status();
+    let metadata = status.metadata();
+    assert!(metadata.get("x-error-field-city").is_none());
+    assert_eq!(
+        metadata
+            .get_bin("x-error-field-city-bin")
+            .unwrap()
+            .to_bytes()
+            .unwrap(),
+        "Zürich".as_bytes()
+    );
+    assert_eq!(
+        metadata
+            .get_bin("x-error-field-note-bin")
+            .unwrap()
+            .to_bytes()
+            .unwrap(),
+        "two\nlines".as_bytes()
+    );
+}
+
+/// Tests that an error returned by a gRPC server is rebuilt from the status its
+/// client receives.
+#[tokio::test]
+async fn test_round_trip_over_the_wire() {
+    anyhow_tracing::set_remote_fields(REMOTE_FIELDS).ok();
+    let received = call_user_server().await;
+    assert_eq!(received.code(), Code::NotFound);
+    assert_eq!(received.message(), "user not found");
+
+    let err = Error::from_status(&received);
+    assert_eq!(
+        err.to_string(),
+        "user not found [grpc.code=NOT_FOUND, user_id=7, city=Zürich]"
+    );
+    assert_eq!(err.public_message(), Some("user not found"));
+    assert_eq!(err.code(), Some("NOT_FOUND"));
+    assert_eq!(err.get_field("user_id"), Some("7"));
+    assert_eq!(err.get_field("city"), Some("Zürich"));
+
+    let again = err.to_status();
+    assert_eq!(again.code(), Code::NotFound);
+    assert_eq!(again.message(), "user not found");
+    assert_eq!(again.metadata().get("x-error-field-user_id").unwrap(), "7");
+}
+
+/// Tests that a status from another server keeps its gRPC code.
+#[test]
+fn test_from_status_keeps_the_grpc_code() {
+    let err = Error::from_status(&Status::permission_denied("not your account"));
+    assert_eq!(err.get_field("grpc.code"), Some("PERMISSION_DENIED"));
+    assert_eq!(err.code(), None);
+    assert_eq!(err.to_status().code(), Code::PermissionDenied);
+}
+
+/// Tests that received fields that were not named with `set_remote_fields` are kept
+/// together in the `grpc.fields` field.
+#[test]
+fn test_from_status_groups_unknown_fields() {
+    anyhow_tracing::set_remote_fields(REMOTE_FIELDS).ok();
+    let status = anyhow!(user_id = 7, shard = 3, "no row returned")
+        .with_field("note", "two\nlines")
+        .to_status();
+    let err = Error::from_status(&status);
+    assert_eq!(err.get_field("user_id"), Some("7"));
+    assert_eq!(err.get_field("shard"), None);
+    assert_eq!(
+        err.get_field("grpc.fields"),
+        Some(r#"shard=3 note="two\nlines""#)
+    );
+
+    let again = Error::from_status(&err.to_status());
+    assert_eq!(err.get_field("grpc.fields"), again.get_field("grpc.fields"));
+}
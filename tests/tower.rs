@@ -0,0 +1,111 @@
+#![cfg(feature = "tower")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Adding fields describing requests to the errors of tower services.
+
+use std::convert::Infallible;
+use std::future;
+use std::time::Duration;
+
+use anyhow_tracing::{Error, ErrorEnrichLayer, anyhow};
+use http::Request;
+use tower::timeout::Timeout;
+use tower::{Layer, ServiceExt, service_fn};
+
+/// A route matched by a router, stored in the extensions of requests.
+#[derive(Clone)]
+struct MatchedRoute(&'static str);
+
+/// The layer under test, with every kind of field.
+fn layer() -> ErrorEnrichLayer {
+    ErrorEnrichLayer::new()
+        .field_from_header("request_id", "x-request-id")
+        .record_method()
+        .record_uri()
+        .fields_from(|parts| {
+            parts
+                .extensions
+                .get::<MatchedRoute>()
+                .map(|route| ("route", route.0.to_owned()))
+                .into_iter()
+                .collect()
+        })
+}
+
+/// Build a GET request for `/users/7`, with a request ID and a matched route.
+fn request() -> Request<()> {
+    let mut request = Request::get("/users/7?full=true")
+        .header("X-Request-Id", "req-42")
+        .body(())
+        .expect("the request is valid");
+    request.extensions_mut().insert(MatchedRoute("/users/{id}"));
+    request
+}
+
+/// Tests that the fields of the request are added to a std error of the service.
+#[tokio::test]
+async fn test_std_error_is_enriched() {
+    let service = layer().layer(service_fn(|_: Request<()>| async {
+        Err::<(), _>(std::io::Error::other("connection refused"))
+    }));
+    let err = service.oneshot(request()).await.unwrap_err();
+    assert_eq!(err.message(), "connection refused");
+    assert_eq!(err.get_field("method"), Some("GET"));
+    assert_eq!(err.get_field("uri"), Some("/users/7?full=true"));
+    assert_eq!(err.get_field("request_id"), Some("req-42"));
+    assert_eq!(err.get_field("route"), Some("/users/{id}"));
+}
+
+/// Tests that an error of this crate keeps its own fields.
+#[tokio::test]
+async fn test_error_keeps_its_fields() {
+    let service =
+        ErrorEnrichLayer::new()
+            .record_method()
+            .layer(service_fn(|_: Request<()>| async {
+                Err::<(), Error>(anyhow!(user_id = 7, "no row returned"))
+            }));
+    let err = service.oneshot(request()).await.unwrap_err();
+    assert_eq!(err.to_string(), "no row returned [user_id=7, method=GET]");
+}
+
+/// Tests that missing headers are skipped and successful responses pass through.
+#[tokio::test]
+async fn test_missing_header_and_success() {
+    let failing = layer().layer(service_fn(|_: Request<()>| async {
+        Err::<(), _>(std::io::Error::other("timed out"))
+    }));
+    let request = Request::post("/jobs")
+        .body(())
+        .expect("the request is valid");
+    let err = failing.oneshot(request).await.unwrap_err();
+    assert_eq!(err.to_string(), "timed out [method=POST, uri=/jobs]");
+
+    let ok = layer().layer(service_fn(|_: Request<()>| async {
+        Ok::<_, Infallible>("done")
+    }));
+    let request = Request::get("/health")
+        .body(())
+        .expect("the request is valid");
+    let response = ok.oneshot(request).await;
+    assert_eq!(response.ok(), Some("done"));
+}
+
+/// Tests that the boxed errors of tower's own middleware are enriched too.
+#[tokio::test]
+async fn test_timeout_error_is_enriched() {
+    let slow = service_fn(|_: Request<()>| future::pending::<Result<(), Infallible>>());
+    let service = ErrorEnrichLayer::new()
+        .field_from_header("request_id", "x-request-id")
+        .record_method()
+        .layer(Timeout::new(slow, Duration::from_millis(10)));
+    let err = service.oneshot(request()).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "request timed out [method=GET, request_id=req-42]"
+    );
+    let boxed = err
+        .downcast_ref::<Box<dyn std::error::Error + Send + Sync>>()
+        .expect("the error is a boxed error");
+    assert!(boxed.is::<tower::timeout::error::Elapsed>());
+}
@@ -0,0 +1,135 @@
+#![cfg(feature = "valuable")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Errors and their fields as `valuable` values.
+
+use anyhow_tracing::anyhow;
+use valuable::{NamedValues, Valuable, Value, Visit};
+
+/// Collects the entries of a map, rendering numbers and booleans with their type.
+#[derive(Default)]
+struct Entries(Vec<(String, String)>);
+
+impl Visit for Entries {
+    fn visit_value(&mut self, value: Value<'_>) {
+        if let Value::Mappable(map) = value {
+            map.visit(self);
+        }
+    }
+
+    fn visit_entry(&mut self, key: Value<'_>, value: Value<'_>) {
+        if let Value::String(key) = key {
+            let rendered = match value {
+                Value::String(value) => value.to_owned(),
+                Value::I64(value) => format!("I64({value})"),
+                Value::U64(value) => format!("U64({value})"),
+                Value::F64(value) => format!("F64({value})"),
+                Value::Bool(value) => format!("Bool({value})"),
+                other @ (Value::Char(_)
+                | Value::F32(_)
+                | Value::I8(_)
+                | Value::I16(_)
+                | Value::I32(_)
+                | Value::I128(_)
+                | Value::Isize(_)
+                | Value::U8(_)
+                | Value::U16(_)
+                | Value::U32(_)
+                | Value::U128(_)
+                | Value::Usize(_)
+                | Value::Path(_)
+                | Value::Error(_)
+                | Value::Listable(_)
+                | Value::Mappable(_)
+                | Value::Structable(_)
+                | Value::Enumerable(_)
+                | Value::Tuplable(_)
+                | Value::Unit
+                | _) => format!("{other:?}"),
+            };
+            self.0.push((key.to_owned(), rendered));
+        }
+    }
+}
+
+/// Tests visiting the fields of an error in the order they were added, with the
+/// numbers and booleans given to the macros keeping their type.
+#[test]
+fn test_field_map() {
+    let err = anyhow!(
+        host = "db-3",
+        port = 5432,
+        ratio = 0.5,
+        retry = true,
+        "connection refused"
+    )
+    .context("failed to sync")
+    .with_field("attempt", 2);
+
+    let map = err.field_map();
+    assert!(matches!(map.as_value(), Value::Mappable(_)));
+
+    let mut entries = Entries::default();
+    valuable::visit(&map, &mut entries);
+    assert_eq!(
+        entries.0,
+        [
+            ("host".to_owned(), "db-3".to_owned()),
+            ("port".to_owned(), "I64(5432)".to_owned()),
+            ("ratio".to_owned(), "F64(0.5)".to_owned()),
+            ("retry".to_owned(), "Bool(true)".to_owned()),
+            ("attempt".to_owned(), "2".to_owned()),
+        ]
+    );
+}
+
+/// Collects the named fields of a structure, rendering nested values with `Debug`.
+#[derive(Default)]
+struct Named(Vec<(String, String)>);
+
+impl Visit for Named {
+    fn visit_value(&mut self, value: Value<'_>) {
+        if let Value::Structable(structure) = value {
+            structure.visit(self);
+        }
+    }
+
+    fn visit_named_fields(&mut self, named_values: &NamedValues<'_>) {
+        for (field, value) in named_values {
+            let rendered = if let Value::String(value) = value {
+                (*value).to_owned()
+            } else {
+                format!("{value:?}")
+            };
+            self.0.push((field.name().to_owned(), rendered));
+        }
+    }
+}
+
+/// Tests visiting an error as a structure with its message, causes and fields.
+#[test]
+fn test_as_value() {
+    let err = anyhow!(host = "db-3", "connection refused")
+        .context("failed to sync")
+        .with_field("attempt", 2);
+
+    let value = err.as_value();
+    let Value::Structable(structure) = value.as_value() else {
+        panic!("expected a structure");
+    };
+    assert_eq!(structure.definition().name(), "Error");
+
+    let mut named = Named::default();
+    valuable::visit(&value, &mut named);
+    assert_eq!(
+        named.0,
+        [
+            ("message".to_owned(), "failed to sync".to_owned()),
+            ("causes".to_owned(), r#"["connection refused"]"#.to_owned()),
+            (
+                "fields".to_owned(),
+                r#"{"host": "db-3", "attempt": "2"}"#.to_owned()
+            ),
+        ]
+    );
+}
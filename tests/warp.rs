@@ -0,0 +1,117 @@
+#![cfg(feature = "warp")]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Rejecting warp requests with errors.
+
+use anyhow_tracing::{Context, Result, anyhow, handle_rejection};
+use serde_json::{Value, json};
+use warp::Filter;
+use warp::http::StatusCode;
+
+/// Load a user, failing with a 404 for unknown users.
+fn load_user(id: u64) -> Result<String> {
+    if id == 1 {
+        return Ok("ada".to_owned());
+    }
+    Err(anyhow!(user_id = id, table = "users", "no row returned")
+        .with_status(http::StatusCode::NOT_FOUND)
+        .with_code("USER_NOT_FOUND")
+        .with_public_message("user not found")
+        .with_field("request_id", "req-7"))
+}
+
+/// Reach the database, failing without a status.
+fn sync() -> Result<()> {
+    let result: std::result::Result<(), std::io::Error> =
+        Err(std::io::Error::other("connection refused"));
+    result
+        .context("failed to reach the database")
+        .with_field("host", "db-3")
+}
+
+/// Send a GET request to `path`, returning the status and the JSON body.
+async fn call(path: &str) -> (StatusCode, Value) {
+    anyhow_tracing::response::set_public_fields(&["request_id"]).ok();
+    let users = warp::path!("users" / u64).and_then(|id: u64| async move {
+        let name = load_user(id).map_err(anyhow_tracing::Error::into_rejection)?;
+        Ok::<_, warp::Rejection>(warp::reply::json(&json!({ "id": id, "name": name })))
+    });
+    let sync = warp::path!("sync").and_then(|| async {
+        sync()?;
+        Ok::<_, warp::Rejection>(warp::reply::json(&json!({ "synced": true })))
+    });
+    let routes = users.or(sync).recover(handle_rejection);
+
+    let response = warp::test::request().path(path).reply(&routes).await;
+    let body = if response.body().is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(response.body()).expect("the body is JSON")
+    };
+    (response.status(), body)
+}
+
+/// Tests a successful request, which is not affected.
+#[tokio::test]
+async fn test_ok() {
+    let (status, body) = call("/users/1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "id": 1, "name": "ada" }));
+}
+
+/// Tests an error mapped to a 404, whose internal fields and messages don't leak.
+#[tokio::test]
+async fn test_not_found() {
+    let (status, body) = call("/users/7").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        body,
+        json!({
+            "message": "user not found",
+            "code": "USER_NOT_FOUND",
+            "fields": { "request_id": "req-7" },
+        })
+    );
+}
+
+/// Tests an error converted with `?`, without a status or public message, answered
+/// with a 500 and the reason phrase.
+#[tokio::test]
+async fn test_default_internal_server_error() {
+    let (status, body) = call("/sync").await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body, json!({ "message": "Internal Server Error" }));
+}
+
+/// Tests that other rejections are left to warp.
+#[tokio::test]
+async fn test_other_rejections_pass_through() {
+    let (status, _) = call("/missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+/// Tests that the error is emitted with all of its details when the reply is built.
+#[cfg(feature = "test-util")]
+#[tokio::test]
+async fn test_error_is_emitted() {
+    use anyhow_tracing::Level;
+    use anyhow_tracing::test::capture_future;
+
+    let events = capture_future(async {
+        call("/users/7").await;
+        call("/sync").await;
+    })
+    .await;
+
+    let [not_found, failed] = events.as_slice() else {
+        panic!("expected two events, got {:?}", events);
+    };
+    assert_eq!(not_found.level(), Level::Warn);
+    assert_eq!(not_found.field("table"), Some("users"));
+    assert_eq!(failed.level(), Level::Error);
+    assert_eq!(
+        failed.chain(),
+        "failed to reach the database: connection refused"
+    );
+    assert_eq!(failed.field("host"), Some("db-3"));
+}
@@ -0,0 +1,77 @@
+#![cfg(all(feature = "wasm", target_arch = "wasm32"))]
+#![allow(clippy::tests_outside_test_module, reason = "integration tests")]
+
+//! Converting errors into JavaScript `Error` objects, run with
+//! `wasm-pack test --node -- --features wasm`.
+
+use anyhow_tracing::{Context, Result, anyhow};
+use js_sys::Reflect;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_test::wasm_bindgen_test;
+
+/// Get the property `key` of `target`.
+fn get(target: &JsValue, key: &str) -> JsValue {
+    Reflect::get(target, &JsValue::from_str(key)).expect("the target is an object")
+}
+
+/// Validate an address, failing with a public message, a code and fields.
+fn validate(address: &str) -> Result<()> {
+    Err(
+        anyhow!(field = "email", length = address.len(), "no @ in address")
+            .with_code("INVALID_EMAIL")
+            .with_public_message("Please enter a valid email address."),
+    )
+}
+
+/// An exported function returning a `JsValue` error, using `?`.
+fn submit(address: &str) -> std::result::Result<(), JsValue> {
+    validate(address)?;
+    Ok(())
+}
+
+/// Tests the message, code and fields of the JavaScript error.
+#[wasm_bindgen_test]
+fn test_property_shapes() {
+    let js = submit("ada.example.com").unwrap_err();
+    assert!(js.is_instance_of::<js_sys::Error>());
+    let error: &js_sys::Error = js.unchecked_ref();
+    assert_eq!(
+        String::from(error.message()),
+        "Please enter a valid email address."
+    );
+    assert_eq!(
+        get(&js, "code").as_string().as_deref(),
+        Some("INVALID_EMAIL")
+    );
+
+    let fields = get(&js, "fields");
+    assert!(fields.is_object());
+    assert_eq!(get(&fields, "field").as_string().as_deref(), Some("email"));
+    assert_eq!(get(&fields, "length").as_string().as_deref(), Some("15"));
+    let own = js_sys::Object::keys(fields.unchecked_ref());
+    assert_eq!(own.length(), 2);
+}
+
+/// Tests an error without a public message or code.
+#[wasm_bindgen_test]
+fn test_defaults() {
+    let err = anyhow!("disk full").context("failed to save the draft");
+    let js = err.to_js();
+    let error: &js_sys::Error = js.unchecked_ref();
+    assert_eq!(String::from(error.message()), "failed to save the draft");
+    assert!(get(&js, "code").is_undefined());
+    assert_eq!(
+        js_sys::Object::keys(get(&js, "fields").unchecked_ref()).length(),
+        0
+    );
+}
+
+/// Tests that JSON fields keep their values.
+#[cfg(feature = "json")]
+#[wasm_bindgen_test]
+fn test_json_fields() {
+    let js = anyhow!("rate limited")
+        .with_field_json("retry_after", serde_json::json!(30))
+        .to_js();
+    assert_eq!(get(&get(&js, "fields"), "retry_after").as_f64(), Some(30.0));
+}